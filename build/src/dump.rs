@@ -0,0 +1,183 @@
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// A request to regenerate a library's C header by running its `ffizz-dump` binary.
+#[derive(Debug, Clone)]
+pub struct DumpHeader {
+    package: String,
+    bin: String,
+    features: Vec<String>,
+    manifest_path: Option<PathBuf>,
+    rerun_if_changed: Vec<PathBuf>,
+}
+
+impl DumpHeader {
+    /// Create a new request for the given package, running the `ffizz-dump` binary with the
+    /// `ffizz-dump` feature enabled.
+    pub fn new(package: impl Into<String>) -> Self {
+        DumpHeader {
+            package: package.into(),
+            bin: "ffizz-dump".into(),
+            features: vec!["ffizz-dump".into()],
+            manifest_path: None,
+            rerun_if_changed: vec![],
+        }
+    }
+
+    /// Run the binary with the given name, in place of `ffizz-dump`.
+    pub fn bin(mut self, bin: impl Into<String>) -> Self {
+        self.bin = bin.into();
+        self
+    }
+
+    /// Enable an additional feature of the package, such as one gating header items.
+    pub fn feature(mut self, feature: impl Into<String>) -> Self {
+        self.features.push(feature.into());
+        self
+    }
+
+    /// Use the given `Cargo.toml`, for a package outside of the current workspace.
+    pub fn manifest_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.manifest_path = Some(path.into());
+        self
+    }
+
+    /// When running in a build script, ask Cargo to re-run it when the given file or directory
+    /// changes.
+    pub fn rerun_if_changed(mut self, path: impl Into<PathBuf>) -> Self {
+        self.rerun_if_changed.push(path.into());
+        self
+    }
+
+    /// Run the dump binary and write the header it generates to the given path.
+    ///
+    /// The file is only written if its content has changed, and any missing parent directories
+    /// are created.  Returns true if the file was written.
+    pub fn emit(&self, path: impl AsRef<Path>) -> io::Result<bool> {
+        let out_dir = env::var_os("OUT_DIR");
+        if out_dir.is_some() {
+            for path in &self.rerun_if_changed {
+                println!("cargo:rerun-if-changed={}", path.display());
+            }
+        }
+
+        let cargo = env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
+        let output = Command::new(cargo)
+            .args(self.args(out_dir.map(|d| Path::new(&d).join("ffizz-build"))))
+            .output()?;
+        if !output.status.success() {
+            return Err(io::Error::other(format!(
+                "running {} for {} failed ({}):\n{}",
+                self.bin,
+                self.package,
+                output.status,
+                String::from_utf8_lossy(&output.stderr)
+            )));
+        }
+        let content = String::from_utf8(output.stdout)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        emit_content(path.as_ref(), &content)
+    }
+
+    /// The arguments to `cargo` to run the dump binary.
+    fn args(&self, target_dir: Option<PathBuf>) -> Vec<OsString> {
+        let mut args: Vec<OsString> = vec!["run".into(), "--quiet".into()];
+        if let Some(manifest_path) = &self.manifest_path {
+            args.push("--manifest-path".into());
+            args.push(manifest_path.into());
+        }
+        if let Some(target_dir) = target_dir {
+            args.push("--target-dir".into());
+            args.push(target_dir.into());
+        }
+        args.push("--package".into());
+        args.push((&self.package).into());
+        args.push("--bin".into());
+        args.push((&self.bin).into());
+        args.push("--features".into());
+        args.push(self.features.join(",").into());
+        args
+    }
+}
+
+/// Write the given content to the given path, if it differs from the existing content.
+fn emit_content(path: &Path, content: &str) -> io::Result<bool> {
+    match fs::read_to_string(path) {
+        Ok(existing) if existing == content => return Ok(false),
+        Ok(_) => {}
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, content)?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_args() {
+        let args = DumpHeader::new("mylib")
+            .feature("sync")
+            .args(Some("/out/ffizz-build".into()));
+        assert_eq!(
+            args,
+            [
+                "run",
+                "--quiet",
+                "--target-dir",
+                "/out/ffizz-build",
+                "--package",
+                "mylib",
+                "--bin",
+                "ffizz-dump",
+                "--features",
+                "ffizz-dump,sync"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_args_manifest_path() {
+        let args = DumpHeader::new("mylib")
+            .bin("mylib-header")
+            .manifest_path("../mylib/Cargo.toml")
+            .args(None);
+        assert_eq!(
+            args,
+            [
+                "run",
+                "--quiet",
+                "--manifest-path",
+                "../mylib/Cargo.toml",
+                "--package",
+                "mylib",
+                "--bin",
+                "mylib-header",
+                "--features",
+                "ffizz-dump"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_emit_content() {
+        let dir = std::env::temp_dir().join(format!("ffizz-build-{}", std::process::id()));
+        let path = dir.join("include").join("test.h");
+
+        assert!(emit_content(&path, "one\n").unwrap());
+        assert!(!emit_content(&path, "one\n").unwrap());
+        assert!(emit_content(&path, "two\n").unwrap());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "two\n");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
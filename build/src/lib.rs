@@ -0,0 +1,5 @@
+#![doc = include_str!("crate-doc.md")]
+
+mod dump;
+
+pub use dump::*;
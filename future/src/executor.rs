@@ -0,0 +1,314 @@
+use crate::FzFuture;
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
+use std::task::{Context, Poll, Wake, Waker};
+
+/// FzExecutor is a minimal, single-queue executor for async Rust work, driven from C.
+///
+/// Rust code spawns futures onto the executor with [`FzExecutor::spawn`], and the C host decides
+/// when and on which thread that work runs by calling [`FzExecutor::run_until_idle`], such as
+/// from its own event loop.  No threads are started.
+///
+/// A library typically accepts an executor from C as a pointer, with the C host creating and
+/// freeing it using the utility functions in this crate, such as
+/// [`fz_executor_new`](crate::fz_executor_new).
+///
+/// # Example
+///
+/// ```
+/// # use ffizz_future::{fz_executor_t, FzExecutor, FzFuture};
+/// # use ffizz_passby::Boxed;
+/// # async fn fetch(id: u32) -> u32 { id * 2 }
+/// type BoxedExecutor = Boxed<fz_executor_t>;
+/// type BoxedFetch = Boxed<FzFuture<u32>>;
+///
+/// #[no_mangle]
+/// pub unsafe extern "C" fn mylib_fetch(
+///     executor: *const fz_executor_t,
+///     id: u32,
+/// ) -> *mut FzFuture<u32> {
+///     // SAFETY: executor is valid (documented in API)
+///     let fut = unsafe { BoxedExecutor::with_ref_nonnull(executor, |ex| ex.spawn(fetch(id))) };
+///     match fut {
+///         // SAFETY: the caller will free the future (documented in API)
+///         Some(fut) => unsafe { BoxedFetch::return_val(fut) },
+///         None => std::ptr::null_mut(),
+///     }
+/// }
+/// ```
+pub struct FzExecutor {
+    shared: Arc<Shared>,
+}
+
+/// The C name for [`FzExecutor`], declared in C as an opaque type:
+///
+/// ```c
+/// typedef struct fz_executor_t fz_executor_t;
+/// ```
+#[allow(non_camel_case_types)]
+pub type fz_executor_t = FzExecutor;
+
+struct Shared {
+    /// Tasks that have been woken and are waiting to be polled.
+    queue: Mutex<VecDeque<Arc<Task>>>,
+    /// All tasks that have not completed, by ID.
+    tasks: Mutex<HashMap<u64, Arc<Task>>>,
+    next_id: AtomicU64,
+    shutdown: AtomicBool,
+}
+
+struct Task {
+    id: u64,
+    future: Mutex<Option<Pin<Box<dyn Future<Output = ()> + Send>>>>,
+    executor: Weak<Shared>,
+    /// True while the task is in the queue, so that it is not queued twice.
+    queued: AtomicBool,
+}
+
+/// The state shared between a spawned task and the future for its result.
+struct Slot<T> {
+    value: Option<T>,
+    waker: Option<Waker>,
+}
+
+/// A future that is ready when the spawned task stores its result in the slot.
+struct Receiver<T>(Arc<Mutex<Slot<T>>>);
+
+impl<T> Future for Receiver<T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        let mut slot = self.0.lock().unwrap();
+        match slot.value.take() {
+            Some(value) => Poll::Ready(value),
+            None => {
+                slot.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl FzExecutor {
+    /// Create a new executor with no tasks.
+    pub fn new() -> Self {
+        FzExecutor {
+            shared: Arc::new(Shared {
+                queue: Mutex::new(VecDeque::new()),
+                tasks: Mutex::new(HashMap::new()),
+                next_id: AtomicU64::new(0),
+                shutdown: AtomicBool::new(false),
+            }),
+        }
+    }
+
+    /// Spawn a future onto the executor, returning an [`FzFuture`] for its result.  The future
+    /// does not run until [`FzExecutor::run_until_idle`] is called.
+    ///
+    /// The task runs to completion even if the returned FzFuture is cancelled or dropped.  If the
+    /// executor has been shut down, the future is dropped and this returns None.  A task that
+    /// has not completed when the executor is shut down is dropped, and its FzFuture never
+    /// becomes ready.
+    pub fn spawn<F>(&self, future: F) -> Option<FzFuture<F::Output>>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        let slot = Arc::new(Mutex::new(Slot {
+            value: None,
+            waker: None,
+        }));
+        let task_slot = slot.clone();
+        self.spawn_detached(async move {
+            let value = future.await;
+            let waker = {
+                let mut slot = task_slot.lock().unwrap();
+                slot.value = Some(value);
+                slot.waker.take()
+            };
+            if let Some(waker) = waker {
+                waker.wake();
+            }
+        })
+        .then(|| FzFuture::new(Receiver(slot)))
+    }
+
+    /// Spawn a future onto the executor, without a way to retrieve its result.  Returns false,
+    /// dropping the future, if the executor has been shut down.
+    pub fn spawn_detached<F: Future<Output = ()> + Send + 'static>(&self, future: F) -> bool {
+        if self.is_shutdown() {
+            return false;
+        }
+        let task = Arc::new(Task {
+            id: self.shared.next_id.fetch_add(1, Ordering::Relaxed),
+            future: Mutex::new(Some(Box::pin(future))),
+            executor: Arc::downgrade(&self.shared),
+            queued: AtomicBool::new(false),
+        });
+        lock(&self.shared.tasks).insert(task.id, task.clone());
+        self.shared.schedule(task);
+        true
+    }
+
+    /// Poll each task that is ready to make progress, until no task is ready, returning the
+    /// number of tasks that have not completed.
+    pub fn run_until_idle(&self) -> usize {
+        loop {
+            let Some(task) = lock(&self.shared.queue).pop_front() else {
+                break;
+            };
+            task.queued.store(false, Ordering::Release);
+            let mut future = lock(&task.future);
+            let Some(fut) = future.as_mut() else {
+                continue;
+            };
+            let waker = Waker::from(task.clone());
+            if fut
+                .as_mut()
+                .poll(&mut Context::from_waker(&waker))
+                .is_ready()
+            {
+                *future = None;
+                lock(&self.shared.tasks).remove(&task.id);
+            }
+        }
+        lock(&self.shared.tasks).len()
+    }
+
+    /// Shut down the executor, dropping all tasks that have not completed.  Subsequent calls to
+    /// spawn will fail.
+    pub fn shutdown(&self) {
+        self.shared.shutdown.store(true, Ordering::Release);
+        lock(&self.shared.queue).clear();
+        let tasks = std::mem::take(&mut *lock(&self.shared.tasks));
+        for task in tasks.into_values() {
+            // drop the future outside of the lock, as dropping it may wake other tasks
+            let future = lock(&task.future).take();
+            drop(future);
+        }
+    }
+
+    /// Determine whether the executor has been shut down.
+    pub fn is_shutdown(&self) -> bool {
+        self.shared.shutdown.load(Ordering::Acquire)
+    }
+}
+
+impl Default for FzExecutor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for FzExecutor {
+    fn drop(&mut self) {
+        // wakers may keep tasks alive after the executor is dropped, so drop their futures now
+        self.shutdown();
+    }
+}
+
+impl Shared {
+    /// Add a task to the queue, if it is not already queued.
+    fn schedule(&self, task: Arc<Task>) {
+        if self.shutdown.load(Ordering::Acquire) {
+            return;
+        }
+        if !task.queued.swap(true, Ordering::AcqRel) {
+            lock(&self.queue).push_back(task);
+        }
+    }
+}
+
+impl Wake for Task {
+    fn wake(self: Arc<Self>) {
+        if let Some(executor) = self.executor.upgrade() {
+            executor.schedule(self);
+        }
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.clone().wake();
+    }
+}
+
+/// Lock a mutex, ignoring poisoning: the executor's state is consistent even if a task panics.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    /// A future that yields once before completing.
+    struct YieldOnce(bool);
+
+    impl Future for YieldOnce {
+        type Output = ();
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+            if self.0 {
+                Poll::Ready(())
+            } else {
+                self.0 = true;
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        }
+    }
+
+    #[test]
+    fn spawn_and_run() {
+        let executor = FzExecutor::new();
+        let fut = executor
+            .spawn(async {
+                YieldOnce(false).await;
+                42
+            })
+            .unwrap();
+        assert!(!fut.poll());
+        assert_eq!(executor.run_until_idle(), 0);
+        assert!(fut.poll());
+        assert_eq!(fut.take_result(), Some(42));
+    }
+
+    #[test]
+    fn pending_tasks() {
+        let executor = FzExecutor::new();
+        executor.spawn_detached(std::future::pending());
+        assert_eq!(executor.run_until_idle(), 1);
+    }
+
+    #[test]
+    fn shutdown() {
+        let dropped = Arc::new(AtomicUsize::new(0));
+        struct DropCounter(Arc<AtomicUsize>);
+        impl Drop for DropCounter {
+            fn drop(&mut self) {
+                self.0.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let executor = FzExecutor::new();
+        let counter = DropCounter(dropped.clone());
+        let fut = executor
+            .spawn(async move {
+                std::future::pending::<()>().await;
+                drop(counter);
+            })
+            .unwrap();
+        executor.run_until_idle();
+        assert_eq!(dropped.load(Ordering::SeqCst), 0);
+
+        executor.shutdown();
+        assert!(executor.is_shutdown());
+        assert_eq!(dropped.load(Ordering::SeqCst), 1);
+        assert_eq!(executor.run_until_idle(), 0);
+        assert!(!fut.poll());
+        assert!(executor.spawn(async {}).is_none());
+    }
+}
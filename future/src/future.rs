@@ -0,0 +1,327 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Context, Poll, Wake, Waker};
+
+/// A boxed future, as stored in an [`FzFuture`].
+type BoxFuture<T> = Pin<Box<dyn Future<Output = T> + Send>>;
+
+/// FzFuture wraps a Rust [`Future`] so that it can be driven and queried from C, typically
+/// behind a pointer managed with `ffizz_passby::Boxed`.
+///
+/// In polling mode, created with [`FzFuture::new`], C calls a "poll" function repeatedly, such as
+/// from its own event loop, until the future is ready, then retrieves the result.  The
+/// [`FzFuture::is_woken`] method indicates whether the future has asked to be polled again, so
+/// C can avoid polling futures that cannot make progress.
+///
+/// In callback mode, created with [`FzFuture::with_callback`], the future is polled immediately
+/// and again each time it is woken, on the thread that wakes it, and the callback is called when
+/// it is ready.
+///
+/// All methods take `&self`, and may be called from any thread.
+///
+/// # Example
+///
+/// ```
+/// # use ffizz_future::FzFuture;
+/// # use ffizz_passby::Boxed;
+/// # async fn fetch(id: u32) -> u32 { id * 2 }
+/// type BoxedFetch = Boxed<FzFuture<u32>>;
+///
+/// #[no_mangle]
+/// pub unsafe extern "C" fn mylib_fetch(id: u32) -> *mut FzFuture<u32> {
+///     // SAFETY: the caller will free the future (documented in API)
+///     unsafe { BoxedFetch::return_val(FzFuture::new(fetch(id))) }
+/// }
+///
+/// #[no_mangle]
+/// pub unsafe extern "C" fn mylib_fetch_poll(fut: *const FzFuture<u32>) -> bool {
+///     // SAFETY: fut is a valid future (documented in API)
+///     unsafe { BoxedFetch::with_ref_nonnull(fut, |fut| fut.poll()) }
+/// }
+///
+/// #[no_mangle]
+/// pub unsafe extern "C" fn mylib_fetch_result(fut: *const FzFuture<u32>, result_out: *mut u32) -> bool {
+///     // SAFETY: fut is a valid future and result_out is valid (documented in API)
+///     unsafe {
+///         match BoxedFetch::with_ref_nonnull(fut, |fut| fut.take_result()) {
+///             Some(result) => {
+///                 *result_out = result;
+///                 true
+///             }
+///             None => false,
+///         }
+///     }
+/// }
+///
+/// #[no_mangle]
+/// pub unsafe extern "C" fn mylib_fetch_free(fut: *mut FzFuture<u32>) {
+///     // SAFETY: fut is a valid future, not used after this call (documented in API)
+///     unsafe { BoxedFetch::take_nonnull(fut) };
+/// }
+/// ```
+pub struct FzFuture<T> {
+    inner: Arc<Inner<T>>,
+}
+
+struct Inner<T> {
+    state: Mutex<State<T>>,
+    /// Set when the future's waker is called, and cleared when it is polled.
+    woken: AtomicBool,
+    /// Called when the future completes, in callback mode.
+    on_complete: Mutex<Option<Box<dyn FnOnce() + Send>>>,
+    /// True in callback mode, where the future is polled when it is woken.
+    poll_on_wake: bool,
+}
+
+enum State<T> {
+    Pending(BoxFuture<T>),
+    /// The future is being polled, and has been temporarily removed from the state.
+    Polling,
+    Ready(T),
+    Taken,
+    Cancelled,
+}
+
+impl<T: Send + 'static> FzFuture<T> {
+    /// Wrap a future in polling mode.  The future does nothing until [`FzFuture::poll`] is
+    /// called.
+    pub fn new<F: Future<Output = T> + Send + 'static>(future: F) -> Self {
+        Self::build(Box::pin(future), None)
+    }
+
+    /// Wrap a future in callback mode.  The future is polled immediately, and again each time it
+    /// is woken, and `on_complete` is called, on the polling thread, when it is ready.  If the
+    /// future is cancelled, `on_complete` is not called.
+    ///
+    /// A C callback can be wrapped with `ffizz_passby::Callback`, if the C API documents that it
+    /// may be called from any thread.
+    pub fn with_callback<F, C>(future: F, on_complete: C) -> Self
+    where
+        F: Future<Output = T> + Send + 'static,
+        C: FnOnce() + Send + 'static,
+    {
+        let fut = Self::build(Box::pin(future), Some(Box::new(on_complete)));
+        fut.inner.poll();
+        fut
+    }
+
+    fn build(future: BoxFuture<T>, on_complete: Option<Box<dyn FnOnce() + Send>>) -> Self {
+        FzFuture {
+            inner: Arc::new(Inner {
+                state: Mutex::new(State::Pending(future)),
+                woken: AtomicBool::new(true),
+                poll_on_wake: on_complete.is_some(),
+                on_complete: Mutex::new(on_complete),
+            }),
+        }
+    }
+
+    /// Poll the future once, returning true if it is ready.  Polling a future that is ready,
+    /// whose result has been taken, or that has been cancelled does nothing.
+    pub fn poll(&self) -> bool {
+        self.inner.poll()
+    }
+}
+
+impl<T> FzFuture<T> {
+    /// Determine whether the future is ready, with a result that has not yet been taken.
+    pub fn is_ready(&self) -> bool {
+        matches!(*self.inner.lock(), State::Ready(_))
+    }
+
+    /// Determine whether the future has been woken since it was last polled, meaning that
+    /// polling it may make progress.  This is initially true.
+    pub fn is_woken(&self) -> bool {
+        self.inner.woken.load(Ordering::Acquire)
+    }
+
+    /// Take the result of the future, if it is ready.  The result can only be taken once.
+    pub fn take_result(&self) -> Option<T> {
+        let mut state = self.inner.lock();
+        match std::mem::replace(&mut *state, State::Taken) {
+            State::Ready(result) => Some(result),
+            other => {
+                *state = other;
+                None
+            }
+        }
+    }
+
+    /// Cancel the future, dropping it or its result.  Returns false if the future had already
+    /// completed or been cancelled.
+    pub fn cancel(&self) -> bool {
+        let mut state = self.inner.lock();
+        let cancelled = matches!(*state, State::Pending(_) | State::Polling);
+        // if the future is being polled, the polling thread will drop it
+        *state = State::Cancelled;
+        drop(state);
+        self.inner.on_complete.lock().unwrap().take();
+        cancelled
+    }
+}
+
+impl<T> Inner<T> {
+    fn lock(&self) -> MutexGuard<'_, State<T>> {
+        // a panic while polling leaves the state consistent, so ignore poisoning
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl<T: Send + 'static> Inner<T> {
+    /// Poll the future, returning true if it is ready.  In callback mode, this repeats until the
+    /// future is not woken during the poll.
+    fn poll(self: &Arc<Self>) -> bool {
+        loop {
+            let mut future = {
+                let mut state = self.lock();
+                match std::mem::replace(&mut *state, State::Polling) {
+                    State::Pending(future) => future,
+                    other => {
+                        // another thread is already polling, or there is nothing to poll
+                        let ready = matches!(other, State::Ready(_));
+                        *state = other;
+                        return ready;
+                    }
+                }
+            };
+
+            self.woken.store(false, Ordering::Release);
+            let waker = Waker::from(self.clone());
+            let poll = future.as_mut().poll(&mut Context::from_waker(&waker));
+
+            let mut state = self.lock();
+            if matches!(*state, State::Cancelled) {
+                return false;
+            }
+            match poll {
+                Poll::Ready(result) => {
+                    *state = State::Ready(result);
+                    drop(state);
+                    if let Some(on_complete) = self.on_complete.lock().unwrap().take() {
+                        on_complete();
+                    }
+                    return true;
+                }
+                Poll::Pending => {
+                    *state = State::Pending(future);
+                    drop(state);
+                    // a wake during the poll could not poll the future, so poll it again here
+                    if !(self.poll_on_wake && self.woken.load(Ordering::Acquire)) {
+                        return false;
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<T: Send + 'static> Wake for Inner<T> {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.woken.store(true, Ordering::Release);
+        if self.poll_on_wake {
+            self.poll();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    /// A future that is ready once a value is sent to it, for testing.
+    #[derive(Clone, Default)]
+    struct Oneshot(Arc<Mutex<(Option<u32>, Option<Waker>)>>);
+
+    impl Oneshot {
+        fn send(&self, value: u32) {
+            let waker = {
+                let mut inner = self.0.lock().unwrap();
+                inner.0 = Some(value);
+                inner.1.take()
+            };
+            if let Some(waker) = waker {
+                waker.wake();
+            }
+        }
+    }
+
+    impl Future for Oneshot {
+        type Output = u32;
+
+        fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<u32> {
+            let mut inner = self.0.lock().unwrap();
+            match inner.0.take() {
+                Some(value) => Poll::Ready(value),
+                None => {
+                    inner.1 = Some(cx.waker().clone());
+                    Poll::Pending
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn poll_mode() {
+        let oneshot = Oneshot::default();
+        let fut = FzFuture::new(oneshot.clone());
+        assert!(fut.is_woken());
+        assert!(!fut.poll());
+        assert!(!fut.is_woken());
+        assert!(!fut.is_ready());
+        assert_eq!(fut.take_result(), None);
+
+        oneshot.send(10);
+        assert!(fut.is_woken());
+        assert!(fut.poll());
+        assert!(fut.is_ready());
+        assert_eq!(fut.take_result(), Some(10));
+        assert!(!fut.is_ready());
+        assert_eq!(fut.take_result(), None);
+        assert!(!fut.cancel());
+    }
+
+    #[test]
+    fn immediately_ready() {
+        let fut = FzFuture::new(async { "done" });
+        assert!(fut.poll());
+        assert!(fut.poll());
+        assert_eq!(fut.take_result(), Some("done"));
+    }
+
+    #[test]
+    fn callback_mode() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let oneshot = Oneshot::default();
+        let fut = FzFuture::with_callback(oneshot.clone(), {
+            let calls = calls.clone();
+            move || {
+                calls.fetch_add(1, Ordering::SeqCst);
+            }
+        });
+        assert!(!fut.is_ready());
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+
+        // waking the future polls it, on another thread
+        std::thread::spawn(move || oneshot.send(5)).join().unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(fut.take_result(), Some(5));
+    }
+
+    #[test]
+    fn cancel() {
+        let oneshot = Oneshot::default();
+        let fut = FzFuture::with_callback(oneshot.clone(), || panic!("should not be called"));
+        assert!(fut.cancel());
+        assert!(!fut.cancel());
+        oneshot.send(5);
+        assert!(!fut.poll());
+        assert_eq!(fut.take_result(), None);
+    }
+}
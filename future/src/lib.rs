@@ -0,0 +1,11 @@
+#![warn(unsafe_op_in_unsafe_fn)]
+#![doc = include_str!("crate-doc.md")]
+
+mod executor;
+mod future;
+mod macros;
+mod utilfns;
+
+pub use executor::*;
+pub use future::*;
+pub use utilfns::*;
@@ -0,0 +1,72 @@
+/// Re-export an `fz_executor_t` utility function in your own crate.
+///
+/// For each utility function, this can be written either as
+///
+/// ```ignore
+/// ffizz_future::reexport!(fz_executor_new);
+/// ```
+/// or, to rename the function,
+/// ```ignore
+/// ffizz_future::reexport!(fz_executor_new as mylib_executor_new);
+/// ```
+///
+/// As with `ffizz_string::reexport!`, it is up to you to include project-specific documentation
+/// and declarations, typically using `ffizz_header::snippet!`.
+#[macro_export]
+macro_rules! reexport(
+    // all functions in future/src/utilfns.rs should be reflected here.
+    { fz_executor_new } => { reexport!(fz_executor_new as fz_executor_new); };
+    { fz_executor_new as $name:ident } => {
+        #[no_mangle]
+        #[allow(unsafe_op_in_unsafe_fn)]
+        pub unsafe extern "C" fn $name() -> *mut $crate::fz_executor_t {
+            $crate::fz_executor_new()
+        }
+    };
+    { fz_executor_run_until_idle } => { reexport!(fz_executor_run_until_idle as fz_executor_run_until_idle); };
+    { fz_executor_run_until_idle as $name:ident } => {
+        #[no_mangle]
+        #[allow(unsafe_op_in_unsafe_fn)]
+        pub unsafe extern "C" fn $name(executor: *const $crate::fz_executor_t) -> usize {
+            $crate::fz_executor_run_until_idle(executor)
+        }
+    };
+    { fz_executor_shutdown } => { reexport!(fz_executor_shutdown as fz_executor_shutdown); };
+    { fz_executor_shutdown as $name:ident } => {
+        #[no_mangle]
+        #[allow(unsafe_op_in_unsafe_fn)]
+        pub unsafe extern "C" fn $name(executor: *const $crate::fz_executor_t) {
+            $crate::fz_executor_shutdown(executor)
+        }
+    };
+    { fz_executor_free } => { reexport!(fz_executor_free as fz_executor_free); };
+    { fz_executor_free as $name:ident } => {
+        #[no_mangle]
+        #[allow(unsafe_op_in_unsafe_fn)]
+        pub unsafe extern "C" fn $name(executor: *mut $crate::fz_executor_t) {
+            $crate::fz_executor_free(executor)
+        }
+    };
+);
+
+#[cfg(test)]
+mod test {
+    reexport!(fz_executor_new);
+    reexport!(fz_executor_run_until_idle);
+    reexport!(fz_executor_shutdown as shutdown);
+    reexport!(fz_executor_free as free_executor);
+
+    #[test]
+    fn test() {
+        // SAFETY: the executor is valid until it is freed, and not used after that
+        unsafe {
+            let executor = fz_executor_new();
+            let fut = (*executor).spawn(async { 1 }).unwrap();
+            assert_eq!(fz_executor_run_until_idle(executor), 0);
+            assert_eq!(fut.take_result(), None);
+            assert!(fut.poll());
+            shutdown(executor);
+            free_executor(executor);
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use crate::fz_executor_t;
+use ffizz_passby::Boxed;
+
+// These functions are used in downstream creates via the `reexport!` macro, which generates a
+// function in that crate, wrapping one of these functions.  As a result, none of these functions
+// are `extern "C"`, and all are tagged with `inline(always)` so that they are inlined into the
+// downstream crate.
+//
+// NOTE: if you add a function to this module, also add it to `reexport!` in future/src/macros.rs.
+
+type BoxedExecutor = Boxed<fz_executor_t>;
+
+/// Create a new executor.
+///
+/// # Safety
+///
+/// The resulting executor must be freed with `fz_executor_free`.
+///
+/// ```c
+/// fz_executor_t *fz_executor_new();
+/// ```
+#[inline(always)]
+pub unsafe fn fz_executor_new() -> *mut fz_executor_t {
+    // SAFETY: caller promises to free this executor
+    unsafe { BoxedExecutor::return_val(fz_executor_t::new()) }
+}
+
+/// Run the executor's tasks until none can make progress, returning the number of tasks that
+/// have not completed.
+///
+/// # Safety
+///
+/// The executor must not be NULL, and must not be freed during this call.
+///
+/// ```c
+/// size_t fz_executor_run_until_idle(const fz_executor_t *);
+/// ```
+#[inline(always)]
+pub unsafe fn fz_executor_run_until_idle(executor: *const fz_executor_t) -> usize {
+    // SAFETY: executor is valid (promised by caller)
+    unsafe { BoxedExecutor::with_ref_nonnull(executor, |ex| ex.run_until_idle()) }
+}
+
+/// Shut down the executor, dropping all tasks that have not completed.  The executor must still
+/// be freed.
+///
+/// # Safety
+///
+/// The executor must not be NULL, and must not be freed during this call.
+///
+/// ```c
+/// void fz_executor_shutdown(const fz_executor_t *);
+/// ```
+#[inline(always)]
+pub unsafe fn fz_executor_shutdown(executor: *const fz_executor_t) {
+    // SAFETY: executor is valid (promised by caller)
+    unsafe { BoxedExecutor::with_ref_nonnull(executor, |ex| ex.shutdown()) }
+}
+
+/// Free an executor, shutting it down if necessary.
+///
+/// # Safety
+///
+/// The executor must not be NULL, and must not be used after this call.
+///
+/// ```c
+/// void fz_executor_free(fz_executor_t *);
+/// ```
+#[inline(always)]
+pub unsafe fn fz_executor_free(executor: *mut fz_executor_t) {
+    // SAFETY: executor is valid and not used again (promised by caller)
+    drop(unsafe { BoxedExecutor::take_nonnull(executor) });
+}
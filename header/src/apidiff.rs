@@ -0,0 +1,282 @@
+use crate::cdecl::{parse, CType, Decl};
+use crate::{all_items, sort_items, HeaderItem};
+use std::fmt;
+
+/// A change to the C API, as reported by [`generate_diff`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ApiChange {
+    /// A declaration that is new in the current header.
+    Added { name: String, decl: String },
+    /// A declaration that is no longer in the current header.
+    Removed { name: String, decl: String },
+    /// A declaration that exists in both headers, but has changed.
+    Changed {
+        name: String,
+        old: String,
+        new: String,
+    },
+}
+
+impl ApiChange {
+    /// Determine whether this change may break existing users of the API.
+    pub fn is_breaking(&self) -> bool {
+        !matches!(self, ApiChange::Added { .. })
+    }
+}
+
+impl fmt::Display for ApiChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiChange::Added { decl, .. } => write!(f, "added: {decl}"),
+            ApiChange::Removed { decl, .. } => write!(f, "removed: {decl}"),
+            ApiChange::Changed { old, new, .. } => write!(f, "changed: {old} -> {new}"),
+        }
+    }
+}
+
+/// Compare the declarations in a previously-generated header with those in the current library,
+/// returning the changes to the API.
+///
+/// Functions, types, and `#define`d macros are compared by name, with a change to a declaration's
+/// signature, fields, variants, or value reported as [`ApiChange::Changed`].  Comments and
+/// formatting are ignored, as are declarations that cannot be parsed.  Removals and changes are
+/// listed in the order they appear in the old header, followed by additions in the order they
+/// appear in the current header.
+///
+/// This is the basis for a semver check on the C API: any [breaking](ApiChange::is_breaking)
+/// change requires a new major version.
+pub fn generate_diff(old_header: &str) -> Vec<ApiChange> {
+    diff_from_vec(all_items(), old_header)
+}
+
+/// Inner version of generate_diff that does not operate on a static value.
+fn diff_from_vec(mut items: Vec<&HeaderItem>, old_header: &str) -> Vec<ApiChange> {
+    sort_items(&mut items);
+    let new: Vec<(String, String)> = items
+        .iter()
+        .filter(|hi| hi.in_bindings())
+        .flat_map(|hi| api_decls(hi.content))
+        .collect();
+    let old = api_decls(old_header);
+
+    let find = |decls: &[(String, String)], name: &str| {
+        decls
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, decl)| decl.clone())
+    };
+    let mut changes = vec![];
+    for (name, old_decl) in &old {
+        match find(&new, name) {
+            None => changes.push(ApiChange::Removed {
+                name: name.clone(),
+                decl: old_decl.clone(),
+            }),
+            Some(new_decl) if &new_decl != old_decl => changes.push(ApiChange::Changed {
+                name: name.clone(),
+                old: old_decl.clone(),
+                new: new_decl,
+            }),
+            Some(_) => {}
+        }
+    }
+    for (name, new_decl) in &new {
+        if find(&old, name).is_none() {
+            changes.push(ApiChange::Added {
+                name: name.clone(),
+                decl: new_decl.clone(),
+            });
+        }
+    }
+    changes
+}
+
+/// Get the name and normalized text of each declaration in C content, including `#define`d macros
+/// other than include guards.
+fn api_decls(content: &str) -> Vec<(String, String)> {
+    let ty = |ty: &CType| ty.declarator("").trim_end().to_string();
+    let mut decls = vec![];
+    for decl in parse(content) {
+        let (name, text) = match decl {
+            Decl::Function(func) => {
+                let mut params: Vec<String> = func.params.iter().map(|p| ty(&p.ty)).collect();
+                if func.variadic {
+                    params.push("...".into());
+                }
+                if params.is_empty() {
+                    params.push("void".into());
+                }
+                let text = format!("{}({})", func.ret.declarator(&func.name), params.join(", "));
+                (func.name, text)
+            }
+            Decl::Opaque { name } => {
+                let text = format!("typedef struct {name}");
+                (name, text)
+            }
+            Decl::Struct { name, fields } => {
+                let fields: Vec<String> = fields
+                    .iter()
+                    .map(|f| format!("{};", f.ty.declarator(&f.name)))
+                    .collect();
+                let text = format!("typedef struct {{ {} }} {name}", fields.join(" "));
+                (name, text)
+            }
+            Decl::Enum { name, variants } => {
+                let variants: Vec<String> = variants
+                    .iter()
+                    .map(|(v, value)| match value {
+                        Some(value) => format!("{v} = {value}"),
+                        None => v.clone(),
+                    })
+                    .collect();
+                let text = format!("typedef enum {{ {} }} {name}", variants.join(", "));
+                (name, text)
+            }
+            Decl::Typedef { name, ty } => {
+                let text = format!("typedef {}", ty.declarator(&name));
+                (name, text)
+            }
+            Decl::Unsupported(_) => continue,
+        };
+        decls.push((name, text));
+    }
+
+    for line in content.lines() {
+        let directive = line.trim_start().strip_prefix('#').map(str::trim_start);
+        let Some(define) = directive.and_then(|d| d.strip_prefix("define ")) else {
+            continue;
+        };
+        let define = define.split("//").next().unwrap_or("");
+        let mut words = define.split_whitespace();
+        let Some(name) = words.next() else {
+            continue;
+        };
+        let value: Vec<&str> = words.collect();
+        // a macro without a value is most likely an include guard
+        if !value.is_empty() {
+            decls.push((
+                name.to_string(),
+                format!("#define {name} {}", value.join(" ")),
+            ));
+        }
+    }
+    decls
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const ITEM: HeaderItem = HeaderItem {
+        order: 0,
+        module_path: "",
+        file: "",
+        line: 0,
+        name: "",
+        content: "",
+        cpp_content: None,
+        cpp_helper: false,
+        deprecated: None,
+        feature: None,
+        group: None,
+        platform: None,
+        since: None,
+        unstable: false,
+        enabled: true,
+        includes: &[],
+    };
+
+    const OLD: &str = "// MyLib\n\
+        #ifndef MYLIB_H\n\
+        #define MYLIB_H\n\
+        #define MYLIB_MAX 10\n\
+        typedef struct foo_t foo_t;\n\
+        // Make a foo.\n\
+        foo_t *foo_new(void);\n\
+        void foo_free(foo_t *foo);\n\
+        int foo_count(const foo_t *foo);\n\
+        #endif";
+
+    #[test]
+    fn test_api_decls() {
+        assert_eq!(
+            api_decls(OLD),
+            vec![
+                ("foo_t".into(), "typedef struct foo_t".into()),
+                ("foo_new".into(), "foo_t *foo_new(void)".into()),
+                ("foo_free".into(), "void foo_free(foo_t *)".into()),
+                ("foo_count".into(), "int foo_count(const foo_t *)".into()),
+                ("MYLIB_MAX".into(), "#define MYLIB_MAX 10".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_no_changes() {
+        let items = [HeaderItem {
+            content: OLD,
+            ..ITEM
+        }];
+        assert_eq!(diff_from_vec(items.iter().collect(), OLD), vec![]);
+    }
+
+    #[test]
+    fn test_changes() {
+        let items = [
+            HeaderItem {
+                order: 1,
+                name: "foo_t",
+                content: "// A foo.\ntypedef struct foo_t foo_t;\n#define MYLIB_MAX 20",
+                ..ITEM
+            },
+            HeaderItem {
+                order: 2,
+                name: "foo_new",
+                content: "// Make a foo, differently.\nfoo_t *foo_new(void);",
+                ..ITEM
+            },
+            HeaderItem {
+                order: 3,
+                name: "foo_count",
+                content: "size_t foo_count(const foo_t *foo);",
+                ..ITEM
+            },
+            HeaderItem {
+                order: 4,
+                name: "foo_reset",
+                content: "void foo_reset(foo_t *foo);",
+                ..ITEM
+            },
+        ];
+        let changes = diff_from_vec(items.iter().collect(), OLD);
+        assert_eq!(
+            changes,
+            vec![
+                ApiChange::Removed {
+                    name: "foo_free".into(),
+                    decl: "void foo_free(foo_t *)".into(),
+                },
+                ApiChange::Changed {
+                    name: "foo_count".into(),
+                    old: "int foo_count(const foo_t *)".into(),
+                    new: "size_t foo_count(const foo_t *)".into(),
+                },
+                ApiChange::Changed {
+                    name: "MYLIB_MAX".into(),
+                    old: "#define MYLIB_MAX 10".into(),
+                    new: "#define MYLIB_MAX 20".into(),
+                },
+                ApiChange::Added {
+                    name: "foo_reset".into(),
+                    decl: "void foo_reset(foo_t *)".into(),
+                },
+            ]
+        );
+        assert!(changes.iter().take(3).all(|c| c.is_breaking()));
+        assert!(!changes[3].is_breaking());
+        assert_eq!(
+            changes[1].to_string(),
+            "changed: int foo_count(const foo_t *) -> size_t foo_count(const foo_t *)"
+        );
+    }
+}
@@ -0,0 +1,70 @@
+//! Support for writing the C header from a build script or post-build step.
+//!
+//! A build script cannot call functions in the crate it is building, so the header must be
+//! generated by a crate that depends on the library.  A typical arrangement is a small
+//! `mylib-header` crate with `mylib` as a build-dependency, and a `build.rs` containing
+//!
+//! ```ignore
+//! extern crate mylib as _; // link the library so its header items are present
+//!
+//! fn main() {
+//!     ffizz_header::build::emit("../mylib/include/mylib.h").unwrap();
+//! }
+//! ```
+//!
+//! Cargo will re-run this build script whenever `mylib` changes, keeping the header up to date.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Generate the C header and write it to the given path.
+///
+/// The file is only written if its content has changed, so that tools watching its modification
+/// time (including Cargo) do not see spurious changes.  Any missing parent directories are
+/// created.
+///
+/// Returns true if the file was written.
+pub fn emit(path: impl AsRef<Path>) -> io::Result<bool> {
+    emit_content(path.as_ref(), &crate::generate())
+}
+
+/// Write the given content to the given path, if it differs from the existing content.
+fn emit_content(path: &Path, content: &str) -> io::Result<bool> {
+    match fs::read_to_string(path) {
+        Ok(existing) if existing == content => return Ok(false),
+        Ok(_) => {}
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, content)?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_emit_content() {
+        let dir = std::env::temp_dir().join(format!("ffizz-header-build-{}", std::process::id()));
+        let path = dir.join("include").join("test.h");
+
+        // initial write creates the directory
+        assert!(emit_content(&path, "one\n").unwrap());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "one\n");
+
+        // unchanged content is not written
+        assert!(!emit_content(&path, "one\n").unwrap());
+
+        // changed content is written
+        assert!(emit_content(&path, "two\n").unwrap());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "two\n");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,686 @@
+//! A lightweight parser for the C declarations in header items.
+//!
+//! This is not a full C parser.  It understands the declarations typically found in a header for
+//! a Rust library: function prototypes, opaque struct typedefs, struct and enum definitions, and
+//! simple typedefs.  Anything else is parsed as [`Decl::Unsupported`], allowing backends to skip
+//! it or emit it verbatim.
+
+/// A C type as used in a declaration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct CType {
+    /// The base type name, including any `struct` or `enum` keyword, such as `uint32_t` or
+    /// `struct foo`.
+    pub(crate) base: String,
+    /// True if the base type is const-qualified.
+    pub(crate) is_const: bool,
+    /// The number of pointer indirections.
+    pub(crate) pointers: usize,
+}
+
+impl CType {
+    /// Determine whether this is `void`, without any pointers.
+    pub(crate) fn is_void(&self) -> bool {
+        self.base == "void" && self.pointers == 0
+    }
+
+    /// Format a C declaration of the given name with this type, such as `const char *name`.
+    pub(crate) fn declarator(&self, name: &str) -> String {
+        format!(
+            "{}{} {}{name}",
+            if self.is_const { "const " } else { "" },
+            self.base,
+            "*".repeat(self.pointers)
+        )
+    }
+}
+
+/// A named, typed value: a function parameter or a struct field.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Field {
+    pub(crate) name: String,
+    pub(crate) ty: CType,
+}
+
+/// A function prototype.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Function {
+    pub(crate) name: String,
+    pub(crate) ret: CType,
+    pub(crate) params: Vec<Field>,
+    pub(crate) variadic: bool,
+}
+
+/// A single C declaration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Decl {
+    /// A function prototype.
+    Function(Function),
+    /// A typedef of an incomplete struct type, such as `typedef struct foo foo_t;`.
+    Opaque { name: String },
+    /// A typedef of a struct definition, such as `typedef struct { int x; } foo_t;`.
+    Struct { name: String, fields: Vec<Field> },
+    /// A typedef of an enum definition, with the variants' names and values, if given.
+    Enum {
+        name: String,
+        variants: Vec<(String, Option<String>)>,
+    },
+    /// A typedef of another type, such as `typedef uint32_t foo_t;`.
+    Typedef { name: String, ty: CType },
+    /// A declaration this parser does not understand, as it appeared in the source (with
+    /// whitespace normalized).
+    Unsupported(String),
+}
+
+/// Words that can only be part of a type, and never a declarator name.
+const TYPE_WORDS: &[&str] = &[
+    "void", "char", "short", "int", "long", "float", "double", "signed", "unsigned", "_Bool",
+    "bool",
+];
+
+/// Parse the declarations in C content, ignoring comments and preprocessor directives.
+pub(crate) fn parse(content: &str) -> Vec<Decl> {
+    let tokens = tokenize(&strip_declarations(content));
+    let mut decls = vec![];
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, tok) in tokens.iter().enumerate() {
+        match tok.as_str() {
+            "{" => depth += 1,
+            "}" => {
+                depth -= 1;
+                // a function definition is not followed by `;`
+                let stmt = &tokens[start..=i];
+                if depth == 0 && stmt[0] != "typedef" && stmt.iter().any(|t| t == "(") {
+                    decls.push(Decl::Unsupported(stmt.join(" ")));
+                    start = i + 1;
+                }
+            }
+            ";" if depth == 0 => {
+                if i > start {
+                    decls.push(parse_statement(&tokens[start..i]));
+                }
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if start < tokens.len() {
+        decls.push(Decl::Unsupported(tokens[start..].join(" ")));
+    }
+    decls
+}
+
+/// Get the text of the `//` comments in C content, with the comment markers removed.
+pub(crate) fn doc_lines(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("//"))
+        .map(|line| {
+            line.strip_prefix(' ')
+                .unwrap_or(line)
+                .trim_end()
+                .to_string()
+        })
+        .collect()
+}
+
+/// Remove comments, preprocessor directives, and `EXTERN_C` markers from C content, returning
+/// the remaining non-blank lines.
+pub(crate) fn strip_declarations(content: &str) -> String {
+    let content = strip_comments(content);
+    let mut lines = vec![];
+    let mut continued = false;
+    for line in content.lines() {
+        // skip preprocessor directives, including any continuation lines
+        if continued || line.trim_start().starts_with('#') {
+            continued = line.trim_end().ends_with('\\');
+            continue;
+        }
+        let line = strip_extern_c(line);
+        if !line.trim().is_empty() {
+            lines.push(line.trim_end().to_string());
+        }
+    }
+    lines.join("\n")
+}
+
+/// Iterate over the identifiers in a string of C code.
+pub(crate) fn identifiers(code: &str) -> impl Iterator<Item = &str> {
+    code.split(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .filter(|w| w.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_'))
+}
+
+/// Get the standard headers, such as `<stdint.h>`, defining the types and macros used in C
+/// content, ignoring any used only in comments.
+pub(crate) fn standard_headers(content: &str) -> Vec<&'static str> {
+    let content = strip_comments(content);
+    let mut headers = vec![];
+    for line in content.lines() {
+        if line.trim_start().starts_with('#')
+            && line.trim_start()[1..].trim_start().starts_with("include")
+        {
+            continue;
+        }
+        for ident in identifiers(line) {
+            let header = match ident {
+                "bool" | "true" | "false" => "<stdbool.h>",
+                "size_t" | "ptrdiff_t" | "max_align_t" | "NULL" | "offsetof" => "<stddef.h>",
+                "SIZE_MAX" | "PTRDIFF_MAX" | "PTRDIFF_MIN" => "<stdint.h>",
+                _ if is_stdint_name(ident) => "<stdint.h>",
+                _ => continue,
+            };
+            if !headers.contains(&header) {
+                headers.push(header);
+            }
+        }
+    }
+    headers
+}
+
+/// Get forward declarations for the struct tags referenced in C content, in order of first
+/// reference.  A tag that is given a typedef name by `typedef struct tag name;` or
+/// `typedef struct tag { .. } name;` is declared with that typedef, such as
+/// `typedef struct foo_t foo_t;`, and any other tag is declared alone, such as `struct foo;`.
+pub(crate) fn forward_declarations(content: &str) -> Vec<String> {
+    let tokens = tokenize(&strip_declarations(content));
+    let mut tags: Vec<(&str, Option<&str>)> = vec![];
+    for pair in tokens.windows(2) {
+        if pair[0] == "struct" && is_ident(&pair[1]) && !tags.iter().any(|(t, _)| *t == pair[1]) {
+            tags.push((&pair[1], None));
+        }
+    }
+
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, tok) in tokens.iter().enumerate() {
+        match tok.as_str() {
+            "{" => depth += 1,
+            "}" => depth -= 1,
+            ";" if depth == 0 => {
+                let stmt = &tokens[start..i];
+                start = i + 1;
+                let [typedef, strukt, tag, rest @ ..] = stmt else {
+                    continue;
+                };
+                if typedef != "typedef" || strukt != "struct" || !is_ident(tag) {
+                    continue;
+                }
+                let name = match rest {
+                    [name] if is_ident(name) => name,
+                    [open, .., close, name] if open == "{" && close == "}" && is_ident(name) => {
+                        name
+                    }
+                    _ => continue,
+                };
+                if let Some(entry) = tags.iter_mut().find(|(t, _)| t == tag) {
+                    entry.1.get_or_insert(name);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    tags.into_iter()
+        .map(|(tag, name)| match name {
+            Some(name) => format!("typedef struct {tag} {name};"),
+            None => format!("struct {tag};"),
+        })
+        .collect()
+}
+
+/// Determine whether an identifier is a type or limit macro defined by `<stdint.h>`, such as
+/// `uint32_t`, `int_least8_t`, `uintptr_t`, or `INT64_MAX`.
+fn is_stdint_name(ident: &str) -> bool {
+    let (base, lower) = if let Some(base) = ident.strip_suffix("_t") {
+        (base, true)
+    } else if let Some(base) = ident
+        .strip_suffix("_MAX")
+        .or_else(|| ident.strip_suffix("_MIN"))
+    {
+        (base, false)
+    } else {
+        return false;
+    };
+    let (int, least, fast) = if lower {
+        ("int", "int_least", "int_fast")
+    } else {
+        ("INT", "INT_LEAST", "INT_FAST")
+    };
+    let base = base
+        .strip_prefix(if lower { "u" } else { "U" })
+        .unwrap_or(base);
+    let Some(rest) = base.strip_prefix(int) else {
+        return false;
+    };
+    let width = base
+        .strip_prefix(least)
+        .or_else(|| base.strip_prefix(fast))
+        .unwrap_or(rest);
+    matches!(width, "8" | "16" | "32" | "64")
+        || (width == rest && matches!(rest, "ptr" | "max" | "PTR" | "MAX"))
+}
+
+/// Remove `//` and `/* .. */` comments from C content, preserving string and character literals.
+fn strip_comments(content: &str) -> String {
+    let mut result = String::with_capacity(content.len());
+    let mut chars = content.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '/' if chars.peek() == Some(&'/') => {
+                // skip to (but not past) the end of the line
+                while chars.peek().is_some_and(|&c| c != '\n') {
+                    chars.next();
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut prev = ' ';
+                for c in chars.by_ref() {
+                    // keep line structure, so that preprocessor directives remain on their own
+                    if c == '\n' {
+                        result.push('\n');
+                    }
+                    if prev == '*' && c == '/' {
+                        break;
+                    }
+                    prev = c;
+                }
+                result.push(' ');
+            }
+            '"' | '\'' => {
+                result.push(c);
+                while let Some(lc) = chars.next() {
+                    result.push(lc);
+                    if lc == '\\' {
+                        if let Some(escaped) = chars.next() {
+                            result.push(escaped);
+                        }
+                    } else if lc == c || lc == '\n' {
+                        break;
+                    }
+                }
+            }
+            _ => result.push(c),
+        }
+    }
+    result
+}
+
+/// Remove a leading `EXTERN_C` marker from a declaration line.
+fn strip_extern_c(line: &str) -> &str {
+    let trimmed = line.trim_start();
+    match trimmed.strip_prefix("EXTERN_C") {
+        Some(rest) if rest.starts_with(char::is_whitespace) => rest.trim_start(),
+        _ => line,
+    }
+}
+
+/// Split comment-free C code into tokens: identifiers and numbers, string literals, `...`, and
+/// single punctuation characters.
+fn tokenize(code: &str) -> Vec<String> {
+    let mut tokens = vec![];
+    let mut chars = code.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c.is_whitespace() {
+            continue;
+        }
+        let mut tok = String::from(c);
+        if c.is_alphanumeric() || c == '_' {
+            while let Some(&c) = chars.peek() {
+                if !(c.is_alphanumeric() || c == '_') {
+                    break;
+                }
+                tok.push(c);
+                chars.next();
+            }
+        } else if c == '"' || c == '\'' {
+            while let Some(lc) = chars.next() {
+                tok.push(lc);
+                if lc == '\\' {
+                    tok.extend(chars.next());
+                } else if lc == c {
+                    break;
+                }
+            }
+        } else if c == '.' && chars.peek() == Some(&'.') {
+            tok.extend(chars.next());
+            tok.extend(chars.next());
+        }
+        tokens.push(tok);
+    }
+    tokens
+}
+
+/// Determine whether a token is an identifier.
+fn is_ident(tok: &str) -> bool {
+    tok.starts_with(|c: char| c.is_alphabetic() || c == '_')
+}
+
+/// Parse a single statement, not including the trailing `;`.
+fn parse_statement(tokens: &[String]) -> Decl {
+    let unsupported = || Decl::Unsupported(tokens.join(" "));
+    let mut tokens = tokens;
+    while let Some(first) = tokens.first() {
+        if first == "extern" {
+            tokens = &tokens[1..];
+        } else {
+            break;
+        }
+    }
+
+    if tokens.first().map(String::as_str) == Some("typedef") {
+        let rest = &tokens[1..];
+        if let Some(open) = rest.iter().position(|t| t == "{") {
+            let Some(close) = rest.iter().rposition(|t| t == "}") else {
+                return unsupported();
+            };
+            let name = match &rest[close + 1..] {
+                [name] if is_ident(name) => name.clone(),
+                _ => return unsupported(),
+            };
+            let body = &rest[open + 1..close];
+            match rest[0].as_str() {
+                "struct" => match parse_fields(body) {
+                    Some(fields) => Decl::Struct { name, fields },
+                    None => unsupported(),
+                },
+                "enum" => match parse_variants(body) {
+                    Some(variants) => Decl::Enum { name, variants },
+                    None => unsupported(),
+                },
+                _ => unsupported(),
+            }
+        } else if rest.iter().any(|t| t == "(" || t == "[") {
+            unsupported()
+        } else {
+            match parse_field(rest) {
+                Some(Field { name, ty }) if ty.base.starts_with("struct ") && ty.pointers == 0 => {
+                    Decl::Opaque { name }
+                }
+                Some(Field { name, ty }) if !name.is_empty() => Decl::Typedef { name, ty },
+                _ => unsupported(),
+            }
+        }
+    } else if let Some(open) = tokens.iter().position(|t| t == "(") {
+        if tokens.last().map(String::as_str) != Some(")") || tokens.iter().any(|t| t == "{") {
+            return unsupported();
+        }
+        let Some(Field { name, ty: ret }) = parse_field(&tokens[..open]) else {
+            return unsupported();
+        };
+        if name.is_empty() {
+            return unsupported();
+        }
+        let args = &tokens[open + 1..tokens.len() - 1];
+        let mut params = vec![];
+        let mut variadic = false;
+        if !(args.is_empty() || (args.len() == 1 && args[0] == "void")) {
+            for (i, arg) in args.split(|t| t == ",").enumerate() {
+                if arg.len() == 1 && arg[0] == "..." {
+                    variadic = true;
+                    continue;
+                }
+                if arg.iter().any(|t| t == "(" || t == "[") {
+                    return unsupported();
+                }
+                let Some(mut param) = parse_field(arg) else {
+                    return unsupported();
+                };
+                if param.name.is_empty() {
+                    param.name = format!("arg{i}");
+                }
+                params.push(param);
+            }
+        }
+        Decl::Function(Function {
+            name,
+            ret,
+            params,
+            variadic,
+        })
+    } else {
+        unsupported()
+    }
+}
+
+/// Parse a type followed by an optional name, such as `const char *name`.  If there is no name,
+/// the returned name is empty.
+fn parse_field(tokens: &[String]) -> Option<Field> {
+    let (name, ty_tokens) = match tokens.split_last() {
+        Some((last, rest))
+            if is_ident(last)
+                && !TYPE_WORDS.contains(&last.as_str())
+                && rest.iter().any(|t| t != "const" && t != "*") =>
+        {
+            (last.clone(), rest)
+        }
+        _ => (String::new(), tokens),
+    };
+
+    let mut base = vec![];
+    let mut is_const = false;
+    let mut pointers = 0;
+    for tok in ty_tokens {
+        match tok.as_str() {
+            "*" => pointers += 1,
+            "const" => {
+                // `const` after a `*` qualifies the pointer, not the base type
+                if pointers == 0 {
+                    is_const = true;
+                }
+            }
+            "volatile" | "restrict" => {}
+            t if is_ident(t) && pointers == 0 => base.push(t),
+            _ => return None,
+        }
+    }
+    if base.is_empty() {
+        return None;
+    }
+    Some(Field {
+        name,
+        ty: CType {
+            base: base.join(" "),
+            is_const,
+            pointers,
+        },
+    })
+}
+
+/// Parse the fields in a struct body.
+fn parse_fields(body: &[String]) -> Option<Vec<Field>> {
+    let mut fields = vec![];
+    for field in body.split(|t| t == ";").filter(|f| !f.is_empty()) {
+        if field
+            .iter()
+            .any(|t| t == "(" || t == "[" || t == "{" || t == ",")
+        {
+            return None;
+        }
+        let field = parse_field(field)?;
+        if field.name.is_empty() {
+            return None;
+        }
+        fields.push(field);
+    }
+    Some(fields)
+}
+
+/// Parse the variants in an enum body.
+fn parse_variants(body: &[String]) -> Option<Vec<(String, Option<String>)>> {
+    let mut variants = vec![];
+    for variant in body.split(|t| t == ",").filter(|v| !v.is_empty()) {
+        match variant {
+            [name] if is_ident(name) => variants.push((name.clone(), None)),
+            [name, eq, value @ ..] if is_ident(name) && eq == "=" && !value.is_empty() => {
+                variants.push((name.clone(), Some(value.concat())))
+            }
+            _ => return None,
+        }
+    }
+    Some(variants)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_standard_headers() {
+        assert_eq!(
+            standard_headers(
+                "// Returns true if the size_t is ok.\n\
+                #include <stdint.h>\n\
+                #define FOO_MAX UINT32_MAX\n\
+                bool foo(int_fast16_t a, uintptr_t *b, size_t c);"
+            ),
+            vec!["<stdint.h>", "<stdbool.h>", "<stddef.h>"]
+        );
+        assert_eq!(
+            standard_headers("myint8_t foo(int_list_t a, int x, INT_COUNT_MAX y);"),
+            Vec::<&str>::new()
+        );
+    }
+
+    #[test]
+    fn test_forward_declarations() {
+        assert_eq!(
+            forward_declarations(
+                "// Uses struct bar_s.\n\
+                void foo_free(foo_t *foo, struct bar_s *bar);\n\
+                typedef struct foo_t foo_t;\n\
+                typedef struct pt { int x; struct bar_s *b; } pt_t;\n\
+                typedef struct opt *opt_p;"
+            ),
+            vec![
+                "struct bar_s;",
+                "typedef struct foo_t foo_t;",
+                "typedef struct pt pt_t;",
+                "struct opt;",
+            ]
+        );
+    }
+
+    fn ty(base: &str, is_const: bool, pointers: usize) -> CType {
+        CType {
+            base: base.into(),
+            is_const,
+            pointers,
+        }
+    }
+
+    fn field(name: &str, ty: CType) -> Field {
+        Field {
+            name: name.into(),
+            ty,
+        }
+    }
+
+    #[test]
+    fn test_strip_comments() {
+        assert_eq!(
+            strip_comments("int x; // an int\n/* a\nblock */ int y;\nchar *s = \"//\";"),
+            "int x; \n\n  int y;\nchar *s = \"//\";"
+        );
+    }
+
+    #[test]
+    fn test_strip_declarations() {
+        assert_eq!(
+            strip_declarations(
+                "#ifdef __cplusplus\n#define EXTERN_C \\\n  extern \"C\"\n#endif\n\
+                 // Add two numbers.\nEXTERN_C int add(int a, int b);\n\n"
+            ),
+            "int add(int a, int b);"
+        );
+    }
+
+    #[test]
+    fn test_doc_lines() {
+        assert_eq!(
+            doc_lines("// Add two\n// numbers.\n//\nint add(int a, int b);"),
+            vec!["Add two", "numbers.", ""]
+        );
+    }
+
+    #[test]
+    fn test_parse_function() {
+        assert_eq!(
+            parse("// Add.\nEXTERN_C const char *add(uint64_t left, struct foo * const *, ...);"),
+            vec![Decl::Function(Function {
+                name: "add".into(),
+                ret: ty("char", true, 1),
+                params: vec![
+                    field("left", ty("uint64_t", false, 0)),
+                    field("arg1", ty("struct foo", false, 2)),
+                ],
+                variadic: true,
+            })]
+        );
+    }
+
+    #[test]
+    fn test_parse_function_void() {
+        assert_eq!(
+            parse("void foo_free(void);\nunsigned int count();"),
+            vec![
+                Decl::Function(Function {
+                    name: "foo_free".into(),
+                    ret: ty("void", false, 0),
+                    params: vec![],
+                    variadic: false,
+                }),
+                Decl::Function(Function {
+                    name: "count".into(),
+                    ret: ty("unsigned int", false, 0),
+                    params: vec![],
+                    variadic: false,
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_typedefs() {
+        assert_eq!(
+            parse(
+                "typedef struct foo foo_t;\n\
+                 typedef uint32_t id_t;\n\
+                 typedef struct point_t {\n  int x;\n  const char *label;\n} point_t;\n\
+                 typedef enum { RED, GREEN = 1 << 2 } color_t;"
+            ),
+            vec![
+                Decl::Opaque {
+                    name: "foo_t".into()
+                },
+                Decl::Typedef {
+                    name: "id_t".into(),
+                    ty: ty("uint32_t", false, 0)
+                },
+                Decl::Struct {
+                    name: "point_t".into(),
+                    fields: vec![
+                        field("x", ty("int", false, 0)),
+                        field("label", ty("char", true, 1)),
+                    ]
+                },
+                Decl::Enum {
+                    name: "color_t".into(),
+                    variants: vec![("RED".into(), None), ("GREEN".into(), Some("1<<2".into()))],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_unsupported() {
+        assert_eq!(
+            parse("typedef void (*cb_t)(int);\nstatic inline int f() { return 1; }\nint arr[3];"),
+            vec![
+                Decl::Unsupported("typedef void ( * cb_t ) ( int )".into()),
+                Decl::Unsupported("static inline int f ( ) { return 1 ; }".into()),
+                Decl::Unsupported("int arr [ 3 ]".into()),
+            ]
+        );
+    }
+}
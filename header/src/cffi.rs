@@ -0,0 +1,124 @@
+use crate::cdecl::strip_declarations;
+use crate::{all_items, sort_items, HeaderItem};
+
+/// Generate declarations for the library suitable for Python's
+/// [cffi](https://cffi.readthedocs.io/) `FFI.cdef`.
+///
+/// This contains the same declarations as [`crate::generate`], but with comments, preprocessor
+/// directives, and `EXTERN_C` markers removed, as `cdef` does not support them.  Items with the
+/// `cpp_helper` property are omitted.
+pub fn generate_cffi() -> String {
+    cffi_from_vec(all_items())
+}
+
+/// Generate a small Python module that loads the named shared library with cffi, using the
+/// declarations from [`generate_cffi`].  The module exposes `ffi` and `lib` attributes.
+///
+/// The `library` is the name of the library as given to `ctypes.util.find_library`, such as
+/// `"mylib"` for `libmylib.so`.
+pub fn generate_cffi_loader(library: &str) -> String {
+    cffi_loader(library, &generate_cffi())
+}
+
+/// Inner version of generate_cffi that does not operate on a static value.
+fn cffi_from_vec(mut items: Vec<&HeaderItem>) -> String {
+    sort_items(&mut items);
+    let mut result = String::new();
+    for item in items.iter().filter(|hi| hi.in_bindings()) {
+        let decls = strip_declarations(item.content);
+        if decls.is_empty() {
+            continue;
+        }
+        if !result.is_empty() {
+            result.push('\n');
+        }
+        result.push_str(&decls);
+        result.push('\n');
+    }
+    result
+}
+
+/// Generate the Python loader module for the given cdef.
+fn cffi_loader(library: &str, cdef: &str) -> String {
+    format!(
+        r#"# Generated by ffizz-header; do not edit.
+import ctypes.util
+
+from cffi import FFI
+
+ffi = FFI()
+ffi.cdef(
+    r"""
+{cdef}"""
+)
+lib = ffi.dlopen(ctypes.util.find_library({library:?}) or {library:?})
+"#
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const ITEM: HeaderItem = HeaderItem {
+        order: 0,
+        module_path: "",
+        file: "",
+        line: 0,
+        name: "",
+        content: "",
+        cpp_content: None,
+        cpp_helper: false,
+        deprecated: None,
+        feature: None,
+        group: None,
+        platform: None,
+        since: None,
+        unstable: false,
+        enabled: true,
+        includes: &[],
+    };
+
+    #[test]
+    fn test_cffi_from_vec() {
+        assert_eq!(
+            cffi_from_vec(vec![
+                &HeaderItem {
+                    order: 2,
+                    name: "add",
+                    content: "// Add two numbers.\nint add(int a, int b);",
+                    ..ITEM
+                },
+                &HeaderItem {
+                    order: 1,
+                    name: "topmatter",
+                    content: "#include <stdint.h>",
+                    ..ITEM
+                },
+                &HeaderItem {
+                    order: 3,
+                    name: "add_cpp",
+                    content: "inline int add3(int a, int b, int c) { return add(add(a, b), c); }",
+                    cpp_helper: true,
+                    ..ITEM
+                },
+                &HeaderItem {
+                    order: 2,
+                    name: "point",
+                    content: "/* A point. */\ntypedef struct point_t {\n  int x; // x\n  int y; // y\n} point_t;",
+                    ..ITEM
+                },
+            ]),
+            String::from(
+                "int add(int a, int b);\n\ntypedef struct point_t {\n  int x;\n  int y;\n} point_t;\n"
+            )
+        );
+    }
+
+    #[test]
+    fn test_cffi_loader() {
+        let loader = cffi_loader("mylib", "int add(int a, int b);\n");
+        assert!(loader.contains("r\"\"\"\nint add(int a, int b);\n\"\"\""));
+        assert!(loader.contains("ffi.dlopen(ctypes.util.find_library(\"mylib\") or \"mylib\")"));
+    }
+}
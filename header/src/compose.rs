@@ -0,0 +1,123 @@
+/// A set of renames applied to the header items imported from another crate, for use with
+/// [`HeaderConfig::compose`](crate::HeaderConfig::compose).
+///
+/// Identifier renames apply to whole C identifiers, in declarations and comments alike, with an
+/// exact rename taking precedence over a prefix rename.  Text renames then replace any
+/// occurrence of the given text, and can be used to adjust documentation.
+///
+/// ```
+/// # use ffizz_header::RenameMap;
+/// let renames = RenameMap::new()
+///     .prefix("fz_", "mylib_")
+///     .ident("fz_string_t", "mylib_str_t")
+///     .text("ffizz-string", "mylib");
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct RenameMap {
+    prefixes: Vec<(String, String)>,
+    idents: Vec<(String, String)>,
+    texts: Vec<(String, String)>,
+}
+
+impl RenameMap {
+    /// Create a new, empty RenameMap.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Rename identifiers beginning with `from` to begin with `to` instead.  If several prefixes
+    /// match, the longest is used.
+    pub fn prefix(mut self, from: impl Into<String>, to: impl Into<String>) -> Self {
+        self.prefixes.push((from.into(), to.into()));
+        self.prefixes
+            .sort_by_key(|(from, _)| std::cmp::Reverse(from.len()));
+        self
+    }
+
+    /// Rename the identifier `from` to `to`.
+    pub fn ident(mut self, from: impl Into<String>, to: impl Into<String>) -> Self {
+        self.idents.push((from.into(), to.into()));
+        self
+    }
+
+    /// Replace each occurrence of the text `from` with `to`.
+    pub fn text(mut self, from: impl Into<String>, to: impl Into<String>) -> Self {
+        self.texts.push((from.into(), to.into()));
+        self
+    }
+
+    /// Apply the renames to an identifier.
+    pub(crate) fn rename_ident(&self, ident: &str) -> String {
+        if let Some((_, to)) = self.idents.iter().find(|(from, _)| from == ident) {
+            return to.clone();
+        }
+        match self
+            .prefixes
+            .iter()
+            .find(|(from, _)| ident.starts_with(from.as_str()))
+        {
+            Some((from, to)) => format!("{to}{}", &ident[from.len()..]),
+            None => ident.to_string(),
+        }
+    }
+
+    /// Apply the renames to header content.
+    pub(crate) fn apply(&self, content: &str) -> String {
+        let is_ident_char = |c: char| c.is_ascii_alphanumeric() || c == '_';
+        let mut result = String::with_capacity(content.len());
+        let mut rest = content;
+        while let Some(start) = rest.find(is_ident_char) {
+            result.push_str(&rest[..start]);
+            rest = &rest[start..];
+            let end = rest.find(|c| !is_ident_char(c)).unwrap_or(rest.len());
+            let word = &rest[..end];
+            if word.starts_with(|c: char| c.is_ascii_digit()) {
+                result.push_str(word);
+            } else {
+                result.push_str(&self.rename_ident(word));
+            }
+            rest = &rest[end..];
+        }
+        result.push_str(rest);
+
+        for (from, to) in &self.texts {
+            result = result.replace(from.as_str(), to);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_rename_ident() {
+        let renames = RenameMap::new()
+            .prefix("fz_", "mylib_")
+            .prefix("fz_string_", "mylib_str_")
+            .ident("fz_string_t", "mystr_t");
+        assert_eq!(renames.rename_ident("fz_string_t"), "mystr_t");
+        assert_eq!(renames.rename_ident("fz_string_free"), "mylib_str_free");
+        assert_eq!(renames.rename_ident("fz_error_t"), "mylib_error_t");
+        assert_eq!(renames.rename_ident("xfz_foo"), "xfz_foo");
+    }
+
+    #[test]
+    fn test_apply() {
+        let renames = RenameMap::new()
+            .prefix("fz_", "mylib_")
+            .prefix("FZ_", "MYLIB_")
+            .text("ffizz-string", "MyLib");
+        assert_eq!(
+            renames.apply(
+                "// Free a fz_string_t, allocated by ffizz-string.\n\
+                #define FZ_MAX 10\n\
+                void fz_string_free(fz_string_t *fz_str, int x2fz_);"
+            ),
+            "// Free a mylib_string_t, allocated by MyLib.\n\
+            #define MYLIB_MAX 10\n\
+            void mylib_string_free(mylib_string_t *mylib_str, int x2fz_);"
+        );
+    }
+}
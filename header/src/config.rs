@@ -0,0 +1,2005 @@
+use crate::cdecl::{forward_declarations, standard_headers};
+use crate::compose::RenameMap;
+use crate::format::{expand_tabs, format_utc, trim_trailing_whitespace, wrap_comments};
+use crate::multi::group_order;
+use crate::{all_items, sort_items_by, HeaderItem};
+use itertools::join;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// The language for which a header is generated.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Profile {
+    /// A C header.
+    #[default]
+    C,
+    /// A C++ header.  The declarations are wrapped in `extern "C" { .. }`, standard C includes are
+    /// replaced with their C++ equivalents (`<cstdint>` for `<stdint.h>`), and deprecated items
+    /// are marked with `[[deprecated]]`.  Items with the `cpp_helper` property are only included
+    /// in this profile.
+    Cpp,
+}
+
+/// The order of header items with equal `order` values.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SortOrder {
+    /// Sort by the path of the module defining the item, and then by name, so that items from the
+    /// same module are kept together.
+    #[default]
+    ModulePath,
+    /// Sort only by name.
+    Name,
+    /// Sort in the order the items appear in the Rust source: by crate, then by the path of the
+    /// defining file, then by line, and finally by name.  Items split from the same docstring
+    /// share a line, and are sorted by name.
+    Source,
+}
+
+/// The treatment of items marked `#[ffizz(unstable)]`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Unstable {
+    /// Include unstable items like any other.
+    #[default]
+    Include,
+    /// Include unstable items, preceded by a comment warning that they may change.
+    Annotate,
+    /// Omit unstable items.
+    Exclude,
+}
+
+/// HeaderConfig controls the generation of a header.
+///
+/// The default configuration generates the same header as [`crate::generate`].
+///
+/// ```
+/// # use ffizz_header::{HeaderConfig, Profile};
+/// let header = HeaderConfig::new()
+///     .profile(Profile::Cpp)
+///     .cpp_namespace("mylib")
+///     .generate();
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct HeaderConfig {
+    profile: Profile,
+    sort_order: SortOrder,
+    unstable: Unstable,
+    cpp_namespace: Option<String>,
+    feature_guards: Option<String>,
+    export_macro: Option<String>,
+    extern_c_guards: bool,
+    forward_declarations: bool,
+    manual_includes: bool,
+    banner: Option<(String, String)>,
+    no_timestamp: bool,
+    source_locations: bool,
+    comment_width: Option<usize>,
+    tab_width: Option<usize>,
+    trim_whitespace: bool,
+    vars: HashMap<String, String>,
+    compose: Vec<(String, RenameMap)>,
+}
+
+impl HeaderConfig {
+    /// Create a new HeaderConfig with the default settings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the language profile for the generated header.
+    pub fn profile(mut self, profile: Profile) -> Self {
+        self.profile = profile;
+        self
+    }
+
+    /// Set the order of items with equal `order` values.
+    pub fn sort_order(mut self, sort_order: SortOrder) -> Self {
+        self.sort_order = sort_order;
+        self
+    }
+
+    /// Set the treatment of items marked `#[ffizz(unstable)]`.
+    pub fn unstable(mut self, unstable: Unstable) -> Self {
+        self.unstable = unstable;
+        self
+    }
+
+    /// In the C++ profile, wrap items with the `cpp_helper` property in `namespace <name> { .. }`.
+    pub fn cpp_namespace(mut self, name: impl Into<String>) -> Self {
+        self.cpp_namespace = Some(name.into());
+        self
+    }
+
+    /// Include all items defined with `#[ffizz(feature = "..")]`, regardless of whether the
+    /// feature is enabled, with each wrapped in `#if defined(<PREFIX>_FEATURE_<FEATURE>)`.  This
+    /// allows a single header to be distributed for builds of the library with any combination of
+    /// features, with the user defining the corresponding macros.
+    ///
+    /// For example, with prefix `MYLIB`, an item with `feature = "sync"` is guarded by
+    /// `#if defined(MYLIB_FEATURE_SYNC)`.
+    pub fn feature_guards(mut self, prefix: impl Into<String>) -> Self {
+        self.feature_guards = Some(prefix.into());
+        self
+    }
+
+    /// Define a macro with the given name, such as `MYLIB_EXPORT`, that marks a declaration as
+    /// exported from the library, and prefix each function declaration with it.
+    ///
+    /// On Windows, the macro expands to `__declspec(dllexport)` when building the library and
+    /// `__declspec(dllimport)` otherwise.  The library's build indicates that it is building the
+    /// library by defining a macro named with `_BUILDING` in place of any `_EXPORT` suffix, such
+    /// as `MYLIB_BUILDING`.  With GCC and Clang, the macro sets default symbol visibility.
+    pub fn export_macro(mut self, name: impl Into<String>) -> Self {
+        self.export_macro = Some(name.into());
+        self
+    }
+
+    /// Follow each item with a comment giving the location of its definition in the Rust source,
+    /// such as `/* from src/strings.rs:120 */`.
+    pub fn source_locations(mut self, enabled: bool) -> Self {
+        self.source_locations = enabled;
+        self
+    }
+
+    /// Begin the header with a banner comment naming the crate and version from which it was
+    /// generated, typically given as `env!("CARGO_PKG_NAME")` and `env!("CARGO_PKG_VERSION")`:
+    ///
+    /// ```text
+    /// // Generated by ffizz-header 0.5.0 from mylib v1.2.3 at 2024-02-29 13:05:00 UTC; do not edit.
+    /// ```
+    ///
+    /// The time is taken from the `SOURCE_DATE_EPOCH` environment variable, if set, for
+    /// reproducible builds.
+    pub fn banner(mut self, krate: impl Into<String>, version: impl Into<String>) -> Self {
+        self.banner = Some((krate.into(), version.into()));
+        self
+    }
+
+    /// Include the time of generation in the banner.  This is enabled by default, and can be
+    /// disabled so that the header only changes when its content does.
+    pub fn timestamp(mut self, enabled: bool) -> Self {
+        self.no_timestamp = !enabled;
+        self
+    }
+
+    /// Wrap the declarations in C headers in `extern "C" { .. }`, guarded by
+    /// `#ifdef __cplusplus`, so that the header can also be included from C++.  The guards follow
+    /// the include guards and `#include`s.  Headers generated with the C++ profile always wrap
+    /// the declarations in `extern "C"`.
+    pub fn extern_c_guards(mut self, enabled: bool) -> Self {
+        self.extern_c_guards = enabled;
+        self
+    }
+
+    /// Declare the struct tags referenced by the header's declarations before any of those
+    /// declarations, so that the header compiles even if an item uses a type such as `foo_t *`
+    /// before the item defining `foo_t`.  Tags given a typedef name, as by
+    /// `typedef struct foo_t foo_t;`, are declared along with that typedef, which repeats the
+    /// typedef and thus requires C11 or C++.
+    pub fn forward_declarations(mut self, enabled: bool) -> Self {
+        self.forward_declarations = enabled;
+        self
+    }
+
+    /// Automatically `#include` the standard headers, such as `<stdint.h>`, `<stdbool.h>`, and
+    /// `<stddef.h>`, defining the types and macros used in the header's declarations.  This is
+    /// enabled by default.  The `#include`s are placed with those given by `#[ffizz(include=..)]`,
+    /// and are omitted if already present in the preamble.
+    pub fn auto_includes(mut self, enabled: bool) -> Self {
+        self.manual_includes = !enabled;
+        self
+    }
+
+    /// Re-wrap paragraphs of `//` comments containing lines longer than the given number of
+    /// columns.  Lists, headings, indented lines, and fenced blocks in the comments are kept
+    /// intact.
+    pub fn comment_width(mut self, width: usize) -> Self {
+        self.comment_width = Some(width);
+        self
+    }
+
+    /// Expand tabs to spaces, with tab stops every `width` columns.
+    pub fn tab_width(mut self, width: usize) -> Self {
+        self.tab_width = Some(width);
+        self
+    }
+
+    /// Remove trailing whitespace from comments and declarations.
+    pub fn trim_trailing_whitespace(mut self, enabled: bool) -> Self {
+        self.trim_whitespace = enabled;
+        self
+    }
+
+    /// Apply `renames` to the items defined in the crate `krate`, such as an upstream crate whose
+    /// items should appear with this library's prefix.  Where a renamed item has the same name as
+    /// an item from another crate, only the other item is included, allowing this library to
+    /// replace upstream items with its own.
+    ///
+    /// ```
+    /// # use ffizz_header::{HeaderConfig, RenameMap};
+    /// let header = HeaderConfig::new()
+    ///     .compose("ffizz-string", RenameMap::new().prefix("fz_", "mylib_"))
+    ///     .generate();
+    /// ```
+    pub fn compose(mut self, krate: impl AsRef<str>, renames: RenameMap) -> Self {
+        self.compose
+            .push((krate.as_ref().replace('-', "_"), renames));
+        self
+    }
+
+    /// Substitute `value` for each occurrence of `{{name}}` in the header content.  This is
+    /// useful for values that appear in many docstrings, such as a symbol prefix or the crate
+    /// version.  Placeholders for names without a value are left as-is.
+    pub fn var(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.vars.insert(name.into(), value.into());
+        self
+    }
+
+    /// Substitute values for a collection of `{{name}}` placeholders, as for [`HeaderConfig::var`].
+    pub fn vars<K, V>(mut self, vars: impl IntoIterator<Item = (K, V)>) -> Self
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.vars
+            .extend(vars.into_iter().map(|(k, v)| (k.into(), v.into())));
+        self
+    }
+
+    /// Generate the header for the library.
+    pub fn generate(&self) -> String {
+        self.generate_from_vec(all_items())
+    }
+
+    /// Generate the header for the library, containing only the items for which `filter` returns
+    /// true.  For example, this can produce a header omitting experimental items, or containing
+    /// only one group.  Note that the filter also applies to snippets such as include guards.
+    pub fn generate_with_filter(&self, filter: impl Fn(&HeaderItem) -> bool) -> String {
+        self.generate_from_vec(all_items().into_iter().filter(|hi| filter(hi)).collect())
+    }
+
+    /// Generate the header for the library, passing each item and its rendered content to `post`
+    /// and including the result in place of the content.  This can, for example, add annotations
+    /// to each declaration or rewrite type names.
+    ///
+    /// ```
+    /// # use ffizz_header::HeaderConfig;
+    /// let header = HeaderConfig::new().generate_with(|item, rendered| {
+    ///     if item.deprecated.is_some() {
+    ///         format!("// ACME: scheduled for removal\n{rendered}")
+    ///     } else {
+    ///         rendered
+    ///     }
+    /// });
+    /// ```
+    pub fn generate_with(&self, post: impl Fn(&HeaderItem, String) -> String) -> String {
+        let items = self.select_items(all_items());
+        self.render_with(&items, vec![], None, &post)
+    }
+
+    /// Inner version of generate that does not operate on a static value.
+    pub(crate) fn generate_from_vec(&self, items: Vec<&HeaderItem>) -> String {
+        let items = self.select_items(items);
+        self.render(&items, vec![], None)
+    }
+
+    /// Generate the header for the library from a [minijinja](https://docs.rs/minijinja) template,
+    /// giving full control over its layout.  This requires the `template` feature.
+    ///
+    /// The template can use:
+    ///  * `items()`, the rendered items, separated by blank lines, optionally limited to a group
+    ///    or feature with `items(group="strings")` or `items(feature="sync")`;
+    ///  * `includes`, the block of `#include`s required by the items;
+    ///  * `banner`, the banner comment, if configured with [`HeaderConfig::banner`]; and
+    ///  * each variable defined with [`HeaderConfig::var`].
+    ///
+    /// Items are rendered as for [`HeaderConfig::generate`], but the header contains only what
+    /// the template produces, so snippets such as include guards are better written in the
+    /// template itself.
+    ///
+    /// ```
+    /// # use ffizz_header::HeaderConfig;
+    /// let header = HeaderConfig::new()
+    ///     .var("version", "1.2.3")
+    ///     .generate_from_template(
+    ///         "// MyLib {{ version }}\n#pragma once\n{{ includes }}\n\n{{ items() }}\n",
+    ///     )
+    ///     .unwrap();
+    /// ```
+    #[cfg(feature = "template")]
+    pub fn generate_from_template(&self, template: &str) -> Result<String, minijinja::Error> {
+        self.generate_from_template_vec(all_items(), template)
+    }
+
+    /// Inner version of generate_from_template that does not operate on a static value.
+    #[cfg(feature = "template")]
+    pub(crate) fn generate_from_template_vec(
+        &self,
+        items: Vec<&HeaderItem>,
+        template: &str,
+    ) -> Result<String, minijinja::Error> {
+        use minijinja::value::{Kwargs, Value};
+
+        let items: Vec<&HeaderItem> = self
+            .select_items(items)
+            .into_iter()
+            .filter(|hi| self.profile == Profile::Cpp || !hi.cpp_helper)
+            .collect();
+        let mut context: HashMap<String, Value> = self
+            .vars
+            .iter()
+            .map(|(k, v)| (k.clone(), Value::from(v.as_str())))
+            .collect();
+        context.insert(
+            "includes".into(),
+            self.render_includes(&items, &[]).unwrap_or_default().into(),
+        );
+        if let Some(banner) = self.render_banner(generation_time()) {
+            context.insert("banner".into(), banner.into());
+        }
+
+        let rendered: Vec<(Option<String>, Option<String>, String)> = items
+            .iter()
+            .map(|hi| {
+                (
+                    hi.group.map(String::from),
+                    hi.feature.map(String::from),
+                    self.render_item(hi),
+                )
+            })
+            .collect();
+        let mut env = minijinja::Environment::new();
+        env.set_keep_trailing_newline(true);
+        env.add_function("items", move |kwargs: Kwargs| {
+            let group: Option<String> = kwargs.get("group")?;
+            let feature: Option<String> = kwargs.get("feature")?;
+            kwargs.assert_all_used()?;
+            let matching = rendered
+                .iter()
+                .filter(|(g, _, _)| group.is_none() || *g == group)
+                .filter(|(_, f, _)| feature.is_none() || *f == feature)
+                .map(|(_, _, content)| content);
+            Ok::<_, minijinja::Error>(join(matching, "\n\n"))
+        });
+        env.render_str(template, context)
+    }
+
+    /// Generate the header for the library as multiple files, with a header for each group of
+    /// items defined with `#[ffizz(group = "..")]`.  The result contains the name and content of
+    /// each file.
+    ///
+    /// The first file, `<name>.h`, is an umbrella header containing the items without a group,
+    /// with `#include`s of each group's header following its preamble (include guards and
+    /// `#include`s).  The group headers are named `<name>_<group>.h`, and are included in an order
+    /// such that each follows the groups declaring the types and macros it uses.  Each group
+    /// header has its own include guard, repeats the `#include`s from the umbrella header's
+    /// preamble, and includes the group headers it depends on.  Consumers can include either
+    /// the umbrella header or individual group headers.
+    pub fn generate_multi(&self, name: &str) -> Vec<(String, String)> {
+        self.generate_multi_from_vec(all_items(), name)
+    }
+
+    /// Generate the header for the library as a base header and a fragment for each of the given
+    /// Cargo features.  Each fragment contains only the items gated on that feature with
+    /// `#[ffizz(feature = "..")]`, and the base header contains the remaining items.  The result
+    /// maps each feature to its fragment, and the empty string to the base header.
+    ///
+    /// Fragments have no include guards or preamble of their own, and are intended to be
+    /// included after the base header, such as from a `mylib_async.h` that includes `mylib.h`.
+    /// Items gated on features that are not enabled are omitted, unless feature guards are
+    /// configured.
+    pub fn generate_for_features(&self, features: &[&str]) -> HashMap<String, String> {
+        self.generate_for_features_from_vec(all_items(), features)
+    }
+
+    /// Inner version of generate_for_features that does not operate on a static value.
+    pub(crate) fn generate_for_features_from_vec(
+        &self,
+        items: Vec<&HeaderItem>,
+        features: &[&str],
+    ) -> HashMap<String, String> {
+        let items = self.select_items(items);
+        let mut result = HashMap::new();
+        for feature in features {
+            let fragment: Vec<&HeaderItem> = items
+                .iter()
+                .filter(|hi| hi.feature == Some(feature))
+                .copied()
+                .collect();
+            result.insert(feature.to_string(), self.render(&fragment, vec![], None));
+        }
+        let base: Vec<&HeaderItem> = items
+            .into_iter()
+            .filter(|hi| !hi.feature.is_some_and(|f| features.contains(&f)))
+            .collect();
+        result.insert(String::new(), self.render(&base, vec![], None));
+        result
+    }
+
+    /// Inner version of generate_multi that does not operate on a static value.
+    pub(crate) fn generate_multi_from_vec(
+        &self,
+        items: Vec<&HeaderItem>,
+        name: &str,
+    ) -> Vec<(String, String)> {
+        let (grouped, ungrouped): (Vec<&HeaderItem>, Vec<&HeaderItem>) = self
+            .select_items(items)
+            .into_iter()
+            .partition(|hi| hi.group.is_some());
+        let mut groups: Vec<(&str, Vec<&HeaderItem>)> = vec![];
+        for item in grouped {
+            let group = item.group.unwrap();
+            match groups.iter_mut().find(|(g, _)| *g == group) {
+                Some((_, items)) => items.push(item),
+                None => groups.push((group, vec![item])),
+            }
+        }
+        let order = group_order(&groups);
+        let file_name = |group: &str| format!("{name}_{group}.h");
+        let include = |group: &str| format!("#include \"{}\"", file_name(group));
+
+        // the umbrella header's preamble includes are repeated in each group header
+        let chunks = ungrouped
+            .iter()
+            .filter(|hi| !hi.cpp_helper)
+            .map(|hi| self.render_item(hi))
+            .collect();
+        let (preamble, _, _) = split_preamble(chunks);
+        let common_includes: Vec<String> = preamble
+            .iter()
+            .flat_map(|chunk| chunk.lines())
+            .filter(|line| is_include(line))
+            .map(String::from)
+            .collect();
+
+        let mut files = vec![];
+        let group_includes: Vec<String> =
+            order.iter().map(|(i, _)| include(groups[*i].0)).collect();
+        let extra = if group_includes.is_empty() {
+            vec![]
+        } else {
+            vec![join(group_includes, "\n")]
+        };
+        files.push((format!("{name}.h"), self.render(&ungrouped, extra, None)));
+
+        for (i, deps) in order {
+            let (group, items) = &groups[i];
+            let mut includes = common_includes.clone();
+            includes.extend(deps.iter().map(|d| include(groups[*d].0)));
+            let extra = if includes.is_empty() {
+                vec![]
+            } else {
+                vec![join(includes, "\n")]
+            };
+            let guard = format!("{}_H", macro_name(&format!("{name}_{group}")));
+            files.push((file_name(group), self.render(items, extra, Some(&guard))));
+        }
+        files
+    }
+
+    /// Generate a `meson.build` snippet declaring a dependency on the library, named `library`,
+    /// and its header, both of which are expected in the same directory as the snippet.
+    ///
+    /// The dependency is declared as `<library>_dep` and registered with
+    /// `meson.override_dependency`, so that it can be found with `dependency('<library>')` from
+    /// a subproject.  Its version is that given to [`HeaderConfig::banner`], if any, and with
+    /// [`HeaderConfig::feature_guards`], its `compile_args` define the macros for each enabled
+    /// feature.
+    pub fn generate_meson(&self, library: &str) -> String {
+        self.meson_from_vec(all_items(), library)
+    }
+
+    /// Inner version of generate_meson that does not operate on a static value.
+    pub(crate) fn meson_from_vec(&self, items: Vec<&HeaderItem>, library: &str) -> String {
+        let compiler = match self.profile {
+            Profile::C => "c",
+            Profile::Cpp => "cpp",
+        };
+        let var = format!("{}_dep", macro_name(library).to_lowercase());
+        let mut out = String::new();
+        writeln!(out, "# Generated by ffizz-header; do not edit.").unwrap();
+        writeln!(out, "{var} = declare_dependency(").unwrap();
+        writeln!(out, "  include_directories: include_directories('.'),").unwrap();
+        writeln!(
+            out,
+            "  dependencies: meson.get_compiler('{compiler}').find_library('{library}', dirs: meson.current_source_dir()),"
+        )
+        .unwrap();
+        if let Some(prefix) = &self.feature_guards {
+            let mut args: Vec<String> = items
+                .iter()
+                .filter(|hi| hi.enabled)
+                .filter_map(|hi| hi.feature)
+                .map(|f| format!("'-D{}'", feature_macro(prefix, f)))
+                .collect();
+            args.sort();
+            args.dedup();
+            if !args.is_empty() {
+                writeln!(out, "  compile_args: [{}],", join(args, ", ")).unwrap();
+            }
+        }
+        if let Some((_, version)) = &self.banner {
+            writeln!(out, "  version: '{version}',").unwrap();
+        }
+        writeln!(out, ")").unwrap();
+        writeln!(out, "meson.override_dependency('{library}', {var})").unwrap();
+        out
+    }
+
+    /// Sort the given items, omitting any that should not be included in the header.
+    fn select_items<'a>(&self, mut items: Vec<&'a HeaderItem>) -> Vec<&'a HeaderItem> {
+        sort_items_by(&mut items, self.sort_order);
+        if self.feature_guards.is_none() {
+            items.retain(|hi| hi.enabled);
+        }
+        if self.unstable == Unstable::Exclude {
+            items.retain(|hi| !hi.unstable);
+        }
+        if !self.compose.is_empty() {
+            // renamed items are replaced by other items of the same name
+            let names: Vec<&str> = items
+                .iter()
+                .filter(|hi| self.renames(hi).is_none())
+                .map(|hi| hi.name)
+                .collect();
+            items.retain(|hi| match self.renames(hi) {
+                Some(renames) => !names.contains(&renames.rename_ident(hi.name).as_str()),
+                None => true,
+            });
+        }
+        items
+    }
+
+    /// Get the renames to apply to the given item, if it was defined in a composed crate.
+    fn renames(&self, item: &HeaderItem) -> Option<&RenameMap> {
+        let krate = item.module_path.split("::").next().unwrap_or("");
+        self.compose
+            .iter()
+            .find(|(k, _)| k == krate)
+            .map(|(_, renames)| renames)
+    }
+
+    /// Render a header containing the given items.  The `extra` chunks follow the preamble of the
+    /// items, and if `guard` is given, the header is wrapped in an include guard using that macro.
+    fn render(&self, items: &[&HeaderItem], extra: Vec<String>, guard: Option<&str>) -> String {
+        self.render_with(items, extra, guard, &|_, rendered| rendered)
+    }
+
+    /// Render a header as for [`HeaderConfig::render`], passing each rendered item through `post`.
+    fn render_with(
+        &self,
+        items: &[&HeaderItem],
+        extra: Vec<String>,
+        guard: Option<&str>,
+        post: &dyn Fn(&HeaderItem, String) -> String,
+    ) -> String {
+        let (helpers, items): (Vec<&HeaderItem>, Vec<&HeaderItem>) =
+            items.iter().copied().partition(|hi| hi.cpp_helper);
+        let chunks: Vec<String> = items
+            .iter()
+            .map(|hi| post(hi, self.render_item(hi)))
+            .collect();
+        let (preamble, body, postamble) = split_preamble(chunks);
+
+        let mut result: Vec<String> = vec![];
+        if let Some(banner) = self.render_banner(generation_time()) {
+            result.push(banner);
+        }
+        if let Some(guard) = guard {
+            result.push(format!("#ifndef {guard}\n#define {guard}"));
+        }
+        result.extend(preamble);
+        result.extend(extra);
+        if let Some(export) = &self.export_macro {
+            result.push(export_macro_definition(export));
+        }
+        let included = match self.profile {
+            Profile::C => items.clone(),
+            Profile::Cpp => items.iter().chain(helpers.iter()).copied().collect(),
+        };
+        if let Some(includes) = self.render_includes(&included, &result) {
+            result.push(includes);
+        }
+        if self.forward_declarations {
+            let decls = forward_declarations(&join(&body, "\n"));
+            if !decls.is_empty() {
+                result.push(join(decls, "\n"));
+            }
+        }
+        match self.profile {
+            Profile::C => {
+                if self.extern_c_guards && !body.is_empty() {
+                    result.push("#ifdef __cplusplus\nextern \"C\" {\n#endif".into());
+                    result.extend(body);
+                    result.push("#ifdef __cplusplus\n} // extern \"C\"\n#endif".into());
+                } else {
+                    result.extend(body);
+                }
+            }
+            Profile::Cpp => {
+                if !body.is_empty() {
+                    result.push("extern \"C\" {".into());
+                    result.extend(body);
+                    result.push("} // extern \"C\"".into());
+                }
+                if !helpers.is_empty() {
+                    if let Some(ns) = &self.cpp_namespace {
+                        result.push(format!("namespace {ns} {{"));
+                    }
+                    result.extend(helpers.iter().map(|hi| post(hi, self.render_item(hi))));
+                    if let Some(ns) = &self.cpp_namespace {
+                        result.push(format!("}} // namespace {ns}"));
+                    }
+                }
+            }
+        }
+        result.extend(postamble);
+        if let Some(guard) = guard {
+            result.push(format!("#endif // {guard}"));
+        }
+
+        // join the items with blank lines
+        let mut result = join(result, "\n\n");
+        // and ensure a trailing newline
+        if !result.is_empty() {
+            result.push('\n');
+        }
+        result
+    }
+
+    /// Render the banner comment, if configured, with the given time of generation.
+    fn render_banner(&self, time: u64) -> Option<String> {
+        let (krate, version) = self.banner.as_ref()?;
+        let mut banner = format!(
+            "// Generated by ffizz-header {} from {krate} v{version}",
+            env!("CARGO_PKG_VERSION")
+        );
+        if !self.no_timestamp {
+            banner.push_str(&format!(" at {}", format_utc(time)));
+        }
+        banner.push_str("; do not edit.");
+        Some(banner)
+    }
+
+    /// Render a single item's content for this configuration.
+    fn render_item(&self, item: &HeaderItem) -> String {
+        let mut content = self.render_content(item);
+        if let Some(renames) = self.renames(item) {
+            content = renames.apply(&content);
+        }
+        let mut content = self.substitute(&content);
+        if let Some(width) = self.tab_width {
+            content = expand_tabs(&content, width);
+        }
+        if self.trim_whitespace {
+            content = trim_trailing_whitespace(&content);
+        }
+        if let Some(width) = self.comment_width {
+            content = wrap_comments(&content, width);
+        }
+        if self.source_locations && !item.file.is_empty() {
+            content.push_str(&format!("\n/* from {}:{} */", item.file, item.line));
+        }
+        if item.unstable && self.unstable == Unstable::Annotate {
+            content = format!(
+                "// UNSTABLE: this item is not covered by the API stability guarantees, and may \
+                change or be removed.\n{content}"
+            );
+        }
+        if let Some(cond) = item.platform.and_then(platform_condition) {
+            content = format!("#if {cond}\n{content}\n#endif");
+        }
+        match (&self.feature_guards, item.feature) {
+            (Some(prefix), Some(feature)) => {
+                let guard = feature_macro(prefix, feature);
+                format!("#if defined({guard})\n{content}\n#endif // {guard}")
+            }
+            _ => content,
+        }
+    }
+
+    /// Render the `#include`s requested by the given items, and any standard headers they require,
+    /// as a single chunk, omitting any already present in the preamble.
+    fn render_includes(&self, items: &[&HeaderItem], preamble: &[String]) -> Option<String> {
+        let mut headers: Vec<&str> = items
+            .iter()
+            .flat_map(|hi| hi.includes.iter())
+            .copied()
+            .collect();
+        if !self.manual_includes {
+            for item in items {
+                let content = match self.profile {
+                    Profile::C => item.content,
+                    Profile::Cpp => item.cpp_content.unwrap_or(item.content),
+                };
+                headers.extend(
+                    standard_headers(content)
+                        .into_iter()
+                        // bool is built in to C++
+                        .filter(|h| self.profile == Profile::C || *h != "<stdbool.h>"),
+                );
+            }
+        }
+        let mut includes: Vec<String> = headers
+            .into_iter()
+            .map(|header| {
+                let include = format!("#include {header}");
+                match self.profile {
+                    Profile::C => include,
+                    Profile::Cpp => cpp_include(&include).unwrap_or(include),
+                }
+            })
+            .collect();
+        includes.sort();
+        includes.dedup();
+        includes.retain(|include| {
+            !preamble
+                .iter()
+                .flat_map(|chunk| chunk.lines())
+                .any(|line| line.split("//").next().unwrap_or("").trim() == include)
+        });
+        if includes.is_empty() {
+            None
+        } else {
+            Some(join(includes, "\n"))
+        }
+    }
+
+    /// Replace `{{name}}` placeholders in the given content with the configured values.
+    fn substitute(&self, content: &str) -> String {
+        if self.vars.is_empty() {
+            return content.to_string();
+        }
+        let mut result = String::with_capacity(content.len());
+        let mut rest = content;
+        while let Some(start) = rest.find("{{") {
+            let Some(len) = rest[start..].find("}}") else {
+                break;
+            };
+            let placeholder = &rest[start..start + len + 2];
+            result.push_str(&rest[..start]);
+            match self.vars.get(placeholder[2..len].trim()) {
+                Some(value) => result.push_str(value),
+                None => result.push_str(placeholder),
+            }
+            rest = &rest[start + len + 2..];
+        }
+        result.push_str(rest);
+        result
+    }
+
+    /// Render a single item's content for this configuration's profile.
+    fn render_content(&self, item: &HeaderItem) -> String {
+        let content = match self.profile {
+            Profile::C => item.content,
+            Profile::Cpp => item.cpp_content.unwrap_or(item.content),
+        }
+        .trim();
+        let content = match &self.export_macro {
+            Some(export) if !item.cpp_helper => export_functions(content, export),
+            _ => content.to_string(),
+        };
+        match self.profile {
+            Profile::C => content,
+            Profile::Cpp => {
+                let mut deprecated = item.deprecated.map(|note| {
+                    if note.is_empty() {
+                        "[[deprecated]] ".to_string()
+                    } else {
+                        let note = note.replace('\\', "\\\\").replace('"', "\\\"");
+                        format!("[[deprecated(\"{note}\")]] ")
+                    }
+                });
+                let lines = content.lines().map(|line| {
+                    if let Some(include) = cpp_include(line) {
+                        return include;
+                    }
+                    if !is_comment_or_preprocessor(line) && !line.trim().is_empty() {
+                        // prefix the first declaration line with the deprecation attribute
+                        if let Some(attr) = deprecated.take() {
+                            return format!("{attr}{line}");
+                        }
+                    }
+                    line.to_string()
+                });
+                join(lines, "\n")
+            }
+        }
+    }
+}
+
+/// Define the export macro with the given name.
+fn export_macro_definition(export: &str) -> String {
+    let building = export.strip_suffix("_EXPORT").unwrap_or(export);
+    let building = format!("{building}_BUILDING");
+    format!(
+        "#ifndef {export}\n\
+        #if defined(_WIN32) || defined(__CYGWIN__)\n\
+        #  if defined({building})\n\
+        #    define {export} __declspec(dllexport)\n\
+        #  else\n\
+        #    define {export} __declspec(dllimport)\n\
+        #  endif\n\
+        #elif defined(__GNUC__) || defined(__clang__)\n\
+        #  define {export} __attribute__((visibility(\"default\")))\n\
+        #else\n\
+        #  define {export}\n\
+        #endif\n\
+        #endif // {export}"
+    )
+}
+
+/// Prefix each function declaration in the given content with the export macro.
+fn export_functions(content: &str, export: &str) -> String {
+    let mut lines: Vec<String> = vec![];
+    // the index of the first line of the current statement, and its text so far
+    let mut stmt_start = 0;
+    let mut stmt = String::new();
+    let mut depth = 0i32;
+    for line in content.lines() {
+        if stmt.is_empty() && (is_comment_or_preprocessor(line) || line.trim().is_empty()) {
+            lines.push(line.to_string());
+            continue;
+        }
+        if stmt.is_empty() {
+            stmt_start = lines.len();
+        }
+        lines.push(line.to_string());
+        let code = line.split("//").next().unwrap_or("");
+        stmt.push_str(code);
+        stmt.push(' ');
+        depth += code.matches('{').count() as i32 - code.matches('}').count() as i32;
+        let end = stmt.trim_end();
+        if depth <= 0 && (end.ends_with(';') || end.ends_with('}')) {
+            if is_function_declaration(&stmt) {
+                let line = &lines[stmt_start];
+                let indent = line.len() - line.trim_start().len();
+                if !line[indent..].starts_with(export) {
+                    lines[stmt_start] = format!("{}{export} {}", &line[..indent], &line[indent..]);
+                }
+            }
+            stmt.clear();
+            depth = 0;
+        }
+    }
+    join(lines, "\n")
+}
+
+/// Determine whether a C statement declares (but does not define) a function.
+fn is_function_declaration(stmt: &str) -> bool {
+    let Some((before, _)) = stmt.split_once('(') else {
+        return false;
+    };
+    let words: Vec<&str> = before.split_whitespace().collect();
+    let is_ident = |w: &str| {
+        !w.is_empty()
+            && w.chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '*')
+            && !w.chars().all(|c| c == '*')
+    };
+    // a return type and a name, or a name with a leading `*`
+    let has_type = words.len() >= 2 || words.first().is_some_and(|w| w.starts_with('*'));
+    has_type
+        && words.last().is_some_and(|w| is_ident(w))
+        && !matches!(words[0], "typedef" | "static" | "inline" | "extern")
+        && !stmt.contains('{')
+        && !before.contains('=')
+}
+
+/// Get the name of the macro guarding items for the given feature.
+fn feature_macro(prefix: &str, feature: &str) -> String {
+    format!("{prefix}_FEATURE_{}", macro_name(feature))
+}
+
+/// Convert a name to the form used in a macro name, in upper case with only alphanumeric
+/// characters and underscores.
+fn macro_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Determine whether a line of header content is an `#include` directive.
+fn is_include(line: &str) -> bool {
+    line.trim_start()
+        .strip_prefix('#')
+        .is_some_and(|rest| rest.trim_start().starts_with("include"))
+}
+
+/// Determine whether a line of header content is a comment or a preprocessor directive.
+fn is_comment_or_preprocessor(line: &str) -> bool {
+    let line = line.trim_start();
+    line.starts_with("//") || line.starts_with("/*") || line.starts_with('#')
+}
+
+/// Split rendered items into a preamble, body, and postamble.  The preamble consists of the
+/// leading items containing only comments and preprocessor directives, such as include guards
+/// and `#include`s; the postamble similarly consists of such trailing items.
+fn split_preamble(mut chunks: Vec<String>) -> (Vec<String>, Vec<String>, Vec<String>) {
+    let is_preamble = |chunk: &String| {
+        chunk
+            .lines()
+            .all(|line| line.trim().is_empty() || is_comment_or_preprocessor(line))
+    };
+    let body_start = chunks
+        .iter()
+        .position(|c| !is_preamble(c))
+        .unwrap_or(chunks.len());
+    let body_end = chunks
+        .iter()
+        .rposition(|c| !is_preamble(c))
+        .map(|i| i + 1)
+        .unwrap_or(body_start);
+    let postamble = chunks.split_off(body_end);
+    let body = chunks.split_off(body_start);
+    (chunks, body, postamble)
+}
+
+/// Get the C preprocessor condition for a platform given with `#[ffizz(platform = "..")]`.
+fn platform_condition(platform: &str) -> Option<&'static str> {
+    match platform {
+        "unix" => Some("defined(__unix__) || defined(__APPLE__)"),
+        "windows" => Some("defined(_WIN32)"),
+        "linux" => Some("defined(__linux__)"),
+        "macos" => Some("defined(__APPLE__)"),
+        _ => None,
+    }
+}
+
+/// Get the time of generation, in seconds since the Unix epoch, from `SOURCE_DATE_EPOCH` if it is
+/// set.
+fn generation_time() -> u64 {
+    std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.trim().parse().ok())
+        .unwrap_or_else(|| {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0)
+        })
+}
+
+/// If this line includes a standard C header with a C++ equivalent, return the C++ include.
+fn cpp_include(line: &str) -> Option<String> {
+    const C_HEADERS: &[&str] = &[
+        "assert", "ctype", "errno", "float", "inttypes", "limits", "locale", "math", "setjmp",
+        "signal", "stdarg", "stddef", "stdint", "stdio", "stdlib", "string", "time", "wchar",
+        "wctype",
+    ];
+    let rest = line.trim().strip_prefix('#')?.trim_start();
+    let rest = rest.strip_prefix("include")?.trim_start();
+    let (header, comment) = rest.strip_prefix('<')?.split_once('>')?;
+    let base = header.strip_suffix(".h")?;
+    if C_HEADERS.contains(&base) {
+        Some(format!("#include <c{base}>{comment}"))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const ITEM: HeaderItem = HeaderItem {
+        order: 0,
+        module_path: "",
+        file: "",
+        line: 0,
+        name: "",
+        content: "",
+        cpp_content: None,
+        cpp_helper: false,
+        deprecated: None,
+        feature: None,
+        group: None,
+        platform: None,
+        since: None,
+        unstable: false,
+        enabled: true,
+        includes: &[],
+    };
+
+    const FEATURE_ITEMS: &[HeaderItem] = &[
+        HeaderItem {
+            order: 1,
+            name: "always",
+            content: "void always(void);",
+            ..ITEM
+        },
+        HeaderItem {
+            order: 2,
+            name: "sync",
+            content: "void sync(void);",
+            feature: Some("sync"),
+            ..ITEM
+        },
+        HeaderItem {
+            order: 3,
+            name: "async",
+            content: "void async(void);",
+            feature: Some("async-io"),
+            enabled: false,
+            ..ITEM
+        },
+    ];
+
+    #[test]
+    fn test_disabled_features_omitted() {
+        assert_eq!(
+            HeaderConfig::default().generate_from_vec(FEATURE_ITEMS.iter().collect()),
+            String::from("void always(void);\n\nvoid sync(void);\n")
+        );
+    }
+
+    #[test]
+    fn test_feature_guards() {
+        assert_eq!(
+            HeaderConfig::new()
+                .feature_guards("MYLIB")
+                .generate_from_vec(FEATURE_ITEMS.iter().collect()),
+            String::from(
+                "void always(void);\n\n\
+                #if defined(MYLIB_FEATURE_SYNC)\nvoid sync(void);\n#endif // MYLIB_FEATURE_SYNC\n\n\
+                #if defined(MYLIB_FEATURE_ASYNC_IO)\nvoid async(void);\n#endif // MYLIB_FEATURE_ASYNC_IO\n"
+            )
+        );
+    }
+
+    #[test]
+    fn test_generate_order_by_order() {
+        assert_eq!(
+            HeaderConfig::default().generate_from_vec(vec![
+                &HeaderItem {
+                    order: 1,
+                    name: "foo",
+                    content: "one",
+                    ..ITEM
+                },
+                &HeaderItem {
+                    order: 3,
+                    name: "foo",
+                    content: "three",
+                    ..ITEM
+                },
+                &HeaderItem {
+                    order: 2,
+                    name: "foo",
+                    content: "two",
+                    ..ITEM
+                },
+            ]),
+            String::from("one\n\ntwo\n\nthree\n")
+        );
+    }
+
+    #[test]
+    fn test_generate_order_by_name() {
+        assert_eq!(
+            HeaderConfig::default().generate_from_vec(vec![
+                &HeaderItem {
+                    order: 3,
+                    name: "bbb",
+                    content: "two",
+                    ..ITEM
+                },
+                &HeaderItem {
+                    order: 3,
+                    name: "ccc",
+                    content: "three",
+                    ..ITEM
+                },
+                &HeaderItem {
+                    order: 3,
+                    name: "aaa",
+                    content: "one",
+                    ..ITEM
+                },
+            ]),
+            String::from("one\n\ntwo\n\nthree\n")
+        );
+    }
+
+    fn module_items() -> Vec<HeaderItem> {
+        vec![
+            HeaderItem {
+                order: 3,
+                module_path: "mylib::foo",
+                name: "foo_b",
+                content: "foo_b",
+                ..ITEM
+            },
+            HeaderItem {
+                order: 3,
+                module_path: "mylib::bar",
+                name: "x_bar",
+                content: "x_bar",
+                ..ITEM
+            },
+            HeaderItem {
+                order: 3,
+                module_path: "mylib::foo",
+                name: "foo_a",
+                content: "foo_a",
+                ..ITEM
+            },
+            HeaderItem {
+                order: 2,
+                module_path: "mylib::foo",
+                name: "foo_z",
+                content: "foo_z",
+                ..ITEM
+            },
+        ]
+    }
+
+    #[test]
+    fn test_generate_order_by_module_path() {
+        let items = module_items();
+        assert_eq!(
+            HeaderConfig::default().generate_from_vec(items.iter().collect()),
+            String::from("foo_z\n\nx_bar\n\nfoo_a\n\nfoo_b\n")
+        );
+    }
+
+    #[test]
+    fn test_generate_sort_order_source() {
+        let items = [
+            HeaderItem {
+                module_path: "mylib::foo",
+                file: "src/foo.rs",
+                line: 20,
+                name: "a",
+                content: "a",
+                ..ITEM
+            },
+            HeaderItem {
+                module_path: "mylib",
+                file: "src/lib.rs",
+                line: 5,
+                name: "b",
+                content: "b",
+                ..ITEM
+            },
+            HeaderItem {
+                module_path: "mylib::foo",
+                file: "src/foo.rs",
+                line: 10,
+                name: "c",
+                content: "c",
+                ..ITEM
+            },
+            HeaderItem {
+                module_path: "fzstring::util",
+                file: "src/util.rs",
+                line: 30,
+                name: "d",
+                content: "d",
+                ..ITEM
+            },
+        ];
+        assert_eq!(
+            HeaderConfig::new()
+                .sort_order(SortOrder::Source)
+                .generate_from_vec(items.iter().collect()),
+            String::from("d\n\nc\n\na\n\nb\n")
+        );
+    }
+
+    #[test]
+    fn test_generate_sort_order_name() {
+        let items = module_items();
+        assert_eq!(
+            HeaderConfig::new()
+                .sort_order(SortOrder::Name)
+                .generate_from_vec(items.iter().collect()),
+            String::from("foo_z\n\nfoo_a\n\nfoo_b\n\nx_bar\n")
+        );
+    }
+
+    fn cpp_items() -> Vec<HeaderItem> {
+        vec![
+            HeaderItem {
+                order: 1,
+                name: "top",
+                content: "// MyLib\n#ifndef MYLIB_H\n#define MYLIB_H\n#include <stdint.h>",
+                ..ITEM
+            },
+            HeaderItem {
+                order: 2,
+                name: "foo",
+                content: "// Do foo.\nuint32_t foo(void);",
+                deprecated: Some("use bar"),
+                ..ITEM
+            },
+            HeaderItem {
+                order: 3,
+                name: "bar",
+                content: "uint32_t bar(void);",
+                cpp_content: Some("uint32_t bar();"),
+                ..ITEM
+            },
+            HeaderItem {
+                order: 4,
+                name: "bar_cpp",
+                content: "inline uint32_t bar2() { return bar() * 2; }",
+                cpp_helper: true,
+                ..ITEM
+            },
+            HeaderItem {
+                order: 5,
+                name: "bottom",
+                content: "#endif",
+                ..ITEM
+            },
+        ]
+    }
+
+    #[test]
+    fn test_c_profile_omits_cpp_helpers() {
+        let items = cpp_items();
+        assert_eq!(
+            HeaderConfig::default().generate_from_vec(items.iter().collect()),
+            String::from(
+                "// MyLib\n#ifndef MYLIB_H\n#define MYLIB_H\n#include <stdint.h>\n\n\
+                // Do foo.\nuint32_t foo(void);\n\n\
+                uint32_t bar(void);\n\n\
+                #endif\n"
+            )
+        );
+    }
+
+    #[test]
+    fn test_cpp_profile() {
+        let items = cpp_items();
+        assert_eq!(
+            HeaderConfig::new()
+                .profile(Profile::Cpp)
+                .cpp_namespace("mylib")
+                .generate_from_vec(items.iter().collect()),
+            String::from(
+                "// MyLib\n#ifndef MYLIB_H\n#define MYLIB_H\n#include <cstdint>\n\n\
+                extern \"C\" {\n\n\
+                // Do foo.\n[[deprecated(\"use bar\")]] uint32_t foo(void);\n\n\
+                uint32_t bar();\n\n\
+                } // extern \"C\"\n\n\
+                namespace mylib {\n\n\
+                inline uint32_t bar2() { return bar() * 2; }\n\n\
+                } // namespace mylib\n\n\
+                #endif\n"
+            )
+        );
+    }
+
+    #[test]
+    fn test_includes() {
+        let items = [
+            HeaderItem {
+                order: 1,
+                name: "top",
+                content: "#ifndef MYLIB_H\n#define MYLIB_H\n#include <stdint.h> // for uint32_t",
+                ..ITEM
+            },
+            HeaderItem {
+                order: 2,
+                name: "foo",
+                content: "uint32_t foo(bool b);",
+                includes: &["<stdint.h>", "<stdbool.h>"],
+                ..ITEM
+            },
+            HeaderItem {
+                order: 2,
+                name: "bar",
+                content: "size_t bar(bool b);",
+                includes: &["<stddef.h>", "<stdbool.h>"],
+                ..ITEM
+            },
+            HeaderItem {
+                order: 3,
+                name: "bottom",
+                content: "#endif",
+                ..ITEM
+            },
+        ];
+        assert_eq!(
+            HeaderConfig::default().generate_from_vec(items.iter().collect()),
+            String::from(
+                "#ifndef MYLIB_H\n#define MYLIB_H\n#include <stdint.h> // for uint32_t\n\n\
+                #include <stdbool.h>\n#include <stddef.h>\n\n\
+                size_t bar(bool b);\n\n\
+                uint32_t foo(bool b);\n\n\
+                #endif\n"
+            )
+        );
+        assert_eq!(
+            HeaderConfig::new()
+                .profile(Profile::Cpp)
+                .generate_from_vec(items.iter().collect()),
+            String::from(
+                "#ifndef MYLIB_H\n#define MYLIB_H\n#include <cstdint> // for uint32_t\n\n\
+                #include <cstddef>\n#include <stdbool.h>\n\n\
+                extern \"C\" {\n\n\
+                size_t bar(bool b);\n\n\
+                uint32_t foo(bool b);\n\n\
+                } // extern \"C\"\n\n\
+                #endif\n"
+            )
+        );
+    }
+
+    #[test]
+    fn test_export_macro() {
+        let items = [
+            HeaderItem {
+                order: 1,
+                name: "top",
+                content: "#ifndef MYLIB_H\n#define MYLIB_H",
+                ..ITEM
+            },
+            HeaderItem {
+                order: 2,
+                name: "foo",
+                content: "// Do foo.\n// Really.\nuint32_t foo(\n    int a,\n    int b);",
+                deprecated: Some(""),
+                ..ITEM
+            },
+            HeaderItem {
+                order: 2,
+                name: "types",
+                content: "typedef void (*cb_t)(int);\nstruct s { void (*f)(int); };\nstatic inline int g(void) { return 1; }\nchar *name(void);",
+                ..ITEM
+            },
+            HeaderItem {
+                order: 2,
+                name: "helper",
+                content: "inline int h();",
+                cpp_helper: true,
+                ..ITEM
+            },
+            HeaderItem {
+                order: 3,
+                name: "bottom",
+                content: "#endif",
+                ..ITEM
+            },
+        ];
+        let definition = export_macro_definition("MYLIB_EXPORT");
+        assert!(definition.contains("#  if defined(MYLIB_BUILDING)\n"));
+        assert_eq!(
+            HeaderConfig::new()
+                .export_macro("MYLIB_EXPORT")
+                .profile(Profile::Cpp)
+                .generate_from_vec(items.iter().collect()),
+            format!(
+                "#ifndef MYLIB_H\n#define MYLIB_H\n\n\
+                {definition}\n\n\
+                #include <cstdint>\n\n\
+                extern \"C\" {{\n\n\
+                // Do foo.\n// Really.\n[[deprecated]] MYLIB_EXPORT uint32_t foo(\n    int a,\n    int b);\n\n\
+                typedef void (*cb_t)(int);\nstruct s {{ void (*f)(int); }};\n\
+                static inline int g(void) {{ return 1; }}\nMYLIB_EXPORT char *name(void);\n\n\
+                }} // extern \"C\"\n\n\
+                inline int h();\n\n\
+                #endif\n"
+            )
+        );
+    }
+
+    #[test]
+    fn test_is_function_declaration() {
+        assert!(is_function_declaration("void foo(void);"));
+        assert!(is_function_declaration("EXTERN_C struct x *foo(int a);"));
+        assert!(!is_function_declaration("FOO(x);"));
+        assert!(!is_function_declaration("typedef int (*f)(void);"));
+        assert!(!is_function_declaration("int x = f(1);"));
+        assert!(!is_function_declaration("int (*f)(void);"));
+        assert!(!is_function_declaration("typedef struct x x;"));
+    }
+
+    #[test]
+    fn test_generate_multi() {
+        let items = [
+            HeaderItem {
+                order: 1,
+                name: "top",
+                content: "#ifndef MYLIB_H\n#define MYLIB_H\n#include <stdint.h>",
+                ..ITEM
+            },
+            HeaderItem {
+                order: 2,
+                name: "version",
+                content: "uint32_t mylib_version(void);",
+                ..ITEM
+            },
+            HeaderItem {
+                order: 3,
+                name: "list_t",
+                content: "typedef struct list_t list_t;\nstr_t *list_get(list_t *l, uint32_t i);",
+                group: Some("list"),
+                ..ITEM
+            },
+            HeaderItem {
+                order: 4,
+                name: "str_t",
+                content: "typedef struct str_t str_t;",
+                group: Some("string"),
+                ..ITEM
+            },
+            HeaderItem {
+                order: 5,
+                name: "bottom",
+                content: "#endif",
+                ..ITEM
+            },
+        ];
+        assert_eq!(
+            HeaderConfig::new().generate_multi_from_vec(items.iter().collect(), "mylib"),
+            vec![
+                (
+                    String::from("mylib.h"),
+                    String::from(
+                        "#ifndef MYLIB_H\n#define MYLIB_H\n#include <stdint.h>\n\n\
+                        #include \"mylib_string.h\"\n#include \"mylib_list.h\"\n\n\
+                        uint32_t mylib_version(void);\n\n\
+                        #endif\n"
+                    )
+                ),
+                (
+                    String::from("mylib_string.h"),
+                    String::from(
+                        "#ifndef MYLIB_STRING_H\n#define MYLIB_STRING_H\n\n\
+                        #include <stdint.h>\n\n\
+                        typedef struct str_t str_t;\n\n\
+                        #endif // MYLIB_STRING_H\n"
+                    )
+                ),
+                (
+                    String::from("mylib_list.h"),
+                    String::from(
+                        "#ifndef MYLIB_LIST_H\n#define MYLIB_LIST_H\n\n\
+                        #include <stdint.h>\n#include \"mylib_string.h\"\n\n\
+                        typedef struct list_t list_t;\nstr_t *list_get(list_t *l, uint32_t i);\n\n\
+                        #endif // MYLIB_LIST_H\n"
+                    )
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_source_locations() {
+        let items = [
+            HeaderItem {
+                order: 1,
+                name: "foo",
+                file: "src/foo.rs",
+                line: 12,
+                content: "// Do foo.\nvoid foo(void);",
+                ..ITEM
+            },
+            HeaderItem {
+                order: 2,
+                name: "bar",
+                content: "void bar(void);",
+                ..ITEM
+            },
+        ];
+        assert_eq!(
+            HeaderConfig::new()
+                .source_locations(true)
+                .generate_from_vec(items.iter().collect()),
+            String::from(
+                "// Do foo.\nvoid foo(void);\n/* from src/foo.rs:12 */\n\nvoid bar(void);\n"
+            )
+        );
+    }
+
+    #[test]
+    fn test_source_locations_preamble() {
+        let items = [
+            HeaderItem {
+                order: 1,
+                name: "top",
+                file: "src/lib.rs",
+                line: 1,
+                content: "#ifndef FOO_H",
+                ..ITEM
+            },
+            HeaderItem {
+                order: 2,
+                name: "foo",
+                content: "void foo(void);",
+                ..ITEM
+            },
+        ];
+        assert_eq!(
+            HeaderConfig::new()
+                .profile(Profile::Cpp)
+                .source_locations(true)
+                .generate_from_vec(items.iter().collect()),
+            String::from(
+                "#ifndef FOO_H\n/* from src/lib.rs:1 */\n\n\
+                extern \"C\" {\n\nvoid foo(void);\n\n} // extern \"C\"\n"
+            )
+        );
+    }
+
+    #[test]
+    fn test_extern_c_guards() {
+        let items = [
+            HeaderItem {
+                order: 1,
+                name: "top",
+                content: "#ifndef MYLIB_H\n#define MYLIB_H\n#include <stdint.h>",
+                ..ITEM
+            },
+            HeaderItem {
+                order: 2,
+                name: "add",
+                content: "// Add.\nuint64_t add(uint64_t a, uint64_t b);",
+                ..ITEM
+            },
+            HeaderItem {
+                order: 3,
+                name: "bottom",
+                content: "#endif",
+                ..ITEM
+            },
+        ];
+        assert_eq!(
+            HeaderConfig::new()
+                .extern_c_guards(true)
+                .generate_from_vec(items.iter().collect()),
+            String::from(
+                "#ifndef MYLIB_H\n#define MYLIB_H\n#include <stdint.h>\n\n\
+                #ifdef __cplusplus\nextern \"C\" {\n#endif\n\n\
+                // Add.\nuint64_t add(uint64_t a, uint64_t b);\n\n\
+                #ifdef __cplusplus\n} // extern \"C\"\n#endif\n\n\
+                #endif\n"
+            )
+        );
+    }
+
+    #[test]
+    fn test_render_with() {
+        let items = [
+            HeaderItem {
+                order: 1,
+                name: "foo",
+                content: "// Foo.\nvoid foo(void);",
+                ..ITEM
+            },
+            HeaderItem {
+                order: 2,
+                name: "bar",
+                content: "void bar(int32_t x);",
+                deprecated: Some("use foo"),
+                ..ITEM
+            },
+        ];
+        let items: Vec<&HeaderItem> = items.iter().collect();
+        assert_eq!(
+            HeaderConfig::new().render_with(&items, vec![], None, &|hi, rendered| {
+                if hi.deprecated.is_some() {
+                    rendered.replace("int32_t", "acme_int")
+                } else {
+                    format!("// ACME: {}\n{rendered}", hi.name)
+                }
+            }),
+            String::from(
+                "#include <stdint.h>\n\n\
+                // ACME: foo\n// Foo.\nvoid foo(void);\n\n\
+                void bar(acme_int x);\n"
+            )
+        );
+    }
+
+    #[test]
+    fn test_forward_declarations() {
+        let items = [
+            HeaderItem {
+                order: 1,
+                name: "foo_free",
+                content: "void foo_free(foo_t *foo);",
+                ..ITEM
+            },
+            HeaderItem {
+                order: 2,
+                name: "foo_t",
+                content: "typedef struct foo_t foo_t;",
+                ..ITEM
+            },
+        ];
+        assert_eq!(
+            HeaderConfig::new()
+                .forward_declarations(true)
+                .generate_from_vec(items.iter().collect()),
+            String::from(
+                "typedef struct foo_t foo_t;\n\n\
+                void foo_free(foo_t *foo);\n\n\
+                typedef struct foo_t foo_t;\n"
+            )
+        );
+    }
+
+    #[cfg(feature = "template")]
+    #[test]
+    fn test_template() {
+        let items = [
+            HeaderItem {
+                order: 1,
+                name: "str_t",
+                content: "typedef struct str_t str_t;",
+                group: Some("strings"),
+                ..ITEM
+            },
+            HeaderItem {
+                order: 2,
+                name: "count",
+                content: "// Count.\nuint64_t count(void);",
+                ..ITEM
+            },
+        ];
+        let config = HeaderConfig::new().var("version", "1.2.3");
+        assert_eq!(
+            config
+                .generate_from_template_vec(
+                    items.iter().collect(),
+                    "/* v{{ version }} */\n{{ includes }}\n\n\
+                    /* strings */\n{{ items(group=\"strings\") }}\n\n\
+                    /* all */\n{{ items() }}\n"
+                )
+                .unwrap(),
+            String::from(
+                "/* v1.2.3 */\n#include <stdint.h>\n\n\
+                /* strings */\ntypedef struct str_t str_t;\n\n\
+                /* all */\ntypedef struct str_t str_t;\n\n// Count.\nuint64_t count(void);\n"
+            )
+        );
+        assert!(config
+            .generate_from_template_vec(items.iter().collect(), "{{ items(colour=\"red\") }}")
+            .is_err());
+    }
+
+    #[test]
+    fn test_meson() {
+        let items = [
+            HeaderItem {
+                name: "sync",
+                content: "void sync(void);",
+                feature: Some("sync-io"),
+                ..ITEM
+            },
+            HeaderItem {
+                name: "net",
+                content: "void net(void);",
+                feature: Some("net"),
+                enabled: false,
+                ..ITEM
+            },
+        ];
+        assert_eq!(
+            HeaderConfig::new()
+                .feature_guards("MYLIB")
+                .banner("mylib", "1.2.3")
+                .meson_from_vec(items.iter().collect(), "my-lib"),
+            String::from(
+                "# Generated by ffizz-header; do not edit.\n\
+                my_lib_dep = declare_dependency(\n\
+                \x20 include_directories: include_directories('.'),\n\
+                \x20 dependencies: meson.get_compiler('c').find_library('my-lib', dirs: meson.current_source_dir()),\n\
+                \x20 compile_args: ['-DMYLIB_FEATURE_SYNC_IO'],\n\
+                \x20 version: '1.2.3',\n\
+                )\n\
+                meson.override_dependency('my-lib', my_lib_dep)\n"
+            )
+        );
+    }
+
+    #[test]
+    fn test_compose() {
+        let items = [
+            HeaderItem {
+                order: 10,
+                module_path: "ffizz_string",
+                name: "fz_string_t",
+                content: "// A string, from ffizz-string.\ntypedef struct fz_string_t fz_string_t;",
+                ..ITEM
+            },
+            HeaderItem {
+                order: 20,
+                module_path: "ffizz_string::utilfns",
+                name: "fz_string_free",
+                content: "void fz_string_free(fz_string_t *s);",
+                ..ITEM
+            },
+            HeaderItem {
+                order: 20,
+                module_path: "mylib",
+                name: "mylib_string_free",
+                content: "// Free a string.\nvoid mylib_string_free(mylib_string_t *s);",
+                ..ITEM
+            },
+        ];
+        assert_eq!(
+            HeaderConfig::new()
+                .compose(
+                    "ffizz-string",
+                    RenameMap::new()
+                        .prefix("fz_", "mylib_")
+                        .text("ffizz-string", "mylib")
+                )
+                .generate_from_vec(items.iter().collect()),
+            String::from(
+                "// A string, from mylib.\ntypedef struct mylib_string_t mylib_string_t;\n\n\
+                // Free a string.\nvoid mylib_string_free(mylib_string_t *s);\n"
+            )
+        );
+    }
+
+    #[test]
+    fn test_generate_for_features() {
+        let items = [
+            HeaderItem {
+                order: 1,
+                name: "top",
+                content: "#ifndef MYLIB_H\n#define MYLIB_H",
+                ..ITEM
+            },
+            HeaderItem {
+                order: 2,
+                name: "open",
+                content: "void open(void);",
+                ..ITEM
+            },
+            HeaderItem {
+                order: 2,
+                name: "open_async",
+                content: "void open_async(void);",
+                feature: Some("async"),
+                ..ITEM
+            },
+            HeaderItem {
+                order: 2,
+                name: "lock",
+                content: "void lock(void);",
+                feature: Some("sync"),
+                ..ITEM
+            },
+            HeaderItem {
+                order: 2,
+                name: "poll",
+                content: "void poll(void);",
+                feature: Some("async"),
+                enabled: false,
+                ..ITEM
+            },
+            HeaderItem {
+                order: 3,
+                name: "bottom",
+                content: "#endif",
+                ..ITEM
+            },
+        ];
+        let headers = HeaderConfig::new()
+            .generate_for_features_from_vec(items.iter().collect(), &["sync", "async"]);
+        assert_eq!(headers.len(), 3);
+        assert_eq!(
+            headers[""],
+            "#ifndef MYLIB_H\n#define MYLIB_H\n\nvoid open(void);\n\n#endif\n"
+        );
+        assert_eq!(headers["sync"], "void lock(void);\n");
+        assert_eq!(headers["async"], "void open_async(void);\n");
+    }
+
+    #[test]
+    fn test_auto_includes() {
+        let items = [
+            HeaderItem {
+                order: 1,
+                name: "top",
+                content: "#include <stddef.h>",
+                ..ITEM
+            },
+            HeaderItem {
+                order: 2,
+                name: "foo",
+                content: "// Returns true on success.\nbool foo(uint8_t *buf, size_t len);",
+                ..ITEM
+            },
+        ];
+        assert_eq!(
+            HeaderConfig::new().generate_from_vec(items.iter().collect()),
+            String::from(
+                "#include <stddef.h>\n\n#include <stdbool.h>\n#include <stdint.h>\n\n\
+                // Returns true on success.\nbool foo(uint8_t *buf, size_t len);\n"
+            )
+        );
+        assert_eq!(
+            HeaderConfig::new()
+                .profile(Profile::Cpp)
+                .generate_from_vec(items.iter().collect()),
+            String::from(
+                "#include <cstddef>\n\n#include <cstdint>\n\n\
+                extern \"C\" {\n\n\
+                // Returns true on success.\nbool foo(uint8_t *buf, size_t len);\n\n\
+                } // extern \"C\"\n"
+            )
+        );
+        assert_eq!(
+            HeaderConfig::new()
+                .auto_includes(false)
+                .generate_from_vec(items.iter().collect()),
+            String::from(
+                "#include <stddef.h>\n\n// Returns true on success.\nbool foo(uint8_t *buf, size_t len);\n"
+            )
+        );
+    }
+
+    #[test]
+    fn test_banner() {
+        let version = env!("CARGO_PKG_VERSION");
+        let config = HeaderConfig::new().banner("mylib", "1.2.3");
+        assert_eq!(
+            config.render_banner(1709211900),
+            Some(format!(
+                "// Generated by ffizz-header {version} from mylib v1.2.3 at 2024-02-29 13:05:00 UTC; do not edit."
+            ))
+        );
+        let config = config.timestamp(false);
+        assert_eq!(
+            config.render_banner(1709211900),
+            Some(format!(
+                "// Generated by ffizz-header {version} from mylib v1.2.3; do not edit."
+            ))
+        );
+
+        let items = [HeaderItem {
+            name: "foo",
+            content: "void foo(void);",
+            ..ITEM
+        }];
+        assert_eq!(
+            config.generate_from_vec(items.iter().collect()),
+            format!("// Generated by ffizz-header {version} from mylib v1.2.3; do not edit.\n\nvoid foo(void);\n")
+        );
+        assert_eq!(HeaderConfig::new().render_banner(0), None);
+    }
+
+    #[test]
+    fn test_platform() {
+        let items = [
+            HeaderItem {
+                name: "from_fd",
+                content: "// From a file descriptor.\nfoo_t *foo_from_fd(int fd);",
+                platform: Some("unix"),
+                ..ITEM
+            },
+            HeaderItem {
+                name: "from_handle",
+                content: "foo_t *foo_from_handle(void *handle);",
+                platform: Some("windows"),
+                feature: Some("handles"),
+                ..ITEM
+            },
+        ];
+        assert_eq!(
+            HeaderConfig::new()
+                .feature_guards("FOO")
+                .generate_from_vec(items.iter().collect()),
+            String::from(
+                "#if defined(__unix__) || defined(__APPLE__)\n\
+                // From a file descriptor.\nfoo_t *foo_from_fd(int fd);\n\
+                #endif\n\n\
+                #if defined(FOO_FEATURE_HANDLES)\n\
+                #if defined(_WIN32)\nfoo_t *foo_from_handle(void *handle);\n#endif\n\
+                #endif // FOO_FEATURE_HANDLES\n"
+            )
+        );
+    }
+
+    #[test]
+    fn test_unstable() {
+        let items = [
+            HeaderItem {
+                order: 1,
+                name: "foo",
+                content: "void foo(void);",
+                since: Some("1.0"),
+                ..ITEM
+            },
+            HeaderItem {
+                order: 2,
+                name: "bar",
+                content: "// Bar.\nvoid bar(void);",
+                since: Some("1.2"),
+                unstable: true,
+                ..ITEM
+            },
+        ];
+        assert_eq!(
+            HeaderConfig::new().generate_from_vec(items.iter().collect()),
+            String::from("void foo(void);\n\n// Bar.\nvoid bar(void);\n")
+        );
+        assert_eq!(
+            HeaderConfig::new()
+                .unstable(Unstable::Annotate)
+                .generate_from_vec(items.iter().collect()),
+            String::from(
+                "void foo(void);\n\n\
+                // UNSTABLE: this item is not covered by the API stability guarantees, and may \
+                change or be removed.\n// Bar.\nvoid bar(void);\n"
+            )
+        );
+        assert_eq!(
+            HeaderConfig::new()
+                .unstable(Unstable::Exclude)
+                .generate_from_vec(items.iter().collect()),
+            String::from("void foo(void);\n")
+        );
+    }
+
+    #[test]
+    fn test_formatting() {
+        let items = [HeaderItem {
+            name: "foo",
+            content: "// Do foo, with all of the many things that foo requires.  \nvoid foo(\n\tint x);\t",
+            ..ITEM
+        }];
+        assert_eq!(
+            HeaderConfig::new()
+                .comment_width(30)
+                .tab_width(4)
+                .trim_trailing_whitespace(true)
+                .generate_from_vec(items.iter().collect()),
+            String::from(
+                "// Do foo, with all of the\n// many things that foo\n// requires.\n\
+                void foo(\n    int x);\n"
+            )
+        );
+    }
+
+    #[test]
+    fn test_vars() {
+        let items = [HeaderItem {
+            name: "foo",
+            content: "// Requires {{ crate_version }}.\nint {{prefix}}_foo(char buf[{{size}}], {{unknown}});",
+            ..ITEM
+        }];
+        assert_eq!(
+            HeaderConfig::new()
+                .var("prefix", "mylib")
+                .vars([("crate_version", "1.2.3"), ("size", "32")])
+                .generate_from_vec(items.iter().collect()),
+            String::from("// Requires 1.2.3.\nint mylib_foo(char buf[32], {{unknown}});\n")
+        );
+    }
+
+    #[test]
+    fn test_substitute_unterminated() {
+        let config = HeaderConfig::new().var("x", "y");
+        assert_eq!(config.substitute("{{x}} {{x"), "y {{x");
+        assert_eq!(config.substitute("{{x}}}}"), "y}}");
+    }
+
+    #[test]
+    fn test_cpp_include() {
+        assert_eq!(
+            cpp_include("#include <stdint.h>"),
+            Some("#include <cstdint>".into())
+        );
+        assert_eq!(
+            cpp_include("#  include <stdio.h> // for FILE"),
+            Some("#include <cstdio> // for FILE".into())
+        );
+        assert_eq!(cpp_include("#include <stdbool.h>"), None);
+        assert_eq!(cpp_include("#include \"stdint.h\""), None);
+        assert_eq!(cpp_include("uint32_t x;"), None);
+    }
+}
@@ -0,0 +1,563 @@
+use crate::cdecl::{doc_lines, parse, CType, Decl, Function};
+use crate::{all_items, sort_items, HeaderItem};
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+
+/// Generate C# P/Invoke bindings for the library.
+///
+/// The result is a C# source file declaring the library's functions as `[DllImport]` extern
+/// methods of a static `NativeMethods` class in the given namespace.  Each opaque type (declared
+/// as `typedef struct foo foo_t;`) with a corresponding free function (`void foo_free(foo_t *)`,
+/// or `_delete` or `_destroy`) is wrapped in a `SafeHandle` subclass named for the type, such as
+/// `FooHandle`, which is used wherever a pointer to that type is passed or returned.  Struct and
+/// enum typedefs are declared as C# structs and enums.  The comments in each item are used as XML
+/// documentation.
+///
+/// Declarations which cannot be represented, such as variadic functions, are included as
+/// comments.  Items with the `cpp_helper` property are omitted.
+///
+/// The `library` is the name given to `DllImport`, such as `"mylib"` for `libmylib.so`.
+pub fn generate_csharp(library: &str, namespace: &str) -> String {
+    csharp_from_vec(all_items(), library, namespace)
+}
+
+/// A parsed header item.
+struct Item {
+    doc: Vec<String>,
+    decls: Vec<Decl>,
+}
+
+/// The types known to the generator, used to map C types to C# types.
+#[derive(Default)]
+struct Types {
+    /// Opaque types with a free function, mapped to the handle class name and free function.
+    handles: HashMap<String, (String, String)>,
+    /// Free functions for the handle types, which take an `IntPtr`.
+    free_fns: HashSet<String>,
+    /// Struct and enum types declared in C#.
+    declared: HashSet<String>,
+    /// Simple typedefs.
+    typedefs: HashMap<String, CType>,
+}
+
+/// Inner version of generate_csharp that does not operate on a static value.
+fn csharp_from_vec(mut items: Vec<&HeaderItem>, library: &str, namespace: &str) -> String {
+    sort_items(&mut items);
+    let items: Vec<Item> = items
+        .iter()
+        .filter(|hi| hi.in_bindings())
+        .map(|hi| Item {
+            doc: doc_lines(hi.content),
+            decls: parse(hi.content),
+        })
+        .collect();
+    let types = collect_types(&items);
+
+    let mut out = String::new();
+    writeln!(out, "// Generated by ffizz-header; do not edit.").unwrap();
+    writeln!(out, "using System;").unwrap();
+    writeln!(out, "using System.Runtime.InteropServices;").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "namespace {namespace}").unwrap();
+    writeln!(out, "{{").unwrap();
+
+    // types
+    for item in &items {
+        for decl in &item.decls {
+            match decl {
+                Decl::Opaque { name } => {
+                    if let Some((class, free)) = types.handles.get(name) {
+                        write_doc(&mut out, "    ", &item.doc);
+                        writeln!(out, "    public sealed class {class} : SafeHandle").unwrap();
+                        writeln!(out, "    {{").unwrap();
+                        writeln!(
+                            out,
+                            "        public {class}() : base(IntPtr.Zero, true) {{ }}"
+                        )
+                        .unwrap();
+                        writeln!(out).unwrap();
+                        writeln!(
+                            out,
+                            "        public override bool IsInvalid => handle == IntPtr.Zero;"
+                        )
+                        .unwrap();
+                        writeln!(out).unwrap();
+                        writeln!(out, "        protected override bool ReleaseHandle()").unwrap();
+                        writeln!(out, "        {{").unwrap();
+                        writeln!(out, "            NativeMethods.{free}(handle);").unwrap();
+                        writeln!(out, "            return true;").unwrap();
+                        writeln!(out, "        }}").unwrap();
+                        writeln!(out, "    }}").unwrap();
+                        writeln!(out).unwrap();
+                    }
+                }
+                Decl::Struct { name, fields } => {
+                    let fields: Option<Vec<String>> = fields
+                        .iter()
+                        .map(|f| {
+                            types.field_type(&f.ty).map(|ty| match ty.as_str() {
+                                "bool" => format!(
+                                    "        [MarshalAs(UnmanagedType.U1)]\n        public bool {};",
+                                    f.name
+                                ),
+                                _ => format!("        public {ty} {};", f.name),
+                            })
+                        })
+                        .collect();
+                    write_doc(&mut out, "    ", &item.doc);
+                    match fields {
+                        Some(fields) => {
+                            writeln!(out, "    [StructLayout(LayoutKind.Sequential)]").unwrap();
+                            writeln!(out, "    public struct {name}").unwrap();
+                            writeln!(out, "    {{").unwrap();
+                            for field in fields {
+                                writeln!(out, "{field}").unwrap();
+                            }
+                            writeln!(out, "    }}").unwrap();
+                        }
+                        None => {
+                            writeln!(out, "    // unsupported struct: {name}").unwrap();
+                        }
+                    }
+                    writeln!(out).unwrap();
+                }
+                Decl::Enum { name, variants } => {
+                    write_doc(&mut out, "    ", &item.doc);
+                    writeln!(out, "    public enum {name}").unwrap();
+                    writeln!(out, "    {{").unwrap();
+                    for (variant, value) in variants {
+                        match value {
+                            Some(value) => writeln!(out, "        {variant} = {value},").unwrap(),
+                            None => writeln!(out, "        {variant},").unwrap(),
+                        }
+                    }
+                    writeln!(out, "    }}").unwrap();
+                    writeln!(out).unwrap();
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // functions
+    writeln!(out, "    public static class NativeMethods").unwrap();
+    writeln!(out, "    {{").unwrap();
+    writeln!(out, "        private const string Library = {library:?};").unwrap();
+    for item in &items {
+        for decl in &item.decls {
+            match decl {
+                Decl::Function(func) => {
+                    writeln!(out).unwrap();
+                    write_doc(&mut out, "        ", &item.doc);
+                    match types.extern_method(func) {
+                        Some(method) => {
+                            writeln!(
+                                out,
+                                "        [DllImport(Library, CallingConvention = CallingConvention.Cdecl)]"
+                            )
+                            .unwrap();
+                            writeln!(out, "{method}").unwrap();
+                        }
+                        None => {
+                            writeln!(out, "        // unsupported function: {}", func.name)
+                                .unwrap();
+                        }
+                    }
+                }
+                Decl::Unsupported(text) => {
+                    writeln!(out).unwrap();
+                    writeln!(out, "        // unsupported declaration: {text}").unwrap();
+                }
+                _ => {}
+            }
+        }
+    }
+    writeln!(out, "    }}").unwrap();
+    writeln!(out, "}}").unwrap();
+    out
+}
+
+/// Find the types declared in the items.
+fn collect_types(items: &[Item]) -> Types {
+    let mut types = Types::default();
+    let decls = || items.iter().flat_map(|i| i.decls.iter());
+    for decl in decls() {
+        match decl {
+            Decl::Struct { name, .. } | Decl::Enum { name, .. } => {
+                types.declared.insert(name.clone());
+            }
+            Decl::Typedef { name, ty } => {
+                types.typedefs.insert(name.clone(), ty.clone());
+            }
+            _ => {}
+        }
+    }
+    for decl in decls() {
+        if let Decl::Opaque { name } = decl {
+            let free = decls().find_map(|d| match d {
+                Decl::Function(f) if is_free_fn(f, name) => Some(f.name.clone()),
+                _ => None,
+            });
+            if let Some(free) = free {
+                types.free_fns.insert(free.clone());
+                types
+                    .handles
+                    .insert(name.clone(), (handle_class(name), free));
+            }
+        }
+    }
+    types
+}
+
+/// Determine whether this is a function to free a value of the given opaque type.
+fn is_free_fn(func: &Function, ty: &str) -> bool {
+    func.ret.is_void()
+        && func.params.len() == 1
+        && func.params[0].ty.base == ty
+        && func.params[0].ty.pointers == 1
+        && ["_free", "_delete", "_destroy"]
+            .iter()
+            .any(|suffix| func.name.ends_with(suffix))
+}
+
+/// Get the name of the SafeHandle class for an opaque type: `foo_bar_t` becomes `FooBarHandle`.
+fn handle_class(name: &str) -> String {
+    let name = name.strip_suffix("_t").unwrap_or(name);
+    let mut class: String = name
+        .split('_')
+        .filter(|w| !w.is_empty())
+        .map(|w| {
+            let mut chars = w.chars();
+            let first = chars.next().unwrap().to_ascii_uppercase();
+            std::iter::once(first).chain(chars).collect::<String>()
+        })
+        .collect();
+    class.push_str("Handle");
+    class
+}
+
+/// Write a comment as an XML documentation summary.
+fn write_doc(out: &mut String, indent: &str, doc: &[String]) {
+    if doc.iter().all(|l| l.is_empty()) {
+        return;
+    }
+    writeln!(out, "{indent}/// <summary>").unwrap();
+    for line in doc {
+        let line = line
+            .replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;");
+        writeln!(out, "{indent}/// {line}").unwrap();
+    }
+    writeln!(out, "{indent}/// </summary>").unwrap();
+}
+
+impl Types {
+    /// Map a non-pointer C type to a C# type.
+    fn value_type(&self, base: &str) -> Option<String> {
+        let ty = match base {
+            "void" => "void",
+            "bool" | "_Bool" => "bool",
+            "char" | "uint8_t" | "unsigned char" => "byte",
+            "int8_t" | "signed char" => "sbyte",
+            "int16_t" | "short" | "short int" => "short",
+            "uint16_t" | "unsigned short" | "unsigned short int" => "ushort",
+            "int32_t" | "int" | "signed" | "signed int" => "int",
+            "uint32_t" | "unsigned" | "unsigned int" => "uint",
+            "int64_t" | "long long" | "long long int" => "long",
+            "uint64_t" | "unsigned long long" | "unsigned long long int" => "ulong",
+            "size_t" | "uintptr_t" => "UIntPtr",
+            "ssize_t" | "intptr_t" | "ptrdiff_t" => "IntPtr",
+            "float" => "float",
+            "double" => "double",
+            _ => {
+                if self.declared.contains(base) {
+                    return Some(base.to_string());
+                }
+                let ty = self.typedefs.get(base)?;
+                if ty.pointers > 0 {
+                    return Some("IntPtr".into());
+                }
+                return self.value_type(&ty.base);
+            }
+        };
+        Some(ty.into())
+    }
+
+    /// Map a C type to a C# type for use as a struct field.
+    fn field_type(&self, ty: &CType) -> Option<String> {
+        if ty.pointers > 0 {
+            return Some("IntPtr".into());
+        }
+        match self.value_type(&ty.base)?.as_str() {
+            "void" => None,
+            other => Some(other.into()),
+        }
+    }
+
+    /// Map a C type to a C# type for use as a parameter or return value.  `is_free` indicates
+    /// a parameter to a handle's free function, which must be an `IntPtr`.
+    fn param_type(&self, ty: &CType, is_param: bool, is_free: bool) -> Option<String> {
+        match ty.pointers {
+            0 => match self.value_type(&ty.base)?.as_str() {
+                "void" if is_param => None,
+                "bool" if is_param => Some("[MarshalAs(UnmanagedType.U1)] bool".into()),
+                other => Some(other.into()),
+            },
+            1 if !is_free && self.handles.contains_key(&ty.base) => {
+                Some(self.handles[&ty.base].0.clone())
+            }
+            1 if is_param && ty.is_const && ty.base == "char" => {
+                Some("[MarshalAs(UnmanagedType.LPUTF8Str)] string".into())
+            }
+            _ => Some("IntPtr".into()),
+        }
+    }
+
+    /// Generate the extern method declaration for a function, if it can be represented.
+    fn extern_method(&self, func: &Function) -> Option<String> {
+        if func.variadic {
+            return None;
+        }
+        let is_free = self.free_fns.contains(&func.name);
+        let mut method = String::new();
+        let ret = self.param_type(&func.ret, false, false)?;
+        if ret == "bool" {
+            writeln!(method, "        [return: MarshalAs(UnmanagedType.U1)]").unwrap();
+        }
+        let params: Option<Vec<String>> = func
+            .params
+            .iter()
+            .map(|p| {
+                self.param_type(&p.ty, true, is_free)
+                    .map(|ty| format!("{ty} {}", csharp_ident(&p.name)))
+            })
+            .collect();
+        write!(
+            method,
+            "        public static extern {ret} {}({});",
+            func.name,
+            params?.join(", ")
+        )
+        .unwrap();
+        Some(method)
+    }
+}
+
+/// Escape C identifiers which are C# reserved keywords.
+fn csharp_ident(name: &str) -> String {
+    const KEYWORDS: &[&str] = &[
+        "abstract",
+        "as",
+        "base",
+        "bool",
+        "break",
+        "byte",
+        "case",
+        "catch",
+        "char",
+        "checked",
+        "class",
+        "const",
+        "continue",
+        "decimal",
+        "default",
+        "delegate",
+        "do",
+        "double",
+        "else",
+        "enum",
+        "event",
+        "explicit",
+        "extern",
+        "false",
+        "finally",
+        "fixed",
+        "float",
+        "for",
+        "foreach",
+        "goto",
+        "if",
+        "implicit",
+        "in",
+        "int",
+        "interface",
+        "internal",
+        "is",
+        "lock",
+        "long",
+        "namespace",
+        "new",
+        "null",
+        "object",
+        "operator",
+        "out",
+        "override",
+        "params",
+        "private",
+        "protected",
+        "public",
+        "readonly",
+        "ref",
+        "return",
+        "sbyte",
+        "sealed",
+        "short",
+        "sizeof",
+        "stackalloc",
+        "static",
+        "string",
+        "struct",
+        "switch",
+        "this",
+        "throw",
+        "true",
+        "try",
+        "typeof",
+        "uint",
+        "ulong",
+        "unchecked",
+        "unsafe",
+        "ushort",
+        "using",
+        "virtual",
+        "void",
+        "volatile",
+        "while",
+    ];
+    if KEYWORDS.contains(&name) {
+        format!("@{name}")
+    } else {
+        name.to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const ITEM: HeaderItem = HeaderItem {
+        order: 0,
+        module_path: "",
+        file: "",
+        line: 0,
+        name: "",
+        content: "",
+        cpp_content: None,
+        cpp_helper: false,
+        deprecated: None,
+        feature: None,
+        group: None,
+        platform: None,
+        since: None,
+        unstable: false,
+        enabled: true,
+        includes: &[],
+    };
+
+    #[test]
+    fn test_handle_class() {
+        assert_eq!(handle_class("foo_bar_t"), "FooBarHandle");
+        assert_eq!(handle_class("infprec"), "InfprecHandle");
+    }
+
+    #[test]
+    fn test_csharp_ident() {
+        assert_eq!(csharp_ident("count"), "count");
+        assert_eq!(csharp_ident("as"), "@as");
+        assert_eq!(csharp_ident("null"), "@null");
+        assert_eq!(csharp_ident("interface"), "@interface");
+        assert_eq!(csharp_ident("string"), "@string");
+    }
+
+    #[test]
+    fn test_csharp() {
+        let items = [
+            HeaderItem {
+                order: 1,
+                name: "topmatter",
+                content: "#include <stdint.h>",
+                ..ITEM
+            },
+            HeaderItem {
+                order: 2,
+                name: "foo_t",
+                content: "// A foo & its <data>.\ntypedef struct foo foo_t;",
+                ..ITEM
+            },
+            HeaderItem {
+                order: 3,
+                name: "foo_new",
+                content: "// Create a foo.\nfoo_t *foo_new(const char *name, bool in);",
+                ..ITEM
+            },
+            HeaderItem {
+                order: 3,
+                name: "foo_free",
+                content: "void foo_free(foo_t *foo);",
+                ..ITEM
+            },
+            HeaderItem {
+                order: 4,
+                name: "point_t",
+                content: "typedef struct point_t {\n  int32_t x;\n  size_t y;\n} point_t;",
+                ..ITEM
+            },
+            HeaderItem {
+                order: 5,
+                name: "foo_printf",
+                content: "int foo_printf(const char *fmt, ...);",
+                ..ITEM
+            },
+        ];
+        assert_eq!(
+            csharp_from_vec(items.iter().collect(), "foo", "Foo.Native"),
+            r#"// Generated by ffizz-header; do not edit.
+using System;
+using System.Runtime.InteropServices;
+
+namespace Foo.Native
+{
+    /// <summary>
+    /// A foo &amp; its &lt;data&gt;.
+    /// </summary>
+    public sealed class FooHandle : SafeHandle
+    {
+        public FooHandle() : base(IntPtr.Zero, true) { }
+
+        public override bool IsInvalid => handle == IntPtr.Zero;
+
+        protected override bool ReleaseHandle()
+        {
+            NativeMethods.foo_free(handle);
+            return true;
+        }
+    }
+
+    [StructLayout(LayoutKind.Sequential)]
+    public struct point_t
+    {
+        public int x;
+        public UIntPtr y;
+    }
+
+    public static class NativeMethods
+    {
+        private const string Library = "foo";
+
+        [DllImport(Library, CallingConvention = CallingConvention.Cdecl)]
+        public static extern void foo_free(IntPtr foo);
+
+        /// <summary>
+        /// Create a foo.
+        /// </summary>
+        [DllImport(Library, CallingConvention = CallingConvention.Cdecl)]
+        public static extern FooHandle foo_new([MarshalAs(UnmanagedType.LPUTF8Str)] string name, [MarshalAs(UnmanagedType.U1)] bool @in);
+
+        // unsupported function: foo_printf
+    }
+}
+"#
+        );
+    }
+}
@@ -0,0 +1,153 @@
+/// The number of unchanged lines shown around each change in a unified diff.
+const CONTEXT: usize = 3;
+
+/// A single line-level edit, referring to line indexes in the old and new text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Edit {
+    Equal(usize, usize),
+    Delete(usize),
+    Insert(usize),
+}
+
+/// Compute a minimal sequence of edits transforming `old` into `new`, using the classic
+/// longest-common-subsequence table.  Headers are small enough that the quadratic cost is not a
+/// concern.
+fn edits(old: &[&str], new: &[&str]) -> Vec<Edit> {
+    let (n, m) = (old.len(), new.len());
+    // lcs[i][j] is the length of the LCS of old[i..] and new[j..]
+    let mut lcs = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut result = Vec::with_capacity(n + m);
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && old[i] == new[j] {
+            result.push(Edit::Equal(i, j));
+            i += 1;
+            j += 1;
+        } else if i < n && (j == m || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            result.push(Edit::Delete(i));
+            i += 1;
+        } else {
+            result.push(Edit::Insert(j));
+            j += 1;
+        }
+    }
+    result
+}
+
+/// Produce a unified diff between the two texts, labeled with the given names.  Returns an empty
+/// string if the texts are identical.
+pub(crate) fn unified_diff(old_name: &str, new_name: &str, old: &str, new: &str) -> String {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let edits = edits(&old_lines, &new_lines);
+
+    // indexes into `edits` of the changed lines
+    let changes: Vec<usize> = edits
+        .iter()
+        .enumerate()
+        .filter(|(_, e)| !matches!(e, Edit::Equal(..)))
+        .map(|(i, _)| i)
+        .collect();
+    if changes.is_empty() {
+        return String::new();
+    }
+
+    // group the changes into hunks, merging changes whose context would overlap
+    let mut hunks: Vec<(usize, usize)> = vec![];
+    for &c in &changes {
+        let start = c.saturating_sub(CONTEXT);
+        let end = (c + CONTEXT + 1).min(edits.len());
+        match hunks.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    let mut result = format!("--- {old_name}\n+++ {new_name}\n");
+    for (start, end) in hunks {
+        let hunk = &edits[start..end];
+
+        // line numbers are 1-based; an empty range is numbered by the preceding line
+        let old_count = hunk
+            .iter()
+            .filter(|e| !matches!(e, Edit::Insert(_)))
+            .count();
+        let new_count = hunk
+            .iter()
+            .filter(|e| !matches!(e, Edit::Delete(_)))
+            .count();
+        let old_start = edits[..start]
+            .iter()
+            .filter(|e| !matches!(e, Edit::Insert(_)))
+            .count()
+            + usize::from(old_count > 0);
+        let new_start = edits[..start]
+            .iter()
+            .filter(|e| !matches!(e, Edit::Delete(_)))
+            .count()
+            + usize::from(new_count > 0);
+        result.push_str(&format!(
+            "@@ -{old_start},{old_count} +{new_start},{new_count} @@\n"
+        ));
+
+        for edit in hunk {
+            match *edit {
+                Edit::Equal(i, _) => result.push_str(&format!(" {}\n", old_lines[i])),
+                Edit::Delete(i) => result.push_str(&format!("-{}\n", old_lines[i])),
+                Edit::Insert(j) => result.push_str(&format!("+{}\n", new_lines[j])),
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_identical() {
+        assert_eq!(unified_diff("a", "b", "x\ny\n", "x\ny\n"), "");
+    }
+
+    #[test]
+    fn test_change() {
+        assert_eq!(
+            unified_diff(
+                "a",
+                "b",
+                "1\n2\n3\n4\n5\n6\n7\n8\n",
+                "1\n2\n3\n4\nfive\n6\n7\n8\n"
+            ),
+            "--- a\n+++ b\n@@ -2,7 +2,7 @@\n 2\n 3\n 4\n-5\n+five\n 6\n 7\n 8\n"
+        );
+    }
+
+    #[test]
+    fn test_separate_hunks() {
+        let old = "a\n1\n2\n3\n4\n5\n6\n7\n8\nb\n";
+        let new = "1\n2\n3\n4\n5\n6\n7\n8\n";
+        assert_eq!(
+            unified_diff("a", "b", old, new),
+            "--- a\n+++ b\n@@ -1,4 +1,3 @@\n-a\n 1\n 2\n 3\n@@ -7,4 +6,3 @@\n 6\n 7\n 8\n-b\n"
+        );
+    }
+
+    #[test]
+    fn test_from_empty() {
+        assert_eq!(
+            unified_diff("a", "b", "", "x\n"),
+            "--- a\n+++ b\n@@ -0,0 +1,1 @@\n+x\n"
+        );
+    }
+}
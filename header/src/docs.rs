@@ -0,0 +1,233 @@
+use crate::cdecl::{doc_lines, strip_declarations};
+use crate::{all_items, sort_items, HeaderItem};
+use std::fmt::Write;
+
+/// Generate Markdown API reference documentation for the library.
+///
+/// Each item with declarations becomes a section, headed by the item's name, containing the
+/// item's documentation followed by its declarations in a code block.  Items containing only
+/// documentation, such as introductory snippets, are included as plain text.  Headings in the
+/// documentation are demoted to fit beneath the item headings.  Items with the `cpp_helper`
+/// property are omitted.
+pub fn generate_markdown(title: &str) -> String {
+    markdown_from_vec(all_items(), title)
+}
+
+/// Generate API reference documentation for the library as a man page, in roff format.
+///
+/// The page is named `name`, in the given manual section (usually `3`), and contains the same
+/// content as [`generate_markdown`], with headings in the documentation shown in bold.
+pub fn generate_man(name: &str, section: &str) -> String {
+    man_from_vec(all_items(), name, section)
+}
+
+/// Get the documentation and declarations for each item to be documented.
+fn doc_items(mut items: Vec<&HeaderItem>) -> Vec<(&HeaderItem, Vec<String>, String)> {
+    sort_items(&mut items);
+    items
+        .into_iter()
+        .filter(|hi| hi.in_bindings())
+        .map(|hi| (hi, doc_lines(hi.content), strip_declarations(hi.content)))
+        .filter(|(_, docs, decls)| !docs.is_empty() || !decls.is_empty())
+        .collect()
+}
+
+/// Inner version of generate_markdown that does not operate on a static value.
+fn markdown_from_vec(items: Vec<&HeaderItem>, title: &str) -> String {
+    let mut out = String::new();
+    writeln!(out, "<!-- Generated by ffizz-header; do not edit. -->").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "# {title}").unwrap();
+
+    for (item, docs, decls) in doc_items(items) {
+        writeln!(out).unwrap();
+        if !decls.is_empty() {
+            writeln!(out, "## `{}`", item.name).unwrap();
+            writeln!(out).unwrap();
+        }
+        let mut in_fence = false;
+        for line in &docs {
+            if line.trim_start().starts_with("```") {
+                in_fence = !in_fence;
+            }
+            if !in_fence && line.starts_with('#') {
+                writeln!(out, "##{line}").unwrap();
+            } else {
+                writeln!(out, "{line}").unwrap();
+            }
+        }
+        if !decls.is_empty() {
+            if !docs.is_empty() {
+                writeln!(out).unwrap();
+            }
+            writeln!(out, "```c").unwrap();
+            writeln!(out, "{decls}").unwrap();
+            writeln!(out, "```").unwrap();
+        }
+    }
+    out
+}
+
+/// Inner version of generate_man that does not operate on a static value.
+fn man_from_vec(items: Vec<&HeaderItem>, name: &str, section: &str) -> String {
+    let mut out = String::new();
+    writeln!(out, ".\\\" Generated by ffizz-header; do not edit.").unwrap();
+    writeln!(out, ".TH \"{}\" \"{section}\"", roff_escape(name)).unwrap();
+    writeln!(out, ".SH NAME").unwrap();
+    writeln!(out, "{} \\- C API reference", roff_escape(name)).unwrap();
+    writeln!(out, ".SH DESCRIPTION").unwrap();
+
+    for (item, docs, decls) in doc_items(items) {
+        if decls.is_empty() {
+            writeln!(out, ".PP").unwrap();
+        } else {
+            writeln!(out, ".SS \"{}\"", roff_escape(item.name)).unwrap();
+        }
+        let mut in_fence = false;
+        for line in &docs {
+            if line.trim_start().starts_with("```") {
+                in_fence = !in_fence;
+                writeln!(out, "{}", if in_fence { ".nf" } else { ".fi" }).unwrap();
+            } else if in_fence {
+                writeln!(out, "{}", roff_line(line)).unwrap();
+            } else if line.is_empty() {
+                writeln!(out, ".PP").unwrap();
+            } else if line.starts_with('#') {
+                let heading = line.trim_start_matches('#').trim();
+                writeln!(out, ".B \"{}\"", roff_escape(heading)).unwrap();
+            } else {
+                writeln!(out, "{}", roff_line(line)).unwrap();
+            }
+        }
+        if !decls.is_empty() {
+            writeln!(out, ".PP").unwrap();
+            writeln!(out, ".nf").unwrap();
+            for line in decls.lines() {
+                writeln!(out, "{}", roff_line(line)).unwrap();
+            }
+            writeln!(out, ".fi").unwrap();
+        }
+    }
+    out
+}
+
+/// Escape text for inclusion in roff.
+fn roff_escape(text: &str) -> String {
+    text.replace('\\', "\\e")
+}
+
+/// Escape a line of text for roff, including a leading character that would make it a request.
+fn roff_line(line: &str) -> String {
+    let line = roff_escape(line);
+    if line.starts_with('.') || line.starts_with('\'') {
+        format!("\\&{line}")
+    } else {
+        line
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const ITEM: HeaderItem = HeaderItem {
+        order: 0,
+        module_path: "",
+        file: "",
+        line: 0,
+        name: "",
+        content: "",
+        cpp_content: None,
+        cpp_helper: false,
+        deprecated: None,
+        feature: None,
+        group: None,
+        platform: None,
+        since: None,
+        unstable: false,
+        enabled: true,
+        includes: &[],
+    };
+
+    fn items() -> Vec<HeaderItem> {
+        vec![
+            HeaderItem {
+                order: 1,
+                name: "topmatter",
+                content: "// MyLib does things.\n#ifndef MYLIB_H\n#define MYLIB_H",
+                ..ITEM
+            },
+            HeaderItem {
+                order: 2,
+                name: "add",
+                content: "// Add two numbers.\n//\n// # Safety\n//\n// .. is not a concern.\nEXTERN_C uint64_t add(uint64_t a, uint64_t b);",
+                ..ITEM
+            },
+            HeaderItem {
+                order: 3,
+                name: "add_cpp",
+                content: "inline uint64_t add3(uint64_t a, uint64_t b, uint64_t c);",
+                cpp_helper: true,
+                ..ITEM
+            },
+            HeaderItem {
+                order: 4,
+                name: "bottomatter",
+                content: "#endif",
+                ..ITEM
+            },
+        ]
+    }
+
+    #[test]
+    fn test_markdown() {
+        let items = items();
+        assert_eq!(
+            markdown_from_vec(items.iter().collect(), "MyLib"),
+            r#"<!-- Generated by ffizz-header; do not edit. -->
+
+# MyLib
+
+MyLib does things.
+
+## `add`
+
+Add two numbers.
+
+### Safety
+
+.. is not a concern.
+
+```c
+uint64_t add(uint64_t a, uint64_t b);
+```
+"#
+        );
+    }
+
+    #[test]
+    fn test_man() {
+        let items = items();
+        assert_eq!(
+            man_from_vec(items.iter().collect(), "mylib", "3"),
+            r#".\" Generated by ffizz-header; do not edit.
+.TH "mylib" "3"
+.SH NAME
+mylib \- C API reference
+.SH DESCRIPTION
+.PP
+MyLib does things.
+.SS "add"
+Add two numbers.
+.PP
+.B "Safety"
+.PP
+\&.. is not a concern.
+.PP
+.nf
+uint64_t add(uint64_t a, uint64_t b);
+.fi
+"#
+        );
+    }
+}
@@ -0,0 +1,170 @@
+use crate::cdecl::{parse, Decl};
+use crate::{all_items, sort_items, HeaderItem};
+use std::fmt::Write;
+
+/// Generate a Windows module-definition (`.def`) file exporting the functions declared in the
+/// header, for the DLL named `library`.
+///
+/// Functions are listed in the order they appear in the header.  Items with the `cpp_helper`
+/// property, and items for platforms other than Windows, are omitted.
+pub fn generate_def(library: &str) -> String {
+    def_from_vec(all_items(), library)
+}
+
+/// Generate a GNU ld version script for the shared library, making the functions declared in the
+/// header global and all other symbols local.
+///
+/// The symbols are tagged with the version node `version`, such as `MYLIB_1.0`, or are
+/// unversioned if `version` is empty.  Items with the `cpp_helper` property, and items for
+/// platforms other than Linux, are omitted.
+///
+/// ```ignore
+/// std::fs::write("mylib.map", ffizz_header::generate_version_script("MYLIB_1.0")).unwrap();
+/// ```
+///
+/// The script is used when linking with `-Wl,--version-script=mylib.map`.
+pub fn generate_version_script(version: &str) -> String {
+    version_script_from_vec(all_items(), version)
+}
+
+/// Get the names of the functions declared in the given items, in header order and without
+/// duplicates, omitting items for platforms other than those given.
+fn function_names(mut items: Vec<&HeaderItem>, platforms: &[&str]) -> Vec<String> {
+    sort_items(&mut items);
+    let mut names: Vec<String> = vec![];
+    for item in items.iter().filter(|hi| hi.in_bindings()) {
+        if item.platform.is_some_and(|p| !platforms.contains(&p)) {
+            continue;
+        }
+        for decl in parse(item.content) {
+            if let Decl::Function(func) = decl {
+                if !names.contains(&func.name) {
+                    names.push(func.name);
+                }
+            }
+        }
+    }
+    names
+}
+
+/// Inner version of generate_def that does not operate on a static value.
+fn def_from_vec(items: Vec<&HeaderItem>, library: &str) -> String {
+    let mut out = String::new();
+    writeln!(out, "; Generated by ffizz-header; do not edit.").unwrap();
+    writeln!(out, "LIBRARY {library}").unwrap();
+    writeln!(out, "EXPORTS").unwrap();
+    for name in function_names(items, &["windows"]) {
+        writeln!(out, "    {name}").unwrap();
+    }
+    out
+}
+
+/// Inner version of generate_version_script that does not operate on a static value.
+fn version_script_from_vec(items: Vec<&HeaderItem>, version: &str) -> String {
+    let mut out = String::new();
+    writeln!(out, "/* Generated by ffizz-header; do not edit. */").unwrap();
+    if version.is_empty() {
+        writeln!(out, "{{").unwrap();
+    } else {
+        writeln!(out, "{version} {{").unwrap();
+    }
+    let names = function_names(items, &["unix", "linux"]);
+    if !names.is_empty() {
+        writeln!(out, "  global:").unwrap();
+        for name in names {
+            writeln!(out, "    {name};").unwrap();
+        }
+    }
+    writeln!(out, "  local:").unwrap();
+    writeln!(out, "    *;").unwrap();
+    writeln!(out, "}};").unwrap();
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const ITEM: HeaderItem = HeaderItem {
+        order: 0,
+        module_path: "",
+        file: "",
+        line: 0,
+        name: "",
+        content: "",
+        cpp_content: None,
+        cpp_helper: false,
+        deprecated: None,
+        feature: None,
+        group: None,
+        platform: None,
+        since: None,
+        unstable: false,
+        enabled: true,
+        includes: &[],
+    };
+
+    fn items() -> Vec<HeaderItem> {
+        vec![
+            HeaderItem {
+                order: 1,
+                name: "topmatter",
+                content: "#include <stdint.h>\ntypedef struct pt_t pt_t;",
+                ..ITEM
+            },
+            HeaderItem {
+                order: 3,
+                name: "sub",
+                content: "// Subtract.\nEXTERN_C uint64_t sub(uint64_t a, uint64_t b);",
+                ..ITEM
+            },
+            HeaderItem {
+                order: 2,
+                name: "add",
+                content: "uint64_t add(uint64_t a, uint64_t b);",
+                ..ITEM
+            },
+            HeaderItem {
+                order: 4,
+                name: "add_fd",
+                content: "int add_fd(int fd);",
+                platform: Some("unix"),
+                ..ITEM
+            },
+            HeaderItem {
+                order: 5,
+                name: "add3",
+                content: "inline uint64_t add3(uint64_t a, uint64_t b, uint64_t c);",
+                cpp_helper: true,
+                ..ITEM
+            },
+        ]
+    }
+
+    #[test]
+    fn test_def() {
+        let items = items();
+        assert_eq!(
+            def_from_vec(items.iter().collect(), "mylib"),
+            "; Generated by ffizz-header; do not edit.\nLIBRARY mylib\nEXPORTS\n    add\n    sub\n"
+        );
+    }
+
+    #[test]
+    fn test_version_script() {
+        let items = items();
+        assert_eq!(
+            version_script_from_vec(items.iter().collect(), "MYLIB_1.0"),
+            "/* Generated by ffizz-header; do not edit. */\n\
+            MYLIB_1.0 {\n  global:\n    add;\n    sub;\n    add_fd;\n  local:\n    *;\n};\n"
+        );
+    }
+
+    #[test]
+    fn test_version_script_unversioned() {
+        assert_eq!(
+            version_script_from_vec(vec![], ""),
+            "/* Generated by ffizz-header; do not edit. */\n{\n  local:\n    *;\n};\n"
+        );
+    }
+}
@@ -0,0 +1,200 @@
+//! Text formatting for generated headers.
+
+/// Replace tabs in each line with spaces, to the next multiple of `width` columns.
+pub(crate) fn expand_tabs(content: &str, width: usize) -> String {
+    let width = width.max(1);
+    let lines = content.lines().map(|line| {
+        let mut result = String::with_capacity(line.len());
+        let mut col = 0;
+        for c in line.chars() {
+            if c == '\t' {
+                let spaces = width - col % width;
+                result.push_str(&" ".repeat(spaces));
+                col += spaces;
+            } else {
+                result.push(c);
+                col += 1;
+            }
+        }
+        result
+    });
+    itertools::join(lines, "\n")
+}
+
+/// Remove trailing whitespace from each line.
+pub(crate) fn trim_trailing_whitespace(content: &str) -> String {
+    itertools::join(content.lines().map(str::trim_end), "\n")
+}
+
+/// Re-wrap paragraphs of `//` comments containing lines longer than `width` columns.
+///
+/// A paragraph is a run of comment lines of ordinary text.  Blank comment lines, indented lines,
+/// headings, and fenced blocks are left as-is, and a list item begins a new paragraph, with its
+/// continuation lines indented to match the item's text.  Words longer than the width are not
+/// broken.
+pub(crate) fn wrap_comments(content: &str, width: usize) -> String {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut result: Vec<String> = vec![];
+    let mut in_fence = false;
+    let mut i = 0;
+    while i < lines.len() {
+        let Some((prefix, text)) = comment_text(lines[i]) else {
+            result.push(lines[i].to_string());
+            i += 1;
+            continue;
+        };
+        if text.starts_with("```") {
+            in_fence = !in_fence;
+        }
+        if in_fence || text.starts_with("```") || !is_paragraph_start(text) {
+            result.push(lines[i].to_string());
+            i += 1;
+            continue;
+        }
+
+        // gather the lines of the paragraph
+        let mut end = i + 1;
+        while end < lines.len() {
+            match comment_text(lines[end]) {
+                Some((p, t)) if p == prefix && is_continuation(t) => end += 1,
+                _ => break,
+            }
+        }
+        let paragraph = &lines[i..end];
+        if paragraph.iter().all(|line| line.chars().count() <= width) {
+            result.extend(paragraph.iter().map(|line| line.to_string()));
+        } else {
+            let hang = " ".repeat(list_marker_len(text));
+            let words = paragraph
+                .iter()
+                .filter_map(|line| comment_text(line))
+                .flat_map(|(_, t)| t.split_whitespace());
+            let mut line = String::new();
+            for word in words {
+                if line.is_empty() {
+                    line = format!("{prefix}{word}");
+                } else if line.chars().count() + 1 + word.chars().count() <= width {
+                    line.push(' ');
+                    line.push_str(word);
+                } else {
+                    result.push(std::mem::take(&mut line));
+                    line = format!("{prefix}{hang}{word}");
+                }
+            }
+            result.push(line);
+        }
+        i = end;
+    }
+    itertools::join(result, "\n")
+}
+
+/// Split a `//` comment line into its prefix (indentation, `//`, and a space) and text.
+fn comment_text(line: &str) -> Option<(&str, &str)> {
+    let indent = line.len() - line.trim_start().len();
+    let rest = line[indent..].strip_prefix("//")?;
+    let text = rest.strip_prefix(' ')?;
+    Some((&line[..line.len() - text.len()], text))
+}
+
+/// Determine whether comment text can begin a paragraph to be wrapped.
+fn is_paragraph_start(text: &str) -> bool {
+    !text.trim().is_empty() && !text.starts_with(char::is_whitespace) && !text.starts_with('#')
+}
+
+/// Determine whether comment text can continue a paragraph.
+fn is_continuation(text: &str) -> bool {
+    is_paragraph_start(text) && list_marker_len(text) == 0 && !text.starts_with("```")
+}
+
+/// Get the length of the list marker (`* `, `- `, or `1. `) at the start of the text, or zero if
+/// there is none.
+fn list_marker_len(text: &str) -> usize {
+    if text.starts_with("* ") || text.starts_with("- ") {
+        return 2;
+    }
+    let digits = text.chars().take_while(|c| c.is_ascii_digit()).count();
+    if digits > 0 && text[digits..].starts_with(". ") {
+        digits + 2
+    } else {
+        0
+    }
+}
+
+/// Format a Unix timestamp as a UTC date and time, such as `2024-02-29 13:05:00 UTC`.
+pub(crate) fn format_utc(secs: u64) -> String {
+    let (days, secs) = (secs / 86400, secs % 86400);
+    // convert days since the epoch to a civil date, after Howard Hinnant's `civil_from_days`
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02} UTC",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_expand_tabs() {
+        assert_eq!(
+            expand_tabs("\tint x;\nab\tc", 4),
+            "    int x;\nab  c".to_string()
+        );
+    }
+
+    #[test]
+    fn test_trim_trailing_whitespace() {
+        assert_eq!(
+            trim_trailing_whitespace("// foo  \nvoid foo(void);\t"),
+            "// foo\nvoid foo(void);".to_string()
+        );
+    }
+
+    #[test]
+    fn test_wrap_short_unchanged() {
+        let content = "// short\n// lines\nvoid foo(void);";
+        assert_eq!(wrap_comments(content, 20), content.to_string());
+    }
+
+    #[test]
+    fn test_wrap_paragraph() {
+        assert_eq!(
+            wrap_comments(
+                "// This is a long line that should wrap.\n// And more.\n//\n// Next.\nvoid foo(void);",
+                20
+            ),
+            "// This is a long\n// line that should\n// wrap. And more.\n//\n// Next.\nvoid foo(void);"
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn test_wrap_list_and_code() {
+        assert_eq!(
+            wrap_comments(
+                "  // * an item that is quite long\n  // * short\n  //     indented code that is long\n  // ```\n  // fenced code that is long\n  // ```",
+                24
+            ),
+            "  // * an item that is\n  //   quite long\n  // * short\n  //     indented code that is long\n  // ```\n  // fenced code that is long\n  // ```"
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn test_format_utc() {
+        assert_eq!(format_utc(0), "1970-01-01 00:00:00 UTC");
+        assert_eq!(format_utc(951782400), "2000-02-29 00:00:00 UTC");
+        assert_eq!(format_utc(1709211900), "2024-02-29 13:05:00 UTC");
+    }
+}
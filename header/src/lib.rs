@@ -1,112 +1,312 @@
 #![doc = include_str!("crate-doc.md")]
 
-use itertools::join;
+#[cfg(not(target_family = "wasm"))]
 use linkme::distributed_slice;
 use std::cmp::Ordering;
+use std::collections::HashMap;
 
+mod apidiff;
+pub mod build;
+mod cdecl;
+mod cffi;
+mod compose;
+mod config;
+mod csharp;
+mod diff;
+mod docs;
+mod exports;
+mod format;
+mod multi;
+mod napi;
+mod pxd;
+mod snapshot;
+mod swig;
+mod zig;
+
+#[cfg(not(target_family = "wasm"))]
 #[doc(hidden)]
 pub use linkme;
 
+#[cfg(any(feature = "inventory", target_family = "wasm"))]
+#[doc(hidden)]
+pub use inventory;
+
+pub use apidiff::*;
+pub use cffi::*;
+pub use compose::*;
+pub use config::*;
+pub use csharp::*;
+pub use docs::*;
+pub use exports::*;
+pub use ffizz_macros::emit_at_build;
+pub use ffizz_macros::emit_main;
 pub use ffizz_macros::item;
 pub use ffizz_macros::snippet;
+pub use ffizz_macros::Opaque;
+pub use napi::*;
+pub use pxd::*;
+pub use snapshot::*;
+pub use swig::*;
+pub use zig::*;
 
 /// A HeaderItem contains an item that should be included in the output C header.
 ///
 /// Only the `content` field will actually appear, with the other fields used to ensure a stable
 /// order for the items.  `order` is used for coarse-grained ordering, such as putting introductory
-/// comments at the top.  For items with equal `order`, `name` is used to sort.
-#[doc(hidden)]
+/// comments at the top.  For items with equal `order`, `module_path` (the path of the module
+/// defining the item) and then `name` are used to sort.  `file` and `line` give the location of the
+/// item's definition.
+///
+/// C++ headers use `cpp_content` in place of `content`, if it is set.  Items with `cpp_helper` set
+/// are only included in C++ headers.  If `deprecated` is set, C++
+/// headers mark the declaration with `[[deprecated]]`, using the note if it is not empty.
+///
+/// Items with a `feature` are only `enabled` if that Cargo feature was enabled for the crate
+/// defining the item.
+///
+/// Items with a `group` are placed in a separate header for that group by
+/// [`HeaderConfig::generate_multi`].
+///
+/// Items with a `platform` are wrapped in a preprocessor conditional for that platform, such as
+/// `#if defined(_WIN32)`.
+///
+/// `since` gives the library version in which the item was introduced, and items that are
+/// `unstable` are not covered by the library's API stability guarantees (see
+/// [`HeaderConfig::unstable`]).
+///
+/// The `includes` are headers the item requires, in the form given to `#include`, and are
+/// collected into a single block near the top of the header.
+///
+/// Items are not normally constructed directly, but are available to the filter given to
+/// [`generate_with_filter`].
 #[derive(Clone)]
 pub struct HeaderItem {
     pub order: usize,
+    pub module_path: &'static str,
+    pub file: &'static str,
+    pub line: u32,
     pub name: &'static str,
     pub content: &'static str,
+    pub cpp_content: Option<&'static str>,
+    pub cpp_helper: bool,
+    pub deprecated: Option<&'static str>,
+    pub feature: Option<&'static str>,
+    pub group: Option<&'static str>,
+    pub platform: Option<&'static str>,
+    pub since: Option<&'static str>,
+    pub unstable: bool,
+    pub enabled: bool,
+    pub includes: &'static [&'static str],
+}
+
+impl HeaderItem {
+    /// Determine whether this item should be included in bindings for other languages: it is
+    /// enabled, and is not a C++ helper.
+    pub(crate) fn in_bindings(&self) -> bool {
+        self.enabled && !self.cpp_helper
+    }
 }
 
 /// FFIZZ_HEADER_ITEMS collects HeaderItems using `linkme`.
+#[cfg(not(target_family = "wasm"))]
 #[doc(hidden)]
 #[distributed_slice]
 pub static FFIZZ_HEADER_ITEMS: [HeaderItem] = [..];
 
+// With the `inventory` feature, or on WebAssembly, HeaderItems are collected using `inventory`
+// instead.
+#[cfg(any(feature = "inventory", target_family = "wasm"))]
+inventory::collect!(HeaderItem);
+
+/// Get all registered header items, from both `linkme` and `inventory`.
+pub(crate) fn all_items() -> Vec<&'static HeaderItem> {
+    #[cfg(not(target_family = "wasm"))]
+    let items = FFIZZ_HEADER_ITEMS.iter();
+    #[cfg(target_family = "wasm")]
+    let items = [].iter();
+    #[cfg(any(feature = "inventory", target_family = "wasm"))]
+    let items = items.chain(inventory::iter::<HeaderItem>);
+    let items: Vec<_> = items.collect();
+    check_unique_names(&items);
+    items
+}
+
+/// Check that no two header items have the same name, panicking with a description of the
+/// duplicates if they do.
+fn check_unique_names(items: &[&HeaderItem]) {
+    let mut by_name: HashMap<&str, &HeaderItem> = HashMap::new();
+    for item in items {
+        if let Some(other) = by_name.insert(item.name, item) {
+            panic!(
+                "duplicate header item name {:?} in {} ({}:{}) and {} ({}:{}); each item must \
+                have a unique name, set with #[ffizz(name=\"..\")].\nfirst item content:\n{}\n\
+                second item content:\n{}",
+                item.name,
+                other.module_path,
+                other.file,
+                other.line,
+                item.module_path,
+                item.file,
+                item.line,
+                other.content.trim(),
+                item.content.trim(),
+            );
+        }
+    }
+}
+
 /// Generate the C header for the library.
 ///
 /// This "magically" concatenates all of the header chunks supplied by `item` and `snippet` macro
 /// invocations throughout all crates used to build the library.
+///
+/// Use [`HeaderConfig`] for more control over the generated header.
 pub fn generate() -> String {
-    generate_from_vec(FFIZZ_HEADER_ITEMS.iter().collect::<Vec<_>>())
+    HeaderConfig::default().generate()
+}
+
+/// Generate the C header for the library, containing only the items for which `filter` returns
+/// true.
+///
+/// Use [`HeaderConfig::generate_with_filter`] for more control over the generated header.
+pub fn generate_with_filter(filter: impl Fn(&HeaderItem) -> bool) -> String {
+    HeaderConfig::default().generate_with_filter(filter)
+}
+
+/// Generate the C header for the library, passing each item and its rendered content to `post`
+/// and including the result in place of the content.
+///
+/// Use [`HeaderConfig::generate_with`] for more control over the generated header.
+pub fn generate_with(post: impl Fn(&HeaderItem, String) -> String) -> String {
+    HeaderConfig::default().generate_with(post)
+}
+
+/// Get all registered header items, sorted in the order they appear in the header, for tooling
+/// such as release checks that examine the items' metadata.
+pub fn items() -> Vec<&'static HeaderItem> {
+    let mut items = all_items();
+    sort_items(&mut items);
+    items
+}
+
+/// Find the registered header item with the given name.
+pub fn find(name: &str) -> Option<&'static HeaderItem> {
+    all_items().into_iter().find(|hi| hi.name == name)
+}
+
+/// Get the names of all registered header items, in the order they appear in the header.
+pub fn names() -> Vec<&'static str> {
+    items().into_iter().map(|hi| hi.name).collect()
 }
 
-/// Inner version of generate that does not operate on a static value.
-fn generate_from_vec(mut items: Vec<&'static HeaderItem>) -> String {
-    items.sort_by(
-        |a: &&'static HeaderItem, b: &&'static HeaderItem| match a.order.cmp(&b.order) {
-            Ordering::Less => Ordering::Less,
-            Ordering::Equal => a.name.cmp(b.name),
-            Ordering::Greater => Ordering::Greater,
+/// Sort header items by order, and then by module path and name.
+pub(crate) fn sort_items(items: &mut [&HeaderItem]) {
+    sort_items_by(items, SortOrder::default())
+}
+
+/// Sort header items by order, and then as given by `sort_order`.
+pub(crate) fn sort_items_by(items: &mut [&HeaderItem], sort_order: SortOrder) {
+    items.sort_by(|a, b| match a.order.cmp(&b.order) {
+        Ordering::Less => Ordering::Less,
+        Ordering::Equal => match sort_order {
+            SortOrder::ModulePath => a.module_path.cmp(b.module_path).then(a.name.cmp(b.name)),
+            SortOrder::Name => a.name.cmp(b.name),
+            SortOrder::Source => {
+                let krate = |hi: &HeaderItem| hi.module_path.split("::").next().unwrap_or("");
+                krate(a)
+                    .cmp(krate(b))
+                    .then(a.file.cmp(b.file))
+                    .then(a.line.cmp(&b.line))
+                    .then(a.name.cmp(b.name))
+            }
         },
-    );
+        Ordering::Greater => Ordering::Greater,
+    });
+}
 
-    // join the items with blank lines
-    let mut result = join(items.iter().map(|hi| hi.content.trim()), "\n\n");
-    // and ensure a trailing newline
-    if !items.is_empty() {
-        result.push('\n');
+/// Write the generated C header for the library.
+///
+/// The header is written to the file named by the first command-line argument, or to stdout if
+/// no argument is given.  This is the body of the `main` function defined by [`emit_main`], but
+/// can also be called directly from a binary of your own.
+pub fn dump_main() {
+    let header = generate();
+    match std::env::args_os().nth(1) {
+        Some(path) => {
+            if let Err(e) = std::fs::write(&path, header) {
+                eprintln!("could not write {}: {e}", path.to_string_lossy());
+                std::process::exit(1);
+            }
+        }
+        None => print!("{header}"),
     }
-    result
 }
 
 #[cfg(test)]
 mod test {
+    use super::*;
+
+    const ITEM: HeaderItem = HeaderItem {
+        order: 0,
+        module_path: "",
+        file: "",
+        line: 0,
+        name: "",
+        content: "",
+        cpp_content: None,
+        cpp_helper: false,
+        deprecated: None,
+        feature: None,
+        group: None,
+        platform: None,
+        since: None,
+        unstable: false,
+        enabled: true,
+        includes: &[],
+    };
+
     #[test]
-    fn test_generate_order_by_order() {
-        assert_eq!(
-            super::generate_from_vec(vec![
-                &super::HeaderItem {
-                    order: 1,
-                    name: "foo",
-                    content: "one"
-                },
-                &super::HeaderItem {
-                    order: 3,
-                    name: "foo",
-                    content: "three"
-                },
-                &super::HeaderItem {
-                    order: 2,
-                    name: "foo",
-                    content: "two"
-                },
-            ]),
-            String::from("one\n\ntwo\n\nthree\n")
-        );
+    fn test_empty() {
+        assert_eq!(generate(), String::new());
     }
 
     #[test]
-    fn test_generate_order_by_name() {
-        assert_eq!(
-            super::generate_from_vec(vec![
-                &super::HeaderItem {
-                    order: 3,
-                    name: "bbb",
-                    content: "two"
-                },
-                &super::HeaderItem {
-                    order: 3,
-                    name: "ccc",
-                    content: "three"
-                },
-                &super::HeaderItem {
-                    order: 3,
-                    name: "aaa",
-                    content: "one"
-                },
-            ]),
-            String::from("one\n\ntwo\n\nthree\n")
-        );
+    fn test_items_empty() {
+        assert!(items().is_empty());
+        assert!(names().is_empty());
+        assert!(find("add").is_none());
     }
 
     #[test]
-    fn test_empty() {
-        assert_eq!(super::generate(), String::new());
+    fn test_unique_names() {
+        let a = HeaderItem { name: "a", ..ITEM };
+        let b = HeaderItem { name: "b", ..ITEM };
+        check_unique_names(&[&a, &b]);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "duplicate header item name \"foo\" in mylib::a (src/a.rs:10) \
+        and mylib::b (src/b.rs:20)"
+    )]
+    fn test_duplicate_names() {
+        let a = HeaderItem {
+            module_path: "mylib::a",
+            file: "src/a.rs",
+            line: 10,
+            name: "foo",
+            content: "void foo(void);",
+            ..ITEM
+        };
+        let b = HeaderItem {
+            order: 10,
+            module_path: "mylib::b",
+            file: "src/b.rs",
+            line: 20,
+            name: "foo",
+            content: "int foo;",
+            ..ITEM
+        };
+        check_unique_names(&[&a, &b]);
     }
 }
@@ -0,0 +1,160 @@
+use crate::cdecl::{identifiers, parse, strip_declarations, Decl};
+use crate::HeaderItem;
+use std::collections::HashSet;
+
+/// Determine the order in which to include groups of header items, such that each group follows
+/// the groups declaring the names it uses.  The result contains the index of each group in
+/// `groups`, with the indexes of the groups it depends on.
+///
+/// Where there is a choice, groups are kept in their original order.  Dependency cycles cannot be
+/// satisfied, and are broken by taking the earliest remaining group.
+pub(crate) fn group_order(groups: &[(&str, Vec<&HeaderItem>)]) -> Vec<(usize, Vec<usize>)> {
+    let declared: Vec<HashSet<String>> = groups
+        .iter()
+        .map(|(_, items)| {
+            items
+                .iter()
+                .flat_map(|hi| declared_names(hi.content))
+                .collect()
+        })
+        .collect();
+    let deps: Vec<Vec<usize>> = groups
+        .iter()
+        .enumerate()
+        .map(|(i, (_, items))| {
+            let used: HashSet<String> =
+                items.iter().flat_map(|hi| used_names(hi.content)).collect();
+            (0..groups.len())
+                .filter(|j| *j != i && declared[*j].iter().any(|name| used.contains(name)))
+                .collect()
+        })
+        .collect();
+
+    let mut done = vec![false; groups.len()];
+    let mut order = vec![];
+    while order.len() < groups.len() {
+        let next = (0..groups.len())
+            .find(|i| !done[*i] && deps[*i].iter().all(|d| done[*d]))
+            .or_else(|| (0..groups.len()).find(|i| !done[*i]))
+            .unwrap();
+        done[next] = true;
+        order.push((next, deps[next].clone()));
+    }
+    order
+}
+
+/// Get the names of the types, functions, enum variants, and macros declared in C content.
+fn declared_names(content: &str) -> Vec<String> {
+    let mut names = vec![];
+    for decl in parse(content) {
+        match decl {
+            Decl::Function(func) => names.push(func.name),
+            Decl::Opaque { name } | Decl::Struct { name, .. } | Decl::Typedef { name, .. } => {
+                names.push(name)
+            }
+            Decl::Enum { name, variants } => {
+                names.push(name);
+                names.extend(variants.into_iter().map(|(variant, _)| variant));
+            }
+            Decl::Unsupported(_) => {}
+        }
+    }
+    for line in content.lines() {
+        let directive = line.trim_start().strip_prefix('#').map(str::trim_start);
+        if let Some(define) = directive.and_then(|d| d.strip_prefix("define")) {
+            if let Some(name) = identifiers(define).next() {
+                names.push(name.to_string());
+            }
+        }
+    }
+    names
+}
+
+/// Get the identifiers used in the declarations in C content, omitting comments and preprocessor
+/// directives.
+fn used_names(content: &str) -> Vec<String> {
+    identifiers(&strip_declarations(content))
+        .map(String::from)
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const ITEM: HeaderItem = HeaderItem {
+        order: 0,
+        module_path: "",
+        file: "",
+        line: 0,
+        name: "",
+        content: "",
+        cpp_content: None,
+        cpp_helper: false,
+        deprecated: None,
+        feature: None,
+        group: None,
+        platform: None,
+        since: None,
+        unstable: false,
+        enabled: true,
+        includes: &[],
+    };
+
+    #[test]
+    fn test_declared_names() {
+        assert_eq!(
+            declared_names(
+                "// A color.\n#define COLOR_MAX 3\n\
+                typedef enum { RED, GREEN = 2 } color_t;\n\
+                typedef struct pal_t pal_t;\n\
+                pal_t *pal_new(color_t c);"
+            ),
+            vec!["color_t", "RED", "GREEN", "pal_t", "pal_new", "COLOR_MAX"]
+        );
+    }
+
+    #[test]
+    fn test_group_order() {
+        let string = HeaderItem {
+            content: "typedef struct str_t str_t;",
+            ..ITEM
+        };
+        let list = HeaderItem {
+            content: "// A list of str_t.\ntypedef struct list_t list_t;\nstr_t *list_get(list_t *l, size_t i);",
+            ..ITEM
+        };
+        let misc = HeaderItem {
+            content: "void misc(void);",
+            ..ITEM
+        };
+        let map = HeaderItem {
+            content: "typedef struct map_t map_t;\nlist_t *map_keys(map_t *m);\nstr_t *map_get(map_t *m, str_t *k);",
+            ..ITEM
+        };
+        let groups = vec![
+            ("map", vec![&map]),
+            ("list", vec![&list]),
+            ("misc", vec![&misc]),
+            ("string", vec![&string]),
+        ];
+        assert_eq!(
+            group_order(&groups),
+            vec![(2, vec![]), (3, vec![]), (1, vec![3]), (0, vec![1, 3])]
+        );
+    }
+
+    #[test]
+    fn test_group_order_cycle() {
+        let a = HeaderItem {
+            content: "typedef struct a_t a_t;\nvoid a(b_t *b);",
+            ..ITEM
+        };
+        let b = HeaderItem {
+            content: "typedef struct b_t b_t;\nvoid b(a_t *a);",
+            ..ITEM
+        };
+        let groups = vec![("a", vec![&a]), ("b", vec![&b])];
+        assert_eq!(group_order(&groups), vec![(0, vec![1]), (1, vec![0])]);
+    }
+}
@@ -0,0 +1,534 @@
+use crate::cdecl::{doc_lines, parse, CType, Decl, Function};
+use crate::{all_items, sort_items, HeaderItem};
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// Generate a C source file wrapping the library's functions for Node.js, using
+/// [Node-API](https://nodejs.org/api/n-api.html).
+///
+/// Each function is wrapped in a `napi_value`-returning function that converts its arguments and
+/// return value, and all of the wrappers are exported from a module initialization function.
+/// Integers up to 32 bits and floating-point values are converted to numbers, 64-bit integers to
+/// bigints, `const char *` to strings, and pointers to externals.  Functions with other types,
+/// such as structs passed by value, are noted in a comment and left for the author to wrap by
+/// hand.  The `header` is the name of the C header as it would be given to `#include`.
+///
+/// The result is intended as a starting point for a hand-maintained wrapper, rather than a
+/// finished product.  See [`generate_napi_dts`] for the corresponding TypeScript declarations.
+pub fn generate_napi_stub(header: &str) -> String {
+    napi_stub_from_vec(all_items(), header)
+}
+
+/// Generate a TypeScript declaration (`.d.ts`) file describing the module generated by
+/// [`generate_napi_stub`].
+pub fn generate_napi_dts() -> String {
+    napi_dts_from_vec(all_items())
+}
+
+/// How a C type is converted to and from a JavaScript value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Conv {
+    Void,
+    Bool,
+    Int32,
+    Uint32,
+    Int64,
+    Uint64,
+    Double,
+    String,
+    External,
+}
+
+impl Conv {
+    /// Determine the conversion for a C type, if it is supported.
+    fn for_type(ty: &CType, typedefs: &HashMap<String, CType>) -> Option<Conv> {
+        if ty.pointers > 0 {
+            if ty.pointers == 1 && ty.base == "char" && ty.is_const {
+                return Some(Conv::String);
+            }
+            return Some(Conv::External);
+        }
+        Some(match ty.base.as_str() {
+            "void" => Conv::Void,
+            "bool" | "_Bool" => Conv::Bool,
+            "int8_t" | "int16_t" | "int32_t" | "int" | "short" | "signed char" => Conv::Int32,
+            "uint8_t" | "uint16_t" | "uint32_t" | "unsigned" | "unsigned int"
+            | "unsigned short" | "unsigned char" | "char" => Conv::Uint32,
+            "int64_t" | "long long" | "ssize_t" | "intptr_t" => Conv::Int64,
+            "uint64_t" | "unsigned long long" | "size_t" | "uintptr_t" => Conv::Uint64,
+            "float" | "double" => Conv::Double,
+            base => return Conv::for_type(typedefs.get(base)?, typedefs),
+        })
+    }
+
+    /// The TypeScript type for values with this conversion.
+    fn ts_type(self, ty: &CType) -> String {
+        match self {
+            Conv::Void => "void".into(),
+            Conv::Bool => "boolean".into(),
+            Conv::Int32 | Conv::Uint32 | Conv::Double => "number".into(),
+            Conv::Int64 | Conv::Uint64 => "bigint".into(),
+            Conv::String => "string".into(),
+            Conv::External if ty.pointers == 1 && ty.base != "void" && ty.base != "char" => {
+                ty.base.trim_start_matches("struct ").to_string()
+            }
+            Conv::External => "object".into(),
+        }
+    }
+
+    /// Write C code converting `argv[i]` into a new variable of the given name and type.
+    fn write_from_js(self, out: &mut String, ty: &CType, name: &str, i: usize) {
+        let arg = format!("argv[{i}]");
+        match self {
+            Conv::Void => unreachable!(),
+            Conv::Bool => {
+                writeln!(out, "    {};", ty.declarator(name)).unwrap();
+                writeln!(
+                    out,
+                    "    NAPI_CALL(env, napi_get_value_bool(env, {arg}, &{name}));"
+                )
+                .unwrap();
+            }
+            Conv::Int32 | Conv::Uint32 | Conv::Int64 | Conv::Double => {
+                let (c_type, getter) = match self {
+                    Conv::Int32 => ("int32_t", "napi_get_value_int32"),
+                    Conv::Uint32 => ("uint32_t", "napi_get_value_uint32"),
+                    Conv::Int64 => ("int64_t", "napi_get_value_bigint_int64"),
+                    _ => ("double", "napi_get_value_double"),
+                };
+                writeln!(out, "    {c_type} {name}_js;").unwrap();
+                if self == Conv::Int64 {
+                    writeln!(out, "    bool {name}_lossless;").unwrap();
+                    writeln!(
+                        out,
+                        "    NAPI_CALL(env, {getter}(env, {arg}, &{name}_js, &{name}_lossless));"
+                    )
+                    .unwrap();
+                } else {
+                    writeln!(out, "    NAPI_CALL(env, {getter}(env, {arg}, &{name}_js));").unwrap();
+                }
+                writeln!(out, "    {} = ({}){name}_js;", ty.declarator(name), ty.base).unwrap();
+            }
+            Conv::Uint64 => {
+                writeln!(out, "    uint64_t {name}_js;").unwrap();
+                writeln!(out, "    bool {name}_lossless;").unwrap();
+                writeln!(
+                    out,
+                    "    NAPI_CALL(env, napi_get_value_bigint_uint64(env, {arg}, &{name}_js, &{name}_lossless));"
+                )
+                .unwrap();
+                writeln!(out, "    {} = ({}){name}_js;", ty.declarator(name), ty.base).unwrap();
+            }
+            Conv::String => {
+                writeln!(out, "    size_t {name}_len;").unwrap();
+                writeln!(
+                    out,
+                    "    NAPI_CALL(env, napi_get_value_string_utf8(env, {arg}, NULL, 0, &{name}_len));"
+                )
+                .unwrap();
+                writeln!(out, "    char *{name} = malloc({name}_len + 1);").unwrap();
+                writeln!(
+                    out,
+                    "    NAPI_CALL(env, napi_get_value_string_utf8(env, {arg}, {name}, {name}_len + 1, NULL));"
+                )
+                .unwrap();
+            }
+            Conv::External => {
+                writeln!(out, "    void *{name}_js;").unwrap();
+                writeln!(
+                    out,
+                    "    NAPI_CALL(env, napi_get_value_external(env, {arg}, &{name}_js));"
+                )
+                .unwrap();
+                writeln!(out, "    {} = {name}_js;", ty.declarator(name)).unwrap();
+            }
+        }
+    }
+
+    /// Write C code converting the C variable `result` into the JS value `result_js`.
+    fn write_to_js(self, out: &mut String) {
+        let call = match self {
+            Conv::Void => "napi_get_undefined(env, &result_js)",
+            Conv::Bool => "napi_get_boolean(env, result, &result_js)",
+            Conv::Int32 => "napi_create_int32(env, (int32_t)result, &result_js)",
+            Conv::Uint32 => "napi_create_uint32(env, (uint32_t)result, &result_js)",
+            Conv::Int64 => "napi_create_bigint_int64(env, (int64_t)result, &result_js)",
+            Conv::Uint64 => "napi_create_bigint_uint64(env, (uint64_t)result, &result_js)",
+            Conv::Double => "napi_create_double(env, (double)result, &result_js)",
+            Conv::String => "napi_create_string_utf8(env, result, NAPI_AUTO_LENGTH, &result_js)",
+            Conv::External => "napi_create_external(env, (void *)result, NULL, NULL, &result_js)",
+        };
+        writeln!(out, "    napi_value result_js;").unwrap();
+        writeln!(out, "    NAPI_CALL(env, {call});").unwrap();
+    }
+}
+
+/// A function and its documentation.
+type DocFunction = (Vec<String>, Function);
+
+/// Parse the items into functions, and gather the typedefs among them.
+fn functions(mut items: Vec<&HeaderItem>) -> (Vec<DocFunction>, HashMap<String, CType>) {
+    sort_items(&mut items);
+    let mut functions = vec![];
+    let mut typedefs = HashMap::new();
+    for item in items.iter().filter(|hi| hi.in_bindings()) {
+        for decl in parse(item.content) {
+            match decl {
+                Decl::Function(func) => functions.push((doc_lines(item.content), func)),
+                Decl::Typedef { name, ty } => {
+                    typedefs.insert(name, ty);
+                }
+                _ => {}
+            }
+        }
+    }
+    (functions, typedefs)
+}
+
+/// Determine the conversions for a function's return value and parameters, if it is supported.
+fn conversions(func: &Function, typedefs: &HashMap<String, CType>) -> Option<(Conv, Vec<Conv>)> {
+    if func.variadic {
+        return None;
+    }
+    let ret = Conv::for_type(&func.ret, typedefs)?;
+    let params = func
+        .params
+        .iter()
+        .map(|p| match Conv::for_type(&p.ty, typedefs)? {
+            Conv::Void => None,
+            conv => Some(conv),
+        })
+        .collect::<Option<Vec<_>>>()?;
+    Some((ret, params))
+}
+
+/// Inner version of generate_napi_stub that does not operate on a static value.
+fn napi_stub_from_vec(items: Vec<&HeaderItem>, header: &str) -> String {
+    let (functions, typedefs) = functions(items);
+    let mut out = String::new();
+    writeln!(out, "// Generated by ffizz-header; do not edit.").unwrap();
+    writeln!(out, "#include <stdlib.h>").unwrap();
+    writeln!(out, "#include <node_api.h>").unwrap();
+    writeln!(out, "#include {header:?}").unwrap();
+    writeln!(out).unwrap();
+    writeln!(
+        out,
+        "#define NAPI_CALL(env, call)                          \\"
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "  do {{                                                \\"
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "    if ((call) != napi_ok) {{                          \\"
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "      napi_throw_error((env), NULL, #call \" failed\"); \\"
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "      return NULL;                                    \\"
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "    }}                                                 \\"
+    )
+    .unwrap();
+    writeln!(out, "  }} while (0)").unwrap();
+
+    let mut exported = vec![];
+    for (doc, func) in &functions {
+        writeln!(out).unwrap();
+        let Some((ret, params)) = conversions(func, &typedefs) else {
+            writeln!(out, "// unsupported: {}", func.name).unwrap();
+            continue;
+        };
+        for line in doc {
+            writeln!(out, "//{}{line}", if line.is_empty() { "" } else { " " }).unwrap();
+        }
+        writeln!(
+            out,
+            "static napi_value napi_{}(napi_env env, napi_callback_info info) {{",
+            func.name
+        )
+        .unwrap();
+        let n = func.params.len();
+        if n > 0 {
+            writeln!(out, "    size_t argc = {n};").unwrap();
+            writeln!(out, "    napi_value argv[{n}];").unwrap();
+            writeln!(
+                out,
+                "    NAPI_CALL(env, napi_get_cb_info(env, info, &argc, argv, NULL, NULL));"
+            )
+            .unwrap();
+            writeln!(out, "    if (argc < {n}) {{").unwrap();
+            writeln!(
+                out,
+                "        napi_throw_type_error(env, NULL, \"expected {n} arguments\");"
+            )
+            .unwrap();
+            writeln!(out, "        return NULL;").unwrap();
+            writeln!(out, "    }}").unwrap();
+        }
+        for (i, (param, conv)) in func.params.iter().zip(&params).enumerate() {
+            conv.write_from_js(&mut out, &param.ty, &param.name, i);
+        }
+
+        let args: Vec<&str> = func.params.iter().map(|p| p.name.as_str()).collect();
+        let call = format!("{}({})", func.name, args.join(", "));
+        if ret == Conv::Void {
+            writeln!(out, "    {call};").unwrap();
+        } else {
+            writeln!(out, "    {} = {call};", func.ret.declarator("result")).unwrap();
+        }
+        for (param, conv) in func.params.iter().zip(&params) {
+            if *conv == Conv::String {
+                writeln!(out, "    free({});", param.name).unwrap();
+            }
+        }
+        ret.write_to_js(&mut out);
+        writeln!(out, "    return result_js;").unwrap();
+        writeln!(out, "}}").unwrap();
+        exported.push(&func.name);
+    }
+
+    writeln!(out).unwrap();
+    writeln!(
+        out,
+        "static napi_value Init(napi_env env, napi_value exports) {{"
+    )
+    .unwrap();
+    if !exported.is_empty() {
+        writeln!(out, "    napi_property_descriptor props[] = {{").unwrap();
+        for name in &exported {
+            writeln!(
+                out,
+                "        {{\"{name}\", NULL, napi_{name}, NULL, NULL, NULL, napi_default, NULL}},"
+            )
+            .unwrap();
+        }
+        writeln!(out, "    }};").unwrap();
+        writeln!(
+            out,
+            "    NAPI_CALL(env, napi_define_properties(env, exports, sizeof(props) / sizeof(props[0]), props));"
+        )
+        .unwrap();
+    }
+    writeln!(out, "    return exports;").unwrap();
+    writeln!(out, "}}").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "NAPI_MODULE(NODE_GYP_MODULE_NAME, Init)").unwrap();
+    out
+}
+
+/// Inner version of generate_napi_dts that does not operate on a static value.
+fn napi_dts_from_vec(mut items: Vec<&HeaderItem>) -> String {
+    sort_items(&mut items);
+    let opaques: Vec<String> = items
+        .iter()
+        .filter(|hi| hi.in_bindings())
+        .flat_map(|hi| parse(hi.content))
+        .filter_map(|d| match d {
+            Decl::Opaque { name } => Some(name),
+            _ => None,
+        })
+        .collect();
+    let (functions, typedefs) = functions(items);
+
+    let mut out = String::new();
+    writeln!(out, "// Generated by ffizz-header; do not edit.").unwrap();
+    for name in &opaques {
+        writeln!(out).unwrap();
+        writeln!(
+            out,
+            "export type {name} = object & {{ readonly __brand: {name:?} }};"
+        )
+        .unwrap();
+    }
+    for (doc, func) in &functions {
+        let Some((ret, params)) = conversions(func, &typedefs) else {
+            continue;
+        };
+        writeln!(out).unwrap();
+        if !doc.is_empty() {
+            writeln!(out, "/**").unwrap();
+            for line in doc {
+                writeln!(out, " *{}{line}", if line.is_empty() { "" } else { " " }).unwrap();
+            }
+            writeln!(out, " */").unwrap();
+        }
+        let ts_type = |conv: Conv, ty: &CType| {
+            let ts = conv.ts_type(ty);
+            if conv == Conv::External && !opaques.contains(&ts) {
+                "object".to_string()
+            } else {
+                ts
+            }
+        };
+        let params: Vec<String> = func
+            .params
+            .iter()
+            .zip(params)
+            .map(|(p, conv)| format!("{}: {}", p.name, ts_type(conv, &p.ty)))
+            .collect();
+        writeln!(
+            out,
+            "export function {}({}): {};",
+            func.name,
+            params.join(", "),
+            ts_type(ret, &func.ret)
+        )
+        .unwrap();
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const ITEM: HeaderItem = HeaderItem {
+        order: 0,
+        module_path: "",
+        file: "",
+        line: 0,
+        name: "",
+        content: "",
+        cpp_content: None,
+        cpp_helper: false,
+        deprecated: None,
+        feature: None,
+        group: None,
+        platform: None,
+        since: None,
+        unstable: false,
+        enabled: true,
+        includes: &[],
+    };
+
+    const ITEMS: &[HeaderItem] = &[
+        HeaderItem {
+            order: 1,
+            name: "foo_t",
+            content: "typedef struct foo foo_t;",
+            ..ITEM
+        },
+        HeaderItem {
+            order: 2,
+            name: "foo_new",
+            content: "// Create a foo.\nfoo_t *foo_new(const char *name, uint64_t size);",
+            ..ITEM
+        },
+        HeaderItem {
+            order: 3,
+            name: "foo_free",
+            content: "void foo_free(foo_t *foo);",
+            ..ITEM
+        },
+        HeaderItem {
+            order: 4,
+            name: "foo_sum",
+            content: "int foo_sum(int count, ...);",
+            ..ITEM
+        },
+    ];
+
+    #[test]
+    fn test_napi_stub() {
+        assert_eq!(
+            napi_stub_from_vec(ITEMS.iter().collect(), "foo.h"),
+            r#"// Generated by ffizz-header; do not edit.
+#include <stdlib.h>
+#include <node_api.h>
+#include "foo.h"
+
+#define NAPI_CALL(env, call)                          \
+  do {                                                \
+    if ((call) != napi_ok) {                          \
+      napi_throw_error((env), NULL, #call " failed"); \
+      return NULL;                                    \
+    }                                                 \
+  } while (0)
+
+// Create a foo.
+static napi_value napi_foo_new(napi_env env, napi_callback_info info) {
+    size_t argc = 2;
+    napi_value argv[2];
+    NAPI_CALL(env, napi_get_cb_info(env, info, &argc, argv, NULL, NULL));
+    if (argc < 2) {
+        napi_throw_type_error(env, NULL, "expected 2 arguments");
+        return NULL;
+    }
+    size_t name_len;
+    NAPI_CALL(env, napi_get_value_string_utf8(env, argv[0], NULL, 0, &name_len));
+    char *name = malloc(name_len + 1);
+    NAPI_CALL(env, napi_get_value_string_utf8(env, argv[0], name, name_len + 1, NULL));
+    uint64_t size_js;
+    bool size_lossless;
+    NAPI_CALL(env, napi_get_value_bigint_uint64(env, argv[1], &size_js, &size_lossless));
+    uint64_t size = (uint64_t)size_js;
+    foo_t *result = foo_new(name, size);
+    free(name);
+    napi_value result_js;
+    NAPI_CALL(env, napi_create_external(env, (void *)result, NULL, NULL, &result_js));
+    return result_js;
+}
+
+static napi_value napi_foo_free(napi_env env, napi_callback_info info) {
+    size_t argc = 1;
+    napi_value argv[1];
+    NAPI_CALL(env, napi_get_cb_info(env, info, &argc, argv, NULL, NULL));
+    if (argc < 1) {
+        napi_throw_type_error(env, NULL, "expected 1 arguments");
+        return NULL;
+    }
+    void *foo_js;
+    NAPI_CALL(env, napi_get_value_external(env, argv[0], &foo_js));
+    foo_t *foo = foo_js;
+    foo_free(foo);
+    napi_value result_js;
+    NAPI_CALL(env, napi_get_undefined(env, &result_js));
+    return result_js;
+}
+
+// unsupported: foo_sum
+
+static napi_value Init(napi_env env, napi_value exports) {
+    napi_property_descriptor props[] = {
+        {"foo_new", NULL, napi_foo_new, NULL, NULL, NULL, napi_default, NULL},
+        {"foo_free", NULL, napi_foo_free, NULL, NULL, NULL, napi_default, NULL},
+    };
+    NAPI_CALL(env, napi_define_properties(env, exports, sizeof(props) / sizeof(props[0]), props));
+    return exports;
+}
+
+NAPI_MODULE(NODE_GYP_MODULE_NAME, Init)
+"#
+        );
+    }
+
+    #[test]
+    fn test_napi_dts() {
+        assert_eq!(
+            napi_dts_from_vec(ITEMS.iter().collect()),
+            r#"// Generated by ffizz-header; do not edit.
+
+export type foo_t = object & { readonly __brand: "foo_t" };
+
+/**
+ * Create a foo.
+ */
+export function foo_new(name: string, size: bigint): foo_t;
+
+export function foo_free(foo: foo_t): void;
+"#
+        );
+    }
+}
@@ -0,0 +1,248 @@
+use crate::cdecl::{doc_lines, parse, CType, Decl, Field};
+use crate::{all_items, sort_items, HeaderItem};
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+/// Generate a Cython `.pxd` file for the library.
+///
+/// The declarations are converted to Cython syntax in a `cdef extern from "<header>":` block,
+/// where `header` is the name of the C header as it would be given to `#include`.  Opaque types
+/// are declared as `ctypedef struct`, and the comments in each item are retained.  Declarations
+/// which Cython cannot represent are included as comments.  Items with the `cpp_helper` property
+/// are omitted.
+pub fn generate_pxd(header: &str) -> String {
+    pxd_from_vec(all_items(), header)
+}
+
+/// Integer types that must be imported from `libc.stdint`.
+const STDINT_TYPES: &[&str] = &[
+    "int8_t",
+    "int16_t",
+    "int32_t",
+    "int64_t",
+    "uint8_t",
+    "uint16_t",
+    "uint32_t",
+    "uint64_t",
+    "intptr_t",
+    "uintptr_t",
+];
+
+/// Inner version of generate_pxd that does not operate on a static value.
+fn pxd_from_vec(mut items: Vec<&HeaderItem>, header: &str) -> String {
+    sort_items(&mut items);
+    let mut body = String::new();
+    let mut stdint = BTreeSet::new();
+    let mut use_type = |ty: &CType| {
+        if STDINT_TYPES.contains(&ty.base.as_str()) {
+            stdint.insert(ty.base.clone());
+        }
+    };
+
+    for item in items.iter().filter(|hi| hi.in_bindings()) {
+        let decls = parse(item.content);
+        if decls.is_empty() {
+            continue;
+        }
+        writeln!(body).unwrap();
+        for line in doc_lines(item.content) {
+            writeln!(
+                body,
+                "    #{}{line}",
+                if line.is_empty() { "" } else { " " }
+            )
+            .unwrap();
+        }
+        for decl in decls {
+            match decl {
+                Decl::Function(func) => {
+                    use_type(&func.ret);
+                    let mut params: Vec<String> = func
+                        .params
+                        .iter()
+                        .map(|p| {
+                            use_type(&p.ty);
+                            declarator(&p.ty, &python_ident(&p.name))
+                        })
+                        .collect();
+                    if func.variadic {
+                        params.push("...".into());
+                    }
+                    let params = params.join(", ");
+                    writeln!(body, "    {}({params})", declarator(&func.ret, &func.name)).unwrap();
+                }
+                Decl::Opaque { name } => {
+                    writeln!(body, "    ctypedef struct {name}").unwrap();
+                }
+                Decl::Struct { name, fields } => {
+                    writeln!(body, "    ctypedef struct {name}:").unwrap();
+                    for Field { name, ty } in &fields {
+                        use_type(ty);
+                        writeln!(body, "        {}", declarator(ty, &python_ident(name))).unwrap();
+                    }
+                    if fields.is_empty() {
+                        writeln!(body, "        pass").unwrap();
+                    }
+                }
+                Decl::Enum { name, variants } => {
+                    writeln!(body, "    ctypedef enum {name}:").unwrap();
+                    for (variant, _) in &variants {
+                        writeln!(body, "        {variant}").unwrap();
+                    }
+                    if variants.is_empty() {
+                        writeln!(body, "        pass").unwrap();
+                    }
+                }
+                Decl::Typedef { name, ty } => {
+                    use_type(&ty);
+                    writeln!(body, "    ctypedef {}", declarator(&ty, &name)).unwrap();
+                }
+                Decl::Unsupported(text) => {
+                    writeln!(body, "    # unsupported: {text}").unwrap();
+                }
+            }
+        }
+    }
+
+    let mut out = String::new();
+    writeln!(out, "# Generated by ffizz-header; do not edit.").unwrap();
+    if !stdint.is_empty() {
+        let stdint: Vec<String> = stdint.into_iter().collect();
+        writeln!(out, "from libc.stdint cimport {}", stdint.join(", ")).unwrap();
+    }
+    writeln!(out).unwrap();
+    writeln!(out, "cdef extern from {header:?}:").unwrap();
+    if body.is_empty() {
+        writeln!(out, "    pass").unwrap();
+    } else {
+        // skip the blank line preceding the first item
+        out.push_str(&body[1..]);
+    }
+    out
+}
+
+/// Format a Cython declarator, such as `const char *name`.
+fn declarator(ty: &CType, name: &str) -> String {
+    let base = ty.base.as_str();
+    let base = base
+        .strip_prefix("struct ")
+        .or_else(|| base.strip_prefix("enum "))
+        .unwrap_or(base);
+    let base = match base {
+        "bool" | "_Bool" => "bint",
+        _ => base,
+    };
+    format!(
+        "{}{base} {}{name}",
+        if ty.is_const { "const " } else { "" },
+        "*".repeat(ty.pointers)
+    )
+}
+
+/// Rename identifiers which are Python keywords, by appending an underscore.
+fn python_ident(name: &str) -> String {
+    const KEYWORDS: &[&str] = &[
+        "and", "as", "assert", "async", "await", "class", "def", "del", "elif", "except",
+        "finally", "from", "global", "import", "in", "is", "lambda", "nonlocal", "not", "or",
+        "pass", "raise", "try", "with", "yield", "cdef", "cpdef", "ctypedef", "cimport", "include",
+        "print",
+    ];
+    if KEYWORDS.contains(&name) {
+        format!("{name}_")
+    } else {
+        name.to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const ITEM: HeaderItem = HeaderItem {
+        order: 0,
+        module_path: "",
+        file: "",
+        line: 0,
+        name: "",
+        content: "",
+        cpp_content: None,
+        cpp_helper: false,
+        deprecated: None,
+        feature: None,
+        group: None,
+        platform: None,
+        since: None,
+        unstable: false,
+        enabled: true,
+        includes: &[],
+    };
+
+    #[test]
+    fn test_pxd_empty() {
+        assert_eq!(
+            pxd_from_vec(vec![], "mylib.h"),
+            "# Generated by ffizz-header; do not edit.\n\ncdef extern from \"mylib.h\":\n    pass\n"
+        );
+    }
+
+    #[test]
+    fn test_pxd() {
+        let items = [
+            HeaderItem {
+                order: 1,
+                name: "topmatter",
+                content: "#include <stdint.h>",
+                ..ITEM
+            },
+            HeaderItem {
+                order: 2,
+                name: "foo_t",
+                content: "// A foo.\ntypedef struct foo foo_t;",
+                ..ITEM
+            },
+            HeaderItem {
+                order: 3,
+                name: "foo_new",
+                content: "// Create a foo.\n//\n// Returns NULL on error.\nfoo_t *foo_new(const char *name, bool in);",
+                ..ITEM
+            },
+            HeaderItem {
+                order: 4,
+                name: "point_t",
+                content: "typedef struct point_t {\n  int32_t x;\n  uint64_t y;\n} point_t;\ntypedef enum { RED, GREEN = 2 } color_t;",
+                ..ITEM
+            },
+            HeaderItem {
+                order: 5,
+                name: "foo_printf",
+                content: "int foo_printf(const char *fmt, ...);\ntypedef void (*cb_t)(void);",
+                ..ITEM
+            },
+        ];
+        assert_eq!(
+            pxd_from_vec(items.iter().collect(), "mylib.h"),
+            r#"# Generated by ffizz-header; do not edit.
+from libc.stdint cimport int32_t, uint64_t
+
+cdef extern from "mylib.h":
+    # A foo.
+    ctypedef struct foo_t
+
+    # Create a foo.
+    #
+    # Returns NULL on error.
+    foo_t *foo_new(const char *name, bint in_)
+
+    ctypedef struct point_t:
+        int32_t x
+        uint64_t y
+    ctypedef enum color_t:
+        RED
+        GREEN
+
+    int foo_printf(const char *fmt, ...)
+    # unsupported: typedef void ( * cb_t ) ( void )
+"#
+        );
+    }
+}
@@ -0,0 +1,67 @@
+use crate::diff::unified_diff;
+use std::path::Path;
+
+/// Assert that the generated header matches the content of a file.
+///
+/// The path is relative to the directory containing the calling crate's `Cargo.toml`.  On a
+/// mismatch, this panics with a unified diff from the file's content to the generated header.
+///
+/// This is intended for use in a test in the library crate, so that a stale header is caught by
+/// `cargo test`:
+///
+/// ```ignore
+/// #[test]
+/// fn header_is_current() {
+///     ffizz_header::assert_header_matches!("include/mylib.h");
+/// }
+/// ```
+#[macro_export]
+macro_rules! assert_header_matches {
+    ($path:expr) => {
+        $crate::assert_header_matches_impl(
+            &::std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join($path),
+        )
+    };
+}
+
+/// Implementation of `assert_header_matches!`.
+#[doc(hidden)]
+pub fn assert_header_matches_impl(path: &Path) {
+    let expected = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) => panic!("could not read {}: {e}", path.display()),
+    };
+    check_header(path, &expected, &crate::generate());
+}
+
+/// Panic if `expected` (the content of `path`) does not match `generated`.
+fn check_header(path: &Path, expected: &str, generated: &str) {
+    if expected != generated {
+        let diff = unified_diff(
+            &path.display().to_string(),
+            "generated",
+            expected,
+            generated,
+        );
+        panic!(
+            "generated header does not match {}; regenerate it to fix:\n{diff}",
+            path.display()
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_matches() {
+        check_header(Path::new("test.h"), "int x;\n", "int x;\n");
+    }
+
+    #[test]
+    #[should_panic(expected = "-int x;\n+int y;")]
+    fn test_mismatch() {
+        check_header(Path::new("test.h"), "int x;\n", "int y;\n");
+    }
+}
@@ -0,0 +1,113 @@
+use crate::cdecl::{doc_lines, strip_declarations};
+use crate::{all_items, sort_items, HeaderItem};
+use std::fmt::Write;
+
+/// Generate a [SWIG](https://www.swig.org/) interface file for the library.
+///
+/// The result declares the named SWIG module, includes the C header (named as it would be given
+/// to `#include`) in the generated wrapper code, and then repeats the library's declarations for
+/// SWIG to wrap.  Preprocessor directives are omitted from the declarations, as the header's
+/// include guards and `EXTERN_C` definitions are not meaningful to SWIG.  Items with the
+/// `cpp_helper` property are omitted.
+pub fn generate_swig(module: &str, header: &str) -> String {
+    swig_from_vec(all_items(), module, header)
+}
+
+/// Inner version of generate_swig that does not operate on a static value.
+fn swig_from_vec(mut items: Vec<&HeaderItem>, module: &str, header: &str) -> String {
+    sort_items(&mut items);
+    let mut out = String::new();
+    writeln!(out, "// Generated by ffizz-header; do not edit.").unwrap();
+    writeln!(out, "%module {module}").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "%{{").unwrap();
+    writeln!(out, "#include {header:?}").unwrap();
+    writeln!(out, "%}}").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "%include <stdint.i>").unwrap();
+
+    for item in items.iter().filter(|hi| hi.in_bindings()) {
+        let decls = strip_declarations(item.content);
+        if decls.is_empty() {
+            continue;
+        }
+        writeln!(out).unwrap();
+        for line in doc_lines(item.content) {
+            writeln!(out, "//{}{line}", if line.is_empty() { "" } else { " " }).unwrap();
+        }
+        writeln!(out, "{decls}").unwrap();
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const ITEM: HeaderItem = HeaderItem {
+        order: 0,
+        module_path: "",
+        file: "",
+        line: 0,
+        name: "",
+        content: "",
+        cpp_content: None,
+        cpp_helper: false,
+        deprecated: None,
+        feature: None,
+        group: None,
+        platform: None,
+        since: None,
+        unstable: false,
+        enabled: true,
+        includes: &[],
+    };
+
+    #[test]
+    fn test_swig() {
+        let items = [
+            HeaderItem {
+                order: 1,
+                name: "topmatter",
+                content: "// MyLib\n#ifndef MYLIB_H\n#define MYLIB_H\n#include <stdint.h>",
+                ..ITEM
+            },
+            HeaderItem {
+                order: 2,
+                name: "add",
+                content: "// Add two numbers.\n//\n// Really.\nEXTERN_C uint64_t add(uint64_t a, uint64_t b);",
+                ..ITEM
+            },
+            HeaderItem {
+                order: 3,
+                name: "add_cpp",
+                content: "inline uint64_t add3(uint64_t a, uint64_t b, uint64_t c);",
+                cpp_helper: true,
+                ..ITEM
+            },
+            HeaderItem {
+                order: 4,
+                name: "bottomatter",
+                content: "#endif",
+                ..ITEM
+            },
+        ];
+        assert_eq!(
+            swig_from_vec(items.iter().collect(), "mylib", "mylib.h"),
+            r#"// Generated by ffizz-header; do not edit.
+%module mylib
+
+%{
+#include "mylib.h"
+%}
+
+%include <stdint.i>
+
+// Add two numbers.
+//
+// Really.
+uint64_t add(uint64_t a, uint64_t b);
+"#
+        );
+    }
+}
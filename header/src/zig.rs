@@ -0,0 +1,322 @@
+use crate::cdecl::{doc_lines, parse, CType, Decl};
+use crate::{all_items, sort_items, HeaderItem};
+use std::collections::HashSet;
+use std::fmt::Write;
+
+/// Generate Zig bindings for the library.
+///
+/// The result is a `.zig` file with a `pub extern fn` for each function, `opaque {}` types for
+/// opaque struct typedefs, and `extern struct` and `enum(c_int)` types for struct and enum
+/// typedefs.  The comments in each item are used as doc comments.  Declarations which cannot be
+/// represented in Zig are included as comments.  Items with the `cpp_helper` property are
+/// omitted.
+pub fn generate_zig() -> String {
+    zig_from_vec(all_items())
+}
+
+/// Inner version of generate_zig that does not operate on a static value.
+fn zig_from_vec(mut items: Vec<&HeaderItem>) -> String {
+    sort_items(&mut items);
+    let items: Vec<(Vec<String>, Vec<Decl>)> = items
+        .iter()
+        .filter(|hi| hi.in_bindings())
+        .map(|hi| (doc_lines(hi.content), parse(hi.content)))
+        .filter(|(_, decls)| !decls.is_empty())
+        .collect();
+    let opaques: HashSet<&str> = items
+        .iter()
+        .flat_map(|(_, decls)| decls)
+        .filter_map(|d| match d {
+            Decl::Opaque { name } => Some(name.as_str()),
+            _ => None,
+        })
+        .collect();
+
+    let mut out = String::new();
+    writeln!(out, "// Generated by ffizz-header; do not edit.").unwrap();
+    for (doc, decls) in &items {
+        writeln!(out).unwrap();
+        for decl in decls {
+            if !matches!(decl, Decl::Unsupported(_)) {
+                for line in doc {
+                    writeln!(out, "///{}{line}", if line.is_empty() { "" } else { " " }).unwrap();
+                }
+            }
+            match decl {
+                Decl::Function(func) => {
+                    let mut params: Vec<String> = func
+                        .params
+                        .iter()
+                        .map(|p| format!("{}: {}", zig_ident(&p.name), zig_type(&p.ty, &opaques)))
+                        .collect();
+                    if func.variadic {
+                        params.push("...".into());
+                    }
+                    writeln!(
+                        out,
+                        "pub extern fn {}({}) {};",
+                        zig_ident(&func.name),
+                        params.join(", "),
+                        zig_type(&func.ret, &opaques)
+                    )
+                    .unwrap();
+                }
+                Decl::Opaque { name } => {
+                    writeln!(out, "pub const {} = opaque {{}};", zig_ident(name)).unwrap();
+                }
+                Decl::Struct { name, fields } => {
+                    writeln!(out, "pub const {} = extern struct {{", zig_ident(name)).unwrap();
+                    for f in fields {
+                        writeln!(
+                            out,
+                            "    {}: {},",
+                            zig_ident(&f.name),
+                            zig_type(&f.ty, &opaques)
+                        )
+                        .unwrap();
+                    }
+                    writeln!(out, "}};").unwrap();
+                }
+                Decl::Enum { name, variants } => {
+                    writeln!(out, "pub const {} = enum(c_int) {{", zig_ident(name)).unwrap();
+                    for (variant, value) in variants {
+                        match value {
+                            Some(value) => {
+                                writeln!(out, "    {} = {value},", zig_ident(variant)).unwrap()
+                            }
+                            None => writeln!(out, "    {},", zig_ident(variant)).unwrap(),
+                        }
+                    }
+                    writeln!(out, "}};").unwrap();
+                }
+                Decl::Typedef { name, ty } => {
+                    writeln!(
+                        out,
+                        "pub const {} = {};",
+                        zig_ident(name),
+                        zig_type(ty, &opaques)
+                    )
+                    .unwrap();
+                }
+                Decl::Unsupported(text) => {
+                    writeln!(out, "// unsupported: {text}").unwrap();
+                }
+            }
+        }
+    }
+    out
+}
+
+/// Map a C type to a Zig type.  Pointers to opaque types and `void` become optional single-item
+/// pointers, and other pointers become C pointers.
+fn zig_type(ty: &CType, opaques: &HashSet<&str>) -> String {
+    let base = ty.base.as_str();
+    let base = base
+        .strip_prefix("struct ")
+        .or_else(|| base.strip_prefix("enum "))
+        .unwrap_or(base);
+    let mapped = match base {
+        "void" if ty.pointers > 0 => "anyopaque",
+        "void" => "void",
+        "bool" | "_Bool" => "bool",
+        "char" | "unsigned char" | "uint8_t" => "u8",
+        "signed char" | "int8_t" => "i8",
+        "uint16_t" => "u16",
+        "int16_t" => "i16",
+        "uint32_t" => "u32",
+        "int32_t" => "i32",
+        "uint64_t" => "u64",
+        "int64_t" => "i64",
+        "size_t" | "uintptr_t" => "usize",
+        "ssize_t" | "intptr_t" | "ptrdiff_t" => "isize",
+        "short" | "short int" => "c_short",
+        "unsigned short" | "unsigned short int" => "c_ushort",
+        "int" | "signed" | "signed int" => "c_int",
+        "unsigned" | "unsigned int" => "c_uint",
+        "long" | "long int" => "c_long",
+        "unsigned long" | "unsigned long int" => "c_ulong",
+        "long long" | "long long int" => "c_longlong",
+        "unsigned long long" | "unsigned long long int" => "c_ulonglong",
+        "float" => "f32",
+        "double" => "f64",
+        _ => return pointer_to(ty, &zig_ident(base), opaques.contains(base)),
+    };
+    pointer_to(ty, mapped, mapped == "anyopaque")
+}
+
+/// Wrap a Zig type in the pointers described by `ty`.
+fn pointer_to(ty: &CType, base: &str, is_opaque: bool) -> String {
+    if ty.pointers == 0 {
+        return base.to_string();
+    }
+    let konst = if ty.is_const { "const " } else { "" };
+    let innermost = if is_opaque {
+        format!("?*{konst}{base}")
+    } else {
+        format!("[*c]{konst}{base}")
+    };
+    format!("{}{innermost}", "[*c]".repeat(ty.pointers - 1))
+}
+
+/// Quote identifiers which are Zig keywords or primitive type names.
+fn zig_ident(name: &str) -> String {
+    const RESERVED: &[&str] = &[
+        "addrspace",
+        "align",
+        "allowzero",
+        "and",
+        "anyframe",
+        "anytype",
+        "asm",
+        "async",
+        "await",
+        "break",
+        "callconv",
+        "catch",
+        "comptime",
+        "const",
+        "continue",
+        "defer",
+        "else",
+        "enum",
+        "errdefer",
+        "error",
+        "export",
+        "extern",
+        "fn",
+        "for",
+        "if",
+        "inline",
+        "linksection",
+        "noalias",
+        "noinline",
+        "nosuspend",
+        "opaque",
+        "or",
+        "orelse",
+        "packed",
+        "pub",
+        "resume",
+        "return",
+        "struct",
+        "suspend",
+        "switch",
+        "test",
+        "threadlocal",
+        "try",
+        "type",
+        "union",
+        "unreachable",
+        "usingnamespace",
+        "var",
+        "volatile",
+        "while",
+        "bool",
+        "void",
+        "null",
+        "undefined",
+        "true",
+        "false",
+    ];
+    let is_int_type = |n: &str| {
+        n.len() > 1
+            && (n.starts_with('u') || n.starts_with('i'))
+            && n[1..].chars().all(|c| c.is_ascii_digit())
+    };
+    if RESERVED.contains(&name) || is_int_type(name) {
+        format!("@\"{name}\"")
+    } else {
+        name.to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const ITEM: HeaderItem = HeaderItem {
+        order: 0,
+        module_path: "",
+        file: "",
+        line: 0,
+        name: "",
+        content: "",
+        cpp_content: None,
+        cpp_helper: false,
+        deprecated: None,
+        feature: None,
+        group: None,
+        platform: None,
+        since: None,
+        unstable: false,
+        enabled: true,
+        includes: &[],
+    };
+
+    #[test]
+    fn test_zig_ident() {
+        assert_eq!(zig_ident("foo"), "foo");
+        assert_eq!(zig_ident("type"), "@\"type\"");
+        assert_eq!(zig_ident("u8"), "@\"u8\"");
+        assert_eq!(zig_ident("u"), "u");
+    }
+
+    #[test]
+    fn test_zig() {
+        let items = [
+            HeaderItem {
+                order: 1,
+                name: "topmatter",
+                content: "#include <stdint.h>",
+                ..ITEM
+            },
+            HeaderItem {
+                order: 2,
+                name: "foo_t",
+                content: "// A foo.\ntypedef struct foo foo_t;",
+                ..ITEM
+            },
+            HeaderItem {
+                order: 3,
+                name: "foo_new",
+                content: "// Create a foo.\nfoo_t *foo_new(const char *name, void *data, size_t *type);",
+                ..ITEM
+            },
+            HeaderItem {
+                order: 4,
+                name: "point_t",
+                content: "typedef struct point_t {\n  int32_t x;\n  const foo_t *foo;\n} point_t;\ntypedef enum { RED, GREEN = 2 } color_t;",
+                ..ITEM
+            },
+            HeaderItem {
+                order: 5,
+                name: "foo_printf",
+                content: "int foo_printf(const char *fmt, ...);\ntypedef void (*cb_t)(void);",
+                ..ITEM
+            },
+        ];
+        assert_eq!(
+            zig_from_vec(items.iter().collect()),
+            r#"// Generated by ffizz-header; do not edit.
+
+/// A foo.
+pub const foo_t = opaque {};
+
+/// Create a foo.
+pub extern fn foo_new(name: [*c]const u8, data: ?*anyopaque, @"type": [*c]usize) ?*foo_t;
+
+pub const point_t = extern struct {
+    x: i32,
+    foo: ?*const foo_t,
+};
+pub const color_t = enum(c_int) {
+    RED,
+    GREEN = 2,
+};
+
+pub extern fn foo_printf(fmt: [*c]const u8, ...) c_int;
+// unsupported: typedef void ( * cb_t ) ( void )
+"#
+        );
+    }
+}
@@ -0,0 +1,257 @@
+use crate::headeritem::HeaderItem;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
+use syn::parse::{Error, Parse, ParseStream, Result};
+
+/// BoxedApi is the result of parsing the input to `boxed_api!`: a Rust type, the prefix for its
+/// C names, whether to generate a clone function, and whether to declare a read-only C type.
+#[derive(Debug)]
+pub(crate) struct BoxedApi {
+    attrs: Vec<syn::Attribute>,
+    rtype: syn::Type,
+    prefix: String,
+    clone: bool,
+    const_handle: bool,
+    header_item: HeaderItem,
+}
+
+impl Parse for BoxedApi {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let mut attrs = input.call(syn::Attribute::parse_outer)?;
+        let rtype: syn::Type = input.parse()?;
+        input.parse::<syn::Token![,]>()?;
+        let prefix = input.parse::<syn::LitStr>()?.value();
+        let mut clone = false;
+        let mut const_handle = false;
+        while input.parse::<Option<syn::Token![,]>>()?.is_some() && !input.is_empty() {
+            if input.parse::<Option<syn::Token![const]>>()?.is_some() {
+                const_handle = true;
+                continue;
+            }
+            let flag: syn::Ident = input.parse()?;
+            if flag != "clone" {
+                return Err(Error::new_spanned(
+                    flag,
+                    "unknown flag; the valid flags are `clone` and `const`",
+                ));
+            }
+            clone = true;
+        }
+        if !input.is_empty() {
+            return Err(input.error(
+                "expected `TypeName, \"prefix\"`, optionally followed by `clone` or `const`",
+            ));
+        }
+
+        let ctype = format!("{prefix}_t");
+        let mut header_item = HeaderItem::from_attrs(ctype.clone(), &mut attrs)?;
+        let mut decl = format!("typedef struct {ctype} {ctype};\n");
+        let const_ctype = if const_handle {
+            decl.push_str(&format!(
+                "// A read-only {ctype}.  Functions taking a {prefix}_const_t do not modify it.\n\
+                typedef const {ctype} {prefix}_const_t;\n"
+            ));
+            format!("{prefix}_const_t")
+        } else {
+            format!("const {ctype}")
+        };
+        decl.push_str(&format!(
+            "\n\
+            // Create a new {ctype} with its default value.  The value must later be freed with\n\
+            // {prefix}_free.\n\
+            {ctype} *{prefix}_new(void);\n\
+            \n\
+            // Free a {ctype}.  If ptr is NULL, this does nothing.  The pointer must not be used\n\
+            // after this call.\n\
+            void {prefix}_free({ctype} *ptr);"
+        ));
+        if clone {
+            decl.push_str(&format!(
+                "\n\
+                \n\
+                // Create a copy of a {ctype}, returning NULL if ptr is NULL.  The copy must later be\n\
+                // freed with {prefix}_free.\n\
+                {ctype} *{prefix}_clone({const_ctype} *ptr);"
+            ));
+        }
+        if header_item.content.is_empty() {
+            header_item.content = decl;
+        } else {
+            header_item.content = format!("{}\n{decl}", header_item.content);
+        }
+
+        Ok(BoxedApi {
+            attrs,
+            rtype,
+            prefix,
+            clone,
+            const_handle,
+            header_item,
+        })
+    }
+}
+
+impl BoxedApi {
+    /// Convert this BoxedApi into a TokenStream containing the C type alias, the functions, and
+    /// the header item.
+    pub(crate) fn to_tokens(&self, tokens: &mut TokenStream2) {
+        let BoxedApi {
+            attrs,
+            rtype,
+            prefix,
+            ..
+        } = self;
+        let ident =
+            |suffix: &str| syn::Ident::new(&format!("{prefix}_{suffix}"), Span::call_site());
+        let (ctype, new, free, clone) = (ident("t"), ident("new"), ident("free"), ident("clone"));
+        let const_ctype = if self.const_handle {
+            ident("const_t")
+        } else {
+            ctype.clone()
+        };
+        let new_doc = format!("Create a new `{ctype}` with its default value.");
+        let free_doc = format!("Free a `{ctype}`, doing nothing if `ptr` is NULL.");
+        let clone_doc = format!("Create a copy of a `{ctype}`, returning NULL if `ptr` is NULL.");
+
+        tokens.extend(quote! {
+            #(#attrs)*
+            #[allow(non_camel_case_types)]
+            pub type #ctype = #rtype;
+        });
+
+        if self.const_handle {
+            let const_doc = format!("A read-only `{ctype}`, passed as `*const {const_ctype}`.");
+            tokens.extend(quote! {
+                #[doc = #const_doc]
+                #[allow(non_camel_case_types)]
+                pub type #const_ctype = #rtype;
+            });
+        }
+
+        tokens.extend(quote! {
+            #[doc = #new_doc]
+            #[no_mangle]
+            pub extern "C" fn #new() -> *mut #ctype {
+                // SAFETY: the C caller frees the value with the free function (documented in the
+                // header)
+                unsafe {
+                    ::ffizz_passby::Boxed::<#rtype>::return_val(
+                        <#rtype as ::std::default::Default>::default(),
+                    )
+                }
+            }
+
+            #[doc = #free_doc]
+            ///
+            /// # Safety
+            ///
+            /// * `ptr` must be NULL or a value returned from a function in this API.
+            /// * `ptr` must not be used after this call.
+            #[no_mangle]
+            pub unsafe extern "C" fn #free(ptr: *mut #ctype) {
+                // SAFETY: see docstring
+                ::std::mem::drop(unsafe { ::ffizz_passby::Boxed::<#rtype>::try_take(ptr) });
+            }
+        });
+
+        if self.clone {
+            tokens.extend(quote! {
+                #[doc = #clone_doc]
+                ///
+                /// # Safety
+                ///
+                /// * `ptr` must be NULL or a value returned from a function in this API.
+                /// * No other thread may mutate the value pointed to by `ptr` until this function
+                ///   returns.
+                #[no_mangle]
+                pub unsafe extern "C" fn #clone(ptr: *const #const_ctype) -> *mut #ctype {
+                    // SAFETY: see docstring; the C caller frees the copy
+                    unsafe { ::ffizz_passby::Boxed::<#rtype>::clone_ptr(ptr) }
+                }
+            });
+        }
+
+        self.header_item.to_tokens(tokens);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_boxed_api() {
+        let api: BoxedApi = syn::parse_quote! {
+            /// A counter.
+            #[ffizz(order=100)]
+            Counter, "hittr_counter"
+        };
+        assert!(!api.clone);
+        assert_eq!(
+            api.header_item,
+            HeaderItem {
+                order: 100,
+                name: "hittr_counter_t".into(),
+                content: "// A counter.\n\
+                    typedef struct hittr_counter_t hittr_counter_t;\n\
+                    \n\
+                    // Create a new hittr_counter_t with its default value.  The value must later be freed with\n\
+                    // hittr_counter_free.\n\
+                    hittr_counter_t *hittr_counter_new(void);\n\
+                    \n\
+                    // Free a hittr_counter_t.  If ptr is NULL, this does nothing.  The pointer must not be used\n\
+                    // after this call.\n\
+                    void hittr_counter_free(hittr_counter_t *ptr);"
+                    .into(),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_boxed_api_clone() {
+        let api: BoxedApi = syn::parse_quote! {
+            crate::Counter, "counter", clone,
+        };
+        assert!(api.clone);
+        assert!(api
+            .header_item
+            .content
+            .starts_with("typedef struct counter_t counter_t;\n"));
+        assert!(api
+            .header_item
+            .content
+            .ends_with("\ncounter_t *counter_clone(const counter_t *ptr);"));
+    }
+
+    #[test]
+    fn test_boxed_api_const() {
+        let api: BoxedApi = syn::parse_quote! {
+            Counter, "counter", clone, const
+        };
+        assert!(api.clone);
+        assert!(api.const_handle);
+        assert!(api.header_item.content.starts_with(
+            "typedef struct counter_t counter_t;\n\
+            // A read-only counter_t.  Functions taking a counter_const_t do not modify it.\n\
+            typedef const counter_t counter_const_t;\n\
+            \n"
+        ));
+        assert!(api
+            .header_item
+            .content
+            .ends_with("\ncounter_t *counter_clone(counter_const_t *ptr);"));
+    }
+
+    #[test]
+    fn test_boxed_api_unknown_flag() {
+        let res: Result<BoxedApi> = syn::parse2(quote! { Counter, "counter", copy });
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_boxed_api_no_prefix() {
+        let res: Result<BoxedApi> = syn::parse2(quote! { Counter });
+        assert!(res.is_err());
+    }
+}
@@ -0,0 +1,356 @@
+use crate::headeritem::HeaderItem;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
+use syn::parse::{Error, Parse, ParseStream, Result};
+
+/// The properties allowed in `#[cenum(..)]`, for error messages.
+const PROPERTIES: &str = "ctype=\"..\", prefix=\"..\", and header";
+
+/// CEnum is the result of parsing a fieldless enum deriving `CEnum`.
+#[derive(Debug)]
+pub(crate) struct CEnum {
+    ident: syn::Ident,
+    vis: syn::Visibility,
+    ctype: Option<String>,
+    variants: Vec<Variant>,
+    header_item: Option<HeaderItem>,
+}
+
+/// A variant of the enum, with the name and value of its C constant.
+#[derive(Debug, PartialEq)]
+struct Variant {
+    ident: syn::Ident,
+    constant: String,
+    value: u32,
+}
+
+/// The properties given in `#[cenum(..)]` attributes.
+#[derive(Default)]
+struct CEnumAttrs {
+    ctype: Option<String>,
+    prefix: Option<String>,
+    header: bool,
+}
+
+impl Parse for CEnum {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let input = input.parse::<syn::DeriveInput>()?;
+        let syn::Data::Enum(data) = &input.data else {
+            return Err(Error::new_spanned(
+                &input.ident,
+                "CEnum can only be derived for enums",
+            ));
+        };
+        let attrs = parse_cenum_attrs(&input.attrs)?;
+        let prefix = match (&attrs.prefix, &attrs.ctype) {
+            (Some(prefix), _) => prefix.clone(),
+            (None, Some(ctype)) => format!("{}_", screaming_snake(ctype.trim_end_matches("_t"))),
+            (None, None) => format!("{}_", screaming_snake(&input.ident.to_string())),
+        };
+
+        let mut variants = vec![];
+        let mut next = Some(0u32);
+        for variant in &data.variants {
+            if !matches!(variant.fields, syn::Fields::Unit) {
+                return Err(Error::new_spanned(
+                    &variant.fields,
+                    "CEnum can only be derived for enums without fields",
+                ));
+            }
+            let value = match &variant.discriminant {
+                Some((
+                    _,
+                    syn::Expr::Lit(syn::ExprLit {
+                        lit: syn::Lit::Int(i),
+                        ..
+                    }),
+                )) => i.base10_parse::<u32>()?,
+                Some((_, expr)) => {
+                    return Err(Error::new_spanned(
+                        expr,
+                        "CEnum discriminants must be integer literals",
+                    ))
+                }
+                None => next.ok_or_else(|| {
+                    Error::new_spanned(&variant.ident, "CEnum discriminant overflows u32")
+                })?,
+            };
+            next = value.checked_add(1);
+            variants.push(Variant {
+                ident: variant.ident.clone(),
+                constant: format!("{prefix}{}", screaming_snake(&variant.ident.to_string())),
+                value,
+            });
+        }
+
+        let header_item = if attrs.header {
+            let Some(ctype) = &attrs.ctype else {
+                return Err(Error::new_spanned(
+                    &input.ident,
+                    "#[cenum(header)] requires #[cenum(ctype=\"..\")]",
+                ));
+            };
+            // derive macros cannot remove attributes, so parse a copy
+            let mut item_attrs = input.attrs.clone();
+            let mut header_item = HeaderItem::from_attrs(ctype.clone(), &mut item_attrs)?;
+            let mut decl = format!("typedef uint32_t {ctype};\nenum {{\n");
+            for variant in &variants {
+                decl.push_str(&format!("    {} = {},\n", variant.constant, variant.value));
+            }
+            decl.push_str("};");
+            if header_item.content.is_empty() {
+                header_item.content = decl;
+            } else {
+                header_item.content = format!("{}\n{decl}", header_item.content);
+            }
+            header_item.includes.push("<stdint.h>".into());
+            Some(header_item)
+        } else {
+            None
+        };
+
+        Ok(CEnum {
+            ident: input.ident,
+            vis: input.vis,
+            ctype: attrs.ctype,
+            variants,
+            header_item,
+        })
+    }
+}
+
+impl CEnum {
+    /// Convert this CEnum into a TokenStream containing the constants, conversions, and any
+    /// header item.
+    pub(crate) fn to_tokens(&self, tokens: &mut TokenStream2) {
+        let CEnum {
+            ident, vis, ctype, ..
+        } = self;
+        let variants: Vec<_> = self.variants.iter().map(|v| &v.ident).collect();
+        let constants: Vec<_> = self
+            .variants
+            .iter()
+            .map(|v| syn::Ident::new(&v.constant, Span::call_site()))
+            .collect();
+        let values = self.variants.iter().map(|v| v.value);
+        let const_docs = self
+            .variants
+            .iter()
+            .map(|v| format!("The C value of [`{ident}::{}`].", v.ident));
+
+        if let Some(ctype) = ctype {
+            let ctype = syn::Ident::new(ctype, Span::call_site());
+            let doc = format!("The C representation of [`{ident}`].");
+            tokens.extend(quote! {
+                #[doc = #doc]
+                #[allow(non_camel_case_types)]
+                #vis type #ctype = u32;
+            });
+        }
+
+        tokens.extend(quote! {
+            #(
+                #[doc = #const_docs]
+                #vis const #constants: u32 = #values;
+            )*
+
+            impl #ident {
+                /// Convert a C value to this enum, failing if it does not correspond to a
+                /// variant.
+                #vis fn try_from_c(cval: u32) -> ::std::result::Result<Self, ::ffizz_passby::InvalidEnumValue> {
+                    match cval {
+                        #( #constants => ::std::result::Result::Ok(Self::#variants), )*
+                        _ => ::std::result::Result::Err(::ffizz_passby::InvalidEnumValue(cval)),
+                    }
+                }
+            }
+
+            impl ::std::convert::From<#ident> for u32 {
+                fn from(rval: #ident) -> u32 {
+                    match rval {
+                        #( #ident::#variants => #constants, )*
+                    }
+                }
+            }
+
+            impl ::std::convert::From<u32> for #ident {
+                /// Convert a C value to this enum, panicking if it does not correspond to a
+                /// variant.
+                fn from(cval: u32) -> Self {
+                    match Self::try_from_c(cval) {
+                        ::std::result::Result::Ok(rval) => rval,
+                        ::std::result::Result::Err(e) => ::std::panic!("{}", e),
+                    }
+                }
+            }
+        });
+
+        if let Some(header_item) = &self.header_item {
+            header_item.to_tokens(tokens);
+        }
+    }
+}
+
+/// Parse the `#[cenum(..)]` attributes of the enum.
+fn parse_cenum_attrs(attrs: &[syn::Attribute]) -> Result<CEnumAttrs> {
+    let mut result = CEnumAttrs::default();
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident("cenum")) {
+        let syn::Meta::List(metalist) = attr.parse_meta()? else {
+            return Err(Error::new_spanned(
+                attr,
+                format!("expected #[cenum(..)] with properties {PROPERTIES}"),
+            ));
+        };
+        for elt in metalist.nested {
+            match elt {
+                syn::NestedMeta::Meta(syn::Meta::NameValue(nv))
+                    if nv.path.is_ident("ctype") || nv.path.is_ident("prefix") =>
+                {
+                    let syn::Lit::Str(s) = &nv.lit else {
+                        return Err(Error::new_spanned(&nv.lit, "expected a string"));
+                    };
+                    if nv.path.is_ident("ctype") {
+                        result.ctype = Some(s.value());
+                    } else {
+                        result.prefix = Some(s.value());
+                    }
+                }
+                syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("header") => {
+                    result.header = true;
+                }
+                elt => {
+                    return Err(Error::new_spanned(
+                        elt,
+                        format!("unknown property; valid #[cenum(..)] properties are {PROPERTIES}"),
+                    ))
+                }
+            }
+        }
+    }
+    Ok(result)
+}
+
+/// Convert a CamelCase or snake_case name to SCREAMING_SNAKE_CASE.
+pub(crate) fn screaming_snake(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut result = String::new();
+    for (i, c) in chars.iter().enumerate() {
+        if i > 0 && c.is_uppercase() {
+            let prev = chars[i - 1];
+            let next_lower = chars.get(i + 1).is_some_and(|n| n.is_lowercase());
+            if prev.is_lowercase() || prev.is_ascii_digit() || (prev.is_uppercase() && next_lower) {
+                result.push('_');
+            }
+        }
+        result.extend(c.to_uppercase());
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_screaming_snake() {
+        assert_eq!(screaming_snake("Status"), "STATUS");
+        assert_eq!(screaming_snake("NotFound"), "NOT_FOUND");
+        assert_eq!(screaming_snake("HTTPError"), "HTTP_ERROR");
+        assert_eq!(screaming_snake("hittr_status"), "HITTR_STATUS");
+    }
+
+    #[test]
+    fn test_cenum_variants() {
+        let ce: CEnum = syn::parse_quote! {
+            #[cenum(ctype="hittr_status_t")]
+            pub enum Status {
+                Ready,
+                NotReady = 5,
+                Failed,
+            }
+        };
+        let constants: Vec<_> = ce
+            .variants
+            .iter()
+            .map(|v| (v.constant.as_str(), v.value))
+            .collect();
+        assert_eq!(
+            constants,
+            vec![
+                ("HITTR_STATUS_READY", 0),
+                ("HITTR_STATUS_NOT_READY", 5),
+                ("HITTR_STATUS_FAILED", 6)
+            ]
+        );
+        assert!(ce.header_item.is_none());
+    }
+
+    #[test]
+    fn test_cenum_prefix() {
+        let ce: CEnum = syn::parse_quote! {
+            #[cenum(prefix="COLOR_")]
+            enum Colour {
+                Red,
+            }
+        };
+        assert_eq!(ce.variants[0].constant, "COLOR_RED");
+
+        let ce: CEnum = syn::parse_quote! {
+            enum Colour {
+                Red,
+            }
+        };
+        assert_eq!(ce.variants[0].constant, "COLOUR_RED");
+    }
+
+    #[test]
+    fn test_cenum_header() {
+        let ce: CEnum = syn::parse_quote! {
+            /// The status of a system.
+            #[cenum(ctype="hittr_status_t", header)]
+            #[ffizz(order=200)]
+            pub enum Status {
+                Ready = 1,
+                Failed,
+            }
+        };
+        assert_eq!(
+            ce.header_item,
+            Some(HeaderItem {
+                order: 200,
+                name: "hittr_status_t".into(),
+                content: "// The status of a system.\n\
+                    typedef uint32_t hittr_status_t;\n\
+                    enum {\n    HITTR_STATUS_READY = 1,\n    HITTR_STATUS_FAILED = 2,\n};"
+                    .into(),
+                includes: vec!["<stdint.h>".into()],
+                ..Default::default()
+            })
+        );
+    }
+
+    #[test]
+    fn test_cenum_header_requires_ctype() {
+        let res: Result<CEnum> = syn::parse2(quote::quote! {
+            #[cenum(header)]
+            enum Status { Ready }
+        });
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_cenum_fields() {
+        let res: Result<CEnum> = syn::parse2(quote::quote! {
+            enum Status { Ready, Running { count: u32 } }
+        });
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_cenum_struct() {
+        let res: Result<CEnum> = syn::parse2(quote::quote! {
+            struct Status;
+        });
+        assert!(res.is_err());
+    }
+}
@@ -0,0 +1,68 @@
+use syn::parse::{Error, Result};
+
+/// Get the C equivalent of a Rust type passed to or from C, adding any necessary includes.
+pub(crate) fn c_type(ty: &syn::Type, includes: &mut Vec<String>) -> Result<String> {
+    let unsupported = || {
+        Error::new_spanned(
+            ty,
+            "unsupported type for C; use a primitive type, a C type named `.._t`, or a \
+            raw pointer to one of these",
+        )
+    };
+    match ty {
+        syn::Type::Ptr(ptr) => {
+            let pointee = c_type(&ptr.elem, includes)?;
+            let pointee = if ptr.const_token.is_some() {
+                format!("const {pointee}")
+            } else {
+                pointee
+            };
+            Ok(c_declarator(&pointee, "*"))
+        }
+        syn::Type::Path(path) if path.qself.is_none() => {
+            let ident = path
+                .path
+                .segments
+                .last()
+                .ok_or_else(unsupported)?
+                .ident
+                .to_string();
+            let (ty, include) = match ident.as_str() {
+                "c_void" => ("void", None),
+                "c_char" => ("char", None),
+                "c_int" => ("int", None),
+                "f32" => ("float", None),
+                "f64" => ("double", None),
+                "bool" => ("bool", Some("<stdbool.h>")),
+                "usize" => ("size_t", Some("<stddef.h>")),
+                "isize" => ("intptr_t", Some("<stdint.h>")),
+                "u8" => ("uint8_t", Some("<stdint.h>")),
+                "u16" => ("uint16_t", Some("<stdint.h>")),
+                "u32" => ("uint32_t", Some("<stdint.h>")),
+                "u64" => ("uint64_t", Some("<stdint.h>")),
+                "i8" => ("int8_t", Some("<stdint.h>")),
+                "i16" => ("int16_t", Some("<stdint.h>")),
+                "i32" => ("int32_t", Some("<stdint.h>")),
+                "i64" => ("int64_t", Some("<stdint.h>")),
+                name if name.ends_with("_t") => return Ok(ident),
+                _ => return Err(unsupported()),
+            };
+            if let Some(include) = include {
+                if !includes.iter().any(|i| i == include) {
+                    includes.push(include.into());
+                }
+            }
+            Ok(ty.into())
+        }
+        _ => Err(unsupported()),
+    }
+}
+
+/// Combine a C type and a declarator, such as a name, without a space after `*`.
+pub(crate) fn c_declarator(ty: &str, declarator: &str) -> String {
+    if ty.ends_with('*') {
+        format!("{ty}{declarator}")
+    } else {
+        format!("{ty} {declarator}")
+    }
+}
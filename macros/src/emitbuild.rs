@@ -0,0 +1,156 @@
+use crate::headeritem::HeaderItem;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use std::path::Path;
+use std::sync::Mutex;
+use syn::parse::{Error, Parse, ParseStream, Result};
+
+/// The header items expanded so far in this process, with the name of the crate being compiled
+/// when each was expanded.  A proc-macro library is loaded once for each invocation of the
+/// compiler, so this accumulates the items in the crate being compiled.
+static EXPANDED: Mutex<Vec<(String, HeaderItem)>> = Mutex::new(Vec::new());
+
+/// Get the name of the crate currently being compiled.
+fn current_crate() -> String {
+    std::env::var("CARGO_CRATE_NAME").unwrap_or_default()
+}
+
+/// Record a header item as it is expanded, for later use by `emit_at_build!`.
+pub(crate) fn record(item: &HeaderItem) {
+    record_in(&mut EXPANDED.lock().unwrap(), current_crate(), item);
+}
+
+fn record_in(expanded: &mut Vec<(String, HeaderItem)>, krate: String, item: &HeaderItem) {
+    // a long-lived process, such as an IDE, may expand the same item many times
+    expanded.retain(|(k, hi)| !(k == &krate && hi.name == item.name));
+    expanded.push((krate, item.clone()));
+}
+
+/// EmitAtBuild is the result of parsing an `emit_at_build!(..)` macro invocation: the path of the
+/// header file, relative to `OUT_DIR`.
+#[derive(Debug, PartialEq)]
+pub(crate) struct EmitAtBuild {
+    path: syn::LitStr,
+}
+
+impl Parse for EmitAtBuild {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let path = input.parse::<syn::LitStr>()?;
+        Ok(EmitAtBuild { path })
+    }
+}
+
+impl EmitAtBuild {
+    /// Write the header containing the items expanded so far in the current crate.  This expands
+    /// to nothing, but fails if the header cannot be written.
+    pub(crate) fn to_tokens(&self, _tokens: &mut TokenStream2) -> Result<()> {
+        let out_dir = std::env::var_os("OUT_DIR").ok_or_else(|| {
+            Error::new(
+                Span::call_site(),
+                "OUT_DIR is not set; emit_at_build! requires the package to have a build script",
+            )
+        })?;
+        let path = Path::new(&out_dir).join(self.path.value());
+
+        let krate = current_crate();
+        let items: Vec<HeaderItem> = EXPANDED
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(k, _)| k == &krate)
+            .map(|(_, hi)| hi.clone())
+            .collect();
+        let content = render(items);
+
+        // only write the file if it has changed, to avoid needlessly triggering rebuilds
+        if std::fs::read_to_string(&path).ok().as_deref() == Some(content.as_str()) {
+            return Ok(());
+        }
+        let write = || -> std::io::Result<()> {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(&path, &content)
+        };
+        write().map_err(|e| {
+            Error::new_spanned(
+                &self.path,
+                format!("could not write {}: {e}", path.display()),
+            )
+        })
+    }
+}
+
+/// Render header items into a C header, in the same fashion as `ffizz_header::generate`.
+fn render(mut items: Vec<HeaderItem>) -> String {
+    items.retain(|hi| !hi.cpp_helper);
+    items.sort_by(|a, b| a.order.cmp(&b.order).then_with(|| a.name.cmp(&b.name)));
+    let chunks = items.iter().map(|hi| match hi.platform_conditions() {
+        Some((_, cond)) => format!("#if {cond}\n{}\n#endif", hi.content.trim()),
+        None => hi.content.trim().to_string(),
+    });
+    let mut result = itertools::join(chunks, "\n\n");
+    if !items.is_empty() {
+        result.push('\n');
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn item(order: usize, name: &str, content: &str) -> HeaderItem {
+        HeaderItem {
+            order,
+            name: name.into(),
+            content: content.into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        let eab: EmitAtBuild = syn::parse_quote! { "include/mylib.h" };
+        assert_eq!(eab.path.value(), "include/mylib.h");
+    }
+
+    #[test]
+    fn test_record_replaces() {
+        let mut expanded = vec![];
+        record_in(&mut expanded, "a".into(), &item(1, "foo", "one"));
+        record_in(&mut expanded, "b".into(), &item(1, "foo", "other crate"));
+        record_in(&mut expanded, "a".into(), &item(1, "foo", "two"));
+        assert_eq!(
+            expanded,
+            vec![
+                ("b".into(), item(1, "foo", "other crate")),
+                ("a".into(), item(1, "foo", "two")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_render() {
+        let mut helper = item(1, "helper", "inline void x() {}");
+        helper.cpp_helper = true;
+        assert_eq!(
+            render(vec![
+                item(2, "bbb", "three"),
+                item(1, "zzz", "one\n"),
+                helper,
+                item(2, "aaa", "two"),
+            ]),
+            "one\n\ntwo\n\nthree\n"
+        );
+    }
+
+    #[test]
+    fn test_render_platform() {
+        let mut win = item(1, "win", "void win(void);");
+        win.platform = Some("windows".into());
+        assert_eq!(
+            render(vec![win]),
+            "#if defined(_WIN32)\nvoid win(void);\n#endif\n"
+        );
+    }
+}
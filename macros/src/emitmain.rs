@@ -0,0 +1,64 @@
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
+use syn::parse::{Error, Parse, ParseStream, Result};
+
+/// EmitMain is the result of parsing an `emit_main!(..)` macro invocation: the name of the library
+/// crate whose header should be dumped.
+#[derive(Debug, PartialEq)]
+pub(crate) struct EmitMain {
+    lib_crate: syn::Ident,
+}
+
+impl Parse for EmitMain {
+    fn parse(input: ParseStream) -> Result<Self> {
+        if !input.is_empty() {
+            let lib_crate = input.parse::<syn::Ident>()?;
+            return Ok(EmitMain { lib_crate });
+        }
+
+        // Default to the library crate of the package containing this binary.  Cargo names
+        // that crate after the package, with dashes converted to underscores.
+        let pkg_name = std::env::var("CARGO_PKG_NAME").map_err(|_| {
+            Error::new(
+                Span::call_site(),
+                "CARGO_PKG_NAME is not set; name the library crate explicitly: `emit_main!(mylib)`",
+            )
+        })?;
+        Ok(EmitMain {
+            lib_crate: syn::Ident::new(&pkg_name.replace('-', "_"), Span::call_site()),
+        })
+    }
+}
+
+impl EmitMain {
+    /// Convert this EmitMain into a TokenStream defining `main`.
+    pub(crate) fn to_tokens(&self, tokens: &mut TokenStream2) {
+        let lib_crate = &self.lib_crate;
+        tokens.extend(quote! {
+            // link the library, so that its header items are included in this binary
+            extern crate #lib_crate as _;
+
+            fn main() {
+                ::ffizz_header::dump_main()
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_explicit() {
+        let em: EmitMain = syn::parse_quote! { mylib };
+        assert_eq!(em.lib_crate.to_string(), "mylib");
+    }
+
+    #[test]
+    fn test_parse_default() {
+        // CARGO_PKG_NAME is set by cargo when running tests
+        let em: EmitMain = syn::parse_quote! {};
+        assert_eq!(em.lib_crate.to_string(), "ffizz_macros");
+    }
+}
@@ -0,0 +1,740 @@
+use crate::ctype::{c_declarator, c_type};
+use crate::headeritem::HeaderItem;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{quote, ToTokens};
+use syn::parse::{Error, Parse, ParseStream, Result};
+
+/// ExportArgs is the result of parsing the arguments to `#[ffizz_export(..)]`.
+#[derive(Debug)]
+pub(crate) struct ExportArgs {
+    prefix: String,
+}
+
+impl Parse for ExportArgs {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let err = |span| Error::new(span, "expected `prefix = \"..\"`");
+        let key: syn::Ident = input.parse().map_err(|e| err(e.span()))?;
+        if key != "prefix" {
+            return Err(err(key.span()));
+        }
+        input.parse::<syn::Token![=]>()?;
+        let prefix = input.parse::<syn::LitStr>()?.value();
+        input.parse::<Option<syn::Token![,]>>()?;
+        if !input.is_empty() {
+            return Err(err(input.span()));
+        }
+        Ok(ExportArgs { prefix })
+    }
+}
+
+/// Export is the result of parsing an impl block annotated with `#[ffizz_export]`.
+#[derive(Debug)]
+pub(crate) struct Export {
+    item: syn::ItemImpl,
+    /// The exported methods, with the name of each
+    functions: Vec<(syn::Ident, Function)>,
+}
+
+/// Function is an exported method or function, which becomes an `extern "C"` function.
+#[derive(Debug)]
+pub(crate) struct Function {
+    /// The name of the `extern "C"` function
+    name: String,
+    /// The C type of the receiver, for methods
+    self_ctype: Option<String>,
+    receiver: Option<Borrow>,
+    args: Vec<Arg>,
+    output: Output,
+    /// The value to return on a panic, from `#[ffizz_catch(..)]`, if given
+    on_panic: Option<TokenStream2>,
+    pub(crate) header_item: HeaderItem,
+}
+
+/// Borrow describes how a value passed by pointer is given to the method.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Borrow {
+    Shared,
+    Exclusive,
+    Owned,
+}
+
+/// Arg is an argument of an exported method, other than the receiver.
+#[derive(Debug)]
+struct Arg {
+    ident: syn::Ident,
+    ty: syn::Type,
+    kind: ArgKind,
+}
+
+/// ArgKind describes how an argument is converted from its C representation.
+#[derive(Debug)]
+enum ArgKind {
+    /// Passed as-is.
+    Plain,
+    /// Passed by pointer with `Boxed`, as `#[ffizz(boxed = "ctype")]`.
+    Boxed { ctype: String, borrow: Borrow },
+    /// Passed by value with `Value`, as `#[ffizz(value = "ctype")]`.
+    Value { ctype: String },
+    /// Passed as a `fz_string_t` pointer, as `#[ffizz(string)]`.
+    String { borrow: Borrow },
+}
+
+/// Output describes how the return value is converted to C.
+#[derive(Debug)]
+enum Output {
+    Unit,
+    /// The method returns `Self`, which is returned with `Boxed`.
+    Boxed,
+    Plain(Box<syn::Type>),
+}
+
+impl Export {
+    /// Parse an impl block, given the arguments to the attribute.
+    pub(crate) fn new(args: ExportArgs, mut item: syn::ItemImpl) -> Result<Self> {
+        if item.trait_.is_some() || !item.generics.params.is_empty() {
+            return Err(Error::new_spanned(
+                &item.self_ty,
+                "#[ffizz_export] must be applied to an inherent impl block without generics",
+            ));
+        }
+        let prefix = args.prefix;
+        let self_ty = item.self_ty.as_ref().clone();
+        let mut functions = vec![];
+        for impl_item in &mut item.items {
+            let syn::ImplItem::Method(method) = impl_item else {
+                continue;
+            };
+            if !matches!(method.vis, syn::Visibility::Public(_)) {
+                continue;
+            }
+            let name = format!("{prefix}_{}", method.sig.ident);
+            let function = Function::parse(
+                &mut method.sig,
+                &mut method.attrs,
+                name,
+                Some((&self_ty, format!("{prefix}_t"))),
+            )?;
+            if !function.header_item.skip {
+                functions.push((method.sig.ident.clone(), function));
+            }
+        }
+        Ok(Export { item, functions })
+    }
+}
+
+impl Function {
+    /// Parse the signature and attributes of a method or function to be called from C, removing
+    /// the `#[ffizz(..)]` and `#[ffizz_catch(..)]` attributes.  For methods, `self_type` gives
+    /// the type of the impl block and its C type.
+    pub(crate) fn parse(
+        sig: &mut syn::Signature,
+        attrs: &mut Vec<syn::Attribute>,
+        name: String,
+        self_type: Option<(&syn::Type, String)>,
+    ) -> Result<Self> {
+        if !sig.generics.params.is_empty()
+            || sig.asyncness.is_some()
+            || sig.unsafety.is_some()
+            || sig.abi.is_some()
+            || sig.variadic.is_some()
+        {
+            return Err(Error::new_spanned(
+                &sig.ident,
+                "functions called from C cannot be generic, async, unsafe, extern, or variadic",
+            ));
+        }
+        let on_panic = take_catch(attrs)?;
+        let mut header_item = HeaderItem::from_attrs(name.clone(), attrs)?;
+
+        let includes = &mut header_item.includes;
+        let self_ctype = self_type.as_ref().map(|(_, ctype)| ctype.clone());
+        let mut receiver = None;
+        let mut args = vec![];
+        let mut params = vec![];
+        for input in &mut sig.inputs {
+            match input {
+                syn::FnArg::Receiver(recv) => {
+                    let Some(ctype) = &self_ctype else {
+                        return Err(Error::new_spanned(
+                            recv,
+                            "expected a function, not a method",
+                        ));
+                    };
+                    let borrow = match (&recv.reference, &recv.mutability) {
+                        (Some(_), None) => Borrow::Shared,
+                        (Some(_), Some(_)) => Borrow::Exclusive,
+                        (None, _) => Borrow::Owned,
+                    };
+                    params.push(c_pointer(ctype, borrow, "ptr"));
+                    receiver = Some(borrow);
+                }
+                syn::FnArg::Typed(pat_type) => {
+                    let arg = Arg::parse(pat_type)?;
+                    if self_ctype.is_some() && arg.ident == "ptr" {
+                        return Err(Error::new_spanned(
+                            &arg.ident,
+                            "`ptr` is reserved for the receiver of exported methods",
+                        ));
+                    }
+                    params.push(arg.c_param(includes)?);
+                    args.push(arg);
+                }
+            }
+        }
+
+        let (output, ret) = match &sig.output {
+            syn::ReturnType::Default => (Output::Unit, "void".to_string()),
+            syn::ReturnType::Type(_, ty) if is_self(ty, &self_type) => {
+                let ctype = self_ctype.as_deref().unwrap_or_default();
+                (Output::Boxed, format!("{ctype} *"))
+            }
+            syn::ReturnType::Type(_, ty) => (Output::Plain(ty.clone()), c_type(ty, includes)?),
+        };
+
+        let params = if params.is_empty() {
+            "void".to_string()
+        } else {
+            params.join(", ")
+        };
+        let decl = c_declarator(&ret, &format!("{name}({params});"));
+        if header_item.content.is_empty() {
+            header_item.content = decl;
+        } else {
+            header_item.content = format!("{}\n{decl}", header_item.content);
+        }
+
+        if on_panic.is_none() {
+            if let Some(arg) = args
+                .iter()
+                .find(|arg| matches!(arg.kind, ArgKind::String { .. }))
+            {
+                return Err(Error::new_spanned(
+                    &arg.ident,
+                    "functions with #[ffizz(string)] arguments must have #[ffizz_catch(..)], as an \
+                    invalid string causes a panic",
+                ));
+            }
+        }
+
+        Ok(Function {
+            name,
+            self_ctype,
+            receiver,
+            args,
+            output,
+            on_panic,
+            header_item,
+        })
+    }
+
+    /// Convert this Function into a TokenStream containing the `extern "C"` function and its
+    /// header item.  The function has the given attributes, such as docstrings, and its body
+    /// begins with `prelude` and calls `callee` with the converted arguments.  For methods,
+    /// `self_ty` is the type of the impl block.
+    pub(crate) fn to_tokens(
+        &self,
+        attrs: TokenStream2,
+        vis: &syn::Visibility,
+        prelude: TokenStream2,
+        callee: TokenStream2,
+        self_ty: Option<&syn::Type>,
+        tokens: &mut TokenStream2,
+    ) {
+        let ident = syn::Ident::new(&self.name, Span::call_site());
+        let ptr = syn::Ident::new("ptr", Span::call_site());
+
+        let mut params = vec![];
+        let mut conversions = vec![];
+        let mut borrows = vec![];
+        let mut call_args = vec![];
+        let mut safety = vec![];
+        if let (Some(borrow), Some(self_ty), Some(ctype)) =
+            (self.receiver, self_ty, &self.self_ctype)
+        {
+            let ptr_type = rust_pointer(self_ty, borrow);
+            params.push(quote!(#ptr: #ptr_type));
+            safety.push(pointer_safety("ptr", ctype, borrow));
+            match borrow {
+                Borrow::Owned => conversions.push(quote! {
+                    let #ptr = ::ffizz_passby::Boxed::<#self_ty>::take_nonnull(#ptr);
+                }),
+                _ => borrows.push((&ptr, self_ty, borrow)),
+            }
+            call_args.push(quote!(#ptr));
+        }
+        // Value::take is the only conversion that is not unsafe
+        let mut safe_conversions = vec![];
+        for arg in &self.args {
+            let Arg { ident, ty, kind } = arg;
+            match kind {
+                ArgKind::Plain => params.push(quote!(#ident: #ty)),
+                ArgKind::Boxed { ctype, borrow } => {
+                    let rtype = referent(ty);
+                    let ptr_type = rust_pointer(rtype, *borrow);
+                    params.push(quote!(#ident: #ptr_type));
+                    safety.push(pointer_safety(&ident.to_string(), ctype, *borrow));
+                    match borrow {
+                        Borrow::Owned => conversions.push(quote! {
+                            let #ident = ::ffizz_passby::Boxed::<#rtype>::take_nonnull(#ident);
+                        }),
+                        _ => borrows.push((ident, rtype, *borrow)),
+                    }
+                }
+                ArgKind::Value { ctype } => {
+                    let ctype: syn::Type = syn::parse_str(ctype).expect("checked in Arg::parse");
+                    params.push(quote!(#ident: #ctype));
+                    safe_conversions.push(quote! {
+                        let #ident = ::ffizz_passby::Value::<#ty, #ctype>::take(#ident);
+                    });
+                }
+                ArgKind::String { borrow } => {
+                    let expect = format!("{ident} is not valid UTF-8");
+                    params.push(quote!(#ident: *mut ::ffizz_string::fz_string_t));
+                    safety.push(pointer_safety(&ident.to_string(), "fz_string_t", *borrow));
+                    safety.push(format!(
+                        "* `{ident}` must not be a NULL string, and must contain valid UTF-8, or \
+                        the function panics."
+                    ));
+                    if *borrow == Borrow::Owned {
+                        conversions.push(quote! {
+                            let #ident = ::ffizz_string::FzString::take_ptr(#ident)
+                                .into_string_nonnull()
+                                .expect(#expect);
+                        });
+                    } else {
+                        // FzString::with_ref_mut takes an `Fn`, so the string is copied out
+                        conversions.push(quote! {
+                            let #ident = ::ffizz_string::FzString::with_ref_mut(#ident, |s| {
+                                s.as_str_nonnull().map(::std::string::String::from)
+                            })
+                            .expect(#expect);
+                        });
+                        call_args.push(quote!(&#ident));
+                        continue;
+                    }
+                }
+            }
+            call_args.push(quote!(#ident));
+        }
+
+        // borrowing the same value twice, where either borrow is exclusive, is not allowed
+        let mut alias_checks = vec![];
+        for (i, (a, _, a_borrow)) in borrows.iter().enumerate() {
+            for (b, _, b_borrow) in &borrows[i + 1..] {
+                if *a_borrow == Borrow::Shared && *b_borrow == Borrow::Shared {
+                    continue;
+                }
+                safety.push(format!(
+                    "* `{a}` and `{b}` must not point to the same value, or the function panics."
+                ));
+                alias_checks.push(quote! {
+                    if ::std::ptr::eq(#a.cast::<()>(), #b.cast::<()>()) {
+                        panic!("pointers to the same value not allowed");
+                    }
+                });
+            }
+        }
+
+        let mut body = quote!(#callee(#(#call_args),*));
+        let mut needs_unsafe = !conversions.is_empty() || !borrows.is_empty();
+        for (ident, rtype, borrow) in borrows.into_iter().rev() {
+            let with_ref = if borrow == Borrow::Shared {
+                quote!(with_ref_nonnull)
+            } else {
+                quote!(with_ref_mut_nonnull)
+            };
+            body = quote!(::ffizz_passby::Boxed::<#rtype>::#with_ref(#ident, |#ident| #body));
+        }
+        let output = match &self.output {
+            Output::Unit => quote!(),
+            Output::Boxed => {
+                let self_ty = self_ty.expect("only methods return Self");
+                needs_unsafe = true;
+                body = quote!(::ffizz_passby::Boxed::<#self_ty>::return_val(#body));
+                quote!(-> *mut #self_ty)
+            }
+            Output::Plain(ty) => quote!(-> #ty),
+        };
+        let mut body = if needs_unsafe {
+            quote! {
+                #(#alias_checks)*
+                #(#safe_conversions)*
+                // SAFETY: see docstring; the C caller frees any returned value
+                unsafe {
+                    #(#conversions)*
+                    #body
+                }
+            }
+        } else {
+            quote! {
+                #(#safe_conversions)*
+                #body
+            }
+        };
+        if let Some(on_panic) = &self.on_panic {
+            body = quote!(::ffizz_passby::LastError::catch(#on_panic, || { #body }));
+        }
+
+        let unsafety = if safety.is_empty() {
+            quote!()
+        } else {
+            quote!(unsafe)
+        };
+        let safety_doc = if safety.is_empty() {
+            quote!()
+        } else {
+            let safety = safety.iter().map(|s| format!(" {s}"));
+            quote! {
+                ///
+                /// # Safety
+                ///
+                #(#[doc = #safety])*
+            }
+        };
+        tokens.extend(quote! {
+            #attrs
+            #safety_doc
+            #[no_mangle]
+            #vis #unsafety extern "C" fn #ident(#(#params),*) #output {
+                #prelude
+                #body
+            }
+        });
+
+        self.header_item.to_tokens(tokens);
+    }
+}
+
+impl Arg {
+    /// Parse an argument, removing its `#[ffizz(..)]` attribute.
+    fn parse(pat_type: &mut syn::PatType) -> Result<Self> {
+        let syn::Pat::Ident(pat) = pat_type.pat.as_ref() else {
+            return Err(Error::new_spanned(
+                &pat_type.pat,
+                "arguments of functions called from C must be simple names",
+            ));
+        };
+        let ident = pat.ident.clone();
+        let ty = pat_type.ty.as_ref().clone();
+
+        let mut kind = ArgKind::Plain;
+        let mut attrs = vec![];
+        for attr in pat_type.attrs.drain(..) {
+            if !attr.path.is_ident("ffizz") {
+                attrs.push(attr);
+                continue;
+            }
+            kind = Self::parse_kind(&attr, &ty)?;
+        }
+        pat_type.attrs = attrs;
+        Ok(Arg { ident, ty, kind })
+    }
+
+    /// Parse an `#[ffizz(..)]` attribute on an argument of the given type.
+    fn parse_kind(attr: &syn::Attribute, ty: &syn::Type) -> Result<ArgKind> {
+        let err = |msg: &str| Error::new_spanned(attr, msg);
+        let expected = "expected #[ffizz(boxed = \"..\")], #[ffizz(value = \"..\")], or \
+            #[ffizz(string)]";
+        let syn::Meta::List(list) = attr.parse_meta()? else {
+            return Err(err(expected));
+        };
+        if list.nested.len() != 1 {
+            return Err(err(expected));
+        }
+        let borrow = match ty {
+            syn::Type::Reference(r) if r.mutability.is_some() => Borrow::Exclusive,
+            syn::Type::Reference(_) => Borrow::Shared,
+            _ => Borrow::Owned,
+        };
+        match &list.nested[0] {
+            syn::NestedMeta::Meta(syn::Meta::NameValue(nv)) => {
+                let syn::Lit::Str(ctype) = &nv.lit else {
+                    return Err(err(expected));
+                };
+                let ctype = ctype.value();
+                if syn::parse_str::<syn::Ident>(&ctype).is_err() {
+                    return Err(Error::new_spanned(&nv.lit, "expected a C type name"));
+                }
+                if nv.path.is_ident("boxed") {
+                    Ok(ArgKind::Boxed { ctype, borrow })
+                } else if nv.path.is_ident("value") {
+                    if borrow != Borrow::Owned {
+                        return Err(err("arguments passed by value cannot be references"));
+                    }
+                    Ok(ArgKind::Value { ctype })
+                } else {
+                    Err(err(expected))
+                }
+            }
+            syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("string") => {
+                match (borrow, ty) {
+                    (Borrow::Shared, syn::Type::Reference(r)) if is_ident(&r.elem, "str") => {}
+                    (Borrow::Owned, ty) if is_ident(ty, "String") => {}
+                    _ => return Err(err("#[ffizz(string)] arguments must be `String` or `&str`")),
+                }
+                Ok(ArgKind::String { borrow })
+            }
+            _ => Err(err(expected)),
+        }
+    }
+
+    /// Get the C declaration of this argument, adding any necessary includes.
+    fn c_param(&self, includes: &mut Vec<String>) -> Result<String> {
+        let name = self.ident.to_string();
+        Ok(match &self.kind {
+            ArgKind::Plain => c_declarator(&c_type(&self.ty, includes)?, &name),
+            ArgKind::Boxed { ctype, borrow } => c_pointer(ctype, *borrow, &name),
+            ArgKind::Value { ctype } => format!("{ctype} {name}"),
+            ArgKind::String { .. } => format!("fz_string_t *{name}"),
+        })
+    }
+}
+
+impl Export {
+    /// Convert this Export into a TokenStream containing the impl block, the `extern "C"`
+    /// functions, and their header items.
+    pub(crate) fn to_tokens(&self, tokens: &mut TokenStream2) {
+        self.item.to_tokens(tokens);
+        let self_ty = self.item.self_ty.as_ref();
+        let vis: syn::Visibility = syn::parse_quote!(pub);
+        for (method, function) in &self.functions {
+            let doc = format!("Call [`{}::{method}`] from C.", self_ty.to_token_stream());
+            function.to_tokens(
+                quote!(#[doc = #doc]),
+                &vis,
+                quote!(),
+                quote!(<#self_ty>::#method),
+                Some(self_ty),
+                tokens,
+            );
+        }
+    }
+}
+
+/// Get the C declaration of a pointer to a value of the given C type.
+fn c_pointer(ctype: &str, borrow: Borrow, name: &str) -> String {
+    match borrow {
+        Borrow::Shared => format!("const {ctype} *{name}"),
+        _ => format!("{ctype} *{name}"),
+    }
+}
+
+/// Get the Rust type of a pointer to a value of the given Rust type.
+fn rust_pointer(rtype: &syn::Type, borrow: Borrow) -> TokenStream2 {
+    match borrow {
+        Borrow::Shared => quote!(*const #rtype),
+        _ => quote!(*mut #rtype),
+    }
+}
+
+/// Get the bullet in the `# Safety` section for an argument pointing to a value of the given C
+/// type.
+fn pointer_safety(name: &str, ctype: &str, borrow: Borrow) -> String {
+    let requirement = match borrow {
+        Borrow::Shared => "not be mutated by another thread until this function returns",
+        Borrow::Exclusive => "not be accessed by another thread until this function returns",
+        Borrow::Owned => "not be used after this call",
+    };
+    format!("* `{name}` must point to a valid `{ctype}`, and must {requirement}.")
+}
+
+/// Get the type referred to by a reference type, or the type itself.
+fn referent(ty: &syn::Type) -> &syn::Type {
+    match ty {
+        syn::Type::Reference(r) => &r.elem,
+        ty => ty,
+    }
+}
+
+/// Determine whether a type is a path consisting of the given identifier.
+fn is_ident(ty: &syn::Type, ident: &str) -> bool {
+    matches!(ty, syn::Type::Path(p) if p.qself.is_none() && p.path.is_ident(ident))
+}
+
+/// Determine whether a type is `Self` or the type of the impl block, if any.
+fn is_self(ty: &syn::Type, self_type: &Option<(&syn::Type, String)>) -> bool {
+    match self_type {
+        Some((self_ty, _)) => is_ident(ty, "Self") || ty == *self_ty,
+        None => false,
+    }
+}
+
+/// Remove a `#[ffizz_catch]` or `#[ffizz_catch(value)]` attribute from the given attributes,
+/// returning the value to return on a panic.
+fn take_catch(attrs: &mut Vec<syn::Attribute>) -> Result<Option<TokenStream2>> {
+    let mut on_panic = None;
+    let mut kept_attrs = vec![];
+    for attr in attrs.drain(..) {
+        if !attr.path.is_ident("ffizz_catch") {
+            kept_attrs.push(attr);
+        } else if attr.tokens.is_empty() {
+            on_panic = Some(quote!(()));
+        } else {
+            on_panic = Some(attr.parse_args::<syn::Expr>()?.into_token_stream());
+        }
+    }
+    *attrs = kept_attrs;
+    Ok(on_panic)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn export(args: TokenStream2, item: TokenStream2) -> Result<Export> {
+        Export::new(syn::parse2(args)?, syn::parse2(item)?)
+    }
+
+    #[test]
+    fn test_export() {
+        let exp = export(
+            quote!(prefix = "hittr_counter"),
+            quote! {
+                impl Counter {
+                    /// Create a counter.
+                    pub fn new(max: u64) -> Self { todo!() }
+
+                    /// Increment the counter.
+                    #[ffizz(order=200)]
+                    pub fn incr(&mut self, by: u32) -> bool { todo!() }
+
+                    #[ffizz_catch]
+                    pub fn merge(
+                        &self,
+                        #[ffizz(boxed = "hittr_counter_t")] other: Counter,
+                        #[ffizz(value = "hittr_status_t")] status: Status,
+                        #[ffizz(string)] name: &str,
+                    ) {}
+
+                    pub fn free(self) {}
+
+                    fn private(&self) {}
+
+                    #[ffizz(skip)]
+                    pub fn skipped(&self) {}
+                }
+            },
+        )
+        .unwrap();
+        let items: Vec<_> = exp.functions.iter().map(|(_, f)| &f.header_item).collect();
+        assert_eq!(
+            items[0],
+            &HeaderItem {
+                order: 100,
+                name: "hittr_counter_new".into(),
+                content: "// Create a counter.\n\
+                    hittr_counter_t *hittr_counter_new(uint64_t max);"
+                    .into(),
+                includes: vec!["<stdint.h>".into()],
+                ..Default::default()
+            }
+        );
+        assert_eq!(items[1].order, 200);
+        assert_eq!(
+            items[1].content,
+            "// Increment the counter.\n\
+            bool hittr_counter_incr(hittr_counter_t *ptr, uint32_t by);"
+        );
+        assert_eq!(items[1].includes, vec!["<stdint.h>", "<stdbool.h>"]);
+        assert_eq!(
+            items[2].content,
+            "void hittr_counter_merge(const hittr_counter_t *ptr, hittr_counter_t *other, \
+            hittr_status_t status, fz_string_t *name);"
+        );
+        assert_eq!(
+            items[3].content,
+            "void hittr_counter_free(hittr_counter_t *ptr);"
+        );
+        assert_eq!(items.len(), 4);
+
+        // the ffizz attributes are removed from the impl block
+        let impl_block = exp.item.to_token_stream().to_string();
+        assert!(!impl_block.contains("ffizz"));
+    }
+
+    #[test]
+    fn test_export_bad_args() {
+        let res = export(quote!(name = "counter"), quote!(impl Counter {}));
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_export_trait_impl() {
+        let res = export(
+            quote!(prefix = "counter"),
+            quote!(impl Default for Counter {}),
+        );
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_export_bad_string() {
+        let res = export(
+            quote!(prefix = "counter"),
+            quote! {
+                impl Counter {
+                    pub fn set_name(&mut self, #[ffizz(string)] name: &mut str) {}
+                }
+            },
+        );
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_export_string_without_catch() {
+        let res = export(
+            quote!(prefix = "counter"),
+            quote! {
+                impl Counter {
+                    pub fn set_name(&mut self, #[ffizz(string)] name: &str) {}
+                }
+            },
+        );
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_export_aliasing() {
+        let exp = export(
+            quote!(prefix = "counter"),
+            quote! {
+                impl Counter {
+                    pub fn add(&mut self, #[ffizz(boxed = "counter_t")] other: &Counter) {}
+                    pub fn eq(&self, #[ffizz(boxed = "counter_t")] other: &Counter) -> bool {
+                        true
+                    }
+                }
+            },
+        )
+        .unwrap();
+        let mut tokens = TokenStream2::new();
+        exp.to_tokens(&mut tokens);
+        let tokens = tokens.to_string();
+        // only counter_add, with an exclusive borrow, checks for aliasing
+        assert_eq!(
+            tokens
+                .matches("pointers to the same value not allowed")
+                .count(),
+            1
+        );
+        assert_eq!(
+            tokens
+                .matches("`ptr` and `other` must not point to the same value")
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_export_ptr_reserved() {
+        let res = export(
+            quote!(prefix = "counter"),
+            quote! {
+                impl Counter {
+                    pub fn set(&mut self, ptr: *const u8) {}
+                }
+            },
+        );
+        assert!(res.is_err());
+    }
+}
@@ -0,0 +1,114 @@
+use crate::export::Function;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{quote, ToTokens};
+use syn::parse::{Parse, ParseStream, Result};
+
+/// FfizzFn is the result of parsing the input to `ffizz_fn!`: a Rust function to be called from C.
+#[derive(Debug)]
+pub(crate) struct FfizzFn {
+    item: syn::ItemFn,
+    function: Function,
+}
+
+impl Parse for FfizzFn {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let mut item: syn::ItemFn = input.parse()?;
+        let name = item.sig.ident.to_string();
+        let function = Function::parse(&mut item.sig, &mut item.attrs, name, None)?;
+        Ok(FfizzFn { item, function })
+    }
+}
+
+impl FfizzFn {
+    /// Convert this FfizzFn into a TokenStream containing the `extern "C"` function, which
+    /// contains the Rust function, and the header item.
+    pub(crate) fn to_tokens(&self, tokens: &mut TokenStream2) {
+        let syn::ItemFn {
+            attrs,
+            vis,
+            sig,
+            block,
+        } = &self.item;
+        let ident = &sig.ident;
+        // the Rust function is nested in the extern "C" function with the same name, so its
+        // body can call itself
+        let prelude = quote! {
+            #sig #block
+        };
+        let attrs = attrs.iter().map(|a| a.to_token_stream()).collect();
+        self.function
+            .to_tokens(attrs, vis, prelude, quote!(#ident), None, tokens);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::headeritem::HeaderItem;
+
+    #[test]
+    fn test_ffizz_fn() {
+        let f: FfizzFn = syn::parse_quote! {
+            /// Count the colors in a palette with the given name.
+            #[ffizz(order=200)]
+            #[ffizz_catch(0)]
+            pub fn hittr_count(
+                #[ffizz(boxed = "palette_t")] palette: &Palette,
+                #[ffizz(string)] name: &str,
+                max: usize,
+            ) -> u32 {
+                palette.count(name).min(max)
+            }
+        };
+        assert_eq!(f.item.attrs.len(), 1);
+        assert!(f.item.sig.inputs.iter().all(|input| match input {
+            syn::FnArg::Typed(pat_type) => pat_type.attrs.is_empty(),
+            _ => false,
+        }));
+        assert_eq!(
+            f.function.header_item,
+            HeaderItem {
+                order: 200,
+                name: "hittr_count".into(),
+                content: "// Count the colors in a palette with the given name.\n\
+                    uint32_t hittr_count(const palette_t *palette, fz_string_t *name, size_t max);"
+                    .into(),
+                includes: vec!["<stddef.h>".into(), "<stdint.h>".into()],
+                ..Default::default()
+            }
+        );
+
+        let mut tokens = TokenStream2::new();
+        f.to_tokens(&mut tokens);
+        let tokens = tokens.to_string();
+        assert!(tokens.contains("LastError :: catch (0"));
+        assert!(tokens.contains("extern \"C\" fn hittr_count"));
+    }
+
+    #[test]
+    fn test_ffizz_fn_no_args() {
+        let f: FfizzFn = syn::parse_quote! {
+            pub fn hittr_version() -> u32 { 1 }
+        };
+        assert_eq!(
+            f.function.header_item.content,
+            "uint32_t hittr_version(void);"
+        );
+    }
+
+    #[test]
+    fn test_ffizz_fn_self() {
+        let res: Result<FfizzFn> = syn::parse2(quote! {
+            pub fn hittr_count(&self) -> u32 { 1 }
+        });
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_ffizz_fn_extern() {
+        let res: Result<FfizzFn> = syn::parse2(quote! {
+            pub extern "C" fn hittr_count() -> u32 { 1 }
+        });
+        assert!(res.is_err());
+    }
+}
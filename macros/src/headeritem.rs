@@ -1,85 +1,274 @@
 use proc_macro2::{Span, TokenStream as TokenStream2};
-use quote::quote;
+use quote::{quote, ToTokens};
 use syn::parse::{Error, Result};
+use syn::spanned::Spanned;
 
 /// The default order for a header item.
 const DEFAULT_ORDER: usize = 100;
 
+/// The properties allowed in `#[ffizz(..)]`, for error messages.
+const PROPERTIES: &str = "name=\"..\", order=.., feature=\"..\", group=\"..\", platform=\"..\", \
+    since=\"..\", include=\"..\", cpp_helper, unstable, no_validate, skip, and force";
+
+/// The platforms allowed in `#[ffizz(platform = "..")]`, with the corresponding `cfg` predicate
+/// and C preprocessor condition.
+const PLATFORMS: &[(&str, &str, &str)] = &[
+    ("unix", "unix", "defined(__unix__) || defined(__APPLE__)"),
+    ("windows", "windows", "defined(_WIN32)"),
+    ("linux", "target_os = \"linux\"", "defined(__linux__)"),
+    ("macos", "target_os = \"macos\"", "defined(__APPLE__)"),
+];
+
 /// HeaderItem is a proc-macro-execution-time version of the HeaderItem object these macros will
 /// insert into the Rust code.
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Default, Clone, PartialEq)]
 pub(crate) struct HeaderItem {
     pub(crate) order: usize,
     pub(crate) name: String,
     pub(crate) content: String,
+    pub(crate) cpp_content: Option<String>,
+    pub(crate) cpp_helper: bool,
+    pub(crate) deprecated: Option<String>,
+    pub(crate) feature: Option<String>,
+    pub(crate) group: Option<String>,
+    pub(crate) platform: Option<String>,
+    pub(crate) since: Option<String>,
+    pub(crate) unstable: bool,
+    pub(crate) includes: Vec<String>,
+    /// True if the item should not appear in the header at all
+    pub(crate) skip: bool,
+    /// Additional items split from this item's docstring with `<!-- ffizz:item .. -->` markers
+    pub(crate) split: Vec<HeaderItem>,
+}
+
+/// ParsedAttrs contains the information gathered from an item's attributes.
+#[derive(Debug, Default)]
+pub(crate) struct ParsedAttrs {
+    /// The lines of the item's docstring
+    pub(crate) doc: Vec<String>,
+    /// The `name` property, if given
+    pub(crate) name: Option<String>,
+    /// The `order` property, if given
+    pub(crate) order: Option<usize>,
+    /// True if the `cpp_helper` property was given
+    pub(crate) cpp_helper: bool,
+    /// The note from a `#[deprecated]` attribute (empty if no note was given)
+    pub(crate) deprecated: Option<String>,
+    /// The `feature` property, if given
+    pub(crate) feature: Option<String>,
+    /// The `group` property, if given
+    pub(crate) group: Option<String>,
+    /// The `platform` property, if given
+    pub(crate) platform: Option<String>,
+    /// The `since` property, if given
+    pub(crate) since: Option<String>,
+    /// True if the `unstable` property was given
+    pub(crate) unstable: bool,
+    /// The `include` properties, in the form given to `#include`
+    pub(crate) includes: Vec<String>,
+    /// True if the `no_validate` property was given
+    pub(crate) no_validate: bool,
+    /// True if the `skip` property was given
+    pub(crate) skip: bool,
+    /// True if the item has a `#[doc(hidden)]` attribute
+    pub(crate) doc_hidden: bool,
+    /// True if the `force` property was given
+    pub(crate) force: bool,
+    /// The span of the docstring attribute containing each line of `doc`
+    pub(crate) doc_spans: Vec<Span>,
+}
+
+/// A section of a docstring following a `<!-- ffizz:item .. -->` marker.
+#[derive(Debug, Default, PartialEq)]
+struct SplitSection {
+    name: String,
+    order: Option<usize>,
+    doc: Vec<String>,
+}
+
+/// The language of a fenced block in a docstring.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Lang {
+    C,
+    Cpp,
+}
+
+impl Lang {
+    /// Get the language of a line opening a fenced block, or None if it is not such a line.
+    fn of_fence(line: &str) -> Option<Lang> {
+        match line.trim() {
+            "```c" => Some(Lang::C),
+            "```cpp" => Some(Lang::Cpp),
+            _ => None,
+        }
+    }
 }
 
 impl HeaderItem {
     /// Create a HeaderItem, given a name and a vec of its attributes.  All ffizz_header-specific
     /// attributes are removed from attrs, and all docstrings are parsed into C header content.
     pub(crate) fn from_attrs(name: String, attrs: &mut Vec<syn::Attribute>) -> Result<Self> {
-        let (doc, override_name, override_order) = Self::parse_attrs(attrs)?;
-        let content = Self::parse_content(doc);
+        let mut attrs = Self::parse_attrs(attrs)?;
+        // hidden items are internal, unless forced into the header
+        if attrs.skip || (attrs.doc_hidden && !attrs.force) {
+            return Ok(Self {
+                name: attrs.name.unwrap_or(name),
+                skip: true,
+                ..Default::default()
+            });
+        }
+        if !attrs.no_validate {
+            crate::validate::validate_doc(&attrs.doc).map_err(|msg| {
+                Error::new(
+                    attrs
+                        .doc_spans
+                        .first()
+                        .copied()
+                        .unwrap_or_else(Span::call_site),
+                    format!("{msg} (use #[ffizz(no_validate)] to skip this check)"),
+                )
+            })?;
+        }
+        let sections = Self::split_doc(&mut attrs.doc, &attrs.doc_spans)?;
+        let order = attrs.order.unwrap_or(DEFAULT_ORDER);
+        let split = sections
+            .into_iter()
+            .map(|section| Self {
+                name: section.name,
+                order: section.order.unwrap_or(order),
+                content: Self::parse_content(section.doc.clone()),
+                cpp_content: Self::parse_cpp_content(&section.doc),
+                cpp_helper: attrs.cpp_helper,
+                feature: attrs.feature.clone(),
+                group: attrs.group.clone(),
+                platform: attrs.platform.clone(),
+                since: attrs.since.clone(),
+                unstable: attrs.unstable,
+                ..Default::default()
+            })
+            .collect();
         Ok(Self {
-            name: override_name.unwrap_or(name),
-            order: override_order.unwrap_or(DEFAULT_ORDER),
-            content,
+            name: attrs.name.unwrap_or(name),
+            order,
+            content: Self::parse_content(attrs.doc.clone()),
+            cpp_content: Self::parse_cpp_content(&attrs.doc),
+            cpp_helper: attrs.cpp_helper,
+            deprecated: attrs.deprecated,
+            feature: attrs.feature,
+            group: attrs.group,
+            platform: attrs.platform,
+            since: attrs.since,
+            unstable: attrs.unstable,
+            includes: attrs.includes,
+            skip: false,
+            split,
         })
     }
 
+    /// Split the sections introduced by `<!-- ffizz:item name=.. order=.. -->` markers out of a
+    /// docstring, leaving only the lines before the first marker.
+    fn split_doc(doc: &mut Vec<String>, spans: &[Span]) -> Result<Vec<SplitSection>> {
+        let mut sections: Vec<SplitSection> = vec![];
+        let mut in_fence = false;
+        for (i, line) in std::mem::take(doc).into_iter().enumerate() {
+            let span = spans.get(i).copied().unwrap_or_else(Span::call_site);
+            if in_fence {
+                in_fence = line.trim() != "```";
+            } else if Lang::of_fence(&line).is_some() {
+                in_fence = true;
+            } else if let Some(section) = Self::parse_split_marker(&line, span)? {
+                sections.push(section);
+                continue;
+            }
+            match sections.last_mut() {
+                Some(section) => section.doc.push(line),
+                None => doc.push(line),
+            }
+        }
+        Ok(sections)
+    }
+
+    /// Parse a `<!-- ffizz:item name=.. order=.. -->` marker line, returning None if the line is
+    /// not a marker.  Errors are reported at the given span.
+    fn parse_split_marker(line: &str, span: Span) -> Result<Option<SplitSection>> {
+        let marker = line
+            .trim()
+            .strip_prefix("<!--")
+            .and_then(|l| l.strip_suffix("-->"))
+            .and_then(|l| l.trim().strip_prefix("ffizz:item"));
+        let Some(marker) = marker else {
+            return Ok(None);
+        };
+
+        let err = || {
+            Error::new(
+                span,
+                format!(
+                    "Invalid marker {:?}; expected <!-- ffizz:item name=\"..\" order=.. -->",
+                    line.trim()
+                ),
+            )
+        };
+        let mut section = SplitSection::default();
+        for prop in marker.split_whitespace() {
+            let (key, value) = prop.split_once('=').ok_or_else(err)?;
+            let value = value.trim_matches('"');
+            match key {
+                "name" => section.name = value.to_string(),
+                "order" => section.order = Some(value.parse().map_err(|_| err())?),
+                _ => return Err(err()),
+            }
+        }
+        if section.name.is_empty() {
+            return Err(err());
+        }
+        Ok(Some(section))
+    }
+
     /// Parse a vec of attributes, extracting docstrings and ffizz attributes (name and header).
     /// Any ffizz attributes are removed from the given vector.
-    ///
-    /// Returns the docstrings, the name property (if found), and the order (if found)
-    pub(crate) fn parse_attrs(
-        attrs: &mut Vec<syn::Attribute>,
-    ) -> Result<(Vec<String>, Option<String>, Option<usize>)> {
-        let mut order = None;
-        let mut name = None;
-
-        let mut doc: Vec<String> = vec![];
+    pub(crate) fn parse_attrs(attrs: &mut Vec<syn::Attribute>) -> Result<ParsedAttrs> {
+        let mut result = ParsedAttrs::default();
+
         let mut kept_attrs = vec![];
         for attr in attrs.drain(..) {
             let mut keep_attr = true;
             match attr.parse_meta() {
                 // docstrings are represented as #[doc = r"..."]
-                Ok(syn::Meta::NameValue(nv)) => {
-                    if nv.path.is_ident("doc") {
-                        if let syn::Lit::Str(s) = nv.lit {
-                            let s = s.value();
-                            doc.extend(Self::parse_docstring_attr(s));
-                        }
+                Ok(syn::Meta::NameValue(nv)) if nv.path.is_ident("doc") => {
+                    if let syn::Lit::Str(s) = nv.lit {
+                        let lines = Self::parse_docstring_attr(s.value());
+                        result.doc_spans.extend(lines.iter().map(|_| attr.span()));
+                        result.doc.extend(lines);
                     }
                 }
-                Ok(syn::Meta::List(metalist)) => {
-                    if metalist.path.is_ident("ffizz") {
-                        keep_attr = false;
-                        for elt in metalist.nested {
-                            let mut ok = false;
-                            if let syn::NestedMeta::Meta(syn::Meta::NameValue(nv)) = elt {
-                                if nv.path.is_ident("name") {
-                                    if let syn::Lit::Str(s) = nv.lit {
-                                        name = Some(s.value());
-                                        ok = true;
-                                    }
-                                } else if nv.path.is_ident("order") {
-                                    if let syn::Lit::Int(i) = nv.lit {
-                                        if let Ok(i) = i.base10_parse::<usize>() {
-                                            order = Some(i);
-                                            ok = true;
-                                        }
-                                    }
-                                }
-                            }
-                            if !ok {
-                                return Err(Error::new_spanned(
-                                    attr,
-                                    "Valid #[fizz(..)] attribute properties here are name=\"..\" and order=.."
-                                ));
-                            }
-                        }
+                Ok(syn::Meta::List(metalist)) if metalist.path.is_ident("doc") => {
+                    result.doc_hidden |= metalist.nested.iter().any(|elt| {
+                        matches!(elt, syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("hidden"))
+                    });
+                }
+                Ok(syn::Meta::List(metalist)) if metalist.path.is_ident("ffizz") => {
+                    keep_attr = false;
+                    for elt in metalist.nested {
+                        Self::parse_property(&mut result, elt)?;
                     }
                 }
+                Ok(meta) if meta.path().is_ident("ffizz") => {
+                    return Err(Error::new_spanned(
+                        meta,
+                        format!("expected #[ffizz(..)] with properties {PROPERTIES}"),
+                    ));
+                }
+                Err(e) if attr.path.is_ident("ffizz") => {
+                    return Err(Error::new(
+                        e.span(),
+                        format!("invalid #[ffizz(..)] attribute: {e}"),
+                    ));
+                }
+                // deprecation is reflected in the header, and the attribute is kept
+                Ok(meta) if meta.path().is_ident("deprecated") => {
+                    result.deprecated = Some(Self::deprecation_note(meta));
+                }
                 _ => {
                     // ignore (and keep) any other attributes
                 }
@@ -90,7 +279,142 @@ impl HeaderItem {
         }
         *attrs = kept_attrs;
 
-        Ok((doc, name, order))
+        Ok(result)
+    }
+
+    /// Parse a single property from an `#[ffizz(..)]` attribute into `result`.  Errors point at the
+    /// offending property or value.
+    fn parse_property(result: &mut ParsedAttrs, elt: syn::NestedMeta) -> Result<()> {
+        let string = |key: &str, lit: &syn::Lit| match lit {
+            syn::Lit::Str(s) => Ok(s.value()),
+            _ => Err(Error::new_spanned(
+                lit,
+                format!(
+                    "{key} must be a string, such as {key}=\"..\", but got `{}`",
+                    lit.to_token_stream()
+                ),
+            )),
+        };
+        let unknown = |path: &syn::Path| {
+            Error::new_spanned(
+                path,
+                format!(
+                    "unknown property `{}`; valid #[ffizz(..)] properties are {PROPERTIES}",
+                    path.to_token_stream()
+                ),
+            )
+        };
+        match elt {
+            syn::NestedMeta::Meta(syn::Meta::NameValue(nv)) => {
+                let key = nv
+                    .path
+                    .get_ident()
+                    .map(|i| i.to_string())
+                    .unwrap_or_default();
+                match key.as_str() {
+                    "name" => result.name = Some(string("name", &nv.lit)?),
+                    "order" => {
+                        let syn::Lit::Int(i) = &nv.lit else {
+                            return Err(Error::new_spanned(
+                                &nv.lit,
+                                format!(
+                                    "order must be an integer, such as order=100, but got `{}`",
+                                    nv.lit.to_token_stream()
+                                ),
+                            ));
+                        };
+                        let order = i.base10_parse::<usize>().map_err(|e| {
+                            Error::new_spanned(i, format!("invalid order `{i}`: {e}"))
+                        })?;
+                        result.order = Some(order);
+                    }
+                    "feature" => result.feature = Some(string("feature", &nv.lit)?),
+                    "group" => result.group = Some(string("group", &nv.lit)?),
+                    "since" => result.since = Some(string("since", &nv.lit)?),
+                    "platform" => {
+                        let platform = string("platform", &nv.lit)?;
+                        if !PLATFORMS.iter().any(|(p, _, _)| *p == platform) {
+                            let valid: Vec<&str> = PLATFORMS.iter().map(|(p, _, _)| *p).collect();
+                            return Err(Error::new_spanned(
+                                &nv.lit,
+                                format!(
+                                    "unknown platform {platform:?}; valid platforms are {}",
+                                    valid.join(", ")
+                                ),
+                            ));
+                        }
+                        result.platform = Some(platform);
+                    }
+                    "include" => {
+                        let s = string("include", &nv.lit)?;
+                        // bare header names are system headers
+                        if s.starts_with('<') || s.starts_with('"') {
+                            result.includes.push(s);
+                        } else {
+                            result.includes.push(format!("<{s}>"));
+                        }
+                    }
+                    "cpp_helper" | "unstable" | "no_validate" | "skip" | "force" => {
+                        return Err(Error::new_spanned(
+                            &nv,
+                            format!("{key} does not take a value; use #[ffizz({key})]"),
+                        ));
+                    }
+                    _ => return Err(unknown(&nv.path)),
+                }
+            }
+            syn::NestedMeta::Meta(syn::Meta::Path(path)) => {
+                let key = path.get_ident().map(|i| i.to_string()).unwrap_or_default();
+                match key.as_str() {
+                    "cpp_helper" => result.cpp_helper = true,
+                    "unstable" => result.unstable = true,
+                    "no_validate" => result.no_validate = true,
+                    "skip" => result.skip = true,
+                    "force" => result.force = true,
+                    "name" | "order" | "feature" | "group" | "platform" | "since" | "include" => {
+                        return Err(Error::new_spanned(
+                            &path,
+                            format!("{key} requires a value, such as {key}=.."),
+                        ));
+                    }
+                    _ => return Err(unknown(&path)),
+                }
+            }
+            elt => {
+                return Err(Error::new_spanned(
+                    &elt,
+                    format!(
+                        "unexpected `{}`; valid #[ffizz(..)] properties are {PROPERTIES}",
+                        elt.to_token_stream()
+                    ),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Get the note from a `#[deprecated]` attribute, in any of its forms, or an empty string if
+    /// there is no note.
+    fn deprecation_note(meta: syn::Meta) -> String {
+        match meta {
+            syn::Meta::NameValue(syn::MetaNameValue {
+                lit: syn::Lit::Str(s),
+                ..
+            }) => s.value(),
+            syn::Meta::List(metalist) => metalist
+                .nested
+                .into_iter()
+                .find_map(|elt| match elt {
+                    syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                        path,
+                        lit: syn::Lit::Str(s),
+                        ..
+                    })) if path.is_ident("note") => Some(s.value()),
+                    _ => None,
+                })
+                .unwrap_or_default(),
+            _ => String::new(),
+        }
     }
 
     /// Parse a docstring attribute value into an array of docstring lines, accounting for
@@ -165,8 +489,35 @@ impl HeaderItem {
 
     /// Parse a docstring, presented as a vec of lines, to extract C declarations and comments.
     pub(crate) fn parse_content(doc: Vec<String>) -> String {
+        Self::parse_content_for(doc, Lang::C)
+    }
+
+    /// Parse a docstring, presented as a vec of lines, to extract C++ declarations and comments,
+    /// if it contains any ```cpp blocks.
+    fn parse_cpp_content(doc: &[String]) -> Option<String> {
+        Self::has_cpp_blocks(doc).then(|| Self::parse_content_for(doc.to_vec(), Lang::Cpp))
+    }
+
+    /// Determine whether a docstring contains any ```cpp blocks.
+    fn has_cpp_blocks(doc: &[String]) -> bool {
+        doc.iter()
+            .any(|line| Lang::of_fence(line) == Some(Lang::Cpp))
+    }
+
+    /// Parse a docstring, presented as a vec of lines, to extract declarations and comments for
+    /// the given language.  For C, ```cpp blocks are omitted.  For C++, each ```c block is
+    /// replaced by a ```cpp block immediately following it, if there is one.
+    fn parse_content_for(doc: Vec<String>, lang: Lang) -> String {
+        // determine the language of each block, and whether to include it
+        let blocks: Vec<Lang> = doc.iter().filter_map(|l| Lang::of_fence(l)).collect();
+        let mut include_block = blocks.iter().enumerate().map(|(i, block)| match lang {
+            Lang::C => *block == Lang::C,
+            Lang::Cpp => *block == Lang::Cpp || blocks.get(i + 1) != Some(&Lang::Cpp),
+        });
+
         let mut content = vec![];
         let mut in_decl = false;
+        let mut include_decl = false;
         let mut strip_new_blank_comments = true;
 
         /// strip trailing blank comment lines
@@ -187,13 +538,16 @@ impl HeaderItem {
                     strip_new_blank_comments = true;
                     continue;
                 }
-                content.push(line);
+                if include_decl {
+                    content.push(line);
+                }
             } else {
                 if strip_new_blank_comments && line.is_empty() {
                     continue;
                 }
-                if line.trim() == "```c" {
+                if Lang::of_fence(&line).is_some() {
                     in_decl = true;
+                    include_decl = include_block.next().unwrap_or(false);
                     strip_trailing_blank_comments(&mut content);
                     continue;
                 }
@@ -211,28 +565,126 @@ impl HeaderItem {
         itertools::join(content, "\n")
     }
 
+    /// Get the `cfg` predicate and C preprocessor condition for this item's platform, if it has one.
+    pub(crate) fn platform_conditions(&self) -> Option<(&'static str, &'static str)> {
+        let platform = self.platform.as_deref()?;
+        PLATFORMS
+            .iter()
+            .find(|(p, _, _)| *p == platform)
+            .map(|(_, cfg, cond)| (*cfg, *cond))
+    }
+
     /// Write the content of this HeaderItem into a TokenStream such that the resulting binary will
-    /// include the HeaderItem in its `::ffizz_header::FFIZZ_HEADER_ITEMS` array.
+    /// include the HeaderItem in its `::ffizz_header::FFIZZ_HEADER_ITEMS` array, or with the
+    /// `inventory` feature, in its `inventory` registry.
     pub(crate) fn to_tokens(&self, tokens: &mut TokenStream2) {
+        if self.skip {
+            return;
+        }
+
+        // record the item for any later `emit_at_build!` in this crate
+        crate::emitbuild::record(self);
+
         let HeaderItem {
             order,
             name,
             content,
+            cpp_content,
+            cpp_helper,
+            deprecated,
+            feature,
+            group,
+            platform,
+            since,
+            unstable,
+            includes,
+            skip: _,
+            split,
         } = self;
-        let item_name = syn::Ident::new(&format!("FFIZZ_HDR__{name}"), Span::call_site());
+        let group = match group {
+            Some(group) => quote!(Some(#group)),
+            None => quote!(None),
+        };
+        let platform = match platform {
+            Some(platform) => quote!(Some(#platform)),
+            None => quote!(None),
+        };
+        let since = match since {
+            Some(since) => quote!(Some(#since)),
+            None => quote!(None),
+        };
+        let cpp_content = match cpp_content {
+            Some(cpp_content) => quote!(Some(#cpp_content)),
+            None => quote!(None),
+        };
+        let deprecated = match deprecated {
+            Some(note) => quote!(Some(#note)),
+            None => quote!(None),
+        };
+        // the item is always registered, but is only enabled when the feature is enabled in the
+        // crate containing the item
+        let (feature, enabled) = match feature {
+            Some(feature) => (quote!(Some(#feature)), quote!(cfg!(feature = #feature))),
+            None => (quote!(None), quote!(true)),
+        };
+        // a constant that only exists to cause a descriptive error when two items in the same
+        // module have the same name; duplicates elsewhere are detected by `generate()`
+        let marker = syn::Ident::new(
+            &format!("ffizz_header_item_named__{name}__is_defined_more_than_once"),
+            Span::call_site(),
+        );
 
-        // insert an invocation of linkme::distributed_slice to add this header item to
-        // the FFIZZ_HEADER_ITEMS slice.
-        tokens.extend(quote! {
-            #[::ffizz_header::linkme::distributed_slice(::ffizz_header::FFIZZ_HEADER_ITEMS)]
-            #[linkme(crate=::ffizz_header::linkme)]
-            #[allow(non_upper_case_globals)]
-            static #item_name: ::ffizz_header::HeaderItem = ::ffizz_header::HeaderItem {
+        let item = quote! {
+            ::ffizz_header::HeaderItem {
                 order: #order,
+                module_path: ::std::module_path!(),
+                file: ::std::file!(),
+                line: ::std::line!(),
                 name: #name,
                 content: #content,
-            };
+                cpp_content: #cpp_content,
+                cpp_helper: #cpp_helper,
+                deprecated: #deprecated,
+                feature: #feature,
+                group: #group,
+                platform: #platform,
+                since: #since,
+                unstable: #unstable,
+                enabled: #enabled,
+                includes: &[#(#includes),*],
+            }
+        };
+
+        tokens.extend(quote! {
+            #[doc(hidden)]
+            #[allow(non_upper_case_globals, dead_code)]
+            const #marker: () = ();
         });
+
+        if cfg!(feature = "inventory") {
+            // submit this header item to `inventory`
+            tokens.extend(quote! {
+                ::ffizz_header::inventory::submit! { #item }
+            });
+        } else {
+            // insert an invocation of linkme::distributed_slice to add this header item to
+            // the FFIZZ_HEADER_ITEMS slice, or submit it to `inventory` on WebAssembly, which
+            // `linkme` does not support.
+            tokens.extend(quote! {
+                #[cfg(not(target_family = "wasm"))]
+                const _: () = {
+                    #[::ffizz_header::linkme::distributed_slice(::ffizz_header::FFIZZ_HEADER_ITEMS)]
+                    #[linkme(crate=::ffizz_header::linkme)]
+                    static FFIZZ_HDR: ::ffizz_header::HeaderItem = #item;
+                };
+                #[cfg(target_family = "wasm")]
+                ::ffizz_header::inventory::submit! { #item }
+            });
+        }
+
+        for item in split {
+            item.to_tokens(tokens);
+        }
     }
 }
 
@@ -257,7 +709,9 @@ mod test {
             /// aaa
             /// bbb
         };
-        let (doc, name, order) = HeaderItem::parse_attrs(&mut attrs.0).unwrap();
+        let ParsedAttrs {
+            doc, name, order, ..
+        } = HeaderItem::parse_attrs(&mut attrs.0).unwrap();
         assert_eq!(order, None);
         assert_eq!(name, None);
         assert_eq!(doc, vec!["aaa", "bbb"]);
@@ -271,7 +725,9 @@ mod test {
              * bbb
              */
         };
-        let (doc, name, order) = HeaderItem::parse_attrs(&mut attrs.0).unwrap();
+        let ParsedAttrs {
+            doc, name, order, ..
+        } = HeaderItem::parse_attrs(&mut attrs.0).unwrap();
         assert_eq!(order, None);
         assert_eq!(name, None);
         assert_eq!(doc, vec!["aaa", "bbb"]);
@@ -284,7 +740,9 @@ mod test {
             #[ffizz(name="override")]
             /// bbb
         };
-        let (doc, name, order) = HeaderItem::parse_attrs(&mut attrs.0).unwrap();
+        let ParsedAttrs {
+            doc, name, order, ..
+        } = HeaderItem::parse_attrs(&mut attrs.0).unwrap();
         assert_eq!(order, None);
         assert_eq!(name, Some(String::from("override")));
         assert_eq!(doc, vec!["aaa", "bbb"]);
@@ -301,7 +759,9 @@ mod test {
             #[ffizz(order=13)]
             /// bbb
         };
-        let (doc, name, order) = HeaderItem::parse_attrs(&mut attrs.0).unwrap();
+        let ParsedAttrs {
+            doc, name, order, ..
+        } = HeaderItem::parse_attrs(&mut attrs.0).unwrap();
         assert_eq!(order, Some(13));
         assert_eq!(name, Some(String::from("override")));
         assert_eq!(doc, vec!["aaa", "bbb"]);
@@ -316,7 +776,9 @@ mod test {
             /// aaa
             /// bbb
         };
-        let (doc, name, order) = HeaderItem::parse_attrs(&mut attrs.0).unwrap();
+        let ParsedAttrs {
+            doc, name, order, ..
+        } = HeaderItem::parse_attrs(&mut attrs.0).unwrap();
         assert_eq!(order, Some(13));
         assert_eq!(name, Some(String::from("override")));
         assert_eq!(doc, vec!["aaa", "bbb"]);
@@ -334,6 +796,252 @@ mod test {
         assert!(HeaderItem::parse_attrs(&mut attrs.0).is_err());
     }
 
+    #[test]
+    fn parse_attrs_error_messages() {
+        let err = |mut attrs: Attrs| {
+            HeaderItem::parse_attrs(&mut attrs.0)
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(
+            err(parse_quote! { #[ffizz(name=13)] }),
+            "name must be a string, such as name=\"..\", but got `13`"
+        );
+        assert_eq!(
+            err(parse_quote! { #[ffizz(order="10")] }),
+            "order must be an integer, such as order=100, but got `\"10\"`"
+        );
+        assert_eq!(
+            err(parse_quote! { #[ffizz(skip=true)] }),
+            "skip does not take a value; use #[ffizz(skip)]"
+        );
+        assert_eq!(
+            err(parse_quote! { #[ffizz(group)] }),
+            "group requires a value, such as group=.."
+        );
+        assert!(err(parse_quote! { #[ffizz(nmae="foo")] }).starts_with("unknown property `nmae`"));
+        assert!(
+            err(parse_quote! { #[ffizz(name=foo)] }).starts_with("invalid #[ffizz(..)] attribute")
+        );
+        assert!(err(parse_quote! { #[ffizz] }).starts_with("expected #[ffizz(..)]"));
+    }
+
+    #[test]
+    fn parse_attrs_cpp_helper() {
+        let mut attrs: Attrs = parse_quote! {
+            #[ffizz(name="helper", cpp_helper)]
+            /// aaa
+        };
+        let attrs = HeaderItem::parse_attrs(&mut attrs.0).unwrap();
+        assert!(attrs.cpp_helper);
+        assert_eq!(attrs.name, Some(String::from("helper")));
+    }
+
+    #[test]
+    fn parse_attrs_deprecated() {
+        let mut attrs: Attrs = parse_quote! {
+            /// aaa
+            #[deprecated]
+        };
+        let parsed = HeaderItem::parse_attrs(&mut attrs.0).unwrap();
+        assert_eq!(parsed.deprecated, Some(String::new()));
+        // check that the #[deprecated] attribute was kept
+        assert_eq!(attrs.0.len(), 2);
+    }
+
+    #[test]
+    fn parse_attrs_deprecated_note() {
+        let mut attrs: Attrs = parse_quote! {
+            #[deprecated(since="1.2", note="use bar")]
+        };
+        let attrs = HeaderItem::parse_attrs(&mut attrs.0).unwrap();
+        assert_eq!(attrs.deprecated, Some(String::from("use bar")));
+
+        let mut attrs: Attrs = parse_quote! {
+            #[deprecated = "use baz"]
+        };
+        let attrs = HeaderItem::parse_attrs(&mut attrs.0).unwrap();
+        assert_eq!(attrs.deprecated, Some(String::from("use baz")));
+    }
+
+    #[test]
+    fn parse_attrs_feature() {
+        let mut attrs: Attrs = parse_quote! {
+            #[ffizz(feature="sync", order=20)]
+            /// aaa
+        };
+        let attrs = HeaderItem::parse_attrs(&mut attrs.0).unwrap();
+        assert_eq!(attrs.feature, Some(String::from("sync")));
+        assert_eq!(attrs.order, Some(20));
+    }
+
+    #[test]
+    fn from_attrs_invalid_c() {
+        let mut attrs: Attrs = parse_quote! {
+            /// ```c
+            /// foo_free(foo_t *);
+            /// ```
+        };
+        assert!(HeaderItem::from_attrs("foo_free".into(), &mut attrs.0).is_err());
+
+        let mut attrs: Attrs = parse_quote! {
+            #[ffizz(no_validate)]
+            /// ```c
+            /// foo_free(foo_t *);
+            /// ```
+        };
+        assert!(HeaderItem::from_attrs("foo_free".into(), &mut attrs.0).is_ok());
+    }
+
+    #[test]
+    fn from_attrs_skip() {
+        let mut attrs: Attrs = parse_quote! {
+            #[ffizz(skip)]
+            /// Internal helper; not valid C.
+            /// ```c
+            /// helper(
+            /// ```
+        };
+        let item = HeaderItem::from_attrs("helper".into(), &mut attrs.0).unwrap();
+        assert!(item.skip);
+        // the docstring is kept
+        assert_eq!(attrs.0.len(), 4);
+
+        let mut tokens = TokenStream2::new();
+        item.to_tokens(&mut tokens);
+        assert!(tokens.is_empty());
+    }
+
+    #[test]
+    fn from_attrs_doc_hidden() {
+        let mut attrs: Attrs = parse_quote! {
+            #[doc(hidden)]
+            /// Internal plumbing.
+        };
+        let item = HeaderItem::from_attrs("plumbing".into(), &mut attrs.0).unwrap();
+        assert!(item.skip);
+
+        let mut attrs: Attrs = parse_quote! {
+            #[doc(hidden)]
+            #[ffizz(force)]
+            /// Internal plumbing.
+        };
+        let item = HeaderItem::from_attrs("plumbing".into(), &mut attrs.0).unwrap();
+        assert!(!item.skip);
+        assert_eq!(item.content, "// Internal plumbing.");
+    }
+
+    #[test]
+    fn parse_attrs_stability() {
+        let mut attrs: Attrs = parse_quote! {
+            #[ffizz(since="1.2", unstable)]
+            /// aaa
+        };
+        let item = HeaderItem::from_attrs("foo".into(), &mut attrs.0).unwrap();
+        assert_eq!(item.since, Some(String::from("1.2")));
+        assert!(item.unstable);
+    }
+
+    #[test]
+    fn parse_attrs_group() {
+        let mut attrs: Attrs = parse_quote! {
+            #[ffizz(group="strings")]
+            /// aaa
+        };
+        let attrs = HeaderItem::parse_attrs(&mut attrs.0).unwrap();
+        assert_eq!(attrs.group, Some(String::from("strings")));
+    }
+
+    #[test]
+    fn parse_attrs_include() {
+        let mut attrs: Attrs = parse_quote! {
+            #[ffizz(include="<stdint.h>", include="stdbool.h")]
+            #[ffizz(include="\"mylib/types.h\"")]
+            /// aaa
+        };
+        let attrs = HeaderItem::parse_attrs(&mut attrs.0).unwrap();
+        assert_eq!(
+            attrs.includes,
+            vec!["<stdint.h>", "<stdbool.h>", "\"mylib/types.h\""]
+        );
+    }
+
+    #[test]
+    fn from_attrs_split() {
+        let mut attrs: Attrs = parse_quote! {
+            #[ffizz(order=20, feature="sync")]
+            /// A color.
+            /// ```c
+            /// typedef uint32_t color_t;
+            /// ```
+            /// <!-- ffizz:item name="COLOR_RED" order=21 -->
+            /// ```c
+            /// #define COLOR_RED 0xff0000
+            /// ```
+            /// <!-- ffizz:item name=COLOR_GREEN -->
+            /// ```c
+            /// #define COLOR_GREEN 0x00ff00
+            /// ```
+        };
+        let item = HeaderItem::from_attrs("color_t".into(), &mut attrs.0).unwrap();
+        assert_eq!(
+            item,
+            HeaderItem {
+                order: 20,
+                name: "color_t".into(),
+                content: "// A color.\ntypedef uint32_t color_t;".into(),
+                feature: Some("sync".into()),
+                split: vec![
+                    HeaderItem {
+                        order: 21,
+                        name: "COLOR_RED".into(),
+                        content: "#define COLOR_RED 0xff0000".into(),
+                        feature: Some("sync".into()),
+                        ..Default::default()
+                    },
+                    HeaderItem {
+                        order: 20,
+                        name: "COLOR_GREEN".into(),
+                        content: "#define COLOR_GREEN 0x00ff00".into(),
+                        feature: Some("sync".into()),
+                        ..Default::default()
+                    },
+                ],
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn parse_split_marker() {
+        assert_eq!(
+            HeaderItem::parse_split_marker("<!-- other -->", Span::call_site()).unwrap(),
+            None
+        );
+        assert_eq!(
+            HeaderItem::parse_split_marker(" <!--ffizz:item name=foo-->", Span::call_site())
+                .unwrap(),
+            Some(SplitSection {
+                name: "foo".into(),
+                ..Default::default()
+            })
+        );
+        assert!(
+            HeaderItem::parse_split_marker("<!-- ffizz:item order=1 -->", Span::call_site())
+                .is_err()
+        );
+        assert!(HeaderItem::parse_split_marker(
+            "<!-- ffizz:item name=x order=y -->",
+            Span::call_site()
+        )
+        .is_err());
+        assert!(HeaderItem::parse_split_marker(
+            "<!-- ffizz:item name=x z=1 -->",
+            Span::call_site()
+        )
+        .is_err());
+    }
+
     fn multiline(s: &'static str) -> String {
         // strip `/**` and `*/`.
         s[3..s.len() - 2].to_string()
@@ -446,6 +1154,38 @@ mod test {
         );
     }
 
+    #[test]
+    fn parse_content_cpp_blocks() {
+        let doc: Vec<String> = [
+            "intro",
+            "```c",
+            "void foo(void);",
+            "```",
+            "```cpp",
+            "void foo();",
+            "```",
+            "```c",
+            "void bar(void);",
+            "```",
+            "```cpp",
+            "inline void baz() {}",
+            "```",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+        assert!(HeaderItem::has_cpp_blocks(&doc));
+        assert_eq!(
+            HeaderItem::parse_content_for(doc.clone(), Lang::C),
+            "// intro\nvoid foo(void);\nvoid bar(void);".to_string()
+        );
+        // the second cpp block does not directly follow a c block, but replaces it anyway
+        assert_eq!(
+            HeaderItem::parse_content_for(doc, Lang::Cpp),
+            "// intro\nvoid foo();\ninline void baz() {}".to_string()
+        );
+    }
+
     #[test]
     fn parse_content_multi_decl() {
         assert_eq!(
@@ -3,11 +3,12 @@ use proc_macro2::TokenStream as TokenStream2;
 use quote::ToTokens;
 use syn::parse::{Error, Parse, ParseStream, Result};
 
-/// DocItem is the result of parsing an item, with a header_item constructed from the
-/// item's docstrings and any ffizz-related attributes.
+/// DocItem is the result of parsing an item, with header_items constructed from the
+/// item's docstrings and any ffizz-related attributes.  Most items produce a single header item,
+/// but an `extern` block produces one for each documented foreign function.
 #[derive(Debug, PartialEq)]
 pub(crate) struct DocItem {
-    header_item: HeaderItem,
+    header_items: Vec<HeaderItem>,
     syn_item: syn::Item,
 }
 
@@ -28,6 +29,24 @@ impl Parse for DocItem {
                 )),
             }
         }
+        if let syn::Item::ForeignMod(foreign) = &mut item {
+            let mut header_items = vec![];
+            for foreign_item in &mut foreign.items {
+                if let syn::ForeignItem::Fn(func) = foreign_item {
+                    if func.attrs.iter().any(|attr| attr.path.is_ident("doc")) {
+                        let name = func.sig.ident.to_string();
+                        let header_item = HeaderItem::from_attrs(name, &mut func.attrs)?;
+                        func.attrs.extend(platform_cfg(&header_item));
+                        header_items.push(header_item);
+                    }
+                }
+            }
+            return Ok(DocItem {
+                header_items,
+                syn_item: item,
+            });
+        }
+
         let (name, attrs) = match &mut item {
             syn::Item::Fn(item) => (item.sig.ident.to_string(), &mut item.attrs),
             syn::Item::Const(item) => (item.ident.to_string(), &mut item.attrs),
@@ -45,18 +64,30 @@ impl Parse for DocItem {
             }
         };
 
+        let header_item = HeaderItem::from_attrs(name, attrs)?;
+        attrs.extend(platform_cfg(&header_item));
         Ok(DocItem {
-            header_item: HeaderItem::from_attrs(name, attrs)?,
+            header_items: vec![header_item],
             syn_item: item,
         })
     }
 }
 
+/// Get a `#[cfg(..)]` attribute limiting the Rust item to the header item's platform, if it has
+/// one.
+fn platform_cfg(header_item: &HeaderItem) -> Option<syn::Attribute> {
+    let (cfg, _) = header_item.platform_conditions()?;
+    let cfg: TokenStream2 = cfg.parse().unwrap();
+    Some(syn::parse_quote!(#[cfg(#cfg)]))
+}
+
 impl DocItem {
     /// Convert this DocItem into a TokenStream that will include it in the built binary.
     pub(crate) fn to_tokens(&self, tokens: &mut TokenStream2) {
         self.syn_item.to_tokens(tokens);
-        self.header_item.to_tokens(tokens);
+        for header_item in &self.header_items {
+            header_item.to_tokens(tokens);
+        }
     }
 }
 
@@ -71,12 +102,13 @@ mod test {
             pub unsafe extern "C" fn add(x: u32, y: u32) -> u32 {}
         };
         assert_eq!(
-            di.header_item,
-            HeaderItem {
+            di.header_items,
+            vec![HeaderItem {
                 order: 100,
                 name: "add".into(),
                 content: "// A docstring".into(),
-            }
+                ..Default::default()
+            }]
         );
     }
 
@@ -87,12 +119,13 @@ mod test {
             pub const X: usize = 13;
         };
         assert_eq!(
-            di.header_item,
-            HeaderItem {
+            di.header_items,
+            vec![HeaderItem {
                 order: 100,
                 name: "X".into(),
                 content: "// A docstring".into(),
-            }
+                ..Default::default()
+            }]
         );
     }
 
@@ -103,12 +136,13 @@ mod test {
             pub static X: usize = 13;
         };
         assert_eq!(
-            di.header_item,
-            HeaderItem {
+            di.header_items,
+            vec![HeaderItem {
                 order: 100,
                 name: "X".into(),
                 content: "// A docstring".into(),
-            }
+                ..Default::default()
+            }]
         );
     }
 
@@ -119,12 +153,13 @@ mod test {
             pub struct Foo {}
         };
         assert_eq!(
-            di.header_item,
-            HeaderItem {
+            di.header_items,
+            vec![HeaderItem {
                 order: 100,
                 name: "Foo".into(),
                 content: "// A docstring".into(),
-            }
+                ..Default::default()
+            }]
         );
     }
 
@@ -135,12 +170,13 @@ mod test {
             pub enum Foo {}
         };
         assert_eq!(
-            di.header_item,
-            HeaderItem {
+            di.header_items,
+            vec![HeaderItem {
                 order: 100,
                 name: "Foo".into(),
                 content: "// A docstring".into(),
-            }
+                ..Default::default()
+            }]
         );
     }
 
@@ -151,12 +187,13 @@ mod test {
             pub union Foo {}
         };
         assert_eq!(
-            di.header_item,
-            HeaderItem {
+            di.header_items,
+            vec![HeaderItem {
                 order: 100,
                 name: "Foo".into(),
                 content: "// A docstring".into(),
-            }
+                ..Default::default()
+            }]
         );
     }
 
@@ -167,12 +204,13 @@ mod test {
             pub type Foo = Bar;
         };
         assert_eq!(
-            di.header_item,
-            HeaderItem {
+            di.header_items,
+            vec![HeaderItem {
                 order: 100,
                 name: "Foo".into(),
                 content: "// A docstring".into(),
-            }
+                ..Default::default()
+            }]
         );
     }
 
@@ -183,12 +221,13 @@ mod test {
             use foo;
         };
         assert_eq!(
-            di.header_item,
-            HeaderItem {
+            di.header_items,
+            vec![HeaderItem {
                 order: 100,
                 name: "foo".into(),
                 content: "// A docstring".into(),
-            }
+                ..Default::default()
+            }]
         );
     }
 
@@ -199,12 +238,13 @@ mod test {
             pub use xxx::foo;
         };
         assert_eq!(
-            di.header_item,
-            HeaderItem {
+            di.header_items,
+            vec![HeaderItem {
                 order: 100,
                 name: "foo".into(),
                 content: "// A docstring".into(),
-            }
+                ..Default::default()
+            }]
         );
     }
 
@@ -215,12 +255,13 @@ mod test {
             use xxx::foo as bar;
         };
         assert_eq!(
-            di.header_item,
-            HeaderItem {
+            di.header_items,
+            vec![HeaderItem {
                 order: 100,
                 name: "bar".into(),
                 content: "// A docstring".into(),
-            }
+                ..Default::default()
+            }]
         );
     }
 
@@ -232,12 +273,82 @@ mod test {
             fn foo() {}
         };
         assert_eq!(
-            di.header_item,
-            HeaderItem {
+            di.header_items,
+            vec![HeaderItem {
                 order: 10,
                 name: "bar".into(),
                 content: "// A docstring".into(),
+                ..Default::default()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parsing_foreign_mod() {
+        let di: DocItem = syn::parse_quote! {
+            extern "C" {
+                /// Frobnicate a thing.
+                ///
+                /// ```c
+                /// int frob(int x);
+                /// ```
+                #[ffizz(order=20)]
+                fn frob(x: i32) -> i32;
+
+                fn undocumented();
+
+                /// A docstring
+                fn other();
             }
+        };
+        assert_eq!(
+            di.header_items,
+            vec![
+                HeaderItem {
+                    order: 20,
+                    name: "frob".into(),
+                    content: "// Frobnicate a thing.\nint frob(int x);".into(),
+                    ..Default::default()
+                },
+                HeaderItem {
+                    order: 100,
+                    name: "other".into(),
+                    content: "// A docstring".into(),
+                    ..Default::default()
+                },
+            ]
         );
+        // the ffizz attribute is removed from the foreign function
+        let syn::Item::ForeignMod(foreign) = &di.syn_item else {
+            panic!("expected a foreign mod");
+        };
+        let syn::ForeignItem::Fn(func) = &foreign.items[0] else {
+            panic!("expected a foreign fn");
+        };
+        assert!(!func.attrs.iter().any(|attr| attr.path.is_ident("ffizz")));
+    }
+
+    #[test]
+    fn test_parsing_platform() {
+        let di: DocItem = syn::parse_quote! {
+            /// A docstring
+            #[ffizz(platform="linux")]
+            pub unsafe extern "C" fn from_fd(fd: i32) {}
+        };
+        assert_eq!(
+            di.header_items,
+            vec![HeaderItem {
+                order: 100,
+                name: "from_fd".into(),
+                content: "// A docstring".into(),
+                platform: Some("linux".into()),
+                ..Default::default()
+            }]
+        );
+        let syn::Item::Fn(func) = &di.syn_item else {
+            panic!("expected a fn");
+        };
+        let cfg: syn::Attribute = syn::parse_quote!(#[cfg(target_os = "linux")]);
+        assert_eq!(func.attrs.last(), Some(&cfg));
     }
 }
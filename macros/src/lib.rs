@@ -1,6 +1,18 @@
+mod boxedapi;
+mod cenum;
+mod ctype;
+mod emitbuild;
+mod emitmain;
+mod export;
+mod ffizzfn;
 mod headeritem;
 mod item;
+mod opaque;
+mod passbypointer;
 mod snippet;
+mod unboxedapi;
+mod validate;
+mod vtable;
 
 use proc_macro::TokenStream;
 use proc_macro2::TokenStream as TokenStream2;
@@ -54,9 +66,10 @@ pub fn snippet(item: TokenStream) -> TokenStream {
 ///
 /// The header file is generated by concatenating the content supplied by this macro any by
 /// `ffizz_header::snippet!`.  Each piece of content has "order" and "name" properties, and these
-/// are used to sort the content in the header file.  The name must also be unique within the
-/// resulting library.  The "order" property defaults to `100` and "name" defaults to the name of
-/// the Rust item.  Both can be overridden with a `ffizz` attribute:
+/// are used to sort the content in the header file, with items of equal order grouped by the
+/// module in which they are defined.  The name must also be unique within the resulting library.
+/// The "order" property defaults to `100` and "name" defaults to the name of the Rust item.  Both
+/// can be overridden with a `ffizz` attribute:
 ///
 /// ```text
 /// #[ffizz(name="FOO_free")]
@@ -64,6 +77,88 @@ pub fn snippet(item: TokenStream) -> TokenStream {
 /// #[ffizz(name="FOO_free", order=200)]
 /// ```
 ///
+/// # Skipping
+///
+/// An item with `#[ffizz(skip)]` keeps its docstring for rustdoc, but does not appear in the
+/// header.  This is useful for `extern "C"` functions that are not part of the public API.
+///
+/// Items marked `#[doc(hidden)]` are internal by convention, and are also skipped unless they
+/// have `#[ffizz(force)]`.
+///
+/// # Foreign Functions
+///
+/// Applied to an `extern "C"` block, the macro produces a header item for each documented
+/// function in the block, named after the function.  This allows C functions that the library
+/// imports to appear in its header.  Each function can have its own `#[ffizz(..)]` attributes.
+///
+/// # Groups
+///
+/// Content can be assigned to a group with `#[ffizz(group="strings")]`.  Groups are ignored by
+/// `ffizz_header::generate`, but `ffizz_header::HeaderConfig::generate_multi` places each group
+/// in its own header file.
+///
+/// # Validation
+///
+/// The `c` and `cpp` blocks are checked for obvious mistakes, such as unbalanced parentheses,
+/// missing semicolons, or a function declaration without a return type, and these cause a
+/// compile error.  The check is not a full C parser, and can be disabled for an item with
+/// `#[ffizz(no_validate)]`.
+///
+/// # Includes
+///
+/// Headers required by an item's declaration can be given with `#[ffizz(include="<stdint.h>")]`,
+/// which may be repeated.  The generated header contains a single, deduplicated block of these
+/// `#include`s, following any include guards and includes in the topmatter.
+///
+/// # Splitting
+///
+/// A docstring can be split into several header items, each sorted independently, with
+/// `<!-- ffizz:item name=".." order=.. -->` markers on their own lines.  The content before the
+/// first marker belongs to the item itself.  Each marker begins a new item with the given name
+/// and order, with the order defaulting to that of the item.
+///
+/// ```text
+/// /// A color, as RGB.
+/// /// ```c
+/// /// typedef uint32_t color_t;
+/// /// ```
+/// /// <!-- ffizz:item name="COLOR_RED" order=150 -->
+/// /// ```c
+/// /// #define COLOR_RED 0xff0000
+/// /// ```
+/// ```
+///
+/// # Features
+///
+/// Content for items that only exist when a Cargo feature is enabled can be marked with
+/// `#[ffizz(feature="sync")]`.  Such content is only included in the header when the feature is
+/// enabled for the crate containing the item, unless the header is generated with feature guards
+/// (see `ffizz_header::HeaderConfig::feature_guards`).
+///
+/// # Stability
+///
+/// `#[ffizz(since="1.2")]` records the version in which an item was introduced, and
+/// `#[ffizz(unstable)]` marks an item as not covered by the library's API stability guarantees.
+/// Unstable items can be annotated or omitted with `ffizz_header::HeaderConfig::unstable`, and
+/// both properties are available to release tooling through `ffizz_header::items`.
+///
+/// # Platforms
+///
+/// Items that only exist on some platforms can be marked with `#[ffizz(platform="unix")]`, where
+/// the platform is one of `unix`, `windows`, `linux`, or `macos`.  The declaration is wrapped in
+/// the corresponding preprocessor conditional, such as `#if defined(_WIN32)`, and the Rust item
+/// is given the matching `#[cfg(..)]` attribute, so that the two agree.
+///
+/// # C++
+///
+/// Content marked with `#[ffizz(cpp_helper)]` is only included in headers generated with the C++
+/// profile (see `ffizz_header::HeaderConfig`), and can contain C++ helpers such as RAII wrappers.
+/// A `#[deprecated]` attribute on the Rust item causes the declaration to be marked
+/// `[[deprecated]]` in C++ headers.
+///
+/// A block with the `cpp` type immediately following a `c` block replaces that block in C++
+/// headers, and is omitted from C headers.
+///
 /// # Example
 ///
 /// ```text
@@ -91,3 +186,463 @@ pub fn item(_attr: TokenStream, item: TokenStream) -> TokenStream {
     docitem.to_tokens(&mut tokens);
     tokens.into()
 }
+
+/// Generate a C declaration for a type that is passed to C by pointer or by value.
+///
+/// The header content contains the type's docstring, followed by a declaration derived from the
+/// type's definition, so that the two cannot diverge.  The C type is named after the Rust type,
+/// or as given by `#[ffizz(name="..")]`, and the other `#[ffizz(..)]` properties are the same as
+/// for `#[ffizz_header::item]`.
+///
+/// A `#[repr(C)]` struct with a single field, an array of `usize` or of a fixed-size integer type,
+/// is declared as a struct with a "reserved" field of the same size, as is usual for values
+/// allocated by C and passed to `ffizz_passby::Unboxed`.  The declaration is followed by static
+/// assertions that the C compiler gives the struct the expected size and alignment.  Any other
+/// type is declared as an opaque struct, suitable for `ffizz_passby::Boxed`.
+///
+/// # Example
+///
+/// ```text
+/// # ignored because ffizz_header isn't available in doctests
+/// /// A string, allocated by the caller.
+/// #[derive(ffizz_header::Opaque)]
+/// #[repr(C)]
+/// pub struct fz_string_t {
+///     __reserved: [usize; 4],
+/// }
+/// ```
+///
+/// produces
+///
+/// ```text
+/// // A string, allocated by the caller.
+/// typedef struct fz_string_t {
+///     size_t __reserved[4];
+/// } fz_string_t;
+/// _Static_assert(sizeof(fz_string_t) == 4 * sizeof(size_t), "fz_string_t must match its Rust definition");
+/// _Static_assert(_Alignof(fz_string_t) == _Alignof(size_t), "fz_string_t must match its Rust definition");
+/// ```
+#[proc_macro_derive(Opaque, attributes(ffizz))]
+pub fn opaque(item: TokenStream) -> TokenStream {
+    let op = syn::parse_macro_input!(item as opaque::Opaque);
+    let mut tokens = TokenStream2::new();
+    op.to_tokens(&mut tokens);
+    tokens.into()
+}
+
+/// Generate C constants and conversions for a fieldless enum, for use with `ffizz_passby::Value`.
+///
+/// Each variant has a `u32` constant, named with a prefix followed by the variant name in
+/// SCREAMING_SNAKE_CASE.  The value of the constant is the variant's discriminant, which must be
+/// an integer literal if given.  The macro also generates a fallible `try_from_c(u32)`, returning
+/// `ffizz_passby::InvalidEnumValue` for values that do not correspond to a variant, and `From`
+/// conversions between the enum and `u32`.  Converting an invalid value with `From` panics.
+///
+/// The macro accepts the following properties in a `#[cenum(..)]` attribute:
+///
+/// * `ctype="name_t"` defines a type alias `name_t` for `u32`, to name the type in C.
+/// * `prefix="NAME_"` sets the prefix for the constants.  It defaults to the C type name, without
+///   a trailing `_t`, or otherwise the enum name, in SCREAMING_SNAKE_CASE.
+/// * `header` includes a declaration of the C type and constants in the generated header,
+///   following the enum's docstring.  This requires `ctype`, and `ffizz_header` must be a
+///   dependency of the crate.  The `#[ffizz(..)]` properties are the same as for
+///   `#[ffizz_header::item]`.
+///
+/// # Example
+///
+/// ```text
+/// # ignored because ffizz_passby isn't available in doctests
+/// /// The status of a system.
+/// #[derive(ffizz_passby::CEnum)]
+/// #[cenum(ctype="hittr_status_t", header)]
+/// pub enum Status {
+///     Ready = 1,
+///     Failed,
+/// }
+/// ```
+///
+/// produces
+///
+/// ```text
+/// // The status of a system.
+/// typedef uint32_t hittr_status_t;
+/// enum {
+///     HITTR_STATUS_READY = 1,
+///     HITTR_STATUS_FAILED = 2,
+/// };
+/// ```
+#[proc_macro_derive(CEnum, attributes(cenum, ffizz))]
+pub fn cenum(item: TokenStream) -> TokenStream {
+    let ce = syn::parse_macro_input!(item as cenum::CEnum);
+    let mut tokens = TokenStream2::new();
+    ce.to_tokens(&mut tokens);
+    tokens.into()
+}
+
+/// Implement `ffizz_passby::PassByPointer` for a type passed to C by pointer, typically a newtype
+/// wrapping a Rust value.
+///
+/// If `#[ffizz(ctype_name="name_t")]` is given, this also declares the opaque C type in the
+/// generated header, following the type's docstring.  The other `#[ffizz(..)]` properties are the
+/// same as for `#[ffizz_header::item]`, and `ffizz_header` must be a dependency of the crate.
+///
+/// # Example
+///
+/// ```text
+/// # ignored because ffizz_passby isn't available in doctests
+/// /// A key-value store.
+/// #[derive(ffizz_passby::PassByPointer)]
+/// #[ffizz(ctype_name="kvstore_t", order=100)]
+/// pub struct kvstore_t(pub Store);
+/// ```
+///
+/// produces a header containing
+///
+/// ```text
+/// // A key-value store.
+/// typedef struct kvstore_t kvstore_t;
+/// ```
+#[proc_macro_derive(PassByPointer, attributes(ffizz))]
+pub fn pass_by_pointer(item: TokenStream) -> TokenStream {
+    let pbp = syn::parse_macro_input!(item as passbypointer::PassByPointer);
+    let mut tokens = TokenStream2::new();
+    pbp.to_tokens(&mut tokens);
+    tokens.into()
+}
+
+/// Generate the standard C API for a type passed to C by pointer with `ffizz_passby::Boxed`.
+///
+/// Given a Rust type and a prefix for C names, this generates a type alias `prefix_t` and the
+/// functions
+///
+/// * `prefix_new`, returning a new value created with `Default::default`;
+/// * `prefix_free`, freeing a value, and doing nothing if given NULL; and
+/// * `prefix_clone`, returning a copy of a value with `ffizz_passby::Boxed::clone_ptr`, if the
+///   `clone` flag is given.
+///
+/// If the `const` flag is given, this also declares a read-only C type, `prefix_const_t`, defined
+/// as `const prefix_t`, with a matching Rust type alias.  Functions that do not modify the value
+/// should take a `*const prefix_const_t`, so that the header shows C programmers which functions
+/// mutate the value.  Use `Boxed::as_const` to return a read-only pointer to a value.
+///
+/// The functions are declared in the generated header, following the docstring given before the
+/// type, which documents the C type.  The `#[ffizz(..)]` properties are the same as for
+/// `#[ffizz_header::item]`.  Both `ffizz_passby` and `ffizz_header` must be dependencies of the
+/// crate.
+///
+/// # Example
+///
+/// ```text
+/// # ignored because ffizz_passby isn't available in doctests
+/// ffizz_passby::boxed_api! {
+///     /// A counter of hits.
+///     #[ffizz(order=100)]
+///     hittr::Counter, "hittr_counter", clone
+/// }
+/// ```
+///
+/// produces a header containing
+///
+/// ```text
+/// // A counter of hits.
+/// typedef struct hittr_counter_t hittr_counter_t;
+///
+/// // Create a new hittr_counter_t with its default value.  The value must later be freed with
+/// // hittr_counter_free.
+/// hittr_counter_t *hittr_counter_new(void);
+///
+/// // Free a hittr_counter_t.  If ptr is NULL, this does nothing.  The pointer must not be used
+/// // after this call.
+/// void hittr_counter_free(hittr_counter_t *ptr);
+///
+/// // Create a copy of a hittr_counter_t, returning NULL if ptr is NULL.  The copy must later be
+/// // freed with hittr_counter_free.
+/// hittr_counter_t *hittr_counter_clone(const hittr_counter_t *ptr);
+/// ```
+#[proc_macro]
+pub fn boxed_api(item: TokenStream) -> TokenStream {
+    let api = syn::parse_macro_input!(item as boxedapi::BoxedApi);
+    let mut tokens = TokenStream2::new();
+    api.to_tokens(&mut tokens);
+    tokens.into()
+}
+
+/// Generate a C type and the standard C API for a value allocated by C and passed with
+/// `ffizz_passby::Unboxed`.
+///
+/// Given a Rust type, a prefix for C names, and the type of a reserved field, such as
+/// `[u64; 4]`, this generates a `#[repr(C)]` struct `prefix_t` containing only the reserved
+/// field, a compile-time check that the Rust type fits in it, and the functions
+///
+/// * `prefix_init`, initializing a value with `Default::default`; and
+/// * `prefix_free`, freeing a value and zeroing its memory.
+///
+/// The struct and functions are declared in the generated header, as for
+/// `#[derive(ffizz_header::Opaque)]`, following the docstring given before the type.  The
+/// `#[ffizz(..)]` properties are the same as for `#[ffizz_header::item]`.  Both `ffizz_passby`
+/// and `ffizz_header` must be dependencies of the crate.
+///
+/// # Example
+///
+/// ```text
+/// # ignored because ffizz_passby isn't available in doctests
+/// ffizz_passby::unboxed_api! {
+///     /// A buffer of bytes.
+///     ByteBuffer, "byte_buffer", [u64; 4]
+/// }
+/// ```
+///
+/// produces a header containing
+///
+/// ```text
+/// // A buffer of bytes.
+/// typedef struct byte_buffer_t {
+///     uint64_t __reserved[4];
+/// } byte_buffer_t;
+/// _Static_assert(sizeof(byte_buffer_t) == 4 * sizeof(uint64_t), "byte_buffer_t must match its Rust definition");
+/// _Static_assert(_Alignof(byte_buffer_t) == _Alignof(uint64_t), "byte_buffer_t must match its Rust definition");
+///
+/// // Initialize a byte_buffer_t with its default value.  The value must later be freed with
+/// // byte_buffer_free.
+/// void byte_buffer_init(byte_buffer_t *ptr);
+///
+/// // Free a byte_buffer_t, which must not be NULL.  The value is zeroed, and must not be used
+/// // after this call unless it is initialized again.
+/// void byte_buffer_free(byte_buffer_t *ptr);
+/// ```
+#[proc_macro]
+pub fn unboxed_api(item: TokenStream) -> TokenStream {
+    let api = syn::parse_macro_input!(item as unboxedapi::UnboxedApi);
+    let mut tokens = TokenStream2::new();
+    api.to_tokens(&mut tokens);
+    tokens.into()
+}
+
+/// Generate a C vtable for a trait, so that values of any type implementing the trait can be
+/// passed to C, such as to a plugin, along with the functions to call its methods.
+///
+/// The vtable is a `#[repr(C)]` struct containing a function pointer for each method, followed by
+/// a `free` function pointer, each taking the object as its first argument.  It is named after
+/// the trait, such as `shape_vtable_t` for `Shape`, or as given by `#[ffizz(name="..")]`.  The
+/// vtable for a type `T` is created with the struct's `new::<T>()` method, which is a `const fn`
+/// so that the result can be stored in a `static`, and its entries call the corresponding methods
+/// of a value passed with `ffizz_passby::Boxed<T>`.
+///
+/// Each method must take `&self` or `&mut self`, and its other arguments and return value must be
+/// primitive types, C types named like `foo_t`, or raw pointers to these.  The struct is declared
+/// in the generated header, following the trait's docstring, and the other `#[ffizz(..)]`
+/// properties are the same as for `#[ffizz_header::item]`.  Both `ffizz_passby` and
+/// `ffizz_header` must be dependencies of the crate.
+///
+/// # Example
+///
+/// ```text
+/// # ignored because ffizz_passby isn't available in doctests
+/// /// A shape, implemented by the library.
+/// #[ffizz_passby::vtable]
+/// #[ffizz(name="hittr_shape_vtable_t")]
+/// pub trait Shape {
+///     fn area(&self) -> f64;
+///     fn scale(&mut self, factor: f64);
+/// }
+/// ```
+///
+/// produces a header containing
+///
+/// ```text
+/// // A shape, implemented by the library.
+/// typedef struct hittr_shape_vtable_t {
+///     double (*area)(const void *obj);
+///     void (*scale)(void *obj, double factor);
+///     void (*free)(void *obj);
+/// } hittr_shape_vtable_t;
+/// ```
+#[proc_macro_attribute]
+pub fn vtable(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    let vt = syn::parse_macro_input!(item as vtable::Vtable);
+    let mut tokens = TokenStream2::new();
+    vt.to_tokens(&mut tokens);
+    tokens.into()
+}
+
+/// Generate `extern "C"` functions calling the methods in an impl block, for a type passed to C
+/// by pointer with `ffizz_passby::Boxed`.
+///
+/// Each `pub` method becomes a function named with the given prefix followed by the method name,
+/// such as `hittr_counter_incr` for `incr`.  The receiver becomes a pointer to the C type
+/// `prefix_t` named `ptr`, which is borrowed for `&self` or `&mut self` and taken for `self`, and a
+/// method returning `Self` returns a new pointer.  Other arguments and return values must be
+/// primitive types, C types named like `foo_t`, or raw pointers to these, unless an argument is
+/// annotated:
+///
+/// * `#[ffizz(boxed = "foo_t")]` passes a `Foo`, `&Foo`, or `&mut Foo` as a pointer to `foo_t`,
+///   with `ffizz_passby::Boxed`.
+/// * `#[ffizz(value = "foo_t")]` passes a `Foo` as a `foo_t`, with `ffizz_passby::Value`.
+/// * `#[ffizz(string)]` passes a `String` or `&str` as a pointer to `fz_string_t`, with
+///   `ffizz_string::FzString`, which must then be a dependency of the crate.  A `String` is taken
+///   from the pointer, and a `&str` is copied from it.
+///
+/// Pointer arguments must not be NULL, and strings must be valid UTF-8, or the function panics.
+/// Since a C caller can easily pass an invalid string, methods with `#[ffizz(string)]` arguments
+/// must be marked `#[ffizz_catch(..)]`.  Pointers to the same value may not be passed for two
+/// arguments, including the receiver, if either is borrowed mutably; this also panics.
+///
+/// A method marked `#[ffizz_catch(value)]` returns the given value if it panics, recording the
+/// panic message with `ffizz_passby::LastError`, as for `ffizz_passby::ffizz_catch!`.  For methods
+/// returning nothing, the value is omitted: `#[ffizz_catch]`.
+///
+/// Each function is declared in the generated header, following the method's docstring, and the
+/// method's `#[ffizz(..)]` properties are the same as for `#[ffizz_header::item]`.  Methods
+/// marked `#[ffizz(skip)]` are not exported.  The C type itself is not declared, so this is
+/// typically combined with `ffizz_passby::boxed_api!`.  Both `ffizz_passby` and `ffizz_header`
+/// must be dependencies of the crate.
+///
+/// # Example
+///
+/// ```text
+/// # ignored because ffizz_passby isn't available in doctests
+/// #[ffizz_passby::ffizz_export(prefix = "hittr_counter")]
+/// impl Counter {
+///     /// Create a new counter with the given maximum.
+///     pub fn with_max(max: u64) -> Self { .. }
+///
+///     /// Increment the counter, returning false if it is at its maximum.
+///     pub fn incr(&mut self, by: u64) -> bool { .. }
+///
+///     /// Set the counter's name.
+///     #[ffizz_catch]
+///     pub fn set_name(&mut self, #[ffizz(string)] name: &str) { .. }
+/// }
+/// ```
+///
+/// produces a header containing
+///
+/// ```text
+/// // Create a new counter with the given maximum.
+/// hittr_counter_t *hittr_counter_with_max(uint64_t max);
+///
+/// // Increment the counter, returning false if it is at its maximum.
+/// bool hittr_counter_incr(hittr_counter_t *ptr, uint64_t by);
+///
+/// // Set the counter's name.
+/// void hittr_counter_set_name(hittr_counter_t *ptr, fz_string_t *name);
+/// ```
+#[proc_macro_attribute]
+pub fn ffizz_export(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let args = syn::parse_macro_input!(attr as export::ExportArgs);
+    let item = syn::parse_macro_input!(item as syn::ItemImpl);
+    let mut tokens = TokenStream2::new();
+    match export::Export::new(args, item) {
+        Ok(export) => export.to_tokens(&mut tokens),
+        Err(e) => return e.to_compile_error().into(),
+    }
+    tokens.into()
+}
+
+/// Define an `extern "C"` function, with its declaration in the generated header, from a Rust
+/// function.
+///
+/// The Rust function is written with Rust argument types, and the `extern "C"` function of the
+/// same name converts each argument from C before calling it.  The arguments are the same as for
+/// the methods of `#[ffizz_passby::ffizz_export]`: primitive types, C types named like `foo_t`,
+/// raw pointers, or arguments annotated with `#[ffizz(boxed = "foo_t")]`, `#[ffizz(value =
+/// "foo_t")]`, or `#[ffizz(string)]`, with the same restrictions.  Pointer arguments must not be
+/// NULL, and the function is `unsafe` if it has any, with a `# Safety` section added to its
+/// docstring.
+///
+/// With `#[ffizz_catch(value)]`, or `#[ffizz_catch]` for a function returning nothing, a panic
+/// returns the given value instead of aborting, recording the panic message with
+/// `ffizz_passby::LastError`.
+///
+/// The function is declared in the generated header, following its docstring, so the docstring
+/// should not contain a C declaration.  The `#[ffizz(..)]` properties are the same as for
+/// `#[ffizz_header::item]`.  Both `ffizz_passby` and `ffizz_header` must be dependencies of the
+/// crate.
+///
+/// # Example
+///
+/// ```text
+/// # ignored because ffizz_passby isn't available in doctests
+/// ffizz_passby::ffizz_fn! {
+///     /// Count the hits in a counter with the given label, returning -1 on error.
+///     #[ffizz_catch(-1)]
+///     pub fn hittr_count(
+///         #[ffizz(boxed = "hittr_counter_t")] counter: &Counter,
+///         #[ffizz(string)] label: &str,
+///     ) -> i64 {
+///         counter.count(label)
+///     }
+/// }
+/// ```
+///
+/// produces a header containing
+///
+/// ```text
+/// // Count the hits in a counter with the given label, returning -1 on error.
+/// int64_t hittr_count(const hittr_counter_t *counter, fz_string_t *label);
+/// ```
+#[proc_macro]
+pub fn ffizz_fn(item: TokenStream) -> TokenStream {
+    let f = syn::parse_macro_input!(item as ffizzfn::FfizzFn);
+    let mut tokens = TokenStream2::new();
+    f.to_tokens(&mut tokens);
+    tokens.into()
+}
+
+/// Define a `main` function that writes the generated header for a library crate.
+///
+/// This is intended for use in a small binary in the library's package, such as
+/// `src/bin/ffizz-dump.rs`.  By default, the library crate is the one named after the package
+/// containing the binary.  A different crate can be named as an argument.
+///
+/// The resulting binary writes the header to the file named by its first command-line argument,
+/// or to stdout if no argument is given.
+///
+/// # Example
+///
+/// ```text
+/// # ignored because ffizz_header isn't available in doctests
+/// ffizz_header::emit_main!();
+/// ```
+///
+/// or
+///
+/// ```text
+/// ffizz_header::emit_main!(mysupercool_lib);
+/// ```
+#[proc_macro]
+pub fn emit_main(item: TokenStream) -> TokenStream {
+    let em = syn::parse_macro_input!(item as emitmain::EmitMain);
+    let mut tokens = TokenStream2::new();
+    em.to_tokens(&mut tokens);
+    tokens.into()
+}
+
+/// Write the generated header for the current crate during its compilation.
+///
+/// The header is written to the given path, relative to `OUT_DIR`.  Cargo only sets `OUT_DIR`
+/// for packages with a build script, so the library must have one, even if it is empty.
+///
+/// This macro can only see header items expanded earlier in the same crate, so it should be
+/// invoked at the end of the crate's `lib.rs`, after all modules.  Items from other crates, such
+/// as dependencies, are not included.  Feature-gated items are included regardless of whether
+/// the feature is enabled, as the enabled features are not visible to the macro.
+///
+/// # Example
+///
+/// ```text
+/// # ignored because ffizz_header isn't available in doctests
+/// mod foo;
+/// mod bar;
+///
+/// ffizz_header::emit_at_build!("mylib.h");
+/// ```
+#[proc_macro]
+pub fn emit_at_build(item: TokenStream) -> TokenStream {
+    let eab = syn::parse_macro_input!(item as emitbuild::EmitAtBuild);
+    let mut tokens = TokenStream2::new();
+    if let Err(e) = eab.to_tokens(&mut tokens) {
+        return e.to_compile_error().into();
+    }
+    tokens.into()
+}
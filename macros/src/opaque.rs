@@ -0,0 +1,249 @@
+use crate::headeritem::HeaderItem;
+use proc_macro2::TokenStream as TokenStream2;
+use syn::parse::{Error, Parse, ParseStream, Result};
+
+/// Opaque is the result of parsing a type deriving `Opaque`, with a header_item containing the
+/// type's docstring followed by a C declaration derived from the type's definition.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Opaque {
+    pub(crate) header_item: HeaderItem,
+}
+
+/// A reserved field, `name: [ty; len]`, in a type that C code allocates.
+struct Reserved {
+    name: String,
+    ty: &'static str,
+    include: &'static str,
+    len: usize,
+}
+
+impl Parse for Opaque {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let input = input.parse::<syn::DeriveInput>()?;
+        // derive macros cannot remove attributes, so parse a copy
+        let mut attrs = input.attrs.clone();
+        let mut header_item = HeaderItem::from_attrs(input.ident.to_string(), &mut attrs)?;
+        let name = header_item.name.clone();
+
+        let (decl, cpp_decl) = match reserved_field(&input)? {
+            None => {
+                let decl = format!("typedef struct {name} {name};");
+                (decl.clone(), decl)
+            }
+            Some(reserved) => {
+                if !has_repr_c(&input.attrs) {
+                    return Err(Error::new_spanned(
+                        &input.ident,
+                        "Opaque types with a reserved field must be #[repr(C)]",
+                    ));
+                }
+                header_item.includes.push(reserved.include.into());
+                let Reserved {
+                    name: field,
+                    ty,
+                    len,
+                    ..
+                } = reserved;
+                let def = format!("typedef struct {name} {{\n    {ty} {field}[{len}];\n}} {name};");
+                let message = format!("\"{name} must match its Rust definition\"");
+                let decl = format!(
+                    "{def}\n\
+                    _Static_assert(sizeof({name}) == {len} * sizeof({ty}), {message});\n\
+                    _Static_assert(_Alignof({name}) == _Alignof({ty}), {message});"
+                );
+                let cpp_decl = format!(
+                    "{def}\n\
+                    static_assert(sizeof({name}) == {len} * sizeof({ty}), {message});\n\
+                    static_assert(alignof({name}) == alignof({ty}), {message});"
+                );
+                (decl, cpp_decl)
+            }
+        };
+
+        let append = |content: &str, decl: &str| {
+            if content.is_empty() {
+                decl.to_string()
+            } else {
+                format!("{content}\n{decl}")
+            }
+        };
+        let cpp_content = header_item
+            .cpp_content
+            .as_deref()
+            .unwrap_or(&header_item.content);
+        header_item.cpp_content = (decl != cpp_decl).then(|| append(cpp_content, &cpp_decl));
+        header_item.content = append(&header_item.content, &decl);
+        Ok(Opaque { header_item })
+    }
+}
+
+impl Opaque {
+    /// Convert this Opaque into a TokenStream that will include it in the built binary.
+    pub(crate) fn to_tokens(&self, tokens: &mut TokenStream2) {
+        self.header_item.to_tokens(tokens);
+    }
+}
+
+/// Get the reserved field of a struct with a single field of array type, or None for any other
+/// type.
+fn reserved_field(input: &syn::DeriveInput) -> Result<Option<Reserved>> {
+    let syn::Data::Struct(data) = &input.data else {
+        return Ok(None);
+    };
+    let mut fields = data.fields.iter();
+    let (Some(field), None) = (fields.next(), fields.next()) else {
+        return Ok(None);
+    };
+    let syn::Type::Array(array) = &field.ty else {
+        return Ok(None);
+    };
+
+    let syn::Type::Path(elt) = array.elem.as_ref() else {
+        return Err(Error::new_spanned(
+            &array.elem,
+            "unsupported reserved field type",
+        ));
+    };
+    let elt = elt.path.segments.last().map(|s| s.ident.to_string());
+    let (ty, include) = match elt.as_deref() {
+        Some("usize" | "size_t") => ("size_t", "<stddef.h>"),
+        Some("u8") => ("uint8_t", "<stdint.h>"),
+        Some("u16") => ("uint16_t", "<stdint.h>"),
+        Some("u32") => ("uint32_t", "<stdint.h>"),
+        Some("u64") => ("uint64_t", "<stdint.h>"),
+        Some("i8") => ("int8_t", "<stdint.h>"),
+        Some("i16") => ("int16_t", "<stdint.h>"),
+        Some("i32") => ("int32_t", "<stdint.h>"),
+        Some("i64") => ("int64_t", "<stdint.h>"),
+        _ => {
+            return Err(Error::new_spanned(
+                &array.elem,
+                "reserved fields must be arrays of usize or a fixed-size integer type",
+            ))
+        }
+    };
+
+    let len = match &array.len {
+        syn::Expr::Lit(syn::ExprLit {
+            lit: syn::Lit::Int(i),
+            ..
+        }) => i.base10_parse::<usize>()?,
+        len => {
+            return Err(Error::new_spanned(
+                len,
+                "the length of a reserved field must be an integer literal",
+            ))
+        }
+    };
+
+    let name = match &field.ident {
+        Some(ident) => ident.to_string(),
+        None => "__reserved".into(),
+    };
+    Ok(Some(Reserved {
+        name,
+        ty,
+        include,
+        len,
+    }))
+}
+
+/// Determine whether the attributes include `#[repr(C)]`.
+fn has_repr_c(attrs: &[syn::Attribute]) -> bool {
+    attrs.iter().any(|attr| match attr.parse_meta() {
+        Ok(syn::Meta::List(list)) if list.path.is_ident("repr") => list.nested.iter().any(
+            |elt| matches!(elt, syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("C")),
+        ),
+        _ => false,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_opaque_typedef() {
+        let op: Opaque = syn::parse_quote! {
+            /// A foo.
+            #[ffizz(name="foo_t")]
+            pub struct Foo {
+                x: u32,
+                y: String,
+            }
+        };
+        assert_eq!(
+            op.header_item,
+            HeaderItem {
+                order: 100,
+                name: "foo_t".into(),
+                content: "// A foo.\ntypedef struct foo_t foo_t;".into(),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_opaque_reserved() {
+        let op: Opaque = syn::parse_quote! {
+            /// A string.
+            #[repr(C)]
+            pub struct fz_string_t {
+                __reserved: [usize; 4],
+            }
+        };
+        assert_eq!(
+            op.header_item,
+            HeaderItem {
+                order: 100,
+                name: "fz_string_t".into(),
+                content: "// A string.\n\
+                    typedef struct fz_string_t {\n    size_t __reserved[4];\n} fz_string_t;\n\
+                    _Static_assert(sizeof(fz_string_t) == 4 * sizeof(size_t), \"fz_string_t must match its Rust definition\");\n\
+                    _Static_assert(_Alignof(fz_string_t) == _Alignof(size_t), \"fz_string_t must match its Rust definition\");"
+                    .into(),
+                cpp_content: Some(
+                    "// A string.\n\
+                    typedef struct fz_string_t {\n    size_t __reserved[4];\n} fz_string_t;\n\
+                    static_assert(sizeof(fz_string_t) == 4 * sizeof(size_t), \"fz_string_t must match its Rust definition\");\n\
+                    static_assert(alignof(fz_string_t) == alignof(size_t), \"fz_string_t must match its Rust definition\");"
+                        .into()
+                ),
+                includes: vec!["<stddef.h>".into()],
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_opaque_tuple_reserved() {
+        let op: Opaque = syn::parse_quote! {
+            #[repr(C)]
+            pub struct uuid_t([u8; 16]);
+        };
+        assert!(op
+            .header_item
+            .content
+            .starts_with("typedef struct uuid_t {\n    uint8_t __reserved[16];\n} uuid_t;"));
+        assert_eq!(op.header_item.includes, vec![String::from("<stdint.h>")]);
+    }
+
+    #[test]
+    fn test_opaque_reserved_requires_repr_c() {
+        let res: Result<Opaque> = syn::parse2(quote::quote! {
+            pub struct fz_string_t {
+                __reserved: [usize; 4],
+            }
+        });
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_opaque_reserved_bad_type() {
+        let res: Result<Opaque> = syn::parse2(quote::quote! {
+            #[repr(C)]
+            pub struct foo_t([f64; 4]);
+        });
+        assert!(res.is_err());
+    }
+}
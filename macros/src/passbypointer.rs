@@ -0,0 +1,175 @@
+use crate::headeritem::HeaderItem;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::parse::{Error, Parse, ParseStream, Result};
+use syn::punctuated::Punctuated;
+
+/// PassByPointer is the result of parsing a type deriving `PassByPointer`, with a header_item
+/// declaring the opaque C type if `ctype_name` was given.
+#[derive(Debug)]
+pub(crate) struct PassByPointer {
+    ident: syn::Ident,
+    generics: syn::Generics,
+    header_item: Option<HeaderItem>,
+}
+
+impl Parse for PassByPointer {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let input = input.parse::<syn::DeriveInput>()?;
+
+        // derive macros cannot remove attributes, so parse a copy
+        let mut attrs = input.attrs.clone();
+        let header_item = match take_ctype_name(&mut attrs)? {
+            Some(ctype_name) => {
+                let mut header_item = HeaderItem::from_attrs(ctype_name, &mut attrs)?;
+                let name = &header_item.name;
+                let decl = format!("typedef struct {name} {name};");
+                let append = |content: &str| {
+                    if content.is_empty() {
+                        decl.clone()
+                    } else {
+                        format!("{content}\n{decl}")
+                    }
+                };
+                header_item.cpp_content = header_item.cpp_content.as_deref().map(append);
+                header_item.content = append(&header_item.content);
+                Some(header_item)
+            }
+            None => {
+                if let Some(attr) = attrs.iter().find(|attr| attr.path.is_ident("ffizz")) {
+                    return Err(Error::new_spanned(
+                        attr,
+                        "#[ffizz(..)] properties on a PassByPointer type require ctype_name=\"..\"",
+                    ));
+                }
+                None
+            }
+        };
+
+        Ok(PassByPointer {
+            ident: input.ident,
+            generics: input.generics,
+            header_item,
+        })
+    }
+}
+
+impl PassByPointer {
+    /// Convert this PassByPointer into a TokenStream containing the trait implementation and any
+    /// header item.
+    pub(crate) fn to_tokens(&self, tokens: &mut TokenStream2) {
+        let ident = &self.ident;
+        let (impl_generics, ty_generics, where_clause) = self.generics.split_for_impl();
+        tokens.extend(quote! {
+            impl #impl_generics ::ffizz_passby::PassByPointer for #ident #ty_generics #where_clause {}
+        });
+
+        if let Some(header_item) = &self.header_item {
+            header_item.to_tokens(tokens);
+        }
+    }
+}
+
+/// Remove the `ctype_name` property from the `#[ffizz(..)]` attributes, returning its value.  The
+/// remaining properties are left for `HeaderItem::from_attrs`.
+fn take_ctype_name(attrs: &mut Vec<syn::Attribute>) -> Result<Option<String>> {
+    let mut ctype_name = None;
+    let mut kept_attrs = vec![];
+    for attr in attrs.drain(..) {
+        let Ok(syn::Meta::List(mut metalist)) = attr.parse_meta() else {
+            kept_attrs.push(attr);
+            continue;
+        };
+        if !metalist.path.is_ident("ffizz") {
+            kept_attrs.push(attr);
+            continue;
+        }
+        let mut rest = Punctuated::<syn::NestedMeta, syn::Token![,]>::new();
+        for elt in metalist.nested {
+            match elt {
+                syn::NestedMeta::Meta(syn::Meta::NameValue(nv))
+                    if nv.path.is_ident("ctype_name") =>
+                {
+                    let syn::Lit::Str(s) = &nv.lit else {
+                        return Err(Error::new_spanned(
+                            &nv.lit,
+                            "ctype_name must be a string, such as ctype_name=\"..\"",
+                        ));
+                    };
+                    ctype_name = Some(s.value());
+                }
+                elt => rest.push(elt),
+            }
+        }
+        if !rest.is_empty() {
+            metalist.nested = rest;
+            kept_attrs.push(syn::parse_quote!(#[#metalist]));
+        }
+    }
+    *attrs = kept_attrs;
+    Ok(ctype_name)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_passbypointer_no_header() {
+        let pbp: PassByPointer = syn::parse_quote! {
+            pub struct kvstore_t(pub Store);
+        };
+        assert_eq!(pbp.ident, "kvstore_t");
+        assert!(pbp.header_item.is_none());
+
+        let mut tokens = TokenStream2::new();
+        pbp.to_tokens(&mut tokens);
+        assert_eq!(
+            tokens.to_string(),
+            quote!(impl ::ffizz_passby::PassByPointer for kvstore_t {}).to_string()
+        );
+    }
+
+    #[test]
+    fn test_passbypointer_ctype_name() {
+        let pbp: PassByPointer = syn::parse_quote! {
+            /// A key-value store.
+            #[ffizz(ctype_name="kvstore_t", order=100)]
+            pub struct KvStore(pub Store);
+        };
+        assert_eq!(
+            pbp.header_item,
+            Some(HeaderItem {
+                order: 100,
+                name: "kvstore_t".into(),
+                content: "// A key-value store.\ntypedef struct kvstore_t kvstore_t;".into(),
+                ..Default::default()
+            })
+        );
+    }
+
+    #[test]
+    fn test_passbypointer_generics() {
+        let pbp: PassByPointer = syn::parse_quote! {
+            pub struct Wrapper<T: Send>(T);
+        };
+        let mut tokens = TokenStream2::new();
+        pbp.to_tokens(&mut tokens);
+        assert_eq!(
+            tokens.to_string(),
+            quote!(
+                impl<T: Send> ::ffizz_passby::PassByPointer for Wrapper<T> {}
+            )
+            .to_string()
+        );
+    }
+
+    #[test]
+    fn test_passbypointer_requires_ctype_name() {
+        let res: Result<PassByPointer> = syn::parse2(quote! {
+            #[ffizz(order=100)]
+            pub struct kvstore_t(pub Store);
+        });
+        assert!(res.is_err());
+    }
+}
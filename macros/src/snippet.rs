@@ -46,6 +46,7 @@ mod test {
                 order: 100,
                 name: "intro".into(),
                 content: "// A docstring".into(),
+                ..Default::default()
             }
         );
     }
@@ -0,0 +1,174 @@
+use crate::headeritem::HeaderItem;
+use crate::opaque::Opaque;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
+use syn::parse::{Parse, ParseStream, Result};
+
+/// UnboxedApi is the result of parsing the input to `unboxed_api!`: a Rust type, the prefix for
+/// its C names, and the reserved field of the C type.
+#[derive(Debug)]
+pub(crate) struct UnboxedApi {
+    attrs: Vec<syn::Attribute>,
+    rtype: syn::Type,
+    prefix: String,
+    reserved: syn::TypeArray,
+    header_item: HeaderItem,
+}
+
+impl Parse for UnboxedApi {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let mut attrs = input.call(syn::Attribute::parse_outer)?;
+        let rtype: syn::Type = input.parse()?;
+        input.parse::<syn::Token![,]>()?;
+        let prefix = input.parse::<syn::LitStr>()?.value();
+        input.parse::<syn::Token![,]>()?;
+        let reserved: syn::TypeArray = input.parse()?;
+        input.parse::<Option<syn::Token![,]>>()?;
+        if !input.is_empty() {
+            return Err(input.error("expected `TypeName, \"prefix\", [type; len]`"));
+        }
+
+        // the C type is declared just as `#[derive(Opaque)]` would declare it
+        let ctype = syn::Ident::new(&format!("{prefix}_t"), Span::call_site());
+        let Opaque { mut header_item } = syn::parse2(quote! {
+            #(#attrs)*
+            #[repr(C)]
+            pub struct #ctype {
+                __reserved: #reserved,
+            }
+        })?;
+        attrs.retain(|attr| !attr.path.is_ident("ffizz"));
+
+        let decl = format!(
+            "\n\
+            \n\
+            // Initialize a {ctype} with its default value.  The value must later be freed with\n\
+            // {prefix}_free.\n\
+            void {prefix}_init({ctype} *ptr);\n\
+            \n\
+            // Free a {ctype}, which must not be NULL.  The value is zeroed, and must not be used\n\
+            // after this call unless it is initialized again.\n\
+            void {prefix}_free({ctype} *ptr);"
+        );
+        header_item.content.push_str(&decl);
+        if let Some(cpp_content) = &mut header_item.cpp_content {
+            cpp_content.push_str(&decl);
+        }
+
+        Ok(UnboxedApi {
+            attrs,
+            rtype,
+            prefix,
+            reserved,
+            header_item,
+        })
+    }
+}
+
+impl UnboxedApi {
+    /// Convert this UnboxedApi into a TokenStream containing the C type, the functions, and the
+    /// header item.
+    pub(crate) fn to_tokens(&self, tokens: &mut TokenStream2) {
+        let UnboxedApi {
+            attrs,
+            rtype,
+            prefix,
+            reserved,
+            ..
+        } = self;
+        let ident =
+            |suffix: &str| syn::Ident::new(&format!("{prefix}_{suffix}"), Span::call_site());
+        let (ctype, init, free) = (ident("t"), ident("init"), ident("free"));
+        let init_doc = format!("Initialize a `{ctype}` with its default value.");
+        let free_doc = format!("Free a `{ctype}`, leaving it zeroed.");
+
+        tokens.extend(quote! {
+            #(#attrs)*
+            #[allow(non_camel_case_types)]
+            #[repr(C)]
+            pub struct #ctype {
+                __reserved: #reserved,
+            }
+
+            ::ffizz_passby::static_assert_layout!(#rtype, #ctype);
+
+            #[doc = #init_doc]
+            ///
+            /// # Safety
+            ///
+            /// * `ptr` must not be NULL, and must be aligned for and have enough space for the
+            ///   value.
+            /// * The value must later be freed.
+            #[no_mangle]
+            pub unsafe extern "C" fn #init(ptr: *mut #ctype) {
+                // SAFETY: see docstring
+                unsafe {
+                    ::ffizz_passby::Unboxed::<#rtype, #ctype>::to_out_param_nonnull(
+                        <#rtype as ::std::default::Default>::default(),
+                        ptr,
+                    )
+                }
+            }
+
+            #[doc = #free_doc]
+            ///
+            /// # Safety
+            ///
+            /// * `ptr` must not be NULL, and must point to a value initialized by a function in
+            ///   this API.
+            /// * The value must not be used after this call, unless it is initialized again.
+            #[no_mangle]
+            pub unsafe extern "C" fn #free(ptr: *mut #ctype) {
+                // SAFETY: see docstring
+                ::std::mem::drop(unsafe {
+                    ::ffizz_passby::Unboxed::<#rtype, #ctype>::take_ptr_nonnull(ptr)
+                });
+            }
+        });
+
+        self.header_item.to_tokens(tokens);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_unboxed_api() {
+        let api: UnboxedApi = syn::parse_quote! {
+            /// A buffer of bytes.
+            #[ffizz(order=100)]
+            ByteBuffer, "byte_buffer", [u64; 4]
+        };
+        assert_eq!(api.attrs.len(), 1);
+        assert_eq!(
+            api.header_item.content,
+            "// A buffer of bytes.\n\
+            typedef struct byte_buffer_t {\n    uint64_t __reserved[4];\n} byte_buffer_t;\n\
+            _Static_assert(sizeof(byte_buffer_t) == 4 * sizeof(uint64_t), \"byte_buffer_t must match its Rust definition\");\n\
+            _Static_assert(_Alignof(byte_buffer_t) == _Alignof(uint64_t), \"byte_buffer_t must match its Rust definition\");\n\
+            \n\
+            // Initialize a byte_buffer_t with its default value.  The value must later be freed with\n\
+            // byte_buffer_free.\n\
+            void byte_buffer_init(byte_buffer_t *ptr);\n\
+            \n\
+            // Free a byte_buffer_t, which must not be NULL.  The value is zeroed, and must not be used\n\
+            // after this call unless it is initialized again.\n\
+            void byte_buffer_free(byte_buffer_t *ptr);"
+        );
+        assert_eq!(api.header_item.includes, vec![String::from("<stdint.h>")]);
+        assert!(api
+            .header_item
+            .cpp_content
+            .unwrap()
+            .ends_with("\nvoid byte_buffer_free(byte_buffer_t *ptr);"));
+    }
+
+    #[test]
+    fn test_unboxed_api_bad_reserved() {
+        let res: Result<UnboxedApi> =
+            syn::parse2(quote! { ByteBuffer, "byte_buffer", [String; 4] });
+        assert!(res.is_err());
+    }
+}
@@ -0,0 +1,321 @@
+/// Check the ```c and ```cpp blocks in a docstring for obviously malformed declarations, returning
+/// a description of the first problem found.
+///
+/// This is not a C parser: it only catches mistakes such as unbalanced parentheses, missing
+/// semicolons, and function declarations without a return type.  Each block is checked on its own,
+/// so a block may open a brace (such as `extern "C" {`) that is closed in another item.
+pub(crate) fn validate_doc(doc: &[String]) -> Result<(), String> {
+    let mut block: Option<Vec<&str>> = None;
+    for line in doc {
+        match block.as_mut() {
+            Some(lines) => {
+                if line.trim() == "```" {
+                    validate_block(lines)?;
+                    block = None;
+                } else {
+                    lines.push(line);
+                }
+            }
+            None => {
+                if matches!(line.trim(), "```c" | "```cpp") {
+                    block = Some(vec![]);
+                }
+            }
+        }
+    }
+    if let Some(lines) = block {
+        validate_block(&lines)?;
+    }
+    Ok(())
+}
+
+/// A token in a declaration block, with the index of the line on which it appears.
+#[derive(Debug, PartialEq)]
+struct Token<'a> {
+    text: &'a str,
+    line: usize,
+}
+
+impl Token<'_> {
+    fn is_ident(&self) -> bool {
+        self.text
+            .starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+    }
+
+    /// Identifiers that may follow a function's parameter list: qualifiers, attributes, and
+    /// anything that looks like a macro.
+    fn is_qualifier(&self) -> bool {
+        matches!(
+            self.text,
+            "const" | "volatile" | "noexcept" | "override" | "final" | "throw" | "asm"
+        ) || self.text.starts_with("__")
+            || !self.text.contains(|c: char| c.is_ascii_lowercase())
+    }
+}
+
+/// The kind of an open delimiter.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Open {
+    Paren,
+    Bracket,
+    /// A brace containing declarations, such as `extern "C" {` or `namespace foo {`
+    Scope,
+    /// A brace containing a function body
+    Body,
+    /// Any other brace, such as a struct definition or initializer
+    Brace,
+}
+
+fn validate_block(lines: &[&str]) -> Result<(), String> {
+    let tokens = tokenize(lines);
+    let err = |tok: &Token, msg: &str| format!("{msg} in `{}`", lines[tok.line].trim());
+
+    let mut stack: Vec<Open> = vec![];
+    // the tokens of the current declaration, at declaration scope
+    let mut stmt: Vec<&Token> = vec![];
+    for (i, tok) in tokens.iter().enumerate() {
+        let at_scope = stack.iter().all(|o| *o == Open::Scope);
+        let prev = i.checked_sub(1).map(|i| &tokens[i]);
+        if at_scope {
+            if stmt.is_empty()
+                && tok.is_ident()
+                && !tok.is_qualifier()
+                && !matches!(tok.text, "static_assert" | "_Static_assert")
+                && tokens.get(i + 1).map(|t| t.text) == Some("(")
+                // `T (*name)(..)` declares a function pointer
+                && !matches!(tokens.get(i + 2).map(|t| t.text), Some("*" | "&" | "^"))
+            {
+                return Err(err(tok, "function declaration without a return type"));
+            }
+            if prev.map(|p| p.text) == Some(")") && tok.is_ident() && !tok.is_qualifier() {
+                return Err(err(prev.unwrap(), "expected `;` after declaration"));
+            }
+        }
+
+        match tok.text {
+            "(" => stack.push(Open::Paren),
+            "[" => stack.push(Open::Bracket),
+            "{" => {
+                let kind = if !at_scope {
+                    Open::Brace
+                } else if stmt.first().map(|t| t.text) == Some("namespace")
+                    || (stmt.len() == 2 && stmt[0].text == "extern")
+                {
+                    Open::Scope
+                } else if stmt.iter().any(|t| t.text == "=")
+                    || stmt
+                        .iter()
+                        .any(|t| matches!(t.text, "struct" | "union" | "enum" | "class"))
+                {
+                    Open::Brace
+                } else if prev.is_some_and(|p| p.text == ")" || p.is_qualifier()) {
+                    Open::Body
+                } else {
+                    Open::Brace
+                };
+                stack.push(kind);
+                if kind == Open::Scope {
+                    stmt.clear();
+                    continue;
+                }
+            }
+            ")" | "]" | "}" => {
+                let expected = match tok.text {
+                    ")" => Open::Paren,
+                    "]" => Open::Bracket,
+                    _ => Open::Brace,
+                };
+                match stack.pop() {
+                    Some(open) if open == expected => {}
+                    Some(Open::Scope | Open::Body | Open::Brace) if expected == Open::Brace => {}
+                    // closing a brace opened in another item
+                    None if expected == Open::Brace => {}
+                    _ => return Err(err(tok, &format!("unbalanced `{}`", tok.text))),
+                }
+                // a function body or a declaration scope ends the declaration
+                if tok.text == "}" && stack.iter().all(|o| *o == Open::Scope) {
+                    let ends_decl = match stmt.first() {
+                        None => true,
+                        Some(_) => !stmt
+                            .iter()
+                            .any(|t| matches!(t.text, "=" | "struct" | "union" | "enum" | "class")),
+                    };
+                    if ends_decl {
+                        stmt.clear();
+                        continue;
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        if stack.iter().all(|o| *o == Open::Scope) && tok.text == ";" {
+            stmt.clear();
+        } else if at_scope || !stmt.is_empty() {
+            stmt.push(tok);
+        }
+    }
+
+    if let Some(open) = stack
+        .iter()
+        .find(|o| matches!(o, Open::Paren | Open::Bracket))
+    {
+        let last = tokens.last().unwrap();
+        let delim = if *open == Open::Paren { "(" } else { "[" };
+        return Err(err(last, &format!("unclosed `{delim}`")));
+    }
+    if let Some(last) = stmt.last() {
+        if stack.iter().all(|o| *o == Open::Scope) {
+            return Err(err(last, "expected `;` after declaration"));
+        }
+    }
+    Ok(())
+}
+
+/// Split a block into tokens, omitting comments and preprocessor directives.  Identifiers, numbers,
+/// and string and character literals are single tokens, and each other character is a token.
+fn tokenize<'a>(lines: &[&'a str]) -> Vec<Token<'a>> {
+    let mut tokens = vec![];
+    let mut in_comment = false;
+    let mut in_directive = false;
+    for (line_idx, line) in lines.iter().enumerate() {
+        if !in_comment && (in_directive || line.trim_start().starts_with('#')) {
+            in_directive = line.trim_end().ends_with('\\');
+            continue;
+        }
+        let mut rest = *line;
+        while !rest.is_empty() {
+            if in_comment {
+                match rest.find("*/") {
+                    Some(end) => {
+                        rest = &rest[end + 2..];
+                        in_comment = false;
+                    }
+                    None => rest = "",
+                }
+                continue;
+            }
+            let c = rest.chars().next().unwrap();
+            if rest.starts_with("//") {
+                break;
+            } else if rest.starts_with("/*") {
+                in_comment = true;
+                rest = &rest[2..];
+            } else if c == '"' || c == '\'' {
+                // a literal is a single token, whatever its contents
+                let mut escaped = false;
+                let end = rest[1..]
+                    .find(|ch: char| {
+                        let found = !escaped && ch == c;
+                        escaped = !escaped && ch == '\\';
+                        found
+                    })
+                    .map(|e| e + 2)
+                    .unwrap_or(rest.len());
+                tokens.push(Token {
+                    text: &rest[..end],
+                    line: line_idx,
+                });
+                rest = &rest[end..];
+            } else if c.is_ascii_alphanumeric() || c == '_' {
+                let end = rest
+                    .find(|ch: char| !(ch.is_ascii_alphanumeric() || ch == '_'))
+                    .unwrap_or(rest.len());
+                tokens.push(Token {
+                    text: &rest[..end],
+                    line: line_idx,
+                });
+                rest = &rest[end..];
+            } else if c.is_whitespace() {
+                rest = &rest[c.len_utf8()..];
+            } else {
+                tokens.push(Token {
+                    text: &rest[..c.len_utf8()],
+                    line: line_idx,
+                });
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn check(block: &str) -> Result<(), String> {
+        let doc: Vec<String> = std::iter::once("```c")
+            .chain(block.lines())
+            .chain(std::iter::once("```"))
+            .map(String::from)
+            .collect();
+        validate_doc(&doc)
+    }
+
+    #[test]
+    fn valid() {
+        for block in [
+            "void foo(void);",
+            "typedef struct foo_t foo_t;",
+            "typedef struct foo_t {\n  int x; // comment (\n} foo_t;",
+            "enum color { RED = 1, GREEN };",
+            "#ifndef FOO_H\n#define FOO_H\n#define FOO(x) \\\n  (x\n#include <stdint.h>",
+            "#endif",
+            "#ifdef __cplusplus\nextern \"C\" {\n#endif",
+            "#ifdef __cplusplus\n}\n#endif",
+            "EXTERN_C int foo(const char *s) MYLIB_NONNULL;",
+            "int (*callback)(void *arg);",
+            "char name[16];",
+            "inline int add(int a, int b) { if (a) return a + b; return b; }",
+            "namespace mylib {\nclass Foo {\npublic:\n  Foo();\n  ~Foo();\n};\n} // namespace mylib",
+            "void foo(void) noexcept;",
+            "static_assert(sizeof(int) == 4, \"int size ( \");",
+            "/* multi\n  line ( comment */\nvoid foo(void);",
+        ] {
+            assert_eq!(check(block), Ok(()), "{block}");
+        }
+    }
+
+    #[test]
+    fn missing_return_type() {
+        assert_eq!(
+            check("fz_string_free(fz_string_t *);"),
+            Err(
+                "function declaration without a return type in `fz_string_free(fz_string_t *);`"
+                    .into()
+            )
+        );
+        assert!(check("extern \"C\" {\nfoo(int x);\n}").is_err());
+    }
+
+    #[test]
+    fn missing_semicolon() {
+        assert_eq!(
+            check("typedef struct infprec_t infprec_t"),
+            Err("expected `;` after declaration in `typedef struct infprec_t infprec_t`".into())
+        );
+        assert!(check("void foo(void)\nvoid bar(void);").is_err());
+        assert!(check("struct foo { int x; }").is_err());
+    }
+
+    #[test]
+    fn unbalanced() {
+        assert_eq!(
+            check("void foo(int x;"),
+            Err("unclosed `(` in `void foo(int x;`".into())
+        );
+        assert!(check("void foo(int x));").is_err());
+        assert!(check("int x[4;").is_err());
+        assert!(check("void foo(int x[4)];").is_err());
+    }
+
+    #[test]
+    fn cpp_blocks_checked() {
+        let doc: Vec<String> = ["```cpp", "void foo()", "```"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert!(validate_doc(&doc).is_err());
+    }
+}
@@ -0,0 +1,337 @@
+use crate::cenum::screaming_snake;
+use crate::ctype::{c_declarator, c_type};
+use crate::headeritem::HeaderItem;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
+use syn::parse::{Error, Parse, ParseStream, Result};
+
+/// Vtable is the result of parsing a trait annotated with `#[vtable]`.
+#[derive(Debug)]
+pub(crate) struct Vtable {
+    item: syn::ItemTrait,
+    methods: Vec<Method>,
+    header_item: HeaderItem,
+}
+
+/// Method is a method of a vtable trait, which becomes an entry in the vtable.
+#[derive(Debug)]
+struct Method {
+    ident: syn::Ident,
+    mutable: bool,
+    args: Vec<(syn::Ident, syn::Type)>,
+    output: syn::ReturnType,
+}
+
+impl Parse for Vtable {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let mut item: syn::ItemTrait = input.parse()?;
+        if !item.generics.params.is_empty() {
+            return Err(Error::new_spanned(
+                &item.generics,
+                "vtable traits cannot have generic parameters",
+            ));
+        }
+        let name = format!(
+            "{}_vtable_t",
+            screaming_snake(&item.ident.to_string()).to_lowercase()
+        );
+        let mut header_item = HeaderItem::from_attrs(name, &mut item.attrs)?;
+
+        let mut methods = vec![];
+        let mut entries = vec![];
+        for trait_item in &item.items {
+            let syn::TraitItem::Method(method) = trait_item else {
+                return Err(Error::new_spanned(
+                    trait_item,
+                    "vtable traits may only contain methods",
+                ));
+            };
+            let method = Method::parse(&method.sig)?;
+            entries.push(method.c_entry(&mut header_item.includes)?);
+            methods.push(method);
+        }
+        entries.push("    void (*free)(void *obj);".into());
+
+        let name = &header_item.name;
+        let decl = format!(
+            "typedef struct {name} {{\n{}\n}} {name};",
+            entries.join("\n")
+        );
+        if header_item.content.is_empty() {
+            header_item.content = decl;
+        } else {
+            header_item.content = format!("{}\n{decl}", header_item.content);
+        }
+
+        Ok(Vtable {
+            item,
+            methods,
+            header_item,
+        })
+    }
+}
+
+impl Method {
+    /// Parse a method signature, checking that it can be called through a vtable.
+    fn parse(sig: &syn::Signature) -> Result<Self> {
+        if !sig.generics.params.is_empty()
+            || sig.asyncness.is_some()
+            || sig.unsafety.is_some()
+            || sig.variadic.is_some()
+        {
+            return Err(Error::new_spanned(
+                sig,
+                "vtable methods cannot be generic, async, unsafe, or variadic",
+            ));
+        }
+        if sig.ident == "free" {
+            return Err(Error::new_spanned(
+                &sig.ident,
+                "`free` is reserved for the vtable's free entry",
+            ));
+        }
+
+        let mut inputs = sig.inputs.iter();
+        let mutable = match inputs.next() {
+            Some(syn::FnArg::Receiver(syn::Receiver {
+                reference: Some(_),
+                mutability,
+                ..
+            })) => mutability.is_some(),
+            _ => {
+                return Err(Error::new_spanned(
+                    sig,
+                    "vtable methods must take `&self` or `&mut self`",
+                ))
+            }
+        };
+        let mut args = vec![];
+        for input in inputs {
+            let syn::FnArg::Typed(pat_type) = input else {
+                unreachable!("receiver after the first argument");
+            };
+            let syn::Pat::Ident(pat) = pat_type.pat.as_ref() else {
+                return Err(Error::new_spanned(
+                    &pat_type.pat,
+                    "vtable method arguments must be simple names",
+                ));
+            };
+            if pat.ident == "obj" {
+                return Err(Error::new_spanned(
+                    &pat.ident,
+                    "`obj` is reserved for the vtable's object argument",
+                ));
+            }
+            args.push((pat.ident.clone(), pat_type.ty.as_ref().clone()));
+        }
+
+        Ok(Method {
+            ident: sig.ident.clone(),
+            mutable,
+            args,
+            output: sig.output.clone(),
+        })
+    }
+
+    /// Get the declaration of this method's entry in the C struct, adding any necessary
+    /// includes.
+    fn c_entry(&self, includes: &mut Vec<String>) -> Result<String> {
+        let mut params = vec![if self.mutable {
+            "void *obj".to_string()
+        } else {
+            "const void *obj".to_string()
+        }];
+        for (ident, ty) in &self.args {
+            params.push(c_declarator(&c_type(ty, includes)?, &ident.to_string()));
+        }
+        let ret = match &self.output {
+            syn::ReturnType::Default => "void".to_string(),
+            syn::ReturnType::Type(_, ty) => c_type(ty, includes)?,
+        };
+        let pointer = format!("(*{})({})", self.ident, params.join(", "));
+        Ok(format!("    {};", c_declarator(&ret, &pointer)))
+    }
+}
+
+impl Vtable {
+    /// Convert this Vtable into a TokenStream containing the trait, the vtable struct and its
+    /// constructor, and the header item.
+    pub(crate) fn to_tokens(&self, tokens: &mut TokenStream2) {
+        let item = &self.item;
+        let vis = &item.vis;
+        let trait_ident = &item.ident;
+        let ctype = syn::Ident::new(&self.header_item.name, Span::call_site());
+        let struct_doc = format!(
+            "The C vtable for [`{trait_ident}`], with an entry for each method followed by `free`."
+        );
+
+        let mut fields = vec![];
+        let mut glue = vec![];
+        let mut entries = vec![];
+        for method in &self.methods {
+            let Method {
+                ident,
+                mutable,
+                args,
+                output,
+            } = method;
+            let arg_idents: Vec<_> = args.iter().map(|(ident, _)| ident).collect();
+            let arg_types: Vec<_> = args.iter().map(|(_, ty)| ty).collect();
+            let (obj_type, with_ref) = if *mutable {
+                (
+                    quote!(*mut ::std::ffi::c_void),
+                    quote!(with_ref_mut_nonnull),
+                )
+            } else {
+                (quote!(*const ::std::ffi::c_void), quote!(with_ref_nonnull))
+            };
+            fields.push(quote! {
+                pub #ident: unsafe extern "C" fn(#obj_type, #(#arg_types),*) #output
+            });
+            glue.push(quote! {
+                unsafe extern "C" fn #ident<T: #trait_ident>(
+                    obj: #obj_type,
+                    #(#arg_idents: #arg_types),*
+                ) #output {
+                    // SAFETY: C only calls this entry with an object of type T (see docstring)
+                    unsafe {
+                        ::ffizz_passby::Boxed::<T>::#with_ref(obj.cast(), |obj| {
+                            <T as #trait_ident>::#ident(obj, #(#arg_idents),*)
+                        })
+                    }
+                }
+            });
+            entries.push(quote!(#ident: #ident::<T>));
+        }
+
+        tokens.extend(quote! {
+            #item
+
+            #[doc = #struct_doc]
+            #[allow(non_camel_case_types)]
+            #[repr(C)]
+            #[derive(Clone, Copy)]
+            #vis struct #ctype {
+                #(#fields,)*
+                pub free: unsafe extern "C" fn(*mut ::std::ffi::c_void),
+            }
+
+            impl #ctype {
+                /// Create a vtable dispatching to a value of type `T`, passed to C with
+                /// `ffizz_passby::Boxed`.
+                ///
+                /// C must only call the entries with an object of type `T`, such as one returned
+                /// from `Boxed::<T>::return_val`.  The `free` entry frees the object, and does
+                /// nothing if it is NULL.
+                #vis const fn new<T: #trait_ident>() -> Self {
+                    #(#glue)*
+
+                    unsafe extern "C" fn free<T: #trait_ident>(obj: *mut ::std::ffi::c_void) {
+                        // SAFETY: C only calls this entry with an object of type T (see docstring)
+                        ::std::mem::drop(unsafe { ::ffizz_passby::Boxed::<T>::try_take(obj.cast()) });
+                    }
+
+                    Self {
+                        #(#entries,)*
+                        free: free::<T>,
+                    }
+                }
+            }
+        });
+
+        self.header_item.to_tokens(tokens);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_vtable() {
+        let vt: Vtable = syn::parse_quote! {
+            /// A shape.
+            #[ffizz(order=200)]
+            pub trait Shape {
+                fn area(&self) -> f64;
+                fn scale(&mut self, factor: f64);
+                fn name(&self) -> *const c_char;
+                fn contains(&self, x: i32, y: i32) -> bool;
+            }
+        };
+        assert_eq!(vt.item.attrs.len(), 1);
+        assert_eq!(vt.methods.len(), 4);
+        assert!(vt.methods[1].mutable);
+        assert_eq!(
+            vt.header_item,
+            HeaderItem {
+                order: 200,
+                name: "shape_vtable_t".into(),
+                content: "// A shape.\n\
+                    typedef struct shape_vtable_t {\n    \
+                        double (*area)(const void *obj);\n    \
+                        void (*scale)(void *obj, double factor);\n    \
+                        const char *(*name)(const void *obj);\n    \
+                        bool (*contains)(const void *obj, int32_t x, int32_t y);\n    \
+                        void (*free)(void *obj);\n\
+                    } shape_vtable_t;"
+                    .into(),
+                includes: vec!["<stdint.h>".into(), "<stdbool.h>".into()],
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_vtable_name() {
+        let vt: Vtable = syn::parse_quote! {
+            #[ffizz(name="hittr_plugin_vtable_t")]
+            trait HttpPlugin {
+                fn handle(&mut self, req: *mut hittr_request_t);
+            }
+        };
+        assert_eq!(vt.header_item.name, "hittr_plugin_vtable_t");
+        assert!(vt
+            .header_item
+            .content
+            .contains("    void (*handle)(void *obj, hittr_request_t *req);\n"));
+    }
+
+    #[test]
+    fn test_vtable_default_name() {
+        let vt: Vtable = syn::parse_quote! {
+            trait HttpPlugin {}
+        };
+        assert_eq!(vt.header_item.name, "http_plugin_vtable_t");
+    }
+
+    #[test]
+    fn test_vtable_unsupported_type() {
+        let res: Result<Vtable> = syn::parse2(quote! {
+            trait Shape {
+                fn name(&self) -> String;
+            }
+        });
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_vtable_no_receiver() {
+        let res: Result<Vtable> = syn::parse2(quote! {
+            trait Shape {
+                fn new() -> i32;
+            }
+        });
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_vtable_free_reserved() {
+        let res: Result<Vtable> = syn::parse2(quote! {
+            trait Shape {
+                fn free(&mut self);
+            }
+        });
+        assert!(res.is_err());
+    }
+}
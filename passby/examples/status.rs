@@ -2,6 +2,7 @@
 #![allow(non_camel_case_types)]
 #![allow(unused_unsafe)]
 #![allow(clippy::missing_safety_doc)]
+#![allow(clippy::from_over_into)]
 #![allow(clippy::new_without_default)]
 
 /// A simple little state machine for a system's status.  This module
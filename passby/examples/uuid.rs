@@ -1,6 +1,7 @@
 #![warn(unsafe_op_in_unsafe_fn)]
 #![allow(non_camel_case_types)]
 #![allow(clippy::missing_safety_doc)]
+#![allow(clippy::from_over_into)]
 #![allow(unused_unsafe)]
 
 use ffizz_passby::Value;
@@ -123,8 +124,8 @@ fn main() {
     let mut u = unsafe { uuid_nil() };
     assert!(unsafe {
         uuid_from_str(
-            // (cheating a little, by creating a C string with explicit trailing NUL)
-            "d9c5d004-1bf4-11ed-861d-0242ac120002\0".as_ptr() as *const c_char,
+            // (a C string literal includes the trailing NUL)
+            c"d9c5d004-1bf4-11ed-861d-0242ac120002".as_ptr(),
             &mut u as *mut uuid_t,
         )
     });
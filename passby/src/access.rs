@@ -0,0 +1,129 @@
+//! Detection of conflicting access to values, for finding thread-safety bugs during integration
+//! testing.
+//!
+//! With the `access-checks` feature enabled, the `with_ref` methods of [`Boxed`](crate::Boxed)
+//! and [`Unboxed`](crate::Unboxed) record each value while it is borrowed, and panic if a value is
+//! borrowed exclusively while it is already borrowed, or vice versa.  This usually means that C
+//! has used a value from two threads at once, which these methods do not allow.  The panic aborts
+//! the process at an `extern "C"` boundary.
+//!
+//! This is a debugging aid: it only detects accesses that happen to overlap, so a race may go
+//! unnoticed in any particular run.
+
+use std::any::type_name;
+use std::collections::BTreeMap;
+use std::mem;
+use std::sync::Mutex;
+
+/// The current borrow of a value.
+enum Borrow {
+    Shared(usize),
+    Exclusive,
+}
+
+static BORROWS: Mutex<BTreeMap<usize, Borrow>> = Mutex::new(BTreeMap::new());
+
+/// A borrow of a value, which ends when this is dropped.
+pub(crate) struct Guard {
+    /// The address of the value, or None for zero-sized values, which do not have distinct
+    /// addresses.
+    addr: Option<usize>,
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        let Some(addr) = self.addr else {
+            return;
+        };
+        let mut borrows = BORROWS.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(Borrow::Shared(n)) = borrows.get_mut(&addr) {
+            if *n > 1 {
+                *n -= 1;
+                return;
+            }
+        }
+        borrows.remove(&addr);
+    }
+}
+
+/// Begin a borrow of the value at `ptr`, panicking if it conflicts with a current borrow.
+fn borrow<T>(ptr: *const T, exclusive: bool) -> Guard {
+    if mem::size_of::<T>() == 0 {
+        return Guard { addr: None };
+    }
+    let addr = ptr as usize;
+    let mut borrows = BORROWS.lock().unwrap_or_else(|e| e.into_inner());
+    let conflict = match (borrows.get_mut(&addr), exclusive) {
+        (None, _) => {
+            let borrow = if exclusive {
+                Borrow::Exclusive
+            } else {
+                Borrow::Shared(1)
+            };
+            borrows.insert(addr, borrow);
+            None
+        }
+        (Some(Borrow::Shared(n)), false) => {
+            *n += 1;
+            None
+        }
+        (Some(Borrow::Shared(_)), true) => Some("exclusively while it is already borrowed"),
+        (Some(Borrow::Exclusive), _) => Some("while it is already borrowed exclusively"),
+    };
+    drop(borrows);
+    if let Some(conflict) = conflict {
+        panic!(
+            "conflicting access to {} at {addr:#x}: borrowed {conflict} (is it being used from \
+            two threads at once?)",
+            type_name::<T>()
+        );
+    }
+    Guard { addr: Some(addr) }
+}
+
+/// Begin a shared borrow of the value at `ptr`, as for `&T`.
+pub(crate) fn shared<T>(ptr: *const T) -> Guard {
+    borrow(ptr, false)
+}
+
+/// Begin an exclusive borrow of the value at `ptr`, as for `&mut T`.
+pub(crate) fn exclusive<T>(ptr: *const T) -> Guard {
+    borrow(ptr, true)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn shared_borrows() {
+        let val = 10u32;
+        let g1 = shared(&val);
+        let g2 = shared(&val);
+        drop(g1);
+        drop(g2);
+        let _g3 = exclusive(&val);
+    }
+
+    #[test]
+    #[should_panic(expected = "borrowed exclusively while it is already borrowed")]
+    fn exclusive_while_shared() {
+        let val = 10u32;
+        let _g1 = shared(&val);
+        let _g2 = exclusive(&val);
+    }
+
+    #[test]
+    #[should_panic(expected = "borrowed while it is already borrowed exclusively")]
+    fn shared_while_exclusive() {
+        let val = 10u32;
+        let _g1 = exclusive(&val);
+        let _g2 = shared(&val);
+    }
+
+    #[test]
+    fn zero_sized() {
+        let _g1 = exclusive(&());
+        let _g2 = exclusive(&());
+    }
+}
@@ -0,0 +1,282 @@
+//! Routing of Rust allocations through allocator functions supplied by C.
+//!
+//! With the `alloc-hooks` feature enabled, a library can install [`HookAllocator`] as its global
+//! allocator.  Until C registers allocator functions with [`set_allocator`], it uses the system
+//! allocator.  After that, every allocation, including those behind [`Boxed`](crate::Boxed),
+//! [`FzVec`](crate::FzVec), and `ffizz_string::FzString`, uses the registered functions.
+//!
+//! Each allocation records which allocator made it, so values allocated before the functions were
+//! registered are still freed correctly.  This costs a small header on each allocation, which is
+//! why the feature is not enabled by default.
+//!
+//! ```
+//! # use ffizz_passby::alloc::{self, FreeFn, HookAllocator, MallocFn, ReallocFn};
+//! #[global_allocator]
+//! static ALLOCATOR: HookAllocator = HookAllocator;
+//!
+//! #[no_mangle]
+//! pub unsafe extern "C" fn mylib_set_allocator(
+//!     malloc: MallocFn,
+//!     free: FreeFn,
+//!     realloc: ReallocFn,
+//! ) -> bool {
+//!     // SAFETY: the functions behave like malloc, free, and realloc (documented in API)
+//!     unsafe { alloc::set_allocator(malloc, free, realloc) }
+//! }
+//! ```
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::ffi::c_void;
+use std::mem;
+use std::ptr;
+use std::sync::OnceLock;
+
+/// The type of a C allocation function, with the semantics of `malloc`.
+pub type MallocFn = unsafe extern "C" fn(size: usize) -> *mut c_void;
+
+/// The type of a C deallocation function, with the semantics of `free`.
+pub type FreeFn = unsafe extern "C" fn(ptr: *mut c_void);
+
+/// The type of a C reallocation function, with the semantics of `realloc`.
+pub type ReallocFn = unsafe extern "C" fn(ptr: *mut c_void, size: usize) -> *mut c_void;
+
+#[derive(Clone, Copy)]
+struct Hooks {
+    malloc: MallocFn,
+    free: FreeFn,
+    realloc: ReallocFn,
+}
+
+static HOOKS: OnceLock<Hooks> = OnceLock::new();
+
+/// Register C allocator functions, to be used by [`HookAllocator`] for all subsequent
+/// allocations.  The functions can only be registered once; later calls return false and have
+/// no effect.
+///
+/// # Safety
+///
+/// * The functions must behave like the C standard library's `malloc`, `free`, and `realloc`,
+///   including returning NULL on failure, and must be safe to call from any thread.
+/// * The functions must remain valid for the life of the process.
+pub unsafe fn set_allocator(malloc: MallocFn, free: FreeFn, realloc: ReallocFn) -> bool {
+    HOOKS
+        .set(Hooks {
+            malloc,
+            free,
+            realloc,
+        })
+        .is_ok()
+}
+
+/// A global allocator using the functions registered with [`set_allocator`], or the system
+/// allocator if none have been registered.
+pub struct HookAllocator;
+
+/// The allocator that made an allocation, as recorded in its header.
+const FROM_SYSTEM: usize = 0;
+const FROM_HOOKS: usize = 1;
+
+/// The size of the header preceding each allocation, containing the pointer returned by the
+/// underlying allocator and the allocator that made it.
+const HEADER: usize = 2 * mem::size_of::<usize>();
+
+/// The size to request from the underlying allocator for the given layout, leaving room for the
+/// header and for alignment, since the underlying allocator's alignment is not known.
+fn underlying_size(layout: Layout) -> Option<usize> {
+    layout.size().checked_add(HEADER + layout.align())
+}
+
+/// The offset of the aligned location for the allocated value, after the header, in an
+/// allocation at `raw`.
+fn value_offset(raw: *mut u8, layout: Layout) -> usize {
+    HEADER + raw.wrapping_add(HEADER).align_offset(layout.align())
+}
+
+/// Find the aligned location for the allocated value, after the header, and write the header.
+///
+/// # Safety
+///
+/// * `raw` must point to an allocation of `underlying_size(layout)` bytes.
+unsafe fn place(raw: *mut u8, layout: Layout, from: usize) -> *mut u8 {
+    let offset = value_offset(raw, layout);
+    // SAFETY: offset is at most HEADER + align - 1, within the allocation (see docstring)
+    unsafe {
+        let user = raw.add(offset);
+        let header = user.sub(HEADER) as *mut usize;
+        header.write_unaligned(raw as usize);
+        header.add(1).write_unaligned(from);
+        user
+    }
+}
+
+/// Read the header for an allocation made by [`place`].
+///
+/// # Safety
+///
+/// * `ptr` must have been returned from [`place`].
+unsafe fn header(ptr: *mut u8) -> (*mut u8, usize) {
+    // SAFETY: place wrote the header before ptr (see docstring)
+    unsafe {
+        let header = ptr.sub(HEADER) as *const usize;
+        (
+            header.read_unaligned() as *mut u8,
+            header.add(1).read_unaligned(),
+        )
+    }
+}
+
+/// The layout of an allocation from the system allocator.
+fn system_layout(size: usize) -> Layout {
+    // SAFETY: an alignment of 1 is valid, and size came from underlying_size, so it was not
+    // too large when allocated
+    unsafe { Layout::from_size_align_unchecked(size, 1) }
+}
+
+unsafe impl GlobalAlloc for HookAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let Some(size) = underlying_size(layout) else {
+            return ptr::null_mut();
+        };
+        let (raw, from) = match HOOKS.get() {
+            // SAFETY: the hook behaves like malloc (see set_allocator)
+            Some(hooks) => (unsafe { (hooks.malloc)(size) } as *mut u8, FROM_HOOKS),
+            // SAFETY: the layout has nonzero size
+            None => (unsafe { System.alloc(system_layout(size)) }, FROM_SYSTEM),
+        };
+        if raw.is_null() {
+            return raw;
+        }
+        // SAFETY: raw points to size bytes
+        unsafe { place(raw, layout, from) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        // SAFETY: ptr was returned from alloc or realloc, with this layout
+        unsafe {
+            match header(ptr) {
+                (raw, FROM_HOOKS) => (HOOKS.get().unwrap().free)(raw as *mut c_void),
+                (raw, _) => System.dealloc(raw, system_layout(underlying_size(layout).unwrap())),
+            }
+        }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        // SAFETY: ptr was returned from alloc or realloc, with this layout
+        let (raw, from) = unsafe { header(ptr) };
+        // SAFETY: new_size, rounded up to the alignment, does not overflow (GlobalAlloc contract)
+        let new_layout = unsafe { Layout::from_size_align_unchecked(new_size, layout.align()) };
+
+        if from == FROM_HOOKS {
+            let Some(size) = underlying_size(new_layout) else {
+                return ptr::null_mut();
+            };
+            let hooks = HOOKS.get().unwrap();
+            // SAFETY: raw came from the malloc or realloc hook (see set_allocator)
+            let new_raw = unsafe { (hooks.realloc)(raw as *mut c_void, size) } as *mut u8;
+            if new_raw.is_null() {
+                return new_raw;
+            }
+            let old_offset = ptr as usize - raw as usize;
+            let new_offset = value_offset(new_raw, new_layout);
+            // SAFETY: new_raw points to size bytes, and the value's bytes were copied to
+            // old_offset within it, which is in bounds since size >= old_offset + new_size
+            unsafe {
+                // move the value before writing the header, which may overlap the value's
+                // bytes at their old offset
+                if old_offset != new_offset {
+                    ptr::copy(
+                        new_raw.add(old_offset),
+                        new_raw.add(new_offset),
+                        layout.size().min(new_size),
+                    );
+                }
+                return place(new_raw, new_layout, FROM_HOOKS);
+            }
+        }
+
+        // move allocations made by the system allocator to the hooks, if registered
+        // SAFETY: new_layout has nonzero size (GlobalAlloc contract)
+        let new_ptr = unsafe { self.alloc(new_layout) };
+        if !new_ptr.is_null() {
+            // SAFETY: both allocations are valid for the copied size, and do not overlap
+            unsafe {
+                ptr::copy_nonoverlapping(ptr, new_ptr, layout.size().min(new_size));
+                self.dealloc(ptr, layout);
+            }
+        }
+        new_ptr
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // use the allocator for the whole test binary
+    #[global_allocator]
+    static ALLOCATOR: HookAllocator = HookAllocator;
+
+    static MALLOCS: AtomicUsize = AtomicUsize::new(0);
+    static REALLOCS: AtomicUsize = AtomicUsize::new(0);
+
+    unsafe extern "C" fn test_malloc(size: usize) -> *mut c_void {
+        MALLOCS.fetch_add(1, Ordering::SeqCst);
+        unsafe { libc::malloc(size) }
+    }
+
+    unsafe extern "C" fn test_free(ptr: *mut c_void) {
+        unsafe { libc::free(ptr) }
+    }
+
+    unsafe extern "C" fn test_realloc(ptr: *mut c_void, size: usize) -> *mut c_void {
+        REALLOCS.fetch_add(1, Ordering::SeqCst);
+        unsafe { libc::realloc(ptr, size) }
+    }
+
+    #[repr(align(64))]
+    struct Aligned(u8);
+
+    #[repr(align(64))]
+    struct Block([u64; 8]);
+
+    #[test]
+    fn hooks() {
+        // allocated by the system allocator, and freed after the hooks are registered
+        let before = vec![1u8, 2, 3];
+
+        unsafe { set_allocator(test_malloc, test_free, test_realloc) };
+        assert!(!unsafe { set_allocator(test_malloc, test_free, test_realloc) });
+
+        let mallocs = MALLOCS.load(Ordering::SeqCst);
+        let boxed = unsafe { crate::Boxed::return_val(String::from("hello")) };
+        assert!(MALLOCS.load(Ordering::SeqCst) > mallocs);
+        assert_eq!(unsafe { crate::Boxed::take_nonnull(boxed) }, "hello");
+
+        let reallocs = REALLOCS.load(Ordering::SeqCst);
+        let mut v = Vec::with_capacity(1);
+        v.extend(0..1000u32);
+        assert!(REALLOCS.load(Ordering::SeqCst) > reallocs);
+        assert_eq!(v.iter().sum::<u32>(), 499500);
+
+        // system allocations move to the hooks when reallocated
+        let mut before = before;
+        before.extend(4..100);
+        assert_eq!(before[..4], [1, 2, 3, 4]);
+
+        let aligned = Box::new(Aligned(7));
+        assert_eq!(&*aligned as *const Aligned as usize % 64, 0);
+        assert_eq!(aligned.0, 7);
+
+        // reallocation may change the offset of an over-aligned value within the allocation
+        let mut blocks = Vec::with_capacity(1);
+        let mut others = Vec::new();
+        for i in 0..1000u64 {
+            blocks.push(Block([i; 8]));
+            assert_eq!(blocks.as_ptr() as usize % 64, 0);
+            // allocate something else, so that growing the vec moves it
+            others.push(Box::new(i));
+        }
+        assert!(blocks.iter().enumerate().all(|(i, b)| b.0 == [i as u64; 8]));
+    }
+}
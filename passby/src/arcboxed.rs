@@ -0,0 +1,560 @@
+use crate::util::catch_panic;
+use crate::NullPointerError;
+use std::default::Default;
+use std::marker::PhantomData;
+use std::sync::{Arc, Weak};
+
+/// ArcBoxed is used to model values that are passed by reference, where their memory allocation
+/// is managed by Rust and shared among several owners.  These are represented in the C API by a
+/// pointer, with each copy of the pointer counting as a reference to the value.  A "clone"
+/// function creates a new reference, and a "free" function releases one.  The value is dropped
+/// when the last reference is released.
+///
+/// This is similar to [`Boxed`](crate::Boxed), but backed by an [`Arc`].  Because the value may
+/// be shared, only shared references to it are available, and any mutation must use interior
+/// mutability, such as a `Mutex`.  If references are used from several threads, `RType` must be
+/// `Send` and `Sync`.
+///
+/// # Example
+///
+/// Define your Rust type, then a type alias parameterizing ArcBoxed:
+///
+/// ```
+/// # use ffizz_passby::ArcBoxed;
+/// struct Config {
+///     // ...
+/// }
+/// type SharedConfig = ArcBoxed<Config>;
+/// ```
+///
+/// Then call static methods on that type alias.
+#[non_exhaustive]
+pub struct ArcBoxed<RType: Sized> {
+    _phantom: PhantomData<RType>,
+}
+
+impl<RType: Sized> ArcBoxed<RType> {
+    /// Take a reference from C as an argument, taking ownership of that reference.
+    ///
+    /// The result is an `Arc` which may or may not be the last reference to the value.  Be
+    /// careful that the C API documents that the passed pointer cannot be used after this
+    /// function is called, although other copies of the pointer remain valid.
+    ///
+    /// # Safety
+    ///
+    /// * `arg` must not be NULL (see [`ArcBoxed::take`] for a version allowing NULL).
+    /// * `arg` must be a value returned from `Arc::into_raw` (via [`ArcBoxed::return_val`] or
+    ///   [`ArcBoxed::clone_ptr`] or a variant), and the reference it represents must not already
+    ///   have been released.
+    /// * `arg` becomes invalid and must not be used after this call.
+    pub unsafe fn take_nonnull(arg: *const RType) -> Arc<RType> {
+        if arg.is_null() {
+            panic!("NULL value not allowed");
+        }
+        #[cfg(feature = "tracing")]
+        crate::trace::taken(arg);
+        // SAFETY: see docstring
+        unsafe { Arc::from_raw(arg) }
+    }
+
+    /// Call [`ArcBoxed::take_nonnull`], but return an error instead of panicking if `arg` is NULL.
+    ///
+    /// # Safety
+    ///
+    /// * If `arg` is not NULL, the requirements are the same as for [`ArcBoxed::take_nonnull`].
+    pub unsafe fn take_nonnull_checked(arg: *const RType) -> Result<Arc<RType>, NullPointerError> {
+        if arg.is_null() {
+            return Err(NullPointerError);
+        }
+        // SAFETY: arg is not NULL (just checked), and see docstring
+        Ok(unsafe { Self::take_nonnull(arg) })
+    }
+
+    /// Release a reference from C, dropping the value if this was the last reference.  This is
+    /// typically used in "free" functions.  If the pointer is NULL, nothing happens.
+    ///
+    /// # Safety
+    ///
+    /// * If not NULL, `arg` must be a value returned from `Arc::into_raw` (via
+    ///   [`ArcBoxed::return_val`] or [`ArcBoxed::clone_ptr`] or a variant), and the reference it
+    ///   represents must not already have been released.
+    /// * `arg` becomes invalid and must not be used after this call.
+    pub unsafe fn free(arg: *const RType) {
+        if !arg.is_null() {
+            #[cfg(feature = "tracing")]
+            crate::trace::taken(arg);
+            // SAFETY: see docstring
+            drop(unsafe { Arc::from_raw(arg) });
+        }
+    }
+
+    /// Create a new reference to the value, returning a pointer that must be freed independently
+    /// of `arg`.  The pointer is equal to `arg`.
+    ///
+    /// # Safety
+    ///
+    /// * `arg` must not be NULL.
+    /// * `arg` must be a value returned from `Arc::into_raw` (via [`ArcBoxed::return_val`] or
+    ///   [`ArcBoxed::clone_ptr`] or a variant), and the reference it represents must not already
+    ///   have been released.
+    /// * The caller must ensure that the new reference is eventually freed.
+    pub unsafe fn clone_ptr(arg: *const RType) -> *const RType {
+        if arg.is_null() {
+            panic!("NULL value not allowed");
+        }
+        // SAFETY: see docstring
+        unsafe { Arc::increment_strong_count(arg) };
+        #[cfg(feature = "tracing")]
+        crate::trace::returned(arg);
+        arg
+    }
+
+    /// Get the number of references to the value, including those held by Rust code.
+    ///
+    /// Other threads may create or release references concurrently, so the result is only a
+    /// snapshot, useful for diagnostics and tests.
+    ///
+    /// # Safety
+    ///
+    /// * `arg` must not be NULL.
+    /// * `arg` must be a value returned from `Arc::into_raw` (via [`ArcBoxed::return_val`] or
+    ///   [`ArcBoxed::clone_ptr`] or a variant), and the reference it represents must not already
+    ///   have been released.
+    pub unsafe fn strong_count(arg: *const RType) -> usize {
+        if arg.is_null() {
+            panic!("NULL value not allowed");
+        }
+        // SAFETY: see docstring; the reference held by `arg` is not released
+        let arc = std::mem::ManuallyDrop::new(unsafe { Arc::from_raw(arg) });
+        Arc::strong_count(&arc)
+    }
+
+    /// Create a weak reference to the value, which does not keep the value alive.  The result
+    /// is a "weak pointer" which can only be used with [`ArcBoxed::upgrade`] and
+    /// [`ArcBoxed::weak_free`], and must not be dereferenced.  This is useful for registrations,
+    /// such as of observers, that should not prevent the value from being dropped.
+    ///
+    /// The C API should use a distinct type for weak pointers, so that they are not confused
+    /// with strong references.
+    ///
+    /// # Safety
+    ///
+    /// * `arg` must not be NULL.
+    /// * `arg` must be a value returned from `Arc::into_raw` (via [`ArcBoxed::return_val`] or
+    ///   [`ArcBoxed::clone_ptr`] or a variant), and the reference it represents must not already
+    ///   have been released.
+    /// * The caller must ensure that the weak pointer is eventually freed with
+    ///   [`ArcBoxed::weak_free`].
+    pub unsafe fn downgrade(arg: *const RType) -> *const RType {
+        if arg.is_null() {
+            panic!("NULL value not allowed");
+        }
+        // SAFETY: see docstring; the reference held by `arg` is not released
+        let arc = std::mem::ManuallyDrop::new(unsafe { Arc::from_raw(arg) });
+        Weak::into_raw(Arc::downgrade(&arc))
+    }
+
+    /// Create a new reference to the value from a weak pointer, or return NULL if the value has
+    /// already been dropped.  The weak pointer remains valid.
+    ///
+    /// # Safety
+    ///
+    /// * `weak` must not be NULL.
+    /// * `weak` must be a value returned from [`ArcBoxed::downgrade`] that has not been freed.
+    /// * The caller must ensure that the new reference, if any, is eventually freed.
+    pub unsafe fn upgrade(weak: *const RType) -> *const RType {
+        if weak.is_null() {
+            panic!("NULL value not allowed");
+        }
+        // SAFETY: see docstring; the weak reference held by `weak` is not released
+        let weak = std::mem::ManuallyDrop::new(unsafe { Weak::from_raw(weak) });
+        match weak.upgrade() {
+            Some(arc) => Arc::into_raw(arc),
+            None => std::ptr::null(),
+        }
+    }
+
+    /// Free a weak pointer.  If the pointer is NULL, nothing happens.
+    ///
+    /// # Safety
+    ///
+    /// * If not NULL, `weak` must be a value returned from [`ArcBoxed::downgrade`] that has not
+    ///   been freed.
+    /// * `weak` becomes invalid and must not be used after this call.
+    pub unsafe fn weak_free(weak: *const RType) {
+        if !weak.is_null() {
+            // SAFETY: see docstring
+            drop(unsafe { Weak::from_raw(weak) });
+        }
+    }
+
+    /// Call the contained function with a shared reference to the value.
+    ///
+    /// # Safety
+    ///
+    /// * `arg` must not be NULL (see [`ArcBoxed::with_ref`] for a version allowing NULL).
+    /// * `arg` must be a value returned from `Arc::into_raw` (via [`ArcBoxed::return_val`] or
+    ///   [`ArcBoxed::clone_ptr`] or a variant), and the reference it represents must not be
+    ///   released until this function returns.
+    /// * Ownership of the reference remains with the caller.
+    pub unsafe fn with_ref_nonnull<T, F: FnOnce(&RType) -> T>(arg: *const RType, f: F) -> T {
+        if arg.is_null() {
+            panic!("NULL value not allowed");
+        }
+        // SAFETY:
+        // - pointer came from Arc::into_raw, so points to a live value
+        // - the reference held by `arg` keeps the value alive during the call
+        f(unsafe { &*arg })
+    }
+
+    /// Call [`ArcBoxed::with_ref_nonnull`], but return an error instead of panicking if `arg` is NULL.
+    ///
+    /// # Safety
+    ///
+    /// * If `arg` is not NULL, the requirements are the same as for [`ArcBoxed::with_ref_nonnull`].
+    pub unsafe fn with_ref_nonnull_checked<T, F: FnOnce(&RType) -> T>(
+        arg: *const RType,
+        f: F,
+    ) -> Result<T, NullPointerError> {
+        if arg.is_null() {
+            return Err(NullPointerError);
+        }
+        // SAFETY: arg is not NULL (just checked), and see docstring
+        Ok(unsafe { Self::with_ref_nonnull(arg, f) })
+    }
+
+    /// Call the contained function as for [`ArcBoxed::with_ref_nonnull`], catching any
+    /// panic, including that for a NULL pointer.
+    ///
+    /// The result is an error if a panic occurred, which the caller can convert into an error
+    /// code rather than allowing the panic to unwind into C.
+    ///
+    /// # Safety
+    ///
+    /// * The requirements are the same as for [`ArcBoxed::with_ref_nonnull`].
+    pub unsafe fn with_ref_nonnull_catch<T, F: FnOnce(&RType) -> T>(
+        arg: *const RType,
+        f: F,
+    ) -> std::thread::Result<T> {
+        // SAFETY: see docstring
+        catch_panic(|| unsafe { Self::with_ref_nonnull(arg, f) })
+    }
+
+    /// Return a value to C, transferring ownership of the single reference to it.
+    ///
+    /// This method is most often used in constructors, to return the built value.
+    ///
+    /// # Safety
+    ///
+    /// * The caller must ensure that the reference is eventually freed.
+    pub unsafe fn return_val(rval: RType) -> *const RType {
+        // SAFETY: return_val_arc and return_val have the same safety requirements.
+        unsafe { Self::return_val_arc(Arc::new(rval)) }
+    }
+
+    /// Return a reference to a shared value to C, transferring ownership of that reference.
+    ///
+    /// This is an alternative to [`ArcBoxed::return_val`] for use when the value is already in an
+    /// `Arc`, perhaps with other references held by Rust code.
+    ///
+    /// # Safety
+    ///
+    /// * The caller must ensure that the reference is eventually freed.
+    pub unsafe fn return_val_arc(rval: Arc<RType>) -> *const RType {
+        let ptr = Arc::into_raw(rval);
+        #[cfg(feature = "tracing")]
+        crate::trace::returned(ptr);
+        ptr
+    }
+
+    /// Return a value to C, transferring ownership of the single reference to it, via an "output
+    /// parameter".
+    ///
+    /// If the pointer is NULL, the value is dropped.  Use [`ArcBoxed::to_out_param_nonnull`] to
+    /// panic in this situation.
+    ///
+    /// # Safety
+    ///
+    /// * The caller must ensure that the reference is eventually freed.
+    /// * If not NULL, `arg_out` must point to valid, properly aligned memory for a pointer value.
+    pub unsafe fn to_out_param(rval: RType, arg_out: *mut *const RType) {
+        if !arg_out.is_null() {
+            // SAFETY: see docstring
+            unsafe { *arg_out = Self::return_val(rval) };
+        }
+    }
+
+    /// Return a value to C, transferring ownership of the single reference to it, via an "output
+    /// parameter".
+    ///
+    /// If the pointer is NULL, this function will panic.  Use [`ArcBoxed::to_out_param`] to
+    /// drop the value in this situation.
+    ///
+    /// # Safety
+    ///
+    /// * The caller must ensure that the reference is eventually freed.
+    /// * `arg_out` must not be NULL.
+    /// * `arg_out` must point to valid, properly aligned memory for a pointer value.
+    pub unsafe fn to_out_param_nonnull(rval: RType, arg_out: *mut *const RType) {
+        if arg_out.is_null() {
+            panic!("out param pointer is NULL");
+        }
+        // SAFETY: see docstring
+        unsafe { *arg_out = Self::return_val(rval) };
+    }
+
+    /// Call [`ArcBoxed::to_out_param_nonnull`], but return an error instead of panicking if `arg_out` is NULL.
+    ///
+    /// If `arg_out` is NULL, `rval` is dropped.
+    ///
+    /// # Safety
+    ///
+    /// * If `arg_out` is not NULL, the requirements are the same as for [`ArcBoxed::to_out_param_nonnull`].
+    pub unsafe fn to_out_param_nonnull_checked(
+        rval: RType,
+        arg_out: *mut *const RType,
+    ) -> Result<(), NullPointerError> {
+        if arg_out.is_null() {
+            return Err(NullPointerError);
+        }
+        // SAFETY: arg_out is not NULL (just checked), and see docstring
+        unsafe { Self::to_out_param_nonnull(rval, arg_out) };
+        Ok(())
+    }
+}
+
+impl<RType: Sized + Default> ArcBoxed<RType> {
+    /// Take a reference from C as an argument.
+    ///
+    /// This function is similar to [`ArcBoxed::take_nonnull`], but returns a new `Arc` containing
+    /// the default value of RType when given NULL.
+    ///
+    /// # Safety
+    ///
+    /// * If not NULL, `arg` must be a value returned from `Arc::into_raw` (via
+    ///   [`ArcBoxed::return_val`] or [`ArcBoxed::clone_ptr`] or a variant), and the reference it
+    ///   represents must not already have been released.
+    /// * `arg` becomes invalid and must not be used after this call.
+    pub unsafe fn take(arg: *const RType) -> Arc<RType> {
+        if arg.is_null() {
+            return Arc::new(RType::default());
+        }
+        #[cfg(feature = "tracing")]
+        crate::trace::taken(arg);
+        // SAFETY: see docstring
+        unsafe { Arc::from_raw(arg) }
+    }
+
+    /// Call the contained function with a shared reference to the value.
+    ///
+    /// If the given pointer is NULL, the contained function is called with a reference to RType's
+    /// default value, which is subsequently dropped.
+    ///
+    /// # Safety
+    ///
+    /// * If not NULL, `arg` must be a value returned from `Arc::into_raw` (via
+    ///   [`ArcBoxed::return_val`] or [`ArcBoxed::clone_ptr`] or a variant), and the reference it
+    ///   represents must not be released until this function returns.
+    /// * Ownership of the reference remains with the caller.
+    pub unsafe fn with_ref<T, F: FnOnce(&RType) -> T>(arg: *const RType, f: F) -> T {
+        if arg.is_null() {
+            let nullval = RType::default();
+            return f(&nullval);
+        }
+
+        // SAFETY:
+        // - pointer is not NULL (just checked)
+        // - pointer came from Arc::into_raw, so points to a live value
+        f(unsafe { &*arg })
+    }
+
+    /// Call the contained function as for [`ArcBoxed::with_ref`], catching any panic.
+    ///
+    /// The result is an error if a panic occurred, which the caller can convert into an error
+    /// code rather than allowing the panic to unwind into C.
+    ///
+    /// # Safety
+    ///
+    /// * The requirements are the same as for [`ArcBoxed::with_ref`].
+    pub unsafe fn with_ref_catch<T, F: FnOnce(&RType) -> T>(
+        arg: *const RType,
+        f: F,
+    ) -> std::thread::Result<T> {
+        // SAFETY: see docstring
+        catch_panic(|| unsafe { Self::with_ref(arg, f) })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::mem;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct RType(u32, Mutex<u64>);
+
+    type SharedTuple = ArcBoxed<RType>;
+
+    #[test]
+    fn clone_and_free() {
+        unsafe {
+            let cptr = SharedTuple::return_val(RType(10, Mutex::new(20)));
+            assert_eq!(SharedTuple::strong_count(cptr), 1);
+
+            let cptr2 = SharedTuple::clone_ptr(cptr);
+            assert_eq!(cptr2, cptr);
+            assert_eq!(SharedTuple::strong_count(cptr), 2);
+
+            SharedTuple::with_ref_nonnull(cptr2, |rref| {
+                assert_eq!(rref.0, 10);
+                *rref.1.lock().unwrap() += 1;
+            });
+
+            SharedTuple::free(cptr);
+            assert_eq!(SharedTuple::strong_count(cptr2), 1);
+
+            SharedTuple::with_ref(cptr2, |rref| {
+                assert_eq!(*rref.1.lock().unwrap(), 21);
+            });
+
+            let arc = SharedTuple::take(cptr2);
+            assert_eq!(Arc::strong_count(&arc), 1);
+            assert_eq!(arc.0, 10);
+        }
+    }
+
+    #[test]
+    fn weak() {
+        unsafe {
+            let cptr = SharedTuple::return_val(RType(10, Mutex::new(20)));
+            let weak = SharedTuple::downgrade(cptr);
+            assert_eq!(SharedTuple::strong_count(cptr), 1);
+
+            let strong = SharedTuple::upgrade(weak);
+            assert_eq!(strong, cptr);
+            assert_eq!(SharedTuple::strong_count(cptr), 2);
+            SharedTuple::free(strong);
+
+            SharedTuple::free(cptr);
+            assert!(SharedTuple::upgrade(weak).is_null());
+            SharedTuple::weak_free(weak);
+            SharedTuple::weak_free(std::ptr::null());
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn upgrade_null() {
+        unsafe { SharedTuple::upgrade(std::ptr::null()) };
+    }
+
+    #[test]
+    fn shared_with_rust() {
+        unsafe {
+            let arc = Arc::new(RType(10, Mutex::new(20)));
+            let cptr = SharedTuple::return_val_arc(arc.clone());
+            assert_eq!(SharedTuple::strong_count(cptr), 2);
+            SharedTuple::free(cptr);
+            assert_eq!(Arc::strong_count(&arc), 1);
+        }
+    }
+
+    #[test]
+    fn to_out_param() {
+        unsafe {
+            let mut cptr = mem::MaybeUninit::<*const RType>::uninit();
+            SharedTuple::to_out_param(RType(10, Mutex::new(20)), cptr.as_mut_ptr());
+            let cptr = cptr.assume_init();
+            assert_eq!(SharedTuple::take_nonnull(cptr).0, 10);
+
+            let mut cptr = mem::MaybeUninit::<*const RType>::uninit();
+            SharedTuple::to_out_param_nonnull(RType(100, Mutex::new(200)), cptr.as_mut_ptr());
+            let cptr = cptr.assume_init();
+            assert_eq!(SharedTuple::take_nonnull(cptr).0, 100);
+
+            SharedTuple::to_out_param(RType(10, Mutex::new(20)), std::ptr::null_mut());
+            // nothing happens
+        }
+    }
+
+    #[test]
+    fn with_null_ptrs() {
+        unsafe {
+            SharedTuple::with_ref(std::ptr::null(), |rref| {
+                assert_eq!(rref.0, 0);
+            });
+            assert_eq!(SharedTuple::take(std::ptr::null()).0, 0);
+            SharedTuple::free(std::ptr::null());
+        }
+    }
+
+    #[test]
+    #[cfg_attr(panic = "abort", ignore = "catching a panic requires unwinding")]
+    fn with_ref_catch() {
+        unsafe {
+            let cptr = SharedTuple::return_val(RType(10, Mutex::new(20)));
+            assert_eq!(
+                SharedTuple::with_ref_nonnull_catch(cptr, |rref| rref.0).unwrap(),
+                10
+            );
+            assert!(SharedTuple::with_ref_catch(cptr, |_| -> () { panic!("oops") }).is_err());
+            assert!(SharedTuple::with_ref_nonnull_catch(std::ptr::null(), |_| {}).is_err());
+            SharedTuple::free(cptr);
+        }
+    }
+
+    #[test]
+    fn checked() {
+        let mut cptr = std::ptr::null();
+        unsafe {
+            assert_eq!(
+                SharedTuple::to_out_param_nonnull_checked(RType::default(), std::ptr::null_mut()),
+                Err(NullPointerError)
+            );
+            assert_eq!(
+                SharedTuple::to_out_param_nonnull_checked(RType(1, Mutex::new(2)), &mut cptr),
+                Ok(())
+            );
+            assert_eq!(
+                SharedTuple::with_ref_nonnull_checked(std::ptr::null(), |_| ()),
+                Err(NullPointerError)
+            );
+            assert_eq!(SharedTuple::with_ref_nonnull_checked(cptr, |r| r.0), Ok(1));
+            assert!(SharedTuple::take_nonnull_checked(std::ptr::null()).is_err());
+            assert_eq!(SharedTuple::take_nonnull_checked(cptr).unwrap().0, 1);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn with_ref_nonnull_null() {
+        unsafe {
+            SharedTuple::with_ref_nonnull(std::ptr::null(), |_| {});
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn clone_ptr_null() {
+        unsafe {
+            SharedTuple::clone_ptr(std::ptr::null());
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn take_nonnull_null() {
+        unsafe {
+            SharedTuple::take_nonnull(std::ptr::null());
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn to_out_param_nonnull_null() {
+        unsafe {
+            SharedTuple::to_out_param_nonnull(RType(10, Mutex::new(20)), std::ptr::null_mut());
+        }
+    }
+}
@@ -1,6 +1,10 @@
+use crate::util::catch_panic;
+use crate::{NullPointerError, Slice};
 use std::default::Default;
 use std::marker::PhantomData;
 
+pub use ffizz_macros::{boxed_api, ffizz_export, ffizz_fn, vtable};
+
 /// Boxed is used to model values that are passed by reference and where their memory allocation is
 /// managed entirely by Rust.  These are represented in the C API by a pointer, with "new" and
 /// "free" functions handling creation and destruction.
@@ -57,8 +61,65 @@ impl<RType: Sized> Boxed<RType> {
     /// * `arg` becomes invalid and must not be used after this call.
     pub unsafe fn take_nonnull(arg: *mut RType) -> RType {
         debug_assert!(!arg.is_null());
+        #[cfg(feature = "quarantine")]
+        crate::quarantine::freed(arg);
+        #[cfg(feature = "track-allocations")]
+        crate::debug::forget(arg);
+        #[cfg(feature = "tracing")]
+        crate::trace::taken(arg);
         // SAFETY: see docstring
-        unsafe { *(Box::from_raw(arg)) }
+        unsafe { take_raw(arg) }
+    }
+
+    /// Call [`Boxed::take_nonnull`], but return an error instead of panicking if `arg` is NULL.
+    ///
+    /// # Safety
+    ///
+    /// * If `arg` is not NULL, the requirements are the same as for [`Boxed::take_nonnull`].
+    pub unsafe fn take_nonnull_checked(arg: *mut RType) -> Result<RType, NullPointerError> {
+        if arg.is_null() {
+            return Err(NullPointerError);
+        }
+        // SAFETY: arg is not NULL (just checked), and see docstring
+        Ok(unsafe { Self::take_nonnull(arg) })
+    }
+
+    /// Take a value from C as an argument, returning None if `arg` is NULL.
+    ///
+    /// This is suited to "free" functions, for which C programmers expect that freeing NULL does
+    /// nothing, as with `free(3)`.
+    ///
+    /// # Safety
+    ///
+    /// * If `arg` is not NULL, the requirements are the same as for [`Boxed::take_nonnull`].
+    pub unsafe fn try_take(arg: *mut RType) -> Option<RType> {
+        if arg.is_null() {
+            return None;
+        }
+        // SAFETY: arg is not NULL (just checked), and see docstring
+        Some(unsafe { Self::take_nonnull(arg) })
+    }
+
+    /// Take a value from C as an argument, leaving it boxed, and returning None if `arg` is NULL.
+    ///
+    /// This is an alternative to [`Boxed::try_take`] that avoids moving large values out of the
+    /// allocation.
+    ///
+    /// # Safety
+    ///
+    /// * If `arg` is not NULL, the requirements are the same as for [`Boxed::take_nonnull`].
+    pub unsafe fn try_take_boxed(arg: *mut RType) -> Option<Box<RType>> {
+        if arg.is_null() {
+            return None;
+        }
+        #[cfg(feature = "quarantine")]
+        crate::quarantine::freed(arg);
+        #[cfg(feature = "track-allocations")]
+        crate::debug::forget(arg);
+        #[cfg(feature = "tracing")]
+        crate::trace::taken(arg);
+        // SAFETY: arg is not NULL (just checked), and see docstring
+        Some(unsafe { take_raw_boxed(arg) })
     }
 
     /// Call the contained function with a shared reference to the value.
@@ -72,9 +133,51 @@ impl<RType: Sized> Boxed<RType> {
         if arg.is_null() {
             panic!("NULL value not allowed");
         }
+        #[cfg(feature = "quarantine")]
+        crate::quarantine::check(arg);
+        #[cfg(feature = "type-tags")]
+        // SAFETY: arg is not NULL (just checked), and came from Boxed::return_val
+        unsafe {
+            crate::typetag::check(arg)
+        };
+        #[cfg(feature = "access-checks")]
+        let _guard = crate::access::shared(arg);
         // SAFETY:
         // - pointer came from Box::into_raw, so has proper size and alignment
-        f(unsafe { &*(arg as *const RType) })
+        f(unsafe { &*arg })
+    }
+
+    /// Call [`Boxed::with_ref_nonnull`], but return an error instead of panicking if `arg` is NULL.
+    ///
+    /// # Safety
+    ///
+    /// * If `arg` is not NULL, the requirements are the same as for [`Boxed::with_ref_nonnull`].
+    pub unsafe fn with_ref_nonnull_checked<T, F: FnOnce(&RType) -> T>(
+        arg: *const RType,
+        f: F,
+    ) -> Result<T, NullPointerError> {
+        if arg.is_null() {
+            return Err(NullPointerError);
+        }
+        // SAFETY: arg is not NULL (just checked), and see docstring
+        Ok(unsafe { Self::with_ref_nonnull(arg, f) })
+    }
+
+    /// Call the contained function as for [`Boxed::with_ref_nonnull`], catching any panic,
+    /// including that for a NULL pointer.
+    ///
+    /// The result is an error if a panic occurred, which the caller can convert into an error
+    /// code rather than allowing the panic to unwind into C.
+    ///
+    /// # Safety
+    ///
+    /// * The requirements are the same as for [`Boxed::with_ref_nonnull`].
+    pub unsafe fn with_ref_nonnull_catch<T, F: FnOnce(&RType) -> T>(
+        arg: *const RType,
+        f: F,
+    ) -> std::thread::Result<T> {
+        // SAFETY: see docstring
+        catch_panic(|| unsafe { Self::with_ref_nonnull(arg, f) })
     }
 
     /// Call the contained function with an exclusive reference to the value.
@@ -88,11 +191,223 @@ impl<RType: Sized> Boxed<RType> {
         if arg.is_null() {
             panic!("NULL value not allowed");
         }
+        #[cfg(feature = "quarantine")]
+        crate::quarantine::check(arg);
+        #[cfg(feature = "type-tags")]
+        // SAFETY: arg is not NULL (just checked), and came from Boxed::return_val
+        unsafe {
+            crate::typetag::check(arg)
+        };
+        #[cfg(feature = "access-checks")]
+        let _guard = crate::access::exclusive(arg);
         // SAFETY:
         // - pointer came from Box::into_raw, so has proper size and alignment
         f(unsafe { &mut *arg })
     }
 
+    /// Call [`Boxed::with_ref_mut_nonnull`], but return an error instead of panicking if `arg` is NULL.
+    ///
+    /// # Safety
+    ///
+    /// * If `arg` is not NULL, the requirements are the same as for [`Boxed::with_ref_mut_nonnull`].
+    pub unsafe fn with_ref_mut_nonnull_checked<T, F: FnOnce(&mut RType) -> T>(
+        arg: *mut RType,
+        f: F,
+    ) -> Result<T, NullPointerError> {
+        if arg.is_null() {
+            return Err(NullPointerError);
+        }
+        // SAFETY: arg is not NULL (just checked), and see docstring
+        Ok(unsafe { Self::with_ref_mut_nonnull(arg, f) })
+    }
+
+    /// Call the contained function as for [`Boxed::with_ref_mut_nonnull`], catching any
+    /// panic, including that for a NULL pointer.
+    ///
+    /// The result is an error if a panic occurred, which the caller can convert into an error
+    /// code rather than allowing the panic to unwind into C.  The value may be left in an
+    /// inconsistent state by a panic.
+    ///
+    /// # Safety
+    ///
+    /// * The requirements are the same as for [`Boxed::with_ref_mut_nonnull`].
+    pub unsafe fn with_ref_mut_nonnull_catch<T, F: FnOnce(&mut RType) -> T>(
+        arg: *mut RType,
+        f: F,
+    ) -> std::thread::Result<T> {
+        // SAFETY: see docstring
+        catch_panic(|| unsafe { Self::with_ref_mut_nonnull(arg, f) })
+    }
+
+    /// Call the contained function with a shared reference to the value, or None if `arg` is
+    /// NULL.  This allows each C API function to decide what NULL means.
+    ///
+    /// # Safety
+    ///
+    /// * If `arg` is not NULL, the requirements are the same as for [`Boxed::with_ref_nonnull`].
+    pub unsafe fn with_ref_opt<T, F: FnOnce(Option<&RType>) -> T>(arg: *const RType, f: F) -> T {
+        if arg.is_null() {
+            return f(None);
+        }
+        // SAFETY: arg is not NULL (just checked), and see docstring
+        unsafe { Self::with_ref_nonnull(arg, |rref| f(Some(rref))) }
+    }
+
+    /// Call the contained function with an exclusive reference to the value, or None if `arg`
+    /// is NULL.  To take a value that may be NULL, use [`Boxed::try_take`].
+    ///
+    /// # Safety
+    ///
+    /// * If `arg` is not NULL, the requirements are the same as for
+    ///   [`Boxed::with_ref_mut_nonnull`].
+    pub unsafe fn with_ref_mut_opt<T, F: FnOnce(Option<&mut RType>) -> T>(
+        arg: *mut RType,
+        f: F,
+    ) -> T {
+        if arg.is_null() {
+            return f(None);
+        }
+        // SAFETY: arg is not NULL (just checked), and see docstring
+        unsafe { Self::with_ref_mut_nonnull(arg, |rref| f(Some(rref))) }
+    }
+
+    /// Call the contained function with shared references to two values, such as for a function
+    /// comparing two values.  The pointers may be equal.
+    ///
+    /// # Safety
+    ///
+    /// * The requirements for each pointer are the same as for [`Boxed::with_ref_nonnull`].
+    pub unsafe fn with_ref2_nonnull<T, F: FnOnce(&RType, &RType) -> T>(
+        a: *const RType,
+        b: *const RType,
+        f: F,
+    ) -> T {
+        // SAFETY: see docstring
+        unsafe { Self::with_ref_nonnull(a, |a| Self::with_ref_nonnull(b, |b| f(a, b))) }
+    }
+
+    /// Call the contained function with exclusive references to two values, such as for a
+    /// function swapping two values.
+    ///
+    /// This function panics if either pointer is NULL, or if the pointers are equal, as that
+    /// would create two exclusive references to the same value.
+    ///
+    /// # Safety
+    ///
+    /// * The requirements for each pointer are the same as for [`Boxed::with_ref_mut_nonnull`].
+    pub unsafe fn with_ref_mut2_nonnull<T, F: FnOnce(&mut RType, &mut RType) -> T>(
+        a: *mut RType,
+        b: *mut RType,
+        f: F,
+    ) -> T {
+        if a.is_null() || b.is_null() {
+            panic!("NULL value not allowed");
+        }
+        if a == b {
+            panic!("pointers to the same value not allowed");
+        }
+        // SAFETY: the pointers are distinct (just checked), and see docstring
+        unsafe { Self::with_ref_mut_nonnull(a, |a| Self::with_ref_mut_nonnull(b, |b| f(a, b))) }
+    }
+
+    /// Call the contained function with an exclusive reference to one value and a shared
+    /// reference to another, such as for a function merging `b` into `a`.
+    ///
+    /// This function panics if either pointer is NULL, or if the pointers are equal, as that
+    /// would create exclusive and shared references to the same value.
+    ///
+    /// # Safety
+    ///
+    /// * The requirements for `a` are the same as for [`Boxed::with_ref_mut_nonnull`].
+    /// * The requirements for `b` are the same as for [`Boxed::with_ref_nonnull`].
+    pub unsafe fn with_ref_mut_ref_nonnull<T, F: FnOnce(&mut RType, &RType) -> T>(
+        a: *mut RType,
+        b: *const RType,
+        f: F,
+    ) -> T {
+        if a.is_null() || b.is_null() {
+            panic!("NULL value not allowed");
+        }
+        if std::ptr::eq(a, b) {
+            panic!("pointers to the same value not allowed");
+        }
+        // SAFETY: the pointers are distinct (just checked), and see docstring
+        unsafe { Self::with_ref_mut_nonnull(a, |a| Self::with_ref_nonnull(b, |b| f(a, b))) }
+    }
+
+    /// Call the contained function with shared references to each value in a C array of
+    /// pointers, such as `foo_t **foos, size_t len`.  This supports batch operations like
+    /// waiting on several objects at once.
+    ///
+    /// This function panics if any element of the array is NULL (see
+    /// [`Boxed::with_refs_opt`] for a version allowing NULL elements), or if `arr` is NULL
+    /// and `len` is nonzero.  The same value may appear more than once.
+    ///
+    /// # Safety
+    ///
+    /// * If not NULL, `arr` must point to `len` consecutive pointers, each meeting the
+    ///   requirements of [`Boxed::with_ref_nonnull`].
+    /// * No other thread may mutate the array until this function returns.
+    pub unsafe fn with_refs_nonnull<T, F: FnOnce(&[&RType]) -> T>(
+        arr: *const *mut RType,
+        len: usize,
+        f: F,
+    ) -> T {
+        // SAFETY: see docstring
+        unsafe {
+            Self::with_refs_opt(arr, len, |opts| {
+                let refs: Vec<&RType> = opts
+                    .iter()
+                    .map(|r| r.unwrap_or_else(|| panic!("NULL value not allowed")))
+                    .collect();
+                f(&refs)
+            })
+        }
+    }
+
+    /// Call the contained function with a shared reference to each value in a C array of
+    /// pointers, as for [`Boxed::with_refs_nonnull`], but with NULL elements represented as
+    /// `None`.
+    ///
+    /// This function panics if `arr` is NULL and `len` is nonzero.
+    ///
+    /// # Safety
+    ///
+    /// * If not NULL, `arr` must point to `len` consecutive pointers, each of which is NULL or
+    ///   meets the requirements of [`Boxed::with_ref_nonnull`].
+    /// * No other thread may mutate the array until this function returns.
+    pub unsafe fn with_refs_opt<T, F: FnOnce(&[Option<&RType>]) -> T>(
+        arr: *const *mut RType,
+        len: usize,
+        f: F,
+    ) -> T {
+        // SAFETY: arr points to len pointers (see docstring)
+        unsafe {
+            Slice::with_ref(arr, len, |ptrs| {
+                #[cfg(feature = "access-checks")]
+                let mut guards = Vec::with_capacity(len);
+                let mut refs = Vec::with_capacity(len);
+                for &ptr in ptrs {
+                    if ptr.is_null() {
+                        refs.push(None);
+                        continue;
+                    }
+                    #[cfg(feature = "quarantine")]
+                    crate::quarantine::check(ptr);
+                    #[cfg(feature = "type-tags")]
+                    // SAFETY: ptr is not NULL (just checked), and came from Boxed::return_val
+                    crate::typetag::check(ptr);
+                    #[cfg(feature = "access-checks")]
+                    guards.push(crate::access::shared(ptr));
+                    // SAFETY:
+                    // - pointer came from Box::into_raw, so has proper size and alignment
+                    refs.push(Some(&*ptr));
+                }
+                f(&refs)
+            })
+        }
+    }
+
     /// Return a value to C, boxing the value and transferring ownership.
     ///
     /// This method is most often used in constructors, to return the built value.
@@ -113,7 +428,14 @@ impl<RType: Sized> Boxed<RType> {
     ///
     /// * The caller must ensure that the value is eventually freed.
     pub unsafe fn return_val_boxed(rval: Box<RType>) -> *mut RType {
-        Box::into_raw(rval)
+        let ptr = into_raw(rval);
+        #[cfg(feature = "quarantine")]
+        crate::quarantine::allocated(ptr);
+        #[cfg(feature = "track-allocations")]
+        crate::debug::record(ptr);
+        #[cfg(feature = "tracing")]
+        crate::trace::returned(ptr);
+        ptr
     }
 
     /// Return a value to C, transferring ownership, via an "output parameter".
@@ -149,28 +471,102 @@ impl<RType: Sized> Boxed<RType> {
         // SAFETY: see docstring
         unsafe { *arg_out = Self::return_val(rval) };
     }
+
+    /// Call [`Boxed::to_out_param_nonnull`], but return an error instead of panicking if `arg_out` is NULL.
+    ///
+    /// If `arg_out` is NULL, `rval` is dropped.
+    ///
+    /// # Safety
+    ///
+    /// * If `arg_out` is not NULL, the requirements are the same as for [`Boxed::to_out_param_nonnull`].
+    pub unsafe fn to_out_param_nonnull_checked(
+        rval: RType,
+        arg_out: *mut *mut RType,
+    ) -> Result<(), NullPointerError> {
+        if arg_out.is_null() {
+            return Err(NullPointerError);
+        }
+        // SAFETY: arg_out is not NULL (just checked), and see docstring
+        unsafe { Self::to_out_param_nonnull(rval, arg_out) };
+        Ok(())
+    }
+
+    /// Return values to C, transferring ownership, by writing pointers to a caller-provided array
+    /// of `cap` pointers.  Returns the number of pointers written.
+    ///
+    /// Values beyond the first `cap` are dropped.  This function panics if `dst` is NULL and
+    /// `cap` is nonzero.
+    ///
+    /// # Safety
+    ///
+    /// * The caller must ensure that the values written are eventually freed.
+    /// * If not NULL, `dst` must point to valid, properly aligned memory for `cap` pointer values.
+    pub unsafe fn to_out_array(
+        values: impl IntoIterator<Item = RType>,
+        dst: *mut *mut RType,
+        cap: usize,
+    ) -> usize {
+        if dst.is_null() && cap != 0 {
+            panic!("NULL pointer with nonzero length");
+        }
+        let mut count = 0;
+        for rval in values {
+            if count < cap {
+                // SAFETY: dst has space for cap pointers (see docstring), and count < cap
+                unsafe { dst.add(count).write(Self::return_val(rval)) };
+                count += 1;
+            }
+        }
+        count
+    }
+
+    /// Convert a pointer to a value into a read-only pointer, such as a `const prefix_const_t *`
+    /// declared by `boxed_api!` with the `const` flag.
+    ///
+    /// This is always safe, just as C allows `prefix_t *` to be passed where
+    /// `const prefix_t *` is expected.  It is useful for functions that return a read-only view of
+    /// a mutable value.
+    pub fn as_const(arg: *mut RType) -> *const RType {
+        arg.cast_const()
+    }
+
+    /// Convert a read-only pointer to a value back into a mutable pointer.
+    ///
+    /// In C, this is a cast that discards `const`, so it should be rare.  It is useful, for
+    /// example, in a "free" function for values that C only ever sees as read-only.
+    ///
+    /// # Safety
+    ///
+    /// * The value must not have been given to C as read-only in order to share it, as the
+    ///   returned pointer may be used to modify or free it.
+    pub unsafe fn as_mut(arg: *const RType) -> *mut RType {
+        arg.cast_mut()
+    }
 }
 
 impl<RType: Sized + Default> Boxed<RType> {
     /// Take a value from C as an argument.
     ///
     /// This function is similar to [`Boxed::take_nonnull`], but returns the default value of RType when
-    /// given NULL.
+    /// given NULL.  Use [`Boxed::try_take`] to handle NULL explicitly.
     ///
     /// # Safety
     ///
-    /// * `arg` must be a value returned from `Box::into_raw` (via [`Boxed::return_val`] or [`Boxed::to_out_param`] or a variant).
+    /// * If not NULL, `arg` must be a value returned from `Box::into_raw` (via [`Boxed::return_val`] or [`Boxed::to_out_param`] or a variant).
     /// * `arg` becomes invalid and must not be used after this call.
     pub unsafe fn take(arg: *mut RType) -> RType {
-        debug_assert!(!arg.is_null());
-        // SAFETY: see docstring
-        unsafe { *(Box::from_raw(arg)) }
+        if arg.is_null() {
+            return RType::default();
+        }
+        // SAFETY: arg is not NULL (just checked), and see docstring
+        unsafe { Self::take_nonnull(arg) }
     }
 
     /// Call the contained function with a shared reference to the value.
     ///
     /// If the given pointer is NULL, the contained function is called with a reference to RType's
-    /// default value, which is subsequently dropped.
+    /// default value, which is subsequently dropped.  Use [`Boxed::with_ref_opt`] to handle NULL
+    /// explicitly.
     ///
     /// # Safety
     ///
@@ -182,16 +578,42 @@ impl<RType: Sized + Default> Boxed<RType> {
             return f(&nullval);
         }
 
+        #[cfg(feature = "quarantine")]
+        crate::quarantine::check(arg);
+        #[cfg(feature = "type-tags")]
+        // SAFETY: arg is not NULL (just checked), and came from Boxed::return_val
+        unsafe {
+            crate::typetag::check(arg)
+        };
+        #[cfg(feature = "access-checks")]
+        let _guard = crate::access::shared(arg);
         // SAFETY:
         // - pointer is not NULL (just checked)
         // - pointer came from Box::into_raw, so has proper size and alignment
-        f(unsafe { &*(arg as *const RType) })
+        f(unsafe { &*arg })
+    }
+
+    /// Call the contained function as for [`Boxed::with_ref`], catching any panic.
+    ///
+    /// The result is an error if a panic occurred, which the caller can convert into an error
+    /// code rather than allowing the panic to unwind into C.
+    ///
+    /// # Safety
+    ///
+    /// * The requirements are the same as for [`Boxed::with_ref`].
+    pub unsafe fn with_ref_catch<T, F: FnOnce(&RType) -> T>(
+        arg: *const RType,
+        f: F,
+    ) -> std::thread::Result<T> {
+        // SAFETY: see docstring
+        catch_panic(|| unsafe { Self::with_ref(arg, f) })
     }
 
     /// Call the contained function with an exclusive reference to the value.
     ///
     /// If the given pointer is NULL, the contained function is called with a reference to RType's
-    /// default value, which is subsequently dropped.
+    /// default value, which is subsequently dropped.  Use [`Boxed::with_ref_mut_opt`] to handle
+    /// NULL explicitly.
     ///
     /// # Safety
     ///
@@ -203,11 +625,94 @@ impl<RType: Sized + Default> Boxed<RType> {
             return f(&mut nullval);
         }
 
+        #[cfg(feature = "quarantine")]
+        crate::quarantine::check(arg);
+        #[cfg(feature = "type-tags")]
+        // SAFETY: arg is not NULL (just checked), and came from Boxed::return_val
+        unsafe {
+            crate::typetag::check(arg)
+        };
+        #[cfg(feature = "access-checks")]
+        let _guard = crate::access::exclusive(arg);
         // SAFETY:
         // - pointer is not NULL (just checked)
         // - pointer came from Box::into_raw, so has proper size and alignment
         f(unsafe { &mut *arg })
     }
+
+    /// Call the contained function as for [`Boxed::with_ref_mut`], catching any panic.
+    ///
+    /// The result is an error if a panic occurred, which the caller can convert into an error
+    /// code rather than allowing the panic to unwind into C.  The value may be left in an
+    /// inconsistent state by a panic.
+    ///
+    /// # Safety
+    ///
+    /// * The requirements are the same as for [`Boxed::with_ref_mut`].
+    pub unsafe fn with_ref_mut_catch<T, F: FnOnce(&mut RType) -> T>(
+        arg: *mut RType,
+        f: F,
+    ) -> std::thread::Result<T> {
+        // SAFETY: see docstring
+        catch_panic(|| unsafe { Self::with_ref_mut(arg, f) })
+    }
+}
+
+impl<RType: Sized + Clone> Boxed<RType> {
+    /// Clone the value pointed to by `arg`, returning a pointer to the copy, or NULL if `arg` is
+    /// NULL.  This implements the typical `foo_clone` function of a C API.
+    ///
+    /// # Safety
+    ///
+    /// * No other thread may mutate the value pointed to by `arg` until this function returns.
+    /// * Ownership of the value remains with the caller.
+    /// * To avoid a leak, the copy must eventually be returned to Rust, as for
+    ///   [`Boxed::return_val`].
+    pub unsafe fn clone_ptr(arg: *const RType) -> *mut RType {
+        if arg.is_null() {
+            return std::ptr::null_mut();
+        }
+        // SAFETY: see docstring
+        unsafe { Self::return_val(Self::with_ref_nonnull(arg, RType::clone)) }
+    }
+}
+
+/// Convert a box into a pointer for C.  With the `type-tags` feature, the allocation is tagged.
+#[cfg(not(feature = "type-tags"))]
+fn into_raw<RType>(rval: Box<RType>) -> *mut RType {
+    Box::into_raw(rval)
+}
+
+/// Take back a pointer returned by [`into_raw`], freeing the allocation.
+///
+/// # Safety
+///
+/// * `arg` must have been returned from [`into_raw`], and not already taken.
+#[cfg(not(feature = "type-tags"))]
+unsafe fn take_raw<RType>(arg: *mut RType) -> RType {
+    // SAFETY: see docstring
+    unsafe { *(Box::from_raw(arg)) }
+}
+
+/// Take back a pointer returned by [`into_raw`], as a box.
+///
+/// # Safety
+///
+/// * `arg` must have been returned from [`into_raw`], and not already taken.
+#[cfg(not(feature = "type-tags"))]
+unsafe fn take_raw_boxed<RType>(arg: *mut RType) -> Box<RType> {
+    // SAFETY: see docstring
+    unsafe { Box::from_raw(arg) }
+}
+
+#[cfg(feature = "type-tags")]
+use crate::typetag::{into_raw, take as take_raw};
+
+/// With type tags, the allocation includes the tag, so the value must be moved into a new box.
+#[cfg(feature = "type-tags")]
+unsafe fn take_raw_boxed<RType>(arg: *mut RType) -> Box<RType> {
+    // SAFETY: same requirements as take_raw
+    Box::new(unsafe { take_raw(arg) })
 }
 
 #[cfg(test)]
@@ -215,7 +720,7 @@ mod test {
     use super::*;
     use std::mem;
 
-    #[derive(Default)]
+    #[derive(Default, Clone)]
     struct RType(u32, u64);
 
     type BoxedTuple = Boxed<RType>;
@@ -296,6 +801,61 @@ mod test {
         }
     }
 
+    #[test]
+    #[cfg_attr(panic = "abort", ignore = "catching a panic requires unwinding")]
+    fn with_ref_catch() {
+        unsafe {
+            let cptr = BoxedTuple::return_val(RType(10, 20));
+            assert_eq!(
+                BoxedTuple::with_ref_nonnull_catch(cptr, |rref| rref.0).unwrap(),
+                10
+            );
+            assert!(BoxedTuple::with_ref_catch(cptr, |_| -> () { panic!("oops") }).is_err());
+            assert!(BoxedTuple::with_ref_mut_catch(cptr, |rref| rref.1 += 1).is_ok());
+            assert!(
+                BoxedTuple::with_ref_mut_nonnull_catch(cptr, |_| -> () { panic!("oops") }).is_err()
+            );
+            assert!(BoxedTuple::with_ref_nonnull_catch(std::ptr::null(), |_| {}).is_err());
+            assert_eq!(BoxedTuple::take(cptr).1, 21);
+        }
+    }
+
+    #[test]
+    fn checked_null() {
+        unsafe {
+            assert!(BoxedTuple::take_nonnull_checked(std::ptr::null_mut()).is_err());
+            assert_eq!(
+                BoxedTuple::with_ref_nonnull_checked(std::ptr::null(), |_| ()),
+                Err(NullPointerError)
+            );
+            assert_eq!(
+                BoxedTuple::with_ref_mut_nonnull_checked(std::ptr::null_mut(), |_| ()),
+                Err(NullPointerError)
+            );
+            assert_eq!(
+                BoxedTuple::to_out_param_nonnull_checked(RType(1, 2), std::ptr::null_mut()),
+                Err(NullPointerError)
+            );
+        }
+    }
+
+    #[test]
+    fn checked() {
+        let mut cptr = std::ptr::null_mut();
+        unsafe {
+            assert_eq!(
+                BoxedTuple::to_out_param_nonnull_checked(RType(1, 2), &mut cptr),
+                Ok(())
+            );
+            assert_eq!(BoxedTuple::with_ref_nonnull_checked(cptr, |r| r.0), Ok(1));
+            assert_eq!(
+                BoxedTuple::with_ref_mut_nonnull_checked(cptr, |r| r.1 += 1),
+                Ok(())
+            );
+            assert_eq!(BoxedTuple::take_nonnull_checked(cptr).unwrap().1, 3);
+        }
+    }
+
     #[test]
     fn to_out_param_null() {
         unsafe {
@@ -313,6 +873,32 @@ mod test {
         }
     }
 
+    #[test]
+    fn to_out_array() {
+        let mut ptrs = [std::ptr::null_mut(); 3];
+        unsafe {
+            let values = (1..=2).map(|i| RType(i, i as u64 * 10));
+            let count = BoxedTuple::to_out_array(values, ptrs.as_mut_ptr(), ptrs.len());
+            assert_eq!(count, 2);
+            assert_eq!(BoxedTuple::take_nonnull(ptrs[0]).0, 1);
+            assert_eq!(BoxedTuple::take_nonnull(ptrs[1]).1, 20);
+            assert!(ptrs[2].is_null());
+
+            // extra values are dropped
+            let count = BoxedTuple::to_out_array([RType(1, 2), RType(3, 4)], ptrs.as_mut_ptr(), 1);
+            assert_eq!(count, 1);
+            assert_eq!(BoxedTuple::take_nonnull(ptrs[0]).0, 1);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn to_out_array_null() {
+        unsafe {
+            BoxedTuple::to_out_array([RType(1, 2)], std::ptr::null_mut(), 1);
+        }
+    }
+
     #[test]
     fn return_val_take() {
         unsafe {
@@ -334,8 +920,145 @@ mod test {
     }
 
     #[test]
-    #[should_panic]
-    fn take_nnull() {
+    fn with_ref2() {
+        unsafe {
+            let a = BoxedTuple::return_val(RType(1, 2));
+            let b = BoxedTuple::return_val(RType(10, 20));
+            assert_eq!(BoxedTuple::with_ref2_nonnull(a, b, |a, b| a.0 + b.0), 11);
+            BoxedTuple::with_ref_mut2_nonnull(a, b, mem::swap);
+            BoxedTuple::with_ref_mut_ref_nonnull(a, b, |a, b| a.1 += b.1);
+            assert_eq!(BoxedTuple::take_nonnull(a).1, 22);
+            assert_eq!(BoxedTuple::take_nonnull(b).0, 1);
+        }
+    }
+
+    #[test]
+    fn with_ref_opt() {
+        unsafe {
+            let cptr = BoxedTuple::return_val(RType(10, 20));
+            assert_eq!(BoxedTuple::with_ref_opt(cptr, |r| r.map(|r| r.0)), Some(10));
+            assert_eq!(
+                BoxedTuple::with_ref_opt(std::ptr::null(), |r| r.map(|r| r.0)),
+                None
+            );
+            BoxedTuple::with_ref_mut_opt(cptr, |r| r.unwrap().1 += 1);
+            assert!(BoxedTuple::with_ref_mut_opt(std::ptr::null_mut(), |r| r.is_none()));
+            assert_eq!(BoxedTuple::take_nonnull(cptr).1, 21);
+        }
+    }
+
+    #[test]
+    fn with_refs() {
+        unsafe {
+            let a = BoxedTuple::return_val(RType(1, 2));
+            let b = BoxedTuple::return_val(RType(10, 20));
+            let arr = [a, b, a];
+            assert_eq!(
+                BoxedTuple::with_refs_nonnull(arr.as_ptr(), arr.len(), |refs| refs
+                    .iter()
+                    .map(|r| r.0)
+                    .sum::<u32>()),
+                12
+            );
+            assert_eq!(
+                BoxedTuple::with_refs_nonnull(std::ptr::null(), 0, |refs| refs.len()),
+                0
+            );
+            let arr = [std::ptr::null_mut(), b];
+            assert_eq!(
+                BoxedTuple::with_refs_opt(arr.as_ptr(), arr.len(), |refs| refs
+                    .iter()
+                    .map(|r| r.map(|r| r.1))
+                    .collect::<Vec<_>>()),
+                vec![None, Some(20)]
+            );
+            BoxedTuple::take_nonnull(a);
+            BoxedTuple::take_nonnull(b);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "NULL value not allowed")]
+    fn with_refs_nonnull_null_element() {
+        unsafe {
+            let arr = [std::ptr::null_mut()];
+            BoxedTuple::with_refs_nonnull(arr.as_ptr(), arr.len(), |_| {});
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "pointers to the same value not allowed")]
+    fn with_ref_mut_ref_same() {
+        unsafe {
+            let a = BoxedTuple::return_val(RType(1, 2));
+            BoxedTuple::with_ref_mut_ref_nonnull(a, a, |_, _| {});
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "NULL value not allowed")]
+    fn with_ref_mut2_null() {
+        unsafe {
+            BoxedTuple::with_ref_mut2_nonnull(
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                |_, _| {},
+            );
+        }
+    }
+
+    #[test]
+    fn try_take() {
+        unsafe {
+            assert!(BoxedTuple::try_take(std::ptr::null_mut()).is_none());
+            assert!(BoxedTuple::try_take_boxed(std::ptr::null_mut()).is_none());
+
+            let cptr = BoxedTuple::return_val(RType(10, 20));
+            assert_eq!(BoxedTuple::try_take(cptr).map(|r| r.0), Some(10));
+
+            let cptr = BoxedTuple::return_val(RType(10, 20));
+            assert_eq!(BoxedTuple::try_take_boxed(cptr).map(|r| r.1), Some(20));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "access-checks")]
+    #[should_panic(expected = "conflicting access")]
+    fn with_ref_mut_while_borrowed() {
+        unsafe {
+            let cptr = BoxedTuple::return_val(RType(10, 20));
+            BoxedTuple::with_ref_nonnull(cptr, |_| {
+                BoxedTuple::with_ref_mut_nonnull(cptr, |_| {});
+            });
+        }
+    }
+
+    #[test]
+    fn as_const_as_mut() {
+        unsafe {
+            let cptr = BoxedTuple::return_val(RType(10, 20));
+            let const_ptr = BoxedTuple::as_const(cptr);
+            assert_eq!(BoxedTuple::with_ref_nonnull(const_ptr, |r| r.0), 10);
+            let rval = BoxedTuple::take_nonnull(BoxedTuple::as_mut(const_ptr));
+            assert_eq!(rval.1, 20);
+        }
+    }
+
+    #[test]
+    fn clone_ptr() {
+        unsafe {
+            let cptr = BoxedTuple::return_val(RType(10, 20));
+            let copy = BoxedTuple::clone_ptr(cptr);
+            assert_ne!(cptr, copy);
+            BoxedTuple::with_ref_mut_nonnull(cptr, |r| r.0 = 11);
+            assert_eq!(BoxedTuple::take_nonnull(copy).0, 10);
+            assert_eq!(BoxedTuple::take_nonnull(cptr).0, 11);
+            assert!(BoxedTuple::clone_ptr(std::ptr::null()).is_null());
+        }
+    }
+
+    #[test]
+    fn take_null() {
         unsafe {
             let rval = BoxedTuple::take(std::ptr::null_mut());
             assert_eq!(rval.0, 0);
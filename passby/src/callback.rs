@@ -0,0 +1,229 @@
+use std::ffi::c_void;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+/// Callback bundles a C function pointer with the `user_data` pointer that C passes along with
+/// it, so that Rust code can call the function like a closure.
+///
+/// `Args` is a tuple of the callback's argument types, and `Ret` is its return type.  The C
+/// function takes the arguments followed by the `user_data` pointer, so a `Callback<(u32,
+/// bool), i32>` wraps a function declared in C as `int (*cb)(uint32_t, bool, void *user_data)`.
+/// Up to six arguments are supported.
+///
+/// A Callback is neither `Send` nor `Sync`, since C code cannot generally be assumed to tolerate
+/// calls from other threads.  Where the C API documents that the callback may be called from any
+/// thread, use [`Callback::into_send`].
+///
+/// # Example
+///
+/// Define a type alias parameterizing Callback, and use it in an exported function:
+///
+/// ```
+/// # use ffizz_passby::Callback;
+/// # use std::ffi::c_void;
+/// type ProgressCallback = Callback<(u32,), bool>;
+///
+/// #[no_mangle]
+/// pub unsafe extern "C" fn mylib_copy(
+///     progress: Option<unsafe extern "C" fn(u32, *mut c_void) -> bool>,
+///     user_data: *mut c_void,
+/// ) {
+///     // SAFETY: progress and user_data remain valid for the duration of the call (documented
+///     // in the API).
+///     let progress = unsafe { ProgressCallback::from_nullable(progress, user_data) };
+///     for pct in [0, 50, 100] {
+///         if let Some(progress) = &progress {
+///             if !progress.call((pct,)) {
+///                 return;
+///             }
+///         }
+///     }
+/// }
+/// ```
+pub struct Callback<Args: CallbackArgs<Ret>, Ret> {
+    func: Args::Func,
+    user_data: *mut c_void,
+}
+
+/// CallbackArgs is implemented for tuples of argument types, defining the type of a C function
+/// taking those arguments and a `user_data` pointer, and returning `Ret`.  It is an
+/// implementation detail of [`Callback`].
+pub trait CallbackArgs<Ret>: Sized {
+    /// The type of the C function pointer.
+    type Func: Copy;
+
+    /// Call the function with these arguments.
+    ///
+    /// # Safety
+    ///
+    /// * `func` and `user_data` must be valid, as described for [`Callback::new`].
+    #[doc(hidden)]
+    unsafe fn invoke(self, func: Self::Func, user_data: *mut c_void) -> Ret;
+}
+
+macro_rules! impl_callback_args {
+    ($($arg:ident),*) => {
+        impl<Ret, $($arg),*> CallbackArgs<Ret> for ($($arg,)*) {
+            type Func = unsafe extern "C" fn($($arg,)* *mut c_void) -> Ret;
+
+            #[allow(non_snake_case)]
+            unsafe fn invoke(self, func: Self::Func, user_data: *mut c_void) -> Ret {
+                let ($($arg,)*) = self;
+                // SAFETY: see docstring
+                unsafe { func($($arg,)* user_data) }
+            }
+        }
+    };
+}
+
+impl_callback_args!();
+impl_callback_args!(A1);
+impl_callback_args!(A1, A2);
+impl_callback_args!(A1, A2, A3);
+impl_callback_args!(A1, A2, A3, A4);
+impl_callback_args!(A1, A2, A3, A4, A5);
+impl_callback_args!(A1, A2, A3, A4, A5, A6);
+
+impl<Args: CallbackArgs<Ret>, Ret> Callback<Args, Ret> {
+    /// Create a new Callback from a function pointer and `user_data` supplied by C.
+    ///
+    /// # Safety
+    ///
+    /// * `func` must be safe to call with any arguments of the given types and `user_data`, for
+    ///   as long as the Callback exists.  C APIs typically document that the caller must keep
+    ///   `user_data` valid until the callback is no longer needed.
+    pub unsafe fn new(func: Args::Func, user_data: *mut c_void) -> Self {
+        Callback { func, user_data }
+    }
+
+    /// Create a new Callback from a possibly-NULL function pointer, returning None if it is NULL.
+    ///
+    /// # Safety
+    ///
+    /// * If not NULL, `func` must meet the requirements of [`Callback::new`].
+    pub unsafe fn from_nullable(func: Option<Args::Func>, user_data: *mut c_void) -> Option<Self> {
+        // SAFETY: see docstring
+        func.map(|func| unsafe { Self::new(func, user_data) })
+    }
+
+    /// Call the C function with the given arguments, followed by `user_data`.
+    ///
+    /// The call is guarded with `catch_unwind`: if it unwinds, for example because the C function
+    /// called back into Rust code that panicked, the process is aborted rather than continuing
+    /// with C code in an unknown state.
+    pub fn call(&self, args: Args) -> Ret {
+        // SAFETY: func and user_data are valid (see `new`)
+        let result = catch_unwind(AssertUnwindSafe(|| unsafe {
+            args.invoke(self.func, self.user_data)
+        }));
+        match result {
+            Ok(ret) => ret,
+            Err(_) => {
+                eprintln!("panic while invoking a C callback; aborting");
+                std::process::abort();
+            }
+        }
+    }
+
+    /// Convert this Callback into a closure.  This is useful for passing the callback to Rust
+    /// APIs that take an `Fn`, since Callback cannot implement the `Fn` traits directly in stable
+    /// Rust.
+    pub fn into_fn(self) -> impl Fn(Args) -> Ret {
+        move |args| self.call(args)
+    }
+
+    /// Assert that this Callback may be sent to and called from other threads.
+    ///
+    /// # Safety
+    ///
+    /// * The C API must document that the callback may be called from any thread, and the
+    ///   function and `user_data` must support that.
+    pub unsafe fn into_send(self) -> SendCallback<Args, Ret> {
+        SendCallback(self)
+    }
+}
+
+/// SendCallback is a [`Callback`] that may be sent to other threads, created with
+/// [`Callback::into_send`].
+pub struct SendCallback<Args: CallbackArgs<Ret>, Ret>(Callback<Args, Ret>);
+
+// SAFETY: the creator of the SendCallback has asserted that the callback may be called from any
+// thread.
+unsafe impl<Args: CallbackArgs<Ret>, Ret> Send for SendCallback<Args, Ret> {}
+
+impl<Args: CallbackArgs<Ret>, Ret> SendCallback<Args, Ret> {
+    /// Call the C function, as for [`Callback::call`].
+    pub fn call(&self, args: Args) -> Ret {
+        self.0.call(args)
+    }
+
+    /// Convert this SendCallback into a closure that is `Send`, as for [`Callback::into_fn`].
+    pub fn into_fn(self) -> impl Fn(Args) -> Ret + Send {
+        move |args| self.call(args)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    unsafe extern "C" fn add(a: u32, b: u32, user_data: *mut c_void) -> u32 {
+        let total = unsafe { &mut *(user_data as *mut u32) };
+        *total += a + b;
+        *total
+    }
+
+    unsafe extern "C" fn nothing(user_data: *mut c_void) {
+        let called = unsafe { &mut *(user_data as *mut bool) };
+        *called = true;
+    }
+
+    type AddCallback = Callback<(u32, u32), u32>;
+
+    #[test]
+    fn call() {
+        let mut total = 0u32;
+        let cb = unsafe { AddCallback::new(add, &mut total as *mut u32 as *mut c_void) };
+        assert_eq!(cb.call((1, 2)), 3);
+        assert_eq!(cb.call((3, 4)), 10);
+        assert_eq!(total, 10);
+    }
+
+    #[test]
+    fn no_args() {
+        let mut called = false;
+        let cb =
+            unsafe { Callback::<(), ()>::new(nothing, &mut called as *mut bool as *mut c_void) };
+        cb.call(());
+        assert!(called);
+    }
+
+    #[test]
+    fn from_nullable() {
+        let cb = unsafe { AddCallback::from_nullable(None, std::ptr::null_mut()) };
+        assert!(cb.is_none());
+        let mut total = 0u32;
+        let cb =
+            unsafe { AddCallback::from_nullable(Some(add), &mut total as *mut u32 as *mut c_void) };
+        assert_eq!(cb.unwrap().call((2, 2)), 4);
+    }
+
+    #[test]
+    fn into_fn() {
+        let mut total = 0u32;
+        let cb = unsafe { AddCallback::new(add, &mut total as *mut u32 as *mut c_void) };
+        let f = cb.into_fn();
+        let results: Vec<u32> = [(1, 1), (2, 2)].into_iter().map(&f).collect();
+        assert_eq!(results, vec![2, 6]);
+    }
+
+    #[test]
+    #[cfg_attr(target_family = "wasm", ignore = "threads are not available")]
+    fn send() {
+        let total = Box::into_raw(Box::new(0u32));
+        let cb = unsafe { AddCallback::new(add, total as *mut c_void).into_send() };
+        let f = cb.into_fn();
+        let result = std::thread::spawn(move || f((5, 6))).join().unwrap();
+        assert_eq!(result, 11);
+        drop(unsafe { Box::from_raw(total) });
+    }
+}
@@ -0,0 +1,16 @@
+use std::fmt;
+
+pub use ffizz_macros::CEnum;
+
+/// InvalidEnumValue is the error returned from the `try_from_c` function generated by
+/// [`derive(CEnum)`](CEnum) when a C value does not correspond to any variant of the enum.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidEnumValue(pub u32);
+
+impl fmt::Display for InvalidEnumValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid enum value {}", self.0)
+    }
+}
+
+impl std::error::Error for InvalidEnumValue {}
@@ -0,0 +1,106 @@
+//! Tracking of live allocations, for finding leaks during integration testing.
+//!
+//! With the `track-allocations` feature enabled, [`Boxed`](crate::Boxed) records each pointer it
+//! returns to C, and forgets it again when the pointer is passed back to be taken.  The pointers
+//! that remain are those C has not yet freed.
+//!
+//! ```
+//! // ..after running the integration tests:
+//! for obj in ffizz_passby::debug::live_objects() {
+//!     eprintln!("leaked {} at {:#x}", obj.type_name, obj.addr);
+//! }
+//! ```
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+/// A value that has been returned to C and not yet taken back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LiveObject {
+    /// The address of the value.
+    pub addr: usize,
+    /// The name of the value's Rust type.
+    pub type_name: &'static str,
+}
+
+static LIVE: Mutex<BTreeMap<usize, &'static str>> = Mutex::new(BTreeMap::new());
+
+/// Record that a value of type T at the given address has been returned to C.
+pub(crate) fn record<T>(ptr: *const T) {
+    LIVE.lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(ptr as usize, std::any::type_name::<T>());
+}
+
+/// Record that the value at the given address has been taken back from C.
+pub(crate) fn forget<T>(ptr: *const T) {
+    LIVE.lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .remove(&(ptr as usize));
+}
+
+/// Get the values that have been returned to C and not yet taken back, in order of address.
+pub fn live_objects() -> Vec<LiveObject> {
+    LIVE.lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .map(|(addr, type_name)| LiveObject {
+            addr: *addr,
+            type_name,
+        })
+        .collect()
+}
+
+/// Generate a report of the live objects, suitable for printing at the end of a test run, or
+/// None if there are none.
+pub fn leak_report() -> Option<String> {
+    let live = live_objects();
+    if live.is_empty() {
+        return None;
+    }
+    let mut report = format!("{} object(s) returned to C were never freed:", live.len());
+    for obj in live {
+        report.push_str(&format!("\n  {} at {:#x}", obj.type_name, obj.addr));
+    }
+    Some(report)
+}
+
+/// Print the [`leak_report`] to stderr when the process exits, if there are live objects.
+pub fn report_at_exit() {
+    extern "C" fn report() {
+        if let Some(report) = leak_report() {
+            eprintln!("{report}");
+        }
+    }
+    extern "C" {
+        fn atexit(cb: extern "C" fn()) -> std::ffi::c_int;
+    }
+    // SAFETY: atexit is provided by the C standard library, and `report` is a valid function
+    unsafe { atexit(report) };
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Boxed;
+
+    struct Tracked(#[allow(dead_code)] u32);
+
+    #[test]
+    fn track_boxed() {
+        let type_name = std::any::type_name::<Tracked>();
+        let is_tracked = |ptr: *mut Tracked| {
+            live_objects().contains(&LiveObject {
+                addr: ptr as usize,
+                type_name,
+            })
+        };
+        unsafe {
+            let ptr = Boxed::return_val(Tracked(1));
+            assert!(is_tracked(ptr));
+            assert!(leak_report().unwrap().contains(type_name));
+            Boxed::take_nonnull(ptr);
+            assert!(!is_tracked(ptr));
+        }
+    }
+}
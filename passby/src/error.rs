@@ -0,0 +1,15 @@
+use std::fmt;
+
+/// NullPointerError is the error returned from the `_checked` variants of methods such as
+/// [`Boxed::with_ref_nonnull_checked`](crate::Boxed::with_ref_nonnull_checked) when given a NULL
+/// pointer, allowing the caller to return an error code to C instead of panicking.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NullPointerError;
+
+impl fmt::Display for NullPointerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "NULL value not allowed")
+    }
+}
+
+impl std::error::Error for NullPointerError {}
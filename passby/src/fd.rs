@@ -0,0 +1,251 @@
+//! Passing file descriptors and Windows handles, with the same ownership semantics as the rest of
+//! this crate.
+
+#[cfg(any(unix, target_os = "wasi"))]
+pub use self::unix::*;
+#[cfg(windows)]
+pub use self::windows::*;
+
+// WASI uses the same file descriptor types as Unix
+#[cfg(any(unix, target_os = "wasi"))]
+mod unix {
+    use std::os::fd::{BorrowedFd, FromRawFd, IntoRawFd, OwnedFd, RawFd};
+
+    /// Fd is used to pass file descriptors, such as files and sockets, to and from C as an `int`.
+    ///
+    /// Ownership of the descriptor is explicit in each method, as for [`crate::Boxed`]: the
+    /// `take` methods take ownership from C, so that the descriptor is closed when the `OwnedFd`
+    /// is dropped, while [`Fd::with_ref_valid`] borrows a descriptor that remains owned by C.  As
+    /// is usual in C, a negative value represents no descriptor.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use ffizz_passby::Fd;
+    /// # use std::io::Write;
+    /// # use std::os::fd::RawFd;
+    /// #[no_mangle]
+    /// pub unsafe extern "C" fn mylib_write_greeting(fd: RawFd) -> bool {
+    ///     // SAFETY: fd is open and remains so for this call (documented in API)
+    ///     unsafe {
+    ///         Fd::with_ref_valid(fd, |fd| {
+    ///             let Ok(fd) = fd.try_clone_to_owned() else {
+    ///                 return false;
+    ///             };
+    ///             std::fs::File::from(fd).write_all(b"hello\n").is_ok()
+    ///         })
+    ///     }
+    /// }
+    /// ```
+    #[non_exhaustive]
+    pub struct Fd;
+
+    impl Fd {
+        /// Take ownership of a file descriptor from C, or None if it is negative.
+        ///
+        /// # Safety
+        ///
+        /// * If not negative, `fd` must be an open file descriptor, owned by the caller.
+        /// * `fd` must not be used by C after this call.
+        pub unsafe fn take(fd: RawFd) -> Option<OwnedFd> {
+            if fd < 0 {
+                return None;
+            }
+            // SAFETY: see docstring
+            Some(unsafe { OwnedFd::from_raw_fd(fd) })
+        }
+
+        /// Take ownership of a file descriptor from C, panicking if it is negative.
+        ///
+        /// # Safety
+        ///
+        /// * `fd` must be an open file descriptor, owned by the caller.
+        /// * `fd` must not be used by C after this call.
+        pub unsafe fn take_valid(fd: RawFd) -> OwnedFd {
+            if fd < 0 {
+                panic!("invalid file descriptor");
+            }
+            // SAFETY: see docstring
+            unsafe { OwnedFd::from_raw_fd(fd) }
+        }
+
+        /// Call the contained function with a borrowed file descriptor, panicking if it is
+        /// negative.  Ownership remains with C.
+        ///
+        /// # Safety
+        ///
+        /// * `fd` must be an open file descriptor, and must remain open until this function
+        ///   returns.
+        pub unsafe fn with_ref_valid<T, F: FnOnce(BorrowedFd<'_>) -> T>(fd: RawFd, f: F) -> T {
+            if fd < 0 {
+                panic!("invalid file descriptor");
+            }
+            // SAFETY: see docstring
+            f(unsafe { BorrowedFd::borrow_raw(fd) })
+        }
+
+        /// Return a file descriptor to C, transferring ownership.
+        pub fn return_val(fd: OwnedFd) -> RawFd {
+            fd.into_raw_fd()
+        }
+
+        /// Return an optional file descriptor to C, transferring ownership, with `-1` for None.
+        pub fn return_option(fd: Option<OwnedFd>) -> RawFd {
+            fd.map_or(-1, Self::return_val)
+        }
+
+        /// Return a file descriptor to C, transferring ownership, via an "output parameter".
+        ///
+        /// If the pointer is NULL, the descriptor is closed.
+        ///
+        /// # Safety
+        ///
+        /// * If not NULL, `arg_out` must point to valid, properly aligned memory for an `int`.
+        pub unsafe fn to_out_param(fd: OwnedFd, arg_out: *mut RawFd) {
+            if !arg_out.is_null() {
+                // SAFETY: see docstring
+                unsafe { *arg_out = Self::return_val(fd) };
+            }
+        }
+    }
+
+    #[cfg(all(test, unix))]
+    mod test {
+        use super::*;
+        use std::os::fd::AsRawFd;
+
+        fn open() -> OwnedFd {
+            std::fs::File::open("/dev/null").unwrap().into()
+        }
+
+        #[test]
+        fn return_and_take() {
+            let raw = Fd::return_val(open());
+            assert!(raw >= 0);
+            let fd = unsafe { Fd::take_valid(raw) };
+            assert_eq!(fd.as_raw_fd(), raw);
+        }
+
+        #[test]
+        fn option() {
+            assert_eq!(Fd::return_option(None), -1);
+            assert!(unsafe { Fd::take(-1) }.is_none());
+            let raw = Fd::return_option(Some(open()));
+            assert!(unsafe { Fd::take(raw) }.is_some());
+        }
+
+        #[test]
+        fn with_ref_valid() {
+            let fd = open();
+            let raw = unsafe { Fd::with_ref_valid(fd.as_raw_fd(), |b| b.as_raw_fd()) };
+            assert_eq!(raw, fd.as_raw_fd());
+        }
+
+        #[test]
+        fn to_out_param() {
+            let mut raw = -1;
+            unsafe {
+                Fd::to_out_param(open(), &mut raw);
+                Fd::to_out_param(open(), std::ptr::null_mut());
+                drop(Fd::take_valid(raw));
+            }
+        }
+
+        #[test]
+        #[should_panic]
+        fn take_valid_negative() {
+            unsafe { Fd::take_valid(-1) };
+        }
+    }
+}
+
+#[cfg(windows)]
+mod windows {
+    use std::os::windows::io::{
+        BorrowedHandle, FromRawHandle, IntoRawHandle, OwnedHandle, RawHandle,
+    };
+
+    /// Determine whether a handle is NULL or `INVALID_HANDLE_VALUE`, both of which are used to
+    /// represent no handle.
+    fn is_invalid(handle: RawHandle) -> bool {
+        handle.is_null() || handle as isize == -1
+    }
+
+    /// WinHandle is used to pass Windows handles to and from C as a `HANDLE`.
+    ///
+    /// This is the Windows equivalent of `Fd`, with the same ownership semantics.  Both NULL and
+    /// `INVALID_HANDLE_VALUE` represent no handle.
+    #[non_exhaustive]
+    pub struct WinHandle;
+
+    impl WinHandle {
+        /// Take ownership of a handle from C, or None if it is invalid.
+        ///
+        /// # Safety
+        ///
+        /// * If valid, `handle` must be an open handle, owned by the caller.
+        /// * `handle` must not be used by C after this call.
+        pub unsafe fn take(handle: RawHandle) -> Option<OwnedHandle> {
+            if is_invalid(handle) {
+                return None;
+            }
+            // SAFETY: see docstring
+            Some(unsafe { OwnedHandle::from_raw_handle(handle) })
+        }
+
+        /// Take ownership of a handle from C, panicking if it is invalid.
+        ///
+        /// # Safety
+        ///
+        /// * `handle` must be an open handle, owned by the caller.
+        /// * `handle` must not be used by C after this call.
+        pub unsafe fn take_valid(handle: RawHandle) -> OwnedHandle {
+            if is_invalid(handle) {
+                panic!("invalid handle");
+            }
+            // SAFETY: see docstring
+            unsafe { OwnedHandle::from_raw_handle(handle) }
+        }
+
+        /// Call the contained function with a borrowed handle, panicking if it is invalid.
+        /// Ownership remains with C.
+        ///
+        /// # Safety
+        ///
+        /// * `handle` must be an open handle, and must remain open until this function returns.
+        pub unsafe fn with_ref_valid<T, F: FnOnce(BorrowedHandle<'_>) -> T>(
+            handle: RawHandle,
+            f: F,
+        ) -> T {
+            if is_invalid(handle) {
+                panic!("invalid handle");
+            }
+            // SAFETY: see docstring
+            f(unsafe { BorrowedHandle::borrow_raw(handle) })
+        }
+
+        /// Return a handle to C, transferring ownership.
+        pub fn return_val(handle: OwnedHandle) -> RawHandle {
+            handle.into_raw_handle()
+        }
+
+        /// Return an optional handle to C, transferring ownership, with NULL for None.
+        pub fn return_option(handle: Option<OwnedHandle>) -> RawHandle {
+            handle.map_or(std::ptr::null_mut(), Self::return_val)
+        }
+
+        /// Return a handle to C, transferring ownership, via an "output parameter".
+        ///
+        /// If the pointer is NULL, the handle is closed.
+        ///
+        /// # Safety
+        ///
+        /// * If not NULL, `arg_out` must point to valid, properly aligned memory for a `HANDLE`.
+        pub unsafe fn to_out_param(handle: OwnedHandle, arg_out: *mut RawHandle) {
+            if !arg_out.is_null() {
+                // SAFETY: see docstring
+                unsafe { *arg_out = Self::return_val(handle) };
+            }
+        }
+    }
+}
@@ -0,0 +1,243 @@
+use crate::NullPointerError;
+use std::fmt;
+use std::marker::PhantomData;
+
+/// CFlags is implemented by Rust flags types that can be passed to C as a `uint32_t` mask, for
+/// use with [`Flags`].
+///
+/// With the `bitflags` feature enabled, this is implemented for every type defined with the
+/// [bitflags](https://docs.rs/bitflags) crate's `bitflags!` macro with `u32` bits.
+pub trait CFlags: Sized {
+    /// Get the bits of this value.
+    fn bits(&self) -> u32;
+
+    /// Create a value from bits, including any unknown bits.
+    fn from_bits_retain(bits: u32) -> Self;
+
+    /// Get the names and values of the defined flags.
+    fn named_flags() -> Vec<(&'static str, u32)>;
+
+    /// Get the bits that correspond to defined flags.
+    fn known_bits() -> u32 {
+        Self::named_flags()
+            .iter()
+            .fold(0, |bits, (_, value)| bits | value)
+    }
+}
+
+#[cfg(feature = "bitflags")]
+impl<B: bitflags::Flags<Bits = u32>> CFlags for B {
+    fn bits(&self) -> u32 {
+        bitflags::Flags::bits(self)
+    }
+
+    fn from_bits_retain(bits: u32) -> Self {
+        bitflags::Flags::from_bits_retain(bits)
+    }
+
+    fn named_flags() -> Vec<(&'static str, u32)> {
+        B::FLAGS
+            .iter()
+            .filter(|flag| flag.is_named())
+            .map(|flag| (flag.name(), flag.value().bits()))
+            .collect()
+    }
+
+    fn known_bits() -> u32 {
+        B::all().bits()
+    }
+}
+
+/// InvalidFlags is the error returned from [`Flags::try_take`] when a C value contains bits that
+/// do not correspond to any flag.  It contains the unknown bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidFlags(pub u32);
+
+impl fmt::Display for InvalidFlags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown flags {:#x}", self.0)
+    }
+}
+
+impl std::error::Error for InvalidFlags {}
+
+/// Flags is used to pass a flags type to and from C as a `uint32_t` mask.
+///
+/// C values may contain bits that do not correspond to any defined flag, such as when a C
+/// program was compiled against a newer version of the library.  Choose a policy for such bits
+/// by using either [`Flags::try_take`], which rejects them, or [`Flags::take_truncate`], which
+/// ignores them.
+///
+/// # Example
+///
+/// ```
+/// # use ffizz_passby::{CFlags, Flags};
+/// #[derive(Debug, PartialEq)]
+/// pub struct OpenFlags(u32);
+///
+/// impl CFlags for OpenFlags {
+///     fn bits(&self) -> u32 { self.0 }
+///     fn from_bits_retain(bits: u32) -> Self { OpenFlags(bits) }
+///     fn named_flags() -> Vec<(&'static str, u32)> { vec![("READ", 1), ("WRITE", 2)] }
+/// }
+///
+/// type OpenFlagsValue = Flags<OpenFlags>;
+///
+/// #[no_mangle]
+/// pub extern "C" fn mylib_open(flags: u32) -> bool {
+///     let Ok(flags) = OpenFlagsValue::try_take(flags) else {
+///         return false;
+///     };
+///     // ...
+///     true
+/// }
+///
+/// assert_eq!(
+///     OpenFlagsValue::c_defines("MYLIB_OPEN_"),
+///     "#define MYLIB_OPEN_READ 0x1u\n#define MYLIB_OPEN_WRITE 0x2u\n",
+/// );
+/// ```
+#[non_exhaustive]
+pub struct Flags<B: CFlags> {
+    _phantom: PhantomData<B>,
+}
+
+impl<B: CFlags> Flags<B> {
+    /// Take a C value, failing if it contains unknown bits.
+    pub fn try_take(cval: u32) -> Result<B, InvalidFlags> {
+        match cval & !B::known_bits() {
+            0 => Ok(B::from_bits_retain(cval)),
+            unknown => Err(InvalidFlags(unknown)),
+        }
+    }
+
+    /// Take a C value, ignoring any unknown bits.
+    pub fn take_truncate(cval: u32) -> B {
+        B::from_bits_retain(cval & B::known_bits())
+    }
+
+    /// Return a value to C.
+    pub fn return_val(rval: B) -> u32 {
+        rval.bits()
+    }
+
+    /// Return a value to C via an "output parameter".
+    ///
+    /// If the pointer is NULL, nothing happens.
+    ///
+    /// # Safety
+    ///
+    /// * If not NULL, `arg_out` must be aligned for and have enough space for a u32.
+    pub unsafe fn to_out_param(rval: B, arg_out: *mut u32) {
+        if !arg_out.is_null() {
+            // SAFETY: see docstring
+            unsafe { *arg_out = rval.bits() };
+        }
+    }
+
+    /// Return a value to C via an "output parameter", returning an error if the pointer is
+    /// NULL.
+    ///
+    /// # Safety
+    ///
+    /// * If not NULL, `arg_out` must be aligned for and have enough space for a u32.
+    pub unsafe fn to_out_param_nonnull_checked(
+        rval: B,
+        arg_out: *mut u32,
+    ) -> Result<(), NullPointerError> {
+        if arg_out.is_null() {
+            return Err(NullPointerError);
+        }
+        // SAFETY: see docstring
+        unsafe { *arg_out = rval.bits() };
+        Ok(())
+    }
+
+    /// Generate C `#define` directives for the defined flags, with names formed by appending the
+    /// flag names to `prefix`.
+    ///
+    /// To include these in a header generated with `ffizz_header`, use a placeholder in a header
+    /// item and supply this result as its value with `HeaderConfig::var`.
+    pub fn c_defines(prefix: &str) -> String {
+        B::named_flags()
+            .iter()
+            .map(|(name, value)| format!("#define {prefix}{name} {value:#x}u\n"))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Perms(u32);
+
+    impl CFlags for Perms {
+        fn bits(&self) -> u32 {
+            self.0
+        }
+
+        fn from_bits_retain(bits: u32) -> Self {
+            Perms(bits)
+        }
+
+        fn named_flags() -> Vec<(&'static str, u32)> {
+            vec![("READ", 0x4), ("WRITE", 0x2), ("EXEC", 0x1)]
+        }
+    }
+
+    type PermsValue = Flags<Perms>;
+
+    #[test]
+    fn take() {
+        assert_eq!(PermsValue::try_take(0x6), Ok(Perms(0x6)));
+        assert_eq!(PermsValue::try_take(0x16), Err(InvalidFlags(0x10)));
+        assert_eq!(PermsValue::take_truncate(0x16), Perms(0x6));
+    }
+
+    #[test]
+    fn return_val() {
+        assert_eq!(PermsValue::return_val(Perms(0x5)), 0x5);
+        let mut cval = 0;
+        unsafe {
+            PermsValue::to_out_param(Perms(0x3), &mut cval);
+            PermsValue::to_out_param(Perms(0x3), std::ptr::null_mut());
+            assert_eq!(
+                PermsValue::to_out_param_nonnull_checked(Perms(0x3), std::ptr::null_mut()),
+                Err(NullPointerError)
+            );
+        }
+        assert_eq!(cval, 0x3);
+    }
+
+    #[test]
+    fn c_defines() {
+        assert_eq!(
+            PermsValue::c_defines("P_"),
+            "#define P_READ 0x4u\n#define P_WRITE 0x2u\n#define P_EXEC 0x1u\n"
+        );
+    }
+
+    #[cfg(feature = "bitflags")]
+    #[test]
+    fn bitflags() {
+        bitflags::bitflags! {
+            #[derive(Debug, PartialEq)]
+            struct Mode: u32 {
+                const APPEND = 0x1;
+                const CREATE = 0x8;
+            }
+        }
+
+        type ModeValue = Flags<Mode>;
+        assert_eq!(ModeValue::try_take(0x9), Ok(Mode::APPEND | Mode::CREATE));
+        assert_eq!(ModeValue::try_take(0x3), Err(InvalidFlags(0x2)));
+        assert_eq!(ModeValue::take_truncate(0x3), Mode::APPEND);
+        assert_eq!(ModeValue::return_val(Mode::CREATE), 0x8);
+        assert_eq!(
+            ModeValue::c_defines("M_"),
+            "#define M_APPEND 0x1u\n#define M_CREATE 0x8u\n"
+        );
+    }
+}
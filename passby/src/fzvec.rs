@@ -0,0 +1,221 @@
+use crate::util::vec_into_raw_parts;
+
+/// FzVec is a `#[repr(C)]` struct holding the pointer, length, and capacity of a `Vec<T>`, used
+/// to return lists of values to C.  The elements must be of a type C can use, usually a
+/// `#[repr(C)]` struct or a pointer.
+///
+/// The memory allocation is managed by Rust: C may read the elements, and modify them in place,
+/// but must not change the fields of the struct, and must pass the struct back to Rust to be
+/// freed.  A zeroed struct, with a NULL pointer, is treated as an empty vector.
+///
+/// In C, declare a struct with the same layout for each element type:
+///
+/// ```c
+/// typedef struct point_list_t {
+///     point_t *ptr;
+///     size_t len;
+///     size_t _capacity;
+/// } point_list_t;
+/// ```
+///
+/// # Example
+///
+/// ```
+/// # use ffizz_passby::FzVec;
+/// #[repr(C)]
+/// pub struct point_t {
+///     x: i32,
+///     y: i32,
+/// }
+///
+/// #[no_mangle]
+/// pub extern "C" fn mylib_corners() -> FzVec<point_t> {
+///     FzVec::return_val(vec![point_t { x: 0, y: 0 }, point_t { x: 1, y: 1 }])
+/// }
+///
+/// #[no_mangle]
+/// pub unsafe extern "C" fn mylib_point_list_free(list: *mut FzVec<point_t>) {
+///     // SAFETY: list is a valid point_list_t, and will not be used again (documented in API)
+///     unsafe { FzVec::free(list) };
+/// }
+/// ```
+#[repr(C)]
+#[derive(Debug)]
+pub struct FzVec<T: Sized> {
+    ptr: *mut T,
+    len: usize,
+    cap: usize,
+}
+
+impl<T: Sized> FzVec<T> {
+    /// Return a vector to C, transferring ownership.
+    ///
+    /// The caller must ensure that the value is eventually freed, or it will leak.
+    pub fn return_val(rval: Vec<T>) -> Self {
+        let (ptr, len, cap) = vec_into_raw_parts(rval);
+        FzVec { ptr, len, cap }
+    }
+
+    /// Return a vector to C, transferring ownership, via an "output parameter".
+    ///
+    /// If the pointer is NULL, the vector is dropped.
+    ///
+    /// # Safety
+    ///
+    /// * The caller must ensure that the value is eventually freed.
+    /// * If not NULL, `arg_out` must be aligned for and have enough space for an FzVec.
+    pub unsafe fn to_out_param(rval: Vec<T>, arg_out: *mut Self) {
+        if !arg_out.is_null() {
+            // SAFETY: see docstring
+            unsafe { arg_out.write(Self::return_val(rval)) };
+        }
+    }
+
+    /// Take an FzVec from C as an argument, taking ownership of the vector.
+    ///
+    /// # Safety
+    ///
+    /// * `cval` must have been returned from [`FzVec::return_val`] or
+    ///   [`FzVec::to_out_param`], or be zeroed.
+    /// * The elements must not be used after this call.
+    pub unsafe fn take(cval: Self) -> Vec<T> {
+        if cval.ptr.is_null() {
+            return Vec::new();
+        }
+        // SAFETY: the parts came from a Vec<T> (see docstring)
+        unsafe { Vec::from_raw_parts(cval.ptr, cval.len, cval.cap) }
+    }
+
+    /// Take the vector from an FzVec pointed to by `cptr`, leaving an empty vector in its place.
+    ///
+    /// # Safety
+    ///
+    /// * `cptr` must not be NULL and must point to a valid FzVec, as for [`FzVec::take`].
+    /// * No other thread may _access_ the value pointed to by `cptr` until this function returns.
+    pub unsafe fn take_ptr(cptr: *mut Self) -> Vec<T> {
+        if cptr.is_null() {
+            panic!("NULL value not allowed");
+        }
+        // SAFETY: cptr is valid (see docstring), and is left containing an empty vector
+        unsafe { Self::take(cptr.replace(Self::return_val(Vec::new()))) }
+    }
+
+    /// Free the vector in the FzVec pointed to by `cptr`, leaving an empty vector in its place.
+    /// If the pointer is NULL, nothing happens.
+    ///
+    /// # Safety
+    ///
+    /// * If not NULL, `cptr` must point to a valid FzVec, as for [`FzVec::take`].
+    /// * No other thread may _access_ the value pointed to by `cptr` until this function returns.
+    pub unsafe fn free(cptr: *mut Self) {
+        if !cptr.is_null() {
+            // SAFETY: see docstring
+            drop(unsafe { Self::take_ptr(cptr) });
+        }
+    }
+
+    /// Call the contained function with a shared reference to the elements.
+    ///
+    /// # Safety
+    ///
+    /// * `cptr` must not be NULL and must point to a valid FzVec, as for [`FzVec::take`].
+    /// * No other thread may mutate the FzVec or its elements until this function returns.
+    /// * Ownership of the vector remains with the caller.
+    pub unsafe fn with_ref<R, F: FnOnce(&[T]) -> R>(cptr: *const Self, f: F) -> R {
+        if cptr.is_null() {
+            panic!("NULL value not allowed");
+        }
+        // SAFETY: cptr is valid (see docstring)
+        let cval = unsafe { &*cptr };
+        if cval.ptr.is_null() {
+            return f(&[]);
+        }
+        // SAFETY: the parts came from a Vec<T>, which remains valid during the call
+        f(unsafe { std::slice::from_raw_parts(cval.ptr, cval.len) })
+    }
+
+    /// Get a pointer to the element at `index`, or NULL if the index is out of range.
+    ///
+    /// # Safety
+    ///
+    /// * `cptr` must not be NULL and must point to a valid FzVec, as for [`FzVec::take`].
+    /// * The resulting pointer is valid only until the vector is freed.
+    pub unsafe fn get(cptr: *const Self, index: usize) -> *const T {
+        // SAFETY: see docstring
+        unsafe {
+            Self::with_ref(cptr, |elts| {
+                elts.get(index)
+                    .map_or(std::ptr::null(), |elt| elt as *const T)
+            })
+        }
+    }
+
+    /// Get the number of elements.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Determine whether there are no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[repr(C)]
+    #[derive(Debug, Clone, PartialEq)]
+    struct Point(i32, i32);
+
+    #[test]
+    fn return_val_take() {
+        let cval = FzVec::return_val(vec![Point(1, 2), Point(3, 4)]);
+        assert_eq!(cval.len(), 2);
+        let rval = unsafe { FzVec::take(cval) };
+        assert_eq!(rval, vec![Point(1, 2), Point(3, 4)]);
+    }
+
+    #[test]
+    fn accessors() {
+        let mut cval = FzVec::return_val(vec![Point(1, 2), Point(3, 4)]);
+        unsafe {
+            assert_eq!(*FzVec::get(&cval, 1), Point(3, 4));
+            assert!(FzVec::get(&cval, 2).is_null());
+            assert_eq!(FzVec::with_ref(&cval, |elts| elts.len()), 2);
+            FzVec::free(&mut cval);
+        }
+        assert!(cval.is_empty());
+        // freeing again is harmless
+        unsafe { FzVec::free(&mut cval) };
+    }
+
+    #[test]
+    fn to_out_param_take_ptr() {
+        let mut cval = std::mem::MaybeUninit::uninit();
+        unsafe {
+            FzVec::to_out_param(vec![1u32, 2, 3], cval.as_mut_ptr());
+            let mut cval = cval.assume_init();
+            assert_eq!(FzVec::take_ptr(&mut cval), vec![1, 2, 3]);
+            assert!(cval.is_empty());
+            assert!(FzVec::take(cval).is_empty());
+
+            FzVec::to_out_param(vec![1u32], std::ptr::null_mut());
+            // nothing happens
+        }
+    }
+
+    #[test]
+    fn zeroed() {
+        let cval: FzVec<u32> = FzVec {
+            ptr: std::ptr::null_mut(),
+            len: 0,
+            cap: 0,
+        };
+        unsafe {
+            assert!(FzVec::get(&cval, 0).is_null());
+            assert!(FzVec::take(cval).is_empty());
+        }
+    }
+}
@@ -0,0 +1,203 @@
+use std::fmt;
+use std::sync::{Mutex, MutexGuard};
+
+/// HandleTable stores values on behalf of C, identifying each by an integer handle rather than a
+/// pointer.  This suits bindings such as JNI or Lua which prefer integers, and turns the use of a
+/// freed or invalid handle into an error instead of undefined behavior.
+///
+/// Handles are nonzero, so zero can be used as a "NULL" handle.  Each handle contains a
+/// generation counter, so a handle is not valid for a different value that later occupies the
+/// same slot in the table.
+///
+/// The table is protected by a mutex, which is held while the closures passed to
+/// [`HandleTable::with_ref`] and [`HandleTable::with_ref_mut`] run, so those closures must not
+/// access the same table.
+///
+/// # Example
+///
+/// ```
+/// # use ffizz_passby::HandleTable;
+/// struct Session {
+///     // ...
+/// }
+///
+/// static SESSIONS: HandleTable<Session> = HandleTable::new();
+///
+/// #[no_mangle]
+/// pub extern "C" fn mylib_session_new() -> u64 {
+///     SESSIONS.insert(Session {})
+/// }
+///
+/// #[no_mangle]
+/// pub extern "C" fn mylib_session_free(handle: u64) -> bool {
+///     SESSIONS.take(handle).is_ok()
+/// }
+/// ```
+pub struct HandleTable<T> {
+    inner: Mutex<Slots<T>>,
+}
+
+struct Slots<T> {
+    slots: Vec<Slot<T>>,
+    free: Vec<usize>,
+}
+
+struct Slot<T> {
+    generation: u32,
+    value: Option<T>,
+}
+
+/// InvalidHandle is the error returned when a handle does not identify a value in a
+/// [`HandleTable`], because it is zero, was never issued, or its value has been taken.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidHandle;
+
+impl fmt::Display for InvalidHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid handle")
+    }
+}
+
+impl std::error::Error for InvalidHandle {}
+
+impl<T> HandleTable<T> {
+    /// Create a new, empty HandleTable.
+    pub const fn new() -> Self {
+        HandleTable {
+            inner: Mutex::new(Slots {
+                slots: Vec::new(),
+                free: Vec::new(),
+            }),
+        }
+    }
+
+    /// Store a value in the table, returning its handle.
+    pub fn insert(&self, value: T) -> u64 {
+        let mut inner = self.lock();
+        let index = match inner.free.pop() {
+            Some(index) => index,
+            None => {
+                inner.slots.push(Slot {
+                    generation: 0,
+                    value: None,
+                });
+                inner.slots.len() - 1
+            }
+        };
+        let slot = &mut inner.slots[index];
+        slot.generation = slot.generation.wrapping_add(1);
+        slot.value = Some(value);
+        (u64::from(slot.generation) << 32) | (index as u64 + 1)
+    }
+
+    /// Call the contained function with a shared reference to the value with the given handle.
+    pub fn with_ref<R, F: FnOnce(&T) -> R>(&self, handle: u64, f: F) -> Result<R, InvalidHandle> {
+        let inner = self.lock();
+        let index = inner.index(handle)?;
+        Ok(f(inner.slots[index].value.as_ref().unwrap()))
+    }
+
+    /// Call the contained function with an exclusive reference to the value with the given
+    /// handle.
+    pub fn with_ref_mut<R, F: FnOnce(&mut T) -> R>(
+        &self,
+        handle: u64,
+        f: F,
+    ) -> Result<R, InvalidHandle> {
+        let mut inner = self.lock();
+        let index = inner.index(handle)?;
+        Ok(f(inner.slots[index].value.as_mut().unwrap()))
+    }
+
+    /// Remove the value with the given handle from the table and return it.  The handle is no
+    /// longer valid after this call.
+    pub fn take(&self, handle: u64) -> Result<T, InvalidHandle> {
+        let mut inner = self.lock();
+        let index = inner.index(handle)?;
+        inner.free.push(index);
+        Ok(inner.slots[index].value.take().unwrap())
+    }
+
+    /// Get the number of values in the table.
+    pub fn len(&self) -> usize {
+        let inner = self.lock();
+        inner.slots.len() - inner.free.len()
+    }
+
+    /// Determine whether the table is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Slots<T>> {
+        // a panic in a closure cannot leave the slots inconsistent, so ignore poisoning
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl<T> Default for HandleTable<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Slots<T> {
+    /// Get the index of the occupied slot for the given handle.
+    fn index(&self, handle: u64) -> Result<usize, InvalidHandle> {
+        let index = (handle & 0xffff_ffff) as usize;
+        let generation = (handle >> 32) as u32;
+        if index == 0 {
+            return Err(InvalidHandle);
+        }
+        match self.slots.get(index - 1) {
+            Some(slot) if slot.generation == generation && slot.value.is_some() => Ok(index - 1),
+            _ => Err(InvalidHandle),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn insert_with_ref_take() {
+        let table = HandleTable::new();
+        let a = table.insert(String::from("a"));
+        let b = table.insert(String::from("b"));
+        assert_ne!(a, 0);
+        assert_ne!(a, b);
+        assert_eq!(table.len(), 2);
+
+        assert_eq!(table.with_ref(a, |s| s.clone()), Ok(String::from("a")));
+        table.with_ref_mut(b, |s| s.push('!')).unwrap();
+        assert_eq!(table.take(b), Ok(String::from("b!")));
+        assert_eq!(table.len(), 1);
+        assert_eq!(table.take(a), Ok(String::from("a")));
+        assert!(table.is_empty());
+    }
+
+    #[test]
+    fn invalid_handles() {
+        let table = HandleTable::new();
+        assert_eq!(table.with_ref(0, |_: &u32| ()), Err(InvalidHandle));
+        assert_eq!(table.take(12345), Err(InvalidHandle));
+
+        let a = table.insert(1u32);
+        assert_eq!(table.take(a), Ok(1));
+        assert_eq!(table.take(a), Err(InvalidHandle));
+
+        // the slot is reused, but the old handle remains invalid
+        let b = table.insert(2u32);
+        assert_ne!(a, b);
+        assert_eq!(table.with_ref(a, |v| *v), Err(InvalidHandle));
+        assert_eq!(table.with_ref_mut(b, |v| *v), Ok(2));
+    }
+
+    #[test]
+    fn static_table() {
+        static TABLE: HandleTable<u32> = HandleTable::new();
+        let h = TABLE.insert(10);
+        assert_eq!(TABLE.take(h), Ok(10));
+    }
+}
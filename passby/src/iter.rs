@@ -0,0 +1,180 @@
+use crate::{Boxed, OutParam};
+
+/// BoxedIter wraps a Rust iterator behind a pointer, allowing C to iterate over its items with
+/// "next", "size_hint", and "free" functions, without collecting the items into a vector.
+///
+/// The type parameter is the pass-by type used to return each item, such as [`crate::Value`] or
+/// [`Boxed`], which determines how ownership of the items is transferred to C.
+///
+/// # Example
+///
+/// ```
+/// # use ffizz_passby::{BoxedIter, Value};
+/// type IdIter = BoxedIter<Value<u32, u32>>;
+///
+/// #[no_mangle]
+/// pub unsafe extern "C" fn mylib_ids() -> *mut IdIter {
+///     // SAFETY: the caller will free the iterator (documented in API)
+///     unsafe { IdIter::return_val((1..=3).map(|i| i * 10)) }
+/// }
+///
+/// #[no_mangle]
+/// pub unsafe extern "C" fn mylib_id_iter_next(iter: *mut IdIter, id_out: *mut u32) -> bool {
+///     // SAFETY: iter is a valid iterator and id_out is NULL or valid (documented in API)
+///     unsafe { IdIter::next(iter, id_out) }
+/// }
+///
+/// #[no_mangle]
+/// pub unsafe extern "C" fn mylib_id_iter_free(iter: *mut IdIter) {
+///     // SAFETY: iter is a valid iterator, not used after this call (documented in API)
+///     unsafe { IdIter::free(iter) };
+/// }
+/// ```
+pub struct BoxedIter<P: OutParam> {
+    iter: Box<dyn Iterator<Item = P::RType>>,
+}
+
+impl<P: OutParam> BoxedIter<P> {
+    /// Return an iterator to C, boxing it and transferring ownership.
+    ///
+    /// # Safety
+    ///
+    /// * The caller must ensure that the iterator is eventually freed.
+    pub unsafe fn return_val<I: Iterator<Item = P::RType> + 'static>(iter: I) -> *mut Self {
+        // SAFETY: see docstring
+        unsafe {
+            Boxed::return_val(BoxedIter {
+                iter: Box::new(iter),
+            })
+        }
+    }
+
+    /// Get the next item, returning it to C via `item_out` and returning true, or returning
+    /// false if the iterator is exhausted.  If `item_out` is NULL, the item is dropped.
+    ///
+    /// # Safety
+    ///
+    /// * `iter` must not be NULL and must be a value returned from [`BoxedIter::return_val`].
+    /// * No other thread may _access_ the iterator until this function returns.
+    /// * The safety requirements for `item_out` are the same as for the pass-by type's
+    ///   `to_out_param` method.
+    pub unsafe fn next(iter: *mut Self, item_out: P::Out) -> bool {
+        // SAFETY: see docstring
+        match unsafe { Boxed::with_ref_mut_nonnull(iter, |iter| iter.iter.next()) } {
+            Some(item) => {
+                // SAFETY: see docstring
+                unsafe { P::to_out_param(item, item_out) };
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Get the bounds on the number of remaining items, as for [`Iterator::size_hint`], writing
+    /// them to `lower_out` and `upper_out`.  Returns true if there is an upper bound.  If there
+    /// is no upper bound, `upper_out` is not written.  Either pointer may be NULL.
+    ///
+    /// # Safety
+    ///
+    /// * `iter` must not be NULL and must be a value returned from [`BoxedIter::return_val`].
+    /// * No other thread may mutate the iterator until this function returns.
+    /// * If not NULL, `lower_out` and `upper_out` must point to valid, properly aligned memory
+    ///   for a usize.
+    pub unsafe fn size_hint(
+        iter: *const Self,
+        lower_out: *mut usize,
+        upper_out: *mut usize,
+    ) -> bool {
+        // SAFETY: see docstring
+        let (lower, upper) = unsafe { Boxed::with_ref_nonnull(iter, |iter| iter.iter.size_hint()) };
+        if !lower_out.is_null() {
+            // SAFETY: see docstring
+            unsafe { *lower_out = lower };
+        }
+        match upper {
+            Some(upper) => {
+                if !upper_out.is_null() {
+                    // SAFETY: see docstring
+                    unsafe { *upper_out = upper };
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Free an iterator, dropping any remaining items.  If the pointer is NULL, nothing happens.
+    ///
+    /// # Safety
+    ///
+    /// * If not NULL, `iter` must be a value returned from [`BoxedIter::return_val`].
+    /// * `iter` becomes invalid and must not be used after this call.
+    pub unsafe fn free(iter: *mut Self) {
+        if !iter.is_null() {
+            // SAFETY: see docstring
+            drop(unsafe { Boxed::take_nonnull(iter) });
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Value;
+
+    type IdIter = BoxedIter<Value<u32, u32>>;
+    type NameIter = BoxedIter<Boxed<String>>;
+
+    #[test]
+    fn next_values() {
+        let mut id = 0u32;
+        unsafe {
+            let iter = IdIter::return_val(vec![10, 20].into_iter());
+            assert!(IdIter::next(iter, &mut id));
+            assert_eq!(id, 10);
+            assert!(IdIter::next(iter, std::ptr::null_mut()));
+            assert!(!IdIter::next(iter, &mut id));
+            assert_eq!(id, 10);
+            IdIter::free(iter);
+        }
+    }
+
+    #[test]
+    fn next_boxed() {
+        let mut name = std::ptr::null_mut();
+        unsafe {
+            let iter = NameIter::return_val(["a", "b"].into_iter().map(String::from));
+            assert!(NameIter::next(iter, &mut name));
+            assert_eq!(Boxed::take_nonnull(name), "a");
+            // remaining items are dropped
+            NameIter::free(iter);
+        }
+    }
+
+    #[test]
+    fn size_hint() {
+        let (mut lower, mut upper) = (0, 0);
+        unsafe {
+            let iter = IdIter::return_val(0..5);
+            assert!(IdIter::size_hint(iter, &mut lower, &mut upper));
+            assert_eq!((lower, upper), (5, 5));
+            IdIter::free(iter);
+
+            let iter = IdIter::return_val(0..);
+            assert!(!IdIter::size_hint(iter, &mut lower, &mut upper));
+            assert_eq!(lower, usize::MAX);
+            IdIter::free(iter);
+        }
+    }
+
+    #[test]
+    fn free_null() {
+        unsafe { IdIter::free(std::ptr::null_mut()) };
+    }
+
+    #[test]
+    #[should_panic]
+    fn next_null() {
+        unsafe { IdIter::next(std::ptr::null_mut(), std::ptr::null_mut()) };
+    }
+}
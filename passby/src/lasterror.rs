@@ -0,0 +1,199 @@
+use crate::util::catch_panic;
+use std::any::Any;
+use std::cell::RefCell;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// LastError records a message describing the most recent error on each thread, for the common C
+/// pattern of a function returning an error code, with a separate function to get a description
+/// of the error.
+///
+/// The message is per-thread, so a C caller must retrieve it on the thread where the error
+/// occurred.  A library typically exposes it with a function such as `mylib_last_error`,
+/// returning the message in whatever string type the library uses.
+///
+/// # Example
+///
+/// ```
+/// # use ffizz_passby::LastError;
+/// # use std::ffi::{c_char, CString};
+/// #[no_mangle]
+/// pub extern "C" fn mylib_parse(n: u32) -> i32 {
+///     if n > 10 {
+///         LastError::set(format!("{n} is too large"));
+///         return -1;
+///     }
+///     0
+/// }
+///
+/// /// Return the last error on this thread, or NULL if there is none.  The string must be freed
+/// /// with mylib_string_free.
+/// #[no_mangle]
+/// pub extern "C" fn mylib_last_error() -> *mut c_char {
+///     match LastError::take() {
+///         Some(msg) => CString::new(msg).unwrap_or_default().into_raw(),
+///         None => std::ptr::null_mut(),
+///     }
+/// }
+/// # assert_eq!(mylib_parse(20), -1);
+/// # let msg = unsafe { CString::from_raw(mylib_last_error()) };
+/// # assert_eq!(msg.to_str(), Ok("20 is too large"));
+/// # assert!(mylib_last_error().is_null());
+/// ```
+#[non_exhaustive]
+pub struct LastError;
+
+impl LastError {
+    /// Record an error message for this thread, replacing any previous message.
+    pub fn set(msg: impl Into<String>) {
+        let msg = msg.into();
+        LAST_ERROR.with(|e| *e.borrow_mut() = Some(msg));
+    }
+
+    /// Take the error message for this thread, leaving no message.
+    pub fn take() -> Option<String> {
+        LAST_ERROR.with(|e| e.borrow_mut().take())
+    }
+
+    /// Call the contained function with the error message for this thread, if any, without
+    /// removing it.
+    pub fn with<T, F: FnOnce(Option<&str>) -> T>(f: F) -> T {
+        LAST_ERROR.with(|e| f(e.borrow().as_deref()))
+    }
+
+    /// Clear the error message for this thread.
+    pub fn clear() {
+        LAST_ERROR.with(|e| *e.borrow_mut() = None);
+    }
+
+    /// Call `f`, catching any panic.  If a panic occurs, its message is recorded as the error for
+    /// this thread, and `on_panic` is returned.  This is the implementation of [`ffizz_catch!`].
+    ///
+    /// Catching a panic requires that panics unwind.  On targets where panics abort, a panic in
+    /// `f` aborts the process.
+    ///
+    /// [`ffizz_catch!`]: crate::ffizz_catch
+    pub fn catch<T, F: FnOnce() -> T>(on_panic: T, f: F) -> T {
+        match catch_panic(f) {
+            Ok(rv) => rv,
+            Err(payload) => {
+                Self::set(panic_message(payload.as_ref()));
+                on_panic
+            }
+        }
+    }
+}
+
+/// Get the message from a panic payload, which is a string for panics with a message.
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(msg) = payload.downcast_ref::<&str>() {
+        format!("panic: {msg}")
+    } else if let Some(msg) = payload.downcast_ref::<String>() {
+        format!("panic: {msg}")
+    } else {
+        String::from("panic")
+    }
+}
+
+/// Evaluate the body of an `extern "C"` function, converting any panic into an error return
+/// value instead of aborting the process.
+///
+/// The first argument is the value to return if the body panics, such as an error code, and may
+/// be omitted for functions returning nothing.  The panic message is recorded with [`LastError`],
+/// prefixed with "panic: ".  A `return` in the body returns from the function, as usual.
+///
+/// Wrapping each function of a C API in this macro means that no panic, including those for
+/// unexpected NULL pointers in methods such as [`Boxed::with_ref_nonnull`], can abort the caller.
+/// Values borrowed by the body may be left in an inconsistent state by a panic.  Catching a panic
+/// requires that panics unwind, so this has no effect on targets where panics abort.
+///
+/// # Example
+///
+/// ```
+/// # use ffizz_passby::{ffizz_catch, Boxed, LastError};
+/// # struct Counter(u32);
+/// type BoxedCounter = Boxed<Counter>;
+///
+/// /// Increment a counter, returning false on error.
+/// #[no_mangle]
+/// pub unsafe extern "C" fn counter_incr(counter: *mut Counter) -> bool {
+///     ffizz_catch!(false, {
+///         // SAFETY: counter is a valid counter_t and not accessed concurrently (documented in API)
+///         unsafe { BoxedCounter::with_ref_mut_nonnull(counter, |c| c.0 += 1) };
+///         true
+///     })
+/// }
+/// # assert!(!unsafe { counter_incr(std::ptr::null_mut()) });
+/// # assert_eq!(LastError::take().as_deref(), Some("panic: NULL value not allowed"));
+/// ```
+///
+/// [`Boxed::with_ref_nonnull`]: crate::Boxed::with_ref_nonnull
+/// [`LastError`]: crate::LastError
+#[macro_export]
+macro_rules! ffizz_catch {
+    ($on_panic:expr, $body:block) => {
+        $crate::LastError::catch($on_panic, || $body)
+    };
+    ($body:block) => {
+        $crate::LastError::catch((), || $body)
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn set_take() {
+        LastError::set("uhoh");
+        assert_eq!(
+            LastError::with(|msg| msg.map(String::from)).as_deref(),
+            Some("uhoh")
+        );
+        assert_eq!(LastError::take().as_deref(), Some("uhoh"));
+        assert_eq!(LastError::take(), None);
+        LastError::set(String::from("again"));
+        LastError::clear();
+        assert_eq!(LastError::take(), None);
+    }
+
+    #[test]
+    #[cfg_attr(target_family = "wasm", ignore = "threads are not available")]
+    fn per_thread() {
+        LastError::set("here");
+        std::thread::spawn(|| assert_eq!(LastError::take(), None))
+            .join()
+            .unwrap();
+        assert_eq!(LastError::take().as_deref(), Some("here"));
+    }
+
+    #[test]
+    #[cfg_attr(panic = "abort", ignore = "catching a panic requires unwinding")]
+    fn catch() {
+        fn f(x: u32) -> i32 {
+            ffizz_catch!(-1, {
+                if x == 0 {
+                    return 0;
+                }
+                assert!(x < 10, "{x} is too big");
+                x as i32
+            })
+        }
+        assert_eq!(f(0), 0);
+        assert_eq!(f(5), 5);
+        assert_eq!(LastError::take(), None);
+        assert_eq!(f(20), -1);
+        assert_eq!(LastError::take().as_deref(), Some("panic: 20 is too big"));
+    }
+
+    #[test]
+    #[cfg_attr(panic = "abort", ignore = "catching a panic requires unwinding")]
+    fn catch_unit() {
+        ffizz_catch!({ panic!("oops") });
+        assert_eq!(LastError::take().as_deref(), Some("panic: oops"));
+        ffizz_catch!({ std::panic::panic_any(10u32) });
+        assert_eq!(LastError::take().as_deref(), Some("panic"));
+    }
+}
@@ -0,0 +1,155 @@
+/// Assert at compile time that a Rust type fits in a C type, for use with [`Unboxed`].
+///
+/// [`Unboxed`] requires that the two types share the same alignment, and that the Rust type is
+/// not larger than the C type.  Its methods check this at runtime in debug builds, but this macro
+/// turns a mismatch into a compile error in every build, so it is a good idea to invoke it
+/// alongside each type alias.
+///
+/// # Example
+///
+/// ```
+/// # use ffizz_passby::{static_assert_layout, Unboxed};
+/// struct Complex {
+///     re: i64,
+///     im: i64,
+/// }
+///
+/// #[repr(C)]
+/// struct complex_t([u64; 2]);
+///
+/// static_assert_layout!(Complex, complex_t);
+/// type UnboxedComplex = Unboxed<Complex, complex_t>;
+/// ```
+///
+/// [`Unboxed`]: crate::Unboxed
+#[macro_export]
+macro_rules! static_assert_layout {
+    ($rtype:ty, $ctype:ty) => {
+        const _: () = {
+            ::std::assert!(
+                ::std::mem::size_of::<$rtype>() <= ::std::mem::size_of::<$ctype>(),
+                ::std::concat!(
+                    ::std::stringify!($rtype),
+                    " is larger than ",
+                    ::std::stringify!($ctype)
+                )
+            );
+            ::std::assert!(
+                ::std::mem::align_of::<$rtype>() == ::std::mem::align_of::<$ctype>(),
+                ::std::concat!(
+                    ::std::stringify!($rtype),
+                    " and ",
+                    ::std::stringify!($ctype),
+                    " have different alignments"
+                )
+            );
+        };
+    };
+}
+
+/// Define a `#[repr(C)]` type with the same size and alignment as a Rust type, for use as the
+/// CType of an [`Unboxed`].
+///
+/// The struct contains a single private field, an array of units with the alignment of the Rust
+/// type, and its length is computed from the size of the Rust type.  This avoids choosing the
+/// size of a reserved field by hand.  The layout is checked with [`static_assert_layout!`].
+///
+/// Since the size of the Rust type may vary between platforms and compiler versions, the C
+/// declaration must be kept in agreement, such as with a generous size and static assertions
+/// in the header.
+///
+/// # Example
+///
+/// ```
+/// # use ffizz_passby::{opaque_ctype, Unboxed};
+/// pub struct Complex {
+///     re: f64,
+///     im: f64,
+/// }
+///
+/// opaque_ctype!(
+///     /// A complex number.
+///     pub struct complex_t for Complex
+/// );
+///
+/// type UnboxedComplex = Unboxed<Complex, complex_t>;
+/// assert_eq!(std::mem::size_of::<complex_t>(), 16);
+/// ```
+///
+/// [`Unboxed`]: crate::Unboxed
+#[macro_export]
+macro_rules! opaque_ctype {
+    ($(#[$attr:meta])* $vis:vis struct $ctype:ident for $rtype:ty) => {
+        $(#[$attr])*
+        #[allow(non_camel_case_types)]
+        #[repr(C)]
+        $vis struct $ctype {
+            __reserved: [
+                <$crate::layout::Align<{ ::std::mem::align_of::<$rtype>() }> as $crate::layout::Aligned>::Unit;
+                ::std::mem::size_of::<$rtype>() / ::std::mem::align_of::<$rtype>()
+            ],
+        }
+
+        $crate::static_assert_layout!($rtype, $ctype);
+    };
+}
+
+/// A marker for an alignment, used by [`opaque_ctype!`].
+#[doc(hidden)]
+pub struct Align<const N: usize>;
+
+/// Aligned gives a unit type with size and alignment N for each `Align<N>`.
+#[doc(hidden)]
+pub trait Aligned {
+    type Unit: Copy;
+}
+
+macro_rules! aligned_units {
+    ($($unit:ident = $n:literal),*) => {
+        $(
+            #[doc(hidden)]
+            #[derive(Clone, Copy)]
+            #[repr(C, align($n))]
+            pub struct $unit([u8; $n]);
+
+            impl Aligned for Align<$n> {
+                type Unit = $unit;
+            }
+        )*
+    };
+}
+
+aligned_units!(
+    Unit1 = 1,
+    Unit2 = 2,
+    Unit4 = 4,
+    Unit8 = 8,
+    Unit16 = 16,
+    Unit32 = 32,
+    Unit64 = 64,
+    Unit128 = 128
+);
+
+#[cfg(test)]
+mod test {
+    #![allow(dead_code)]
+
+    struct TwoInts(u64, u64);
+    struct Reserved([u64; 3]);
+
+    static_assert_layout!(TwoInts, Reserved);
+    static_assert_layout!(u32, u32);
+
+    #[repr(align(32))]
+    struct Aligned([u8; 96]);
+
+    opaque_ctype!(struct aligned_t for Aligned);
+    opaque_ctype!(struct unit_t for ());
+
+    #[test]
+    fn opaque_ctype_layout() {
+        assert_eq!(std::mem::size_of::<aligned_t>(), 96);
+        assert_eq!(std::mem::align_of::<aligned_t>(), 32);
+        assert_eq!(std::mem::size_of::<unit_t>(), 0);
+    }
+}
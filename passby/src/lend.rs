@@ -0,0 +1,141 @@
+use std::fmt;
+use std::marker::PhantomData;
+
+/// Lend a shared reference to C for the duration of `f`, as a [`Lent`] pointer.
+///
+/// The lifetime of the `Lent` is only valid within `f`, so Rust code cannot store it for later
+/// use.  Declaring the C callback's parameter as `Lent` rather than a raw pointer means the
+/// pointer never appears in Rust code at all.  No allocation or transfer of ownership occurs.
+/// The C API must still document that the pointer is only valid during the callback.
+///
+/// # Example
+///
+/// ```
+/// # use ffizz_passby::{lend, Lent};
+/// # use std::ffi::c_void;
+/// #[repr(C)]
+/// pub struct entry_t {
+///     key: u32,
+///     value: u32,
+/// }
+///
+/// pub struct Table(Vec<entry_t>);
+///
+/// /// Declared in C as `void (*visit)(const entry_t *entry, void *user_data)`.
+/// type VisitFn = for<'a> unsafe extern "C" fn(Lent<'a, entry_t>, *mut c_void);
+///
+/// #[no_mangle]
+/// pub unsafe extern "C" fn table_visit(table: *const Table, visit: VisitFn, user_data: *mut c_void) {
+///     // SAFETY: table is valid (documented in API)
+///     let table = unsafe { &*table };
+///     for entry in &table.0 {
+///         // SAFETY: visit is a valid function pointer (documented in API)
+///         lend(entry, |entry| unsafe { visit(entry, user_data) });
+///     }
+/// }
+/// ```
+///
+/// A `Lent` cannot escape the closure:
+///
+/// ```compile_fail
+/// # use ffizz_passby::{lend, Lent};
+/// let mut stored: Option<Lent<u32>> = None;
+/// lend(&10, |lent| stored = Some(lent));
+/// ```
+pub fn lend<T, R>(value: &T, f: impl for<'a> FnOnce(Lent<'a, T>) -> R) -> R {
+    f(Lent {
+        ptr: value,
+        _phantom: PhantomData,
+    })
+}
+
+/// Lend an exclusive reference to C for the duration of `f`, as a [`LentMut`] pointer.  This is
+/// the mutable equivalent of [`lend`].
+pub fn lend_mut<T, R>(value: &mut T, f: impl for<'a> FnOnce(LentMut<'a, T>) -> R) -> R {
+    f(LentMut {
+        ptr: value,
+        _phantom: PhantomData,
+    })
+}
+
+/// A pointer to a value lent to C by [`lend`], with the same representation as `*const T`.
+#[repr(transparent)]
+pub struct Lent<'a, T> {
+    ptr: *const T,
+    _phantom: PhantomData<&'a T>,
+}
+
+impl<T> Lent<'_, T> {
+    /// Get the raw pointer, for passing to C.  The pointer must not be used after the call to
+    /// [`lend`] returns.
+    pub fn as_ptr(&self) -> *const T {
+        self.ptr
+    }
+}
+
+impl<T> Clone for Lent<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Lent<'_, T> {}
+
+impl<T> fmt::Debug for Lent<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Lent").field(&self.ptr).finish()
+    }
+}
+
+/// A pointer to a value lent to C by [`lend_mut`], with the same representation as `*mut T`.
+#[repr(transparent)]
+pub struct LentMut<'a, T> {
+    ptr: *mut T,
+    _phantom: PhantomData<&'a mut T>,
+}
+
+impl<T> LentMut<'_, T> {
+    /// Get the raw pointer, for passing to C.  The pointer must not be used after the call to
+    /// [`lend_mut`] returns.
+    pub fn as_mut_ptr(&mut self) -> *mut T {
+        self.ptr
+    }
+}
+
+impl<T> fmt::Debug for LentMut<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("LentMut").field(&self.ptr).finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    unsafe extern "C" fn read(value: Lent<'_, u32>) -> u32 {
+        unsafe { *value.as_ptr() }
+    }
+
+    unsafe extern "C" fn incr(mut value: LentMut<'_, u32>) {
+        unsafe { *value.as_mut_ptr() += 1 };
+    }
+
+    #[test]
+    fn lend_to_c() {
+        let value = 10u32;
+        assert_eq!(lend(&value, |v| unsafe { read(v) }), 10);
+    }
+
+    #[test]
+    fn lend_mut_to_c() {
+        let mut value = 10u32;
+        lend_mut(&mut value, |v| unsafe { incr(v) });
+        assert_eq!(value, 11);
+    }
+
+    #[test]
+    fn same_pointer() {
+        let value = 10u32;
+        lend(&value, |v| assert_eq!(v.as_ptr(), &value as *const u32));
+    }
+}
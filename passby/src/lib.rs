@@ -2,11 +2,73 @@
 #![allow(unused_unsafe)]
 #![doc = include_str!("crate-doc.md")]
 
+#[cfg(feature = "access-checks")]
+mod access;
+#[cfg(feature = "alloc-hooks")]
+pub mod alloc;
+mod arcboxed;
 mod boxed;
+mod callback;
+mod cenum;
+#[cfg(feature = "track-allocations")]
+pub mod debug;
+mod error;
+#[cfg(any(unix, windows, target_os = "wasi"))]
+mod fd;
+mod flags;
+mod fzvec;
+mod handle;
+mod iter;
+mod lasterror;
+#[doc(hidden)]
+pub mod layout;
+mod lend;
+mod nonzero;
+mod nullable;
+mod passbypointer;
+mod pinnedboxed;
+#[cfg(feature = "quarantine")]
+mod quarantine;
+mod result;
+mod sendsync;
+mod slice;
+mod smallunboxed;
+mod stream;
+mod take;
+mod time;
+#[cfg(feature = "tracing")]
+mod trace;
+#[cfg(feature = "type-tags")]
+mod typetag;
 mod unboxed;
 mod util;
 mod value;
+mod valuewith;
 
+pub use arcboxed::*;
 pub use boxed::*;
+pub use callback::*;
+pub use cenum::*;
+pub use error::*;
+#[cfg(any(unix, windows, target_os = "wasi"))]
+pub use fd::*;
+pub use flags::*;
+pub use fzvec::*;
+pub use handle::*;
+pub use iter::*;
+pub use lasterror::*;
+pub use lend::*;
+pub use nonzero::*;
+pub use nullable::*;
+pub use passbypointer::*;
+pub use pinnedboxed::*;
+pub use result::*;
+pub use sendsync::*;
+pub use slice::*;
+pub use smallunboxed::*;
+pub use stream::*;
+pub use take::*;
+pub use time::*;
 pub use unboxed::*;
 pub use value::*;
+pub use valuewith::*;
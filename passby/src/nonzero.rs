@@ -0,0 +1,205 @@
+use std::fmt;
+use std::marker::PhantomData;
+use std::num;
+
+/// NonZeroInt is implemented for the standard library's non-zero integer types, such as
+/// `NonZeroU32`, for use with [`NonZeroValue`].
+pub trait NonZeroInt: Copy {
+    /// The corresponding integer type, used in C.
+    type Int: Copy + PartialEq;
+
+    /// The zero value of the integer type.
+    const ZERO: Self::Int;
+
+    /// Create a value, or None if `int` is zero.
+    fn new(int: Self::Int) -> Option<Self>;
+
+    /// Get the integer value.
+    fn get(self) -> Self::Int;
+}
+
+macro_rules! impl_nonzero_int {
+    ($($nonzero:ident: $int:ty),*) => {
+        $(
+            impl NonZeroInt for num::$nonzero {
+                type Int = $int;
+                const ZERO: $int = 0;
+
+                fn new(int: $int) -> Option<Self> {
+                    num::$nonzero::new(int)
+                }
+
+                fn get(self) -> $int {
+                    num::$nonzero::get(self)
+                }
+            }
+        )*
+    };
+}
+
+impl_nonzero_int!(
+    NonZeroU8: u8,
+    NonZeroU16: u16,
+    NonZeroU32: u32,
+    NonZeroU64: u64,
+    NonZeroUsize: usize,
+    NonZeroI8: i8,
+    NonZeroI16: i16,
+    NonZeroI32: i32,
+    NonZeroI64: i64,
+    NonZeroIsize: isize
+);
+
+/// ZeroValueError is the error returned from [`NonZeroValue::try_take`] when C passes zero.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ZeroValueError;
+
+impl fmt::Display for ZeroValueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "zero value not allowed")
+    }
+}
+
+impl std::error::Error for ZeroValueError {}
+
+/// NonZeroValue is used to pass non-zero integer types, such as `NonZeroU32`, to and from C as
+/// plain integers.
+///
+/// C can pass any integer, including zero, so values from C are checked rather than transmuted,
+/// which would be undefined behavior.  Zero can either be rejected, with [`NonZeroValue::take`]
+/// or [`NonZeroValue::try_take`], or represent None, with [`NonZeroValue::take_option`].  In the
+/// other direction, [`NonZeroValue::return_option`] returns None as zero.
+///
+/// # Example
+///
+/// ```
+/// # use ffizz_passby::NonZeroValue;
+/// # use std::num::NonZeroU32;
+/// type ConnIdValue = NonZeroValue<NonZeroU32>;
+///
+/// fn find_conn(name: &str) -> Option<NonZeroU32> {
+///     // ...
+/// # NonZeroU32::new(name.len() as u32)
+/// }
+///
+/// /// Returns 0 if no connection is found.
+/// #[no_mangle]
+/// pub extern "C" fn mylib_find_conn() -> u32 {
+///     ConnIdValue::return_option(find_conn("main"))
+/// }
+///
+/// #[no_mangle]
+/// pub extern "C" fn mylib_close_conn(conn_id: u32) -> bool {
+///     let Ok(conn_id) = ConnIdValue::try_take(conn_id) else {
+///         return false;
+///     };
+///     // ...
+///     true
+/// }
+/// ```
+#[non_exhaustive]
+pub struct NonZeroValue<N: NonZeroInt> {
+    _phantom: PhantomData<N>,
+}
+
+impl<N: NonZeroInt> NonZeroValue<N> {
+    /// Take a value from C, panicking if it is zero.
+    pub fn take(cval: N::Int) -> N {
+        match N::new(cval) {
+            Some(rval) => rval,
+            None => panic!("zero value not allowed"),
+        }
+    }
+
+    /// Take a value from C, returning an error if it is zero.
+    pub fn try_take(cval: N::Int) -> Result<N, ZeroValueError> {
+        N::new(cval).ok_or(ZeroValueError)
+    }
+
+    /// Take a value from C, representing zero as None.
+    pub fn take_option(cval: N::Int) -> Option<N> {
+        N::new(cval)
+    }
+
+    /// Return a value to C.
+    pub fn return_val(rval: N) -> N::Int {
+        rval.get()
+    }
+
+    /// Return an optional value to C, representing None as zero.
+    pub fn return_option(rval: Option<N>) -> N::Int {
+        rval.map_or(N::ZERO, N::get)
+    }
+
+    /// Return a value to C via an "output parameter".
+    ///
+    /// If the pointer is NULL, nothing happens.
+    ///
+    /// # Safety
+    ///
+    /// * If not NULL, `arg_out` must be aligned for and have enough space for the integer type.
+    pub unsafe fn to_out_param(rval: N, arg_out: *mut N::Int) {
+        if !arg_out.is_null() {
+            // SAFETY: see docstring
+            unsafe { *arg_out = rval.get() };
+        }
+    }
+
+    /// Return an optional value to C via an "output parameter", representing None as zero.
+    ///
+    /// If the pointer is NULL, nothing happens.
+    ///
+    /// # Safety
+    ///
+    /// * If not NULL, `arg_out` must be aligned for and have enough space for the integer type.
+    pub unsafe fn to_out_param_option(rval: Option<N>, arg_out: *mut N::Int) {
+        if !arg_out.is_null() {
+            // SAFETY: see docstring
+            unsafe { *arg_out = Self::return_option(rval) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::num::{NonZeroI64, NonZeroU32};
+
+    type IdValue = NonZeroValue<NonZeroU32>;
+
+    #[test]
+    fn take() {
+        assert_eq!(IdValue::take(5), NonZeroU32::new(5).unwrap());
+        assert_eq!(IdValue::try_take(0), Err(ZeroValueError));
+        assert_eq!(IdValue::take_option(0), None);
+        assert_eq!(
+            NonZeroValue::<NonZeroI64>::take_option(-3),
+            NonZeroI64::new(-3)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "zero value not allowed")]
+    fn take_zero() {
+        IdValue::take(0);
+    }
+
+    #[test]
+    fn option_round_trip() {
+        for rval in [None, NonZeroU32::new(7)] {
+            assert_eq!(IdValue::take_option(IdValue::return_option(rval)), rval);
+        }
+    }
+
+    #[test]
+    fn to_out_param() {
+        let mut cval = 1;
+        unsafe {
+            IdValue::to_out_param_option(None, &mut cval);
+            assert_eq!(cval, 0);
+            IdValue::to_out_param(NonZeroU32::new(9).unwrap(), &mut cval);
+            assert_eq!(cval, 9);
+            IdValue::to_out_param(NonZeroU32::new(9).unwrap(), std::ptr::null_mut());
+        }
+    }
+}
@@ -0,0 +1,318 @@
+use crate::Boxed;
+use std::marker::PhantomData;
+
+/// NullStrategy defines how an optional value is represented in C.  It is implemented by
+/// [`NullPointer`], [`Sentinel`], and [`ValidFlag`], for use with [`Nullable`].
+pub trait NullStrategy {
+    /// The Rust type of the value, when present.
+    type RType;
+    /// The C representation of the optional value.
+    type CType;
+
+    /// Convert an optional Rust value into its C representation.
+    ///
+    /// # Safety
+    ///
+    /// * The caller must ensure that the value is eventually freed, if it owns an allocation.
+    unsafe fn from_option(rval: Option<Self::RType>) -> Self::CType;
+
+    /// Convert the C representation of an optional value into a Rust value, taking ownership.
+    ///
+    /// # Safety
+    ///
+    /// * `cval` must have been created by [`NullStrategy::from_option`], and must not be used
+    ///   after this call if it owns an allocation.
+    unsafe fn into_option(cval: Self::CType) -> Option<Self::RType>;
+}
+
+/// NullPointer represents an optional value as a pointer to a [`Boxed`] value, with NULL
+/// representing `None`.
+#[non_exhaustive]
+pub struct NullPointer<RType: Sized> {
+    _phantom: PhantomData<RType>,
+}
+
+impl<RType: Sized> NullStrategy for NullPointer<RType> {
+    type RType = RType;
+    type CType = *mut RType;
+
+    unsafe fn from_option(rval: Option<RType>) -> *mut RType {
+        match rval {
+            // SAFETY: see docstring
+            Some(rval) => unsafe { Boxed::return_val(rval) },
+            None => std::ptr::null_mut(),
+        }
+    }
+
+    unsafe fn into_option(cval: *mut RType) -> Option<RType> {
+        if cval.is_null() {
+            None
+        } else {
+            // SAFETY: cval is not NULL (just checked) and came from Boxed::return_val
+            Some(unsafe { Boxed::take_nonnull(cval) })
+        }
+    }
+}
+
+/// SentinelValue identifies a C value that represents `None`, for use with [`Sentinel`].
+pub trait SentinelValue: PartialEq {
+    /// The value representing `None`.
+    const SENTINEL: Self;
+}
+
+/// Sentinel represents an optional value as a C value, with a reserved value representing
+/// `None`, such as `-1` for an index or `0` for an ID.
+///
+/// As with [`crate::Value`], `CType` must be convertible to and from `RType`, and should be a
+/// `Copy` type without an allocation.  Converting a Rust value equal to the sentinel results in
+/// `None` on the C side.
+#[non_exhaustive]
+pub struct Sentinel<RType, CType>
+where
+    RType: Sized,
+    CType: Sized + SentinelValue + From<RType> + Into<RType>,
+{
+    _phantom: PhantomData<(RType, CType)>,
+}
+
+impl<RType, CType> NullStrategy for Sentinel<RType, CType>
+where
+    RType: Sized,
+    CType: Sized + SentinelValue + From<RType> + Into<RType>,
+{
+    type RType = RType;
+    type CType = CType;
+
+    unsafe fn from_option(rval: Option<RType>) -> CType {
+        rval.map_or(CType::SENTINEL, CType::from)
+    }
+
+    unsafe fn into_option(cval: CType) -> Option<RType> {
+        if cval == CType::SENTINEL {
+            None
+        } else {
+            Some(cval.into())
+        }
+    }
+}
+
+/// Flagged is the C representation of an optional value used by [`ValidFlag`]: a value and a
+/// flag indicating whether it is valid.  When `valid` is false, `value` is the default value of
+/// its type and should be ignored.
+///
+/// ```c
+/// typedef struct optional_uuid_t {
+///     bool valid;
+///     uuid_t value;
+/// } optional_uuid_t;
+/// ```
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Flagged<CType> {
+    /// True if `value` is present.
+    pub valid: bool,
+    /// The value, if `valid` is true.
+    pub value: CType,
+}
+
+/// ValidFlag represents an optional value as a [`Flagged`] struct containing a C value and a
+/// boolean flag.
+///
+/// As with [`crate::Value`], `CType` must be convertible to and from `RType`, and should be a
+/// `Copy` type without an allocation.
+#[non_exhaustive]
+pub struct ValidFlag<RType, CType>
+where
+    RType: Sized,
+    CType: Sized + Default + From<RType> + Into<RType>,
+{
+    _phantom: PhantomData<(RType, CType)>,
+}
+
+impl<RType, CType> NullStrategy for ValidFlag<RType, CType>
+where
+    RType: Sized,
+    CType: Sized + Default + From<RType> + Into<RType>,
+{
+    type RType = RType;
+    type CType = Flagged<CType>;
+
+    unsafe fn from_option(rval: Option<RType>) -> Flagged<CType> {
+        match rval {
+            Some(rval) => Flagged {
+                valid: true,
+                value: CType::from(rval),
+            },
+            None => Flagged::default(),
+        }
+    }
+
+    unsafe fn into_option(cval: Flagged<CType>) -> Option<RType> {
+        if cval.valid {
+            Some(cval.value.into())
+        } else {
+            None
+        }
+    }
+}
+
+/// Nullable is used to model optional values, using a [`NullStrategy`] to represent `None` in C.
+///
+/// # Example
+///
+/// Define a type alias parameterizing Nullable with the strategy:
+///
+/// ```
+/// # use ffizz_passby::{Nullable, Sentinel, SentinelValue};
+/// # fn find(haystack: &[u32], needle: u32) -> Option<usize> { haystack.iter().position(|v| *v == needle) }
+/// #[repr(C)]
+/// #[derive(PartialEq)]
+/// pub struct index_t(i64);
+///
+/// impl SentinelValue for index_t {
+///     const SENTINEL: Self = index_t(-1);
+/// }
+/// # impl From<usize> for index_t {
+/// #     fn from(rval: usize) -> index_t { index_t(rval as i64) }
+/// # }
+/// # impl Into<usize> for index_t {
+/// #     fn into(self) -> usize { self.0 as usize }
+/// # }
+///
+/// type OptionalIndex = Nullable<Sentinel<usize, index_t>>;
+///
+/// #[no_mangle]
+/// pub extern "C" fn mylib_find(needle: u32) -> index_t {
+///     // SAFETY: index_t does not contain an allocation
+///     unsafe { OptionalIndex::from_option(find(&[1, 2, 3], needle)) }
+/// }
+/// ```
+#[non_exhaustive]
+pub struct Nullable<S: NullStrategy> {
+    _phantom: PhantomData<S>,
+}
+
+impl<S: NullStrategy> Nullable<S> {
+    /// Return an optional value to C.
+    ///
+    /// # Safety
+    ///
+    /// * The caller must ensure that the value is eventually freed, if it owns an allocation.
+    pub unsafe fn from_option(rval: Option<S::RType>) -> S::CType {
+        // SAFETY: see docstring
+        unsafe { S::from_option(rval) }
+    }
+
+    /// Take an optional value from C as an argument.
+    ///
+    /// # Safety
+    ///
+    /// * `cval` must be a valid representation of an optional value for the strategy, such as
+    ///   one returned from [`Nullable::from_option`].
+    /// * `cval` must not be used after this call if it owns an allocation.
+    pub unsafe fn into_option(cval: S::CType) -> Option<S::RType> {
+        // SAFETY: see docstring
+        unsafe { S::into_option(cval) }
+    }
+
+    /// Return an optional value to C via an "output parameter", returning true if the value is
+    /// present.  This suits functions which return a boolean indicating whether the output
+    /// parameter is valid.
+    ///
+    /// If the pointer is NULL, the value is dropped.
+    ///
+    /// # Safety
+    ///
+    /// * The caller must ensure that the value is eventually freed, if it owns an allocation.
+    /// * If not NULL, `arg_out` must point to valid, properly aligned memory for CType.
+    pub unsafe fn to_out_param(rval: Option<S::RType>, arg_out: *mut S::CType) -> bool {
+        let present = rval.is_some();
+        if !arg_out.is_null() {
+            // SAFETY: see docstring
+            unsafe { arg_out.write(S::from_option(rval)) };
+        }
+        present
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Node(u32);
+
+    #[derive(Debug, Clone, Copy, PartialEq, Default)]
+    struct NodeId(u32);
+
+    impl SentinelValue for NodeId {
+        const SENTINEL: Self = NodeId(0);
+    }
+
+    impl From<u32> for NodeId {
+        fn from(rval: u32) -> NodeId {
+            NodeId(rval)
+        }
+    }
+
+    impl From<NodeId> for u32 {
+        fn from(cval: NodeId) -> u32 {
+            cval.0
+        }
+    }
+
+    #[test]
+    fn null_pointer() {
+        type OptionalNode = Nullable<NullPointer<Node>>;
+        unsafe {
+            let cval = OptionalNode::from_option(Some(Node(10)));
+            assert_eq!(OptionalNode::into_option(cval), Some(Node(10)));
+            let cval = OptionalNode::from_option(None);
+            assert!(cval.is_null());
+            assert_eq!(OptionalNode::into_option(cval), None);
+        }
+    }
+
+    #[test]
+    fn sentinel() {
+        type OptionalId = Nullable<Sentinel<u32, NodeId>>;
+        unsafe {
+            assert_eq!(OptionalId::from_option(Some(5)), NodeId(5));
+            assert_eq!(OptionalId::from_option(None), NodeId(0));
+            assert_eq!(OptionalId::into_option(NodeId(5)), Some(5));
+            assert_eq!(OptionalId::into_option(NodeId(0)), None);
+        }
+    }
+
+    #[test]
+    fn valid_flag() {
+        type OptionalId = Nullable<ValidFlag<u32, NodeId>>;
+        unsafe {
+            let cval = OptionalId::from_option(Some(0));
+            assert_eq!(
+                cval,
+                Flagged {
+                    valid: true,
+                    value: NodeId(0)
+                }
+            );
+            assert_eq!(OptionalId::into_option(cval), Some(0));
+            let cval = OptionalId::from_option(None);
+            assert!(!cval.valid);
+            assert_eq!(OptionalId::into_option(cval), None);
+        }
+    }
+
+    #[test]
+    fn to_out_param() {
+        type OptionalId = Nullable<Sentinel<u32, NodeId>>;
+        let mut cval = NodeId(99);
+        unsafe {
+            assert!(OptionalId::to_out_param(Some(3), &mut cval));
+            assert_eq!(cval, NodeId(3));
+            assert!(!OptionalId::to_out_param(None, &mut cval));
+            assert_eq!(cval, NodeId(0));
+            assert!(OptionalId::to_out_param(Some(3), std::ptr::null_mut()));
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use crate::Boxed;
+
+pub use ffizz_macros::PassByPointer;
+
+/// PassByPointer is implemented by types that are passed to C by pointer, allocated with
+/// [`Boxed`].  Its methods are shorthand for those of `Boxed<Self>`, so a newtype wrapping a Rust
+/// value can be used without a type alias.
+///
+/// Implement this trait with [`derive(PassByPointer)`](macro@PassByPointer).  The derive also
+/// declares the opaque C type in the generated header, given `#[ffizz(ctype_name = "..")]`.
+///
+/// # Example
+///
+/// ```
+/// # use ffizz_passby::PassByPointer;
+/// # #[derive(Default)]
+/// # pub struct Store;
+/// /// A key-value store.
+/// #[derive(PassByPointer)]
+/// #[ffizz(ctype_name = "kvstore_t", order = 100)]
+/// pub struct kvstore_t(pub Store);
+///
+/// #[no_mangle]
+/// pub unsafe extern "C" fn kvstore_new() -> *mut kvstore_t {
+///     unsafe { kvstore_t(Store::default()).return_val() }
+/// }
+///
+/// #[no_mangle]
+/// pub unsafe extern "C" fn kvstore_free(store: *mut kvstore_t) {
+///     drop(unsafe { kvstore_t::take_nonnull(store) });
+/// }
+/// # unsafe { kvstore_free(kvstore_new()) };
+/// # assert!(ffizz_header::generate().contains("typedef struct kvstore_t kvstore_t;"));
+/// ```
+pub trait PassByPointer: Sized {
+    /// Return a value to C, boxing the value and transferring ownership.  See
+    /// [`Boxed::return_val`].
+    ///
+    /// # Safety
+    ///
+    /// * The requirements are the same as for [`Boxed::return_val`].
+    unsafe fn return_val(self) -> *mut Self {
+        // SAFETY: see docstring
+        unsafe { Boxed::return_val(self) }
+    }
+
+    /// Return a value to C, transferring ownership, via an "output parameter".  See
+    /// [`Boxed::to_out_param`].
+    ///
+    /// # Safety
+    ///
+    /// * The requirements are the same as for [`Boxed::to_out_param`].
+    unsafe fn to_out_param(self, arg_out: *mut *mut Self) {
+        // SAFETY: see docstring
+        unsafe { Boxed::to_out_param(self, arg_out) }
+    }
+
+    /// Take a value from C as an argument.  See [`Boxed::take_nonnull`].
+    ///
+    /// # Safety
+    ///
+    /// * The requirements are the same as for [`Boxed::take_nonnull`].
+    unsafe fn take_nonnull(arg: *mut Self) -> Self {
+        // SAFETY: see docstring
+        unsafe { Boxed::take_nonnull(arg) }
+    }
+
+    /// Call the contained function with a shared reference to the value.  See
+    /// [`Boxed::with_ref_nonnull`].
+    ///
+    /// # Safety
+    ///
+    /// * The requirements are the same as for [`Boxed::with_ref_nonnull`].
+    unsafe fn with_ref_nonnull<T, F: FnOnce(&Self) -> T>(arg: *const Self, f: F) -> T {
+        // SAFETY: see docstring
+        unsafe { Boxed::with_ref_nonnull(arg, f) }
+    }
+
+    /// Call the contained function with an exclusive reference to the value.  See
+    /// [`Boxed::with_ref_mut_nonnull`].
+    ///
+    /// # Safety
+    ///
+    /// * The requirements are the same as for [`Boxed::with_ref_mut_nonnull`].
+    unsafe fn with_ref_mut_nonnull<T, F: FnOnce(&mut Self) -> T>(arg: *mut Self, f: F) -> T {
+        // SAFETY: see docstring
+        unsafe { Boxed::with_ref_mut_nonnull(arg, f) }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct Counter(u32);
+    impl PassByPointer for Counter {}
+
+    #[test]
+    fn round_trip() {
+        unsafe {
+            let ptr = Counter(1).return_val();
+            Counter::with_ref_mut_nonnull(ptr, |c| c.0 += 1);
+            assert_eq!(Counter::with_ref_nonnull(ptr, |c| c.0), 2);
+            assert_eq!(Counter::take_nonnull(ptr).0, 2);
+        }
+    }
+
+    #[test]
+    fn out_param() {
+        unsafe {
+            let mut ptr = std::ptr::null_mut();
+            Counter(3).to_out_param(&mut ptr);
+            assert_eq!(Counter::take_nonnull(ptr).0, 3);
+        }
+    }
+}
@@ -0,0 +1,245 @@
+use crate::NullPointerError;
+use std::marker::PhantomData;
+use std::pin::Pin;
+
+/// PinnedBoxed is like [`crate::Boxed`], but for values that must not move once they are
+/// allocated, such as self-referential types or types holding intrusive lists.  The value is
+/// allocated as a `Pin<Box<RType>>`, and is never moved while C holds the pointer.
+///
+/// Methods giving mutable access to the value only provide `Pin<&mut RType>`, and
+/// [`PinnedBoxed::take_nonnull`] returns the `Pin<Box<RType>>`, so that the pinning guarantees
+/// are preserved.
+///
+/// # Example
+///
+/// ```
+/// # use ffizz_passby::PinnedBoxed;
+/// # use std::future::Future;
+/// # use std::pin::Pin;
+/// # use std::task::{Context, Poll, Waker};
+/// type Task = Pin<Box<dyn Future<Output = u32>>>;
+///
+/// struct Job {
+///     task: Task,
+/// }
+///
+/// type PinnedJob = PinnedBoxed<Job>;
+///
+/// #[no_mangle]
+/// pub unsafe extern "C" fn job_poll(job: *mut Job) -> bool {
+///     // SAFETY: job is valid and not accessed concurrently (documented in API)
+///     unsafe {
+///         PinnedJob::with_ref_mut_nonnull(job, |job| {
+///             let mut cx = Context::from_waker(Waker::noop());
+///             job.get_mut().task.as_mut().poll(&mut cx).is_ready()
+///         })
+///     }
+/// }
+/// ```
+#[non_exhaustive]
+pub struct PinnedBoxed<RType: Sized> {
+    _phantom: PhantomData<RType>,
+}
+
+impl<RType: Sized> PinnedBoxed<RType> {
+    /// Take a value from C as an argument, taking ownership of the value it points to.  The value
+    /// remains pinned.
+    ///
+    /// # Safety
+    ///
+    /// * `arg` must not be NULL.
+    /// * `arg` must be a value returned from [`PinnedBoxed::return_val`] or a variant.
+    /// * `arg` becomes invalid and must not be used after this call.
+    pub unsafe fn take_nonnull(arg: *mut RType) -> Pin<Box<RType>> {
+        if arg.is_null() {
+            panic!("NULL value not allowed");
+        }
+        #[cfg(feature = "track-allocations")]
+        crate::debug::forget(arg);
+        #[cfg(feature = "tracing")]
+        crate::trace::taken(arg);
+        // SAFETY: arg came from a pinned box (see docstring), so re-pinning it is sound
+        unsafe { Pin::new_unchecked(Box::from_raw(arg)) }
+    }
+
+    /// Call [`PinnedBoxed::take_nonnull`], but return an error instead of panicking if `arg` is
+    /// NULL.
+    ///
+    /// # Safety
+    ///
+    /// * If `arg` is not NULL, the requirements are the same as for
+    ///   [`PinnedBoxed::take_nonnull`].
+    pub unsafe fn take_nonnull_checked(
+        arg: *mut RType,
+    ) -> Result<Pin<Box<RType>>, NullPointerError> {
+        if arg.is_null() {
+            return Err(NullPointerError);
+        }
+        // SAFETY: arg is not NULL (just checked), and see docstring
+        Ok(unsafe { Self::take_nonnull(arg) })
+    }
+
+    /// Call the contained function with a shared reference to the value.
+    ///
+    /// # Safety
+    ///
+    /// * `arg` must not be NULL.
+    /// * No other thread may mutate the value pointed to by `arg` until this function returns.
+    /// * Ownership of the value remains with the caller.
+    pub unsafe fn with_ref_nonnull<T, F: FnOnce(Pin<&RType>) -> T>(arg: *const RType, f: F) -> T {
+        if arg.is_null() {
+            panic!("NULL value not allowed");
+        }
+        // SAFETY: pointer came from a pinned box, so it has not moved
+        f(unsafe { Pin::new_unchecked(&*arg) })
+    }
+
+    /// Call the contained function with a pinned exclusive reference to the value.
+    ///
+    /// # Safety
+    ///
+    /// * `arg` must not be NULL.
+    /// * No other thread may _access_ the value pointed to by `arg` until this function returns.
+    /// * Ownership of the value remains with the caller.
+    pub unsafe fn with_ref_mut_nonnull<T, F: FnOnce(Pin<&mut RType>) -> T>(
+        arg: *mut RType,
+        f: F,
+    ) -> T {
+        if arg.is_null() {
+            panic!("NULL value not allowed");
+        }
+        // SAFETY: pointer came from a pinned box, so it has not moved
+        f(unsafe { Pin::new_unchecked(&mut *arg) })
+    }
+
+    /// Return a value to C, pinning it in a new allocation and transferring ownership.
+    ///
+    /// # Safety
+    ///
+    /// * The caller must ensure that the value is eventually freed.
+    pub unsafe fn return_val(rval: RType) -> *mut RType {
+        // SAFETY: same requirements as return_val_pinned
+        unsafe { Self::return_val_pinned(Box::pin(rval)) }
+    }
+
+    /// Return an already-pinned value to C, transferring ownership.
+    ///
+    /// # Safety
+    ///
+    /// * The caller must ensure that the value is eventually freed.
+    pub unsafe fn return_val_pinned(rval: Pin<Box<RType>>) -> *mut RType {
+        // SAFETY: the pointer is only converted back into a pinned box, or used to create
+        // pinned references, so the value is never moved
+        let ptr = Box::into_raw(unsafe { Pin::into_inner_unchecked(rval) });
+        #[cfg(feature = "track-allocations")]
+        crate::debug::record(ptr);
+        #[cfg(feature = "tracing")]
+        crate::trace::returned(ptr);
+        ptr
+    }
+
+    /// Return a value to C, transferring ownership, via an "output parameter".
+    ///
+    /// If the pointer is NULL, the value is dropped.
+    ///
+    /// # Safety
+    ///
+    /// * The caller must ensure that the value is eventually freed.
+    /// * If not NULL, `arg_out` must point to valid, properly aligned memory for a pointer value.
+    pub unsafe fn to_out_param(rval: RType, arg_out: *mut *mut RType) {
+        if !arg_out.is_null() {
+            // SAFETY: see docstring
+            unsafe { *arg_out = Self::return_val(rval) };
+        }
+    }
+
+    /// Return a value to C, transferring ownership, via an "output parameter".
+    ///
+    /// If the pointer is NULL, this function will panic.
+    ///
+    /// # Safety
+    ///
+    /// * The caller must ensure that the value is eventually freed.
+    /// * `arg_out` must not be NULL.
+    /// * `arg_out` must point to valid, properly aligned memory for a pointer value.
+    pub unsafe fn to_out_param_nonnull(rval: RType, arg_out: *mut *mut RType) {
+        if arg_out.is_null() {
+            panic!("out param pointer is NULL");
+        }
+        // SAFETY: see docstring
+        unsafe { *arg_out = Self::return_val(rval) };
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::marker::PhantomPinned;
+
+    /// A self-referential type, with a pointer to its own field.
+    struct SelfRef {
+        value: u32,
+        ptr: *const u32,
+        _pin: PhantomPinned,
+    }
+
+    impl SelfRef {
+        fn init(self: Pin<&mut Self>) {
+            // SAFETY: the value is not moved
+            let this = unsafe { self.get_unchecked_mut() };
+            this.ptr = &this.value;
+        }
+
+        fn check(self: Pin<&Self>) -> u32 {
+            assert_eq!(self.ptr, &self.value as *const u32);
+            unsafe { *self.ptr }
+        }
+    }
+
+    type PinnedSelfRef = PinnedBoxed<SelfRef>;
+
+    #[test]
+    fn pinned() {
+        unsafe {
+            let p = PinnedSelfRef::return_val(SelfRef {
+                value: 5,
+                ptr: std::ptr::null(),
+                _pin: PhantomPinned,
+            });
+            PinnedSelfRef::with_ref_mut_nonnull(p, |r| r.init());
+            assert_eq!(PinnedSelfRef::with_ref_nonnull(p, |r| r.check()), 5);
+            let pinned = PinnedSelfRef::take_nonnull(p);
+            assert_eq!(pinned.as_ref().check(), 5);
+        }
+    }
+
+    #[test]
+    fn to_out_param() {
+        let mut p = std::ptr::null_mut();
+        unsafe {
+            PinnedBoxed::to_out_param(10u32, &mut p);
+            assert_eq!(*PinnedBoxed::take_nonnull(p), 10);
+            PinnedBoxed::to_out_param(10u32, std::ptr::null_mut());
+        }
+    }
+
+    #[test]
+    fn checked() {
+        assert_eq!(
+            unsafe { PinnedBoxed::<u32>::take_nonnull_checked(std::ptr::null_mut()) },
+            Err(NullPointerError)
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn with_ref_mut_nonnull_null() {
+        unsafe { PinnedBoxed::<u32>::with_ref_mut_nonnull(std::ptr::null_mut(), |_| {}) };
+    }
+
+    #[test]
+    #[should_panic]
+    fn to_out_param_nonnull_null() {
+        unsafe { PinnedBoxed::to_out_param_nonnull(10u32, std::ptr::null_mut()) };
+    }
+}
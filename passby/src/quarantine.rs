@@ -0,0 +1,169 @@
+//! Quarantine of freed [`Boxed`](crate::Boxed) pointers, for detecting use after free and double
+//! free during integration testing.
+//!
+//! With the `quarantine` feature enabled, `Boxed` remembers the most recently freed pointers, and
+//! panics if one of them is passed back to Rust before it is returned to C again.  The panic names
+//! the type and address, and aborts the process at an `extern "C"` boundary, rather than leaving
+//! the bug to corrupt the heap.
+//!
+//! This is a debugging aid: only the last [`CAPACITY`] freed pointers are remembered, and a
+//! pointer is forgotten as soon as the allocator reuses its address for another `Boxed` value.
+
+use std::any::type_name;
+use std::collections::{BTreeMap, VecDeque};
+use std::mem;
+use std::sync::Mutex;
+
+/// The number of freed pointers to remember.
+const CAPACITY: usize = 1024;
+
+struct Quarantine {
+    /// Freed addresses, mapped to the sequence number at which they were freed.
+    freed: BTreeMap<usize, u64>,
+    /// Freed addresses and their sequence numbers, oldest first.  An address may appear here
+    /// after it has been removed from `freed`, or with an old sequence number.
+    order: VecDeque<(usize, u64)>,
+    seq: u64,
+}
+
+impl Quarantine {
+    const fn new() -> Self {
+        Quarantine {
+            freed: BTreeMap::new(),
+            order: VecDeque::new(),
+            seq: 0,
+        }
+    }
+
+    fn contains(&self, addr: usize) -> bool {
+        self.freed.contains_key(&addr)
+    }
+
+    fn insert(&mut self, addr: usize) {
+        self.seq += 1;
+        self.freed.insert(addr, self.seq);
+        self.order.push_back((addr, self.seq));
+        while self.order.len() > CAPACITY {
+            let (addr, seq) = self.order.pop_front().expect("order is not empty");
+            if self.freed.get(&addr) == Some(&seq) {
+                self.freed.remove(&addr);
+            }
+        }
+    }
+
+    fn remove(&mut self, addr: usize) {
+        self.freed.remove(&addr);
+    }
+}
+
+static QUARANTINE: Mutex<Quarantine> = Mutex::new(Quarantine::new());
+
+/// Panic if the value at `ptr` has been freed.
+pub(crate) fn check<T>(ptr: *const T) {
+    // zero-sized values do not have distinct addresses
+    if mem::size_of::<T>() == 0 {
+        return;
+    }
+    let freed = QUARANTINE
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .contains(ptr as usize);
+    if freed {
+        already_freed(ptr);
+    }
+}
+
+/// Record that the value at `ptr` is being freed, panicking if it has already been freed.
+pub(crate) fn freed<T>(ptr: *const T) {
+    if mem::size_of::<T>() == 0 {
+        return;
+    }
+    let mut q = QUARANTINE.lock().unwrap_or_else(|e| e.into_inner());
+    if q.contains(ptr as usize) {
+        drop(q);
+        already_freed(ptr);
+    }
+    q.insert(ptr as usize);
+}
+
+fn already_freed<T>(ptr: *const T) -> ! {
+    panic!(
+        "pointer to {} at {:#x} has already been freed (use after free or double free?)",
+        type_name::<T>(),
+        ptr as usize
+    );
+}
+
+/// Record that a value has been allocated at `ptr`, so it is no longer freed.
+pub(crate) fn allocated<T>(ptr: *const T) {
+    QUARANTINE
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .remove(ptr as usize);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Boxed;
+
+    struct Value(#[allow(dead_code)] u32);
+
+    #[test]
+    fn insert_and_remove() {
+        let mut q = Quarantine::new();
+        q.insert(0x100);
+        assert!(q.contains(0x100));
+        q.remove(0x100);
+        assert!(!q.contains(0x100));
+        q.insert(0x100);
+        assert!(q.contains(0x100));
+    }
+
+    #[test]
+    fn bounded() {
+        let mut q = Quarantine::new();
+        for addr in 0..=CAPACITY {
+            q.insert(addr);
+        }
+        assert!(!q.contains(0));
+        assert!(q.contains(1));
+        assert!(q.contains(CAPACITY));
+
+        // re-freeing an address keeps it until its newest entry is evicted
+        q.insert(1);
+        q.insert(CAPACITY + 1);
+        assert!(q.contains(1));
+    }
+
+    #[test]
+    #[should_panic(expected = "has already been freed")]
+    fn use_after_free() {
+        unsafe {
+            let ptr = Boxed::return_val(Value(1));
+            Boxed::take_nonnull(ptr);
+            Boxed::with_ref_nonnull(ptr, |_| {});
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "has already been freed")]
+    fn double_free() {
+        unsafe {
+            let ptr = Boxed::return_val(Value(1));
+            Boxed::take_nonnull(ptr);
+            Boxed::take_nonnull(ptr);
+        }
+    }
+
+    #[test]
+    fn reallocated() {
+        unsafe {
+            let ptr = Boxed::return_val(Value(1));
+            Boxed::take_nonnull(ptr);
+            // simulate the allocator reusing the address
+            allocated(ptr);
+            check(ptr);
+        }
+    }
+}
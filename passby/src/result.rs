@@ -0,0 +1,176 @@
+use crate::{ArcBoxed, Boxed, Unboxed, Value};
+use std::marker::PhantomData;
+
+/// OutParam is implemented by the pass-by types in this crate, such as [`Boxed`] and [`Value`],
+/// allowing a value to be returned to C via an "output parameter" without naming the specific
+/// pass-by type.
+pub trait OutParam {
+    /// The Rust type of the value.
+    type RType;
+    /// The type of the output parameter.
+    type Out: Copy;
+
+    /// Return a value to C via an output parameter, dropping the value if the pointer is NULL.
+    ///
+    /// # Safety
+    ///
+    /// * The safety requirements are the same as for the pass-by type's `to_out_param` method.
+    unsafe fn to_out_param(rval: Self::RType, arg_out: Self::Out);
+}
+
+impl<RType: Sized> OutParam for Boxed<RType> {
+    type RType = RType;
+    type Out = *mut *mut RType;
+
+    unsafe fn to_out_param(rval: RType, arg_out: Self::Out) {
+        // SAFETY: see docstring
+        unsafe { Boxed::to_out_param(rval, arg_out) }
+    }
+}
+
+impl<RType: Sized> OutParam for ArcBoxed<RType> {
+    type RType = RType;
+    type Out = *mut *const RType;
+
+    unsafe fn to_out_param(rval: RType, arg_out: Self::Out) {
+        // SAFETY: see docstring
+        unsafe { ArcBoxed::to_out_param(rval, arg_out) }
+    }
+}
+
+impl<RType: Sized, CType: Sized> OutParam for Unboxed<RType, CType> {
+    type RType = RType;
+    type Out = *mut CType;
+
+    unsafe fn to_out_param(rval: RType, arg_out: Self::Out) {
+        // SAFETY: see docstring
+        unsafe { Unboxed::to_out_param(rval, arg_out) }
+    }
+}
+
+impl<RType: Sized, CType: Sized + From<RType> + Into<RType>> OutParam for Value<RType, CType> {
+    type RType = RType;
+    type Out = *mut CType;
+
+    unsafe fn to_out_param(rval: RType, arg_out: Self::Out) {
+        // SAFETY: see docstring
+        unsafe { Value::to_out_param(rval, arg_out) }
+    }
+}
+
+/// FfiResult bridges a Rust `Result` to the common C pattern of a function returning a boolean
+/// success flag, with the value or error returned via output parameters.
+///
+/// The type parameters are the pass-by types used to return the value and the error.
+///
+/// # Example
+///
+/// ```
+/// # use ffizz_passby::{Boxed, FfiResult, Value};
+/// # struct Db;
+/// # fn open(path: u32) -> Result<Db, u32> { if path == 0 { Err(2) } else { Ok(Db) } }
+/// type DbResult = FfiResult<Boxed<Db>, Value<u32, u32>>;
+///
+/// #[no_mangle]
+/// pub unsafe extern "C" fn db_open(path: u32, db_out: *mut *mut Db, err_out: *mut u32) -> bool {
+///     // SAFETY:
+///     //  - db_out and err_out are NULL or valid pointers (documented in API)
+///     //  - the caller will free the db (documented in API)
+///     unsafe { DbResult::emit(open(path), db_out, err_out) }
+/// }
+/// ```
+#[non_exhaustive]
+pub struct FfiResult<V: OutParam, E: OutParam> {
+    _phantom: PhantomData<(V, E)>,
+}
+
+impl<V: OutParam, E: OutParam> FfiResult<V, E> {
+    /// Return a result to C, returning true and writing the value to `value_out` on success, or
+    /// returning false and writing the error to `err_out` on failure.
+    ///
+    /// Either output parameter may be NULL, in which case the corresponding value is dropped.
+    /// The other output parameter is not modified.
+    ///
+    /// # Safety
+    ///
+    /// * The safety requirements are the same as for the `to_out_param` methods of `V` and `E`.
+    pub unsafe fn emit(
+        result: Result<V::RType, E::RType>,
+        value_out: V::Out,
+        err_out: E::Out,
+    ) -> bool {
+        match result {
+            Ok(value) => {
+                // SAFETY: see docstring
+                unsafe { V::to_out_param(value, value_out) };
+                true
+            }
+            Err(err) => {
+                // SAFETY: see docstring
+                unsafe { E::to_out_param(err, err_out) };
+                false
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Db(u32);
+
+    type DbResult = FfiResult<Boxed<Db>, Value<u32, u32>>;
+
+    #[test]
+    fn emit_ok() {
+        let mut db: *mut Db = std::ptr::null_mut();
+        let mut err = 0u32;
+        unsafe {
+            assert!(DbResult::emit(Ok(Db(10)), &mut db, &mut err));
+            assert_eq!(err, 0);
+            assert_eq!(Boxed::take_nonnull(db), Db(10));
+        }
+    }
+
+    #[test]
+    fn emit_err() {
+        let mut db: *mut Db = std::ptr::null_mut();
+        let mut err = 0u32;
+        unsafe {
+            assert!(!DbResult::emit(Err(13), &mut db, &mut err));
+        }
+        assert!(db.is_null());
+        assert_eq!(err, 13);
+    }
+
+    #[test]
+    fn emit_null() {
+        unsafe {
+            assert!(DbResult::emit(
+                Ok(Db(10)),
+                std::ptr::null_mut(),
+                std::ptr::null_mut()
+            ));
+            assert!(!DbResult::emit(
+                Err(13),
+                std::ptr::null_mut(),
+                std::ptr::null_mut()
+            ));
+        }
+    }
+
+    #[test]
+    fn emit_shared_and_unboxed() {
+        type ArcResult = FfiResult<ArcBoxed<Db>, Unboxed<u64, u64>>;
+        let mut db: *const Db = std::ptr::null();
+        let mut err = 0u64;
+        unsafe {
+            assert!(!ArcResult::emit(Err(5), &mut db, &mut err));
+            assert_eq!(err, 5);
+            assert!(ArcResult::emit(Ok(Db(1)), &mut db, &mut err));
+            assert_eq!(ArcBoxed::take_nonnull(db).0, 1);
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use crate::{ArcBoxed, Boxed};
+
+/// Assert at compile time that a type is `Send`, for C APIs that allow values to be moved
+/// between threads.
+///
+/// This does nothing at runtime; it is used in a constant so that the compiler checks the claim
+/// made in the API documentation.  The `ASSERT_SEND` constants on [`Boxed`] and [`ArcBoxed`] do
+/// the same, given a type alias.
+///
+/// # Example
+///
+/// ```
+/// # use ffizz_passby::{assert_send, Boxed};
+/// pub struct Connection {
+///     // ...
+/// }
+///
+/// const _: () = assert_send::<Connection>();
+///
+/// type BoxedConnection = Boxed<Connection>;
+/// const _: () = BoxedConnection::ASSERT_SEND;
+/// ```
+///
+/// A type that is not `Send` fails to compile:
+///
+/// ```compile_fail
+/// # use ffizz_passby::assert_send;
+/// const _: () = assert_send::<std::rc::Rc<u32>>();
+/// ```
+pub const fn assert_send<T: Send + ?Sized>() {}
+
+/// Assert at compile time that a type is `Sync`, for C APIs that allow values to be used from
+/// several threads at once.  See [`assert_send`].
+pub const fn assert_sync<T: Sync + ?Sized>() {}
+
+impl<RType: Sized + Send> Boxed<RType> {
+    /// Assert that pointers to RType may be moved between threads.  Use this in a constant, as
+    /// for [`assert_send`].
+    ///
+    /// The constant only exists when RType is `Send`, so for any other type the compiler reports
+    /// that "the associated item `ASSERT_SEND` exists for struct `Boxed<..>`, but its trait bounds
+    /// were not satisfied" (older compilers say "no associated item named `ASSERT_SEND` found"),
+    /// followed by a note naming the unsatisfied `Send` bound:
+    ///
+    /// ```compile_fail
+    /// # use ffizz_passby::Boxed;
+    /// const _: () = Boxed::<std::rc::Rc<()>>::ASSERT_SEND;
+    /// ```
+    pub const ASSERT_SEND: () = assert_send::<RType>();
+}
+
+impl<RType: Sized + Sync> Boxed<RType> {
+    /// Assert that pointers to RType may be used from several threads at once.  Use this in a
+    /// constant, as for [`assert_send`].
+    ///
+    /// For a type that is not `Sync`, the compiler reports that the trait bounds of `ASSERT_SYNC`
+    /// were not satisfied, as for [`Boxed::ASSERT_SEND`]:
+    ///
+    /// ```compile_fail
+    /// # use ffizz_passby::Boxed;
+    /// const _: () = Boxed::<std::rc::Rc<()>>::ASSERT_SYNC;
+    /// ```
+    pub const ASSERT_SYNC: () = assert_sync::<RType>();
+}
+
+impl<RType: Sized + Send + Sync> ArcBoxed<RType> {
+    /// Assert that pointers to RType may be moved between threads.  Use this in a constant, as
+    /// for [`assert_send`].
+    ///
+    /// As for `Arc`, this requires RType to be both `Send` and `Sync`, since the value may be
+    /// dropped on any thread holding a reference.  Otherwise, the compiler reports that the trait
+    /// bounds of `ASSERT_SEND` were not satisfied, as for [`Boxed::ASSERT_SEND`]:
+    ///
+    /// ```compile_fail
+    /// # use ffizz_passby::ArcBoxed;
+    /// const _: () = ArcBoxed::<std::rc::Rc<()>>::ASSERT_SEND;
+    /// ```
+    pub const ASSERT_SEND: () = assert_send::<std::sync::Arc<RType>>();
+
+    /// Assert that pointers to RType may be used from several threads at once.  Use this in a
+    /// constant, as for [`assert_send`].
+    ///
+    /// ```compile_fail
+    /// # use ffizz_passby::ArcBoxed;
+    /// const _: () = ArcBoxed::<std::rc::Rc<()>>::ASSERT_SYNC;
+    /// ```
+    pub const ASSERT_SYNC: () = assert_sync::<std::sync::Arc<RType>>();
+}
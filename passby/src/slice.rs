@@ -0,0 +1,153 @@
+use std::marker::PhantomData;
+
+/// Slice is used to model a borrowed, read-only array passed from C as a pointer and a length,
+/// such as `const uint32_t *values, size_t len`.
+///
+/// A NULL pointer is allowed if the length is zero, and is treated as an empty slice.
+///
+/// # Example
+///
+/// ```
+/// # use ffizz_passby::Slice;
+/// type U32Slice = Slice<u32>;
+///
+/// #[no_mangle]
+/// pub unsafe extern "C" fn mylib_sum(values: *const u32, len: usize) -> u64 {
+///     // SAFETY: values points to len valid u32's (documented in API)
+///     unsafe { U32Slice::with_ref(values, len, |values| values.iter().map(|v| *v as u64).sum()) }
+/// }
+/// ```
+#[non_exhaustive]
+pub struct Slice<T: Sized> {
+    _phantom: PhantomData<T>,
+}
+
+impl<T: Sized> Slice<T> {
+    /// Call the contained function with a shared reference to the slice.
+    ///
+    /// This function panics if `ptr` is NULL and `len` is nonzero, or if `ptr` is not properly
+    /// aligned for T.
+    ///
+    /// # Safety
+    ///
+    /// * If not NULL, `ptr` must point to `len` consecutive, initialized values of type T.
+    /// * No other thread may mutate the values until this function returns.
+    /// * Ownership of the values remains with the caller.
+    pub unsafe fn with_ref<R, F: FnOnce(&[T]) -> R>(ptr: *const T, len: usize, f: F) -> R {
+        if ptr.is_null() {
+            if len != 0 {
+                panic!("NULL pointer with nonzero length");
+            }
+            return f(&[]);
+        }
+        check_slice(ptr, len);
+        // SAFETY:
+        //  - ptr is not NULL (just checked) and is properly aligned (checked by check_slice)
+        //  - ptr points to len initialized values (see docstring)
+        f(unsafe { std::slice::from_raw_parts(ptr, len) })
+    }
+}
+
+/// SliceMut is used to model a borrowed, mutable array passed from C as a pointer and a length,
+/// such as `uint8_t *buf, size_t len`.
+///
+/// A NULL pointer is allowed if the length is zero, and is treated as an empty slice.
+#[non_exhaustive]
+pub struct SliceMut<T: Sized> {
+    _phantom: PhantomData<T>,
+}
+
+impl<T: Sized> SliceMut<T> {
+    /// Call the contained function with an exclusive reference to the slice.
+    ///
+    /// This function panics if `ptr` is NULL and `len` is nonzero, or if `ptr` is not properly
+    /// aligned for T.
+    ///
+    /// # Safety
+    ///
+    /// * If not NULL, `ptr` must point to `len` consecutive, initialized values of type T.
+    /// * No other thread may _access_ the values until this function returns.
+    /// * Ownership of the values remains with the caller.
+    pub unsafe fn with_ref_mut<R, F: FnOnce(&mut [T]) -> R>(ptr: *mut T, len: usize, f: F) -> R {
+        if ptr.is_null() {
+            if len != 0 {
+                panic!("NULL pointer with nonzero length");
+            }
+            return f(&mut []);
+        }
+        check_slice(ptr, len);
+        // SAFETY:
+        //  - ptr is not NULL (just checked) and is properly aligned (checked by check_slice)
+        //  - ptr points to len initialized values, not otherwise accessed (see docstring)
+        f(unsafe { std::slice::from_raw_parts_mut(ptr, len) })
+    }
+}
+
+/// Check that a non-NULL pointer is aligned and that a slice of `len` values will not exceed
+/// `isize::MAX` bytes, panicking if not.
+fn check_slice<T>(ptr: *const T, len: usize) {
+    // alignments are powers of two
+    if ptr as usize & (std::mem::align_of::<T>() - 1) != 0 {
+        panic!("slice pointer is not properly aligned");
+    }
+    let too_large = match len.checked_mul(std::mem::size_of::<T>()) {
+        Some(size) => size > isize::MAX as usize,
+        None => true,
+    };
+    if too_large {
+        panic!("slice length is too large");
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn with_ref() {
+        let values = [1u32, 2, 3];
+        let sum: u32 =
+            unsafe { Slice::with_ref(values.as_ptr(), values.len(), |s| s.iter().sum()) };
+        assert_eq!(sum, 6);
+    }
+
+    #[test]
+    fn with_ref_null_empty() {
+        let len = unsafe { Slice::<u32>::with_ref(std::ptr::null(), 0, |s| s.len()) };
+        assert_eq!(len, 0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn with_ref_null_nonempty() {
+        unsafe { Slice::<u32>::with_ref(std::ptr::null(), 3, |_| {}) };
+    }
+
+    #[test]
+    #[should_panic]
+    fn with_ref_too_long() {
+        let values = [1u32, 2, 3];
+        unsafe { Slice::with_ref(values.as_ptr(), usize::MAX / 2, |_| {}) };
+    }
+
+    #[test]
+    fn with_ref_mut() {
+        let mut values = [1u8, 2, 3];
+        unsafe {
+            SliceMut::with_ref_mut(values.as_mut_ptr(), 2, |s| s.fill(0));
+        }
+        assert_eq!(values, [0, 0, 3]);
+    }
+
+    #[test]
+    fn with_ref_mut_null_empty() {
+        let len = unsafe { SliceMut::<u8>::with_ref_mut(std::ptr::null_mut(), 0, |s| s.len()) };
+        assert_eq!(len, 0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn with_ref_mut_null_nonempty() {
+        unsafe { SliceMut::<u8>::with_ref_mut(std::ptr::null_mut(), 1, |_| {}) };
+    }
+}
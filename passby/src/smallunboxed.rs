@@ -0,0 +1,312 @@
+use std::marker::PhantomData;
+use std::mem;
+
+/// SmallUnboxed is like [`crate::Unboxed`], with C managing the allocation for the value, except
+/// that an RType too large for CType is stored in a Rust allocation, with CType holding a pointer
+/// to it.
+///
+/// This allows RType to grow, such as by adding a field, without changing the C ABI.  Only an
+/// additional allocation is required, and only when the value does not fit.
+///
+/// Whether the value is stored inline is determined from the sizes and alignments of the types,
+/// so it does not need to be recorded in the C value.  RType is stored inline if it is no larger
+/// than CType and its alignment is no greater than that of CType.  Otherwise, CType must be large
+/// enough and sufficiently aligned to hold a pointer, and functions in this type will panic if it
+/// is not.
+///
+/// # Example
+///
+/// ```
+/// # use ffizz_passby::SmallUnboxed;
+/// struct Request {
+///     url: String,
+///     retries: u32,
+/// }
+///
+/// #[repr(C)]
+/// pub struct request_t([u64; 4]);
+///
+/// type SmallUnboxedRequest = SmallUnboxed<Request, request_t>;
+///
+/// #[no_mangle]
+/// pub unsafe extern "C" fn request_init(req_out: *mut request_t) {
+///     let req = Request { url: String::new(), retries: 3 };
+///     // SAFETY: req_out is valid and the value will be freed (documented in API)
+///     unsafe { SmallUnboxedRequest::to_out_param_nonnull(req, req_out) };
+/// }
+///
+/// #[no_mangle]
+/// pub unsafe extern "C" fn request_free(req: *mut request_t) {
+///     // SAFETY: req is valid and initialized (documented in API)
+///     drop(unsafe { SmallUnboxedRequest::take_ptr_nonnull(req) });
+/// }
+/// ```
+#[non_exhaustive]
+pub struct SmallUnboxed<RType: Sized, CType: Sized> {
+    _phantom: PhantomData<(RType, CType)>,
+}
+
+impl<RType: Sized, CType: Sized> SmallUnboxed<RType, CType> {
+    /// True if values are stored inline in CType, rather than in a Rust allocation.
+    pub const INLINE: bool = mem::size_of::<RType>() <= mem::size_of::<CType>()
+        && mem::align_of::<RType>() <= mem::align_of::<CType>();
+
+    /// Take a CType and return an owned value.
+    ///
+    /// As with [`crate::Unboxed::take`], prefer [`SmallUnboxed::take_ptr_nonnull`], which
+    /// invalidates the C value.
+    ///
+    /// # Safety
+    ///
+    /// * `cval` must be a valid CType value, initialized by this type.
+    pub unsafe fn take(cval: CType) -> RType {
+        let mut cval = mem::MaybeUninit::new(cval);
+        // SAFETY: cval is valid (see docstring), and is not used again
+        unsafe { Self::read(cval.as_mut_ptr()) }
+    }
+
+    /// Take a pointer to a CType and return an owned value, leaving behind a zeroed, invalid
+    /// value.
+    ///
+    /// # Safety
+    ///
+    /// * `cptr` must not be NULL and must point to a valid CType value, initialized by this
+    ///   type.
+    /// * The memory pointed to by `cptr` is invalid when this function returns.
+    pub unsafe fn take_ptr_nonnull(cptr: *mut CType) -> RType {
+        if cptr.is_null() {
+            panic!("NULL value not allowed");
+        }
+        // SAFETY: see docstring
+        unsafe {
+            let rval = Self::read(cptr);
+            (cptr as *mut u8).write_bytes(0, mem::size_of::<CType>());
+            rval
+        }
+    }
+
+    /// Call the contained function with a shared reference to the value.
+    ///
+    /// # Safety
+    ///
+    /// * `cptr` must not be NULL and must point to a valid CType value, initialized by this
+    ///   type.
+    /// * No other thread may mutate the value pointed to by `cptr` until the function returns.
+    /// * Ownership of the value remains with the caller.
+    pub unsafe fn with_ref_nonnull<T, F: FnOnce(&RType) -> T>(cptr: *const CType, f: F) -> T {
+        if cptr.is_null() {
+            panic!("NULL value not allowed");
+        }
+        // SAFETY: cptr points to a value stored by this type (see docstring)
+        f(unsafe { &*Self::value_ptr_const(cptr) })
+    }
+
+    /// Call the contained function with an exclusive reference to the value.
+    ///
+    /// # Safety
+    ///
+    /// * `cptr` must not be NULL and must point to a valid CType value, initialized by this
+    ///   type.
+    /// * No other thread may _access_ the value pointed to by `cptr` until the function returns.
+    /// * Ownership of the value remains with the caller.
+    pub unsafe fn with_ref_mut_nonnull<T, F: FnOnce(&mut RType) -> T>(cptr: *mut CType, f: F) -> T {
+        if cptr.is_null() {
+            panic!("NULL value not allowed");
+        }
+        // SAFETY: cptr points to a value stored by this type (see docstring)
+        f(unsafe { &mut *Self::value_ptr(cptr) })
+    }
+
+    /// Return a CType containing `rval`, moving `rval` in the process.
+    ///
+    /// # Safety
+    ///
+    /// * The caller must ensure that the value is eventually freed.
+    pub unsafe fn return_val(rval: RType) -> CType {
+        let mut cval = mem::MaybeUninit::<CType>::zeroed();
+        // SAFETY: cval is valid for writes, and is initialized afterward
+        unsafe {
+            Self::write(rval, cval.as_mut_ptr());
+            cval.assume_init()
+        }
+    }
+
+    /// Initialize the value pointed to by `arg_out` with `rval`, "moving" `rval` into the
+    /// pointer.
+    ///
+    /// If the pointer is NULL, `rval` is dropped.  Use [`SmallUnboxed::to_out_param_nonnull`] to
+    /// panic in this situation.
+    ///
+    /// # Safety
+    ///
+    /// * The caller must ensure that the value is eventually freed.
+    /// * If not NULL, `arg_out` must point to valid, properly aligned memory for CType.
+    pub unsafe fn to_out_param(rval: RType, arg_out: *mut CType) {
+        if !arg_out.is_null() {
+            // SAFETY: see docstring
+            unsafe { arg_out.write(Self::return_val(rval)) };
+        }
+    }
+
+    /// Initialize the value pointed to by `arg_out` with `rval`, "moving" `rval` into the
+    /// pointer.
+    ///
+    /// If the pointer is NULL, this method will panic.
+    ///
+    /// # Safety
+    ///
+    /// * The caller must ensure that the value is eventually freed.
+    /// * `arg_out` must not be NULL and must point to valid, properly aligned memory for CType.
+    pub unsafe fn to_out_param_nonnull(rval: RType, arg_out: *mut CType) {
+        if arg_out.is_null() {
+            panic!("out param pointer is NULL");
+        }
+        // SAFETY: see docstring
+        unsafe { arg_out.write(Self::return_val(rval)) };
+    }
+
+    /// Verify that CType can hold a pointer, if RType is not stored inline.  As with
+    /// [`crate::Unboxed`], this compiles to nothing if the requirements are met.
+    fn check_layout() {
+        assert!(
+            Self::INLINE
+                || (mem::size_of::<Box<RType>>() <= mem::size_of::<CType>()
+                    && mem::align_of::<Box<RType>>() <= mem::align_of::<CType>())
+        );
+    }
+
+    /// Get a shared pointer to the value stored in the CType at `cptr`, without creating an
+    /// exclusive reference to the Box, if any.
+    ///
+    /// # Safety
+    ///
+    /// * `cptr` must point to a valid CType value, initialized by this type.
+    unsafe fn value_ptr_const(cptr: *const CType) -> *const RType {
+        Self::check_layout();
+        if Self::INLINE {
+            cptr as *const RType
+        } else {
+            // SAFETY: the CType contains a Box (see docstring)
+            unsafe { &**(cptr as *const Box<RType>) }
+        }
+    }
+
+    /// Get a pointer to the value stored in the CType at `cptr`.
+    ///
+    /// # Safety
+    ///
+    /// * `cptr` must point to a valid CType value, initialized by this type.
+    unsafe fn value_ptr(cptr: *mut CType) -> *mut RType {
+        Self::check_layout();
+        if Self::INLINE {
+            cptr as *mut RType
+        } else {
+            // SAFETY: the CType contains a Box (see docstring)
+            unsafe { &mut **(cptr as *mut Box<RType>) }
+        }
+    }
+
+    /// Move `rval` into the CType at `cptr`, boxing it if required.
+    ///
+    /// # Safety
+    ///
+    /// * `cptr` must be valid for writes and properly aligned for CType.
+    unsafe fn write(rval: RType, cptr: *mut CType) {
+        Self::check_layout();
+        // SAFETY: check_layout ensures the value fits in CType, with compatible alignment
+        unsafe {
+            if Self::INLINE {
+                (cptr as *mut RType).write(rval);
+            } else {
+                (cptr as *mut Box<RType>).write(Box::new(rval));
+            }
+        }
+    }
+
+    /// Move the value out of the CType at `cptr`, freeing any allocation.
+    ///
+    /// # Safety
+    ///
+    /// * `cptr` must point to a valid CType value, initialized by this type.
+    /// * The value pointed to by `cptr` must not be used afterward.
+    unsafe fn read(cptr: *mut CType) -> RType {
+        Self::check_layout();
+        // SAFETY: the CType contains the value or a Box of the value (see docstring)
+        unsafe {
+            if Self::INLINE {
+                (cptr as *mut RType).read()
+            } else {
+                *(cptr as *mut Box<RType>).read()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Small(u64);
+    #[derive(Debug, PartialEq)]
+    struct Large(String, [u64; 4]);
+    struct CType([u64; 2]);
+
+    type SmallUnboxedSmall = SmallUnboxed<Small, CType>;
+    type SmallUnboxedLarge = SmallUnboxed<Large, CType>;
+
+    const _: () = assert!(SmallUnboxedSmall::INLINE && !SmallUnboxedLarge::INLINE);
+
+    #[test]
+    fn inline() {
+        unsafe {
+            let mut cval = SmallUnboxedSmall::return_val(Small(10));
+            assert_eq!(cval.0[0], 10);
+            SmallUnboxedSmall::with_ref_mut_nonnull(&mut cval, |rval| rval.0 += 1);
+            SmallUnboxedSmall::with_ref_nonnull(&cval, |rval| assert_eq!(rval.0, 11));
+            assert_eq!(SmallUnboxedSmall::take_ptr_nonnull(&mut cval), Small(11));
+            assert_eq!(cval.0, [0, 0]);
+        }
+    }
+
+    #[test]
+    fn spilled() {
+        unsafe {
+            let mut cval = mem::MaybeUninit::<CType>::uninit();
+            SmallUnboxedLarge::to_out_param_nonnull(Large("x".into(), [1; 4]), cval.as_mut_ptr());
+            let mut cval = cval.assume_init();
+            SmallUnboxedLarge::with_ref_mut_nonnull(&mut cval, |rval| rval.0.push('y'));
+            SmallUnboxedLarge::with_ref_nonnull(&cval, |rval| assert_eq!(rval.0, "xy"));
+            assert_eq!(
+                SmallUnboxedLarge::take_ptr_nonnull(&mut cval),
+                Large("xy".into(), [1; 4])
+            );
+
+            let cval = SmallUnboxedLarge::return_val(Large("z".into(), [2; 4]));
+            assert_eq!(SmallUnboxedLarge::take(cval), Large("z".into(), [2; 4]));
+        }
+    }
+
+    #[test]
+    fn to_out_param_null() {
+        unsafe { SmallUnboxedLarge::to_out_param(Large("x".into(), [0; 4]), std::ptr::null_mut()) };
+    }
+
+    #[test]
+    #[should_panic]
+    fn to_out_param_nonnull_null() {
+        unsafe { SmallUnboxedSmall::to_out_param_nonnull(Small(1), std::ptr::null_mut()) };
+    }
+
+    #[test]
+    #[should_panic]
+    fn with_ref_nonnull_null() {
+        unsafe { SmallUnboxedLarge::with_ref_nonnull(std::ptr::null(), |_| {}) };
+    }
+
+    #[test]
+    #[should_panic]
+    fn too_small_for_pointer() {
+        unsafe { SmallUnboxed::<Large, u8>::return_val(Large("x".into(), [0; 4])) };
+    }
+}
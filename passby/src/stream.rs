@@ -0,0 +1,365 @@
+use crate::{Boxed, Callback, Slice, SliceMut};
+use std::ffi::{c_int, c_void};
+use std::io::{self, Read, Write};
+
+/// The type of a C read function: read up to `len` bytes into `buf`, returning the number of
+/// bytes read, zero at end of stream, or a negative value on error.
+pub type CReadFn = unsafe extern "C" fn(buf: *mut u8, len: usize, user_data: *mut c_void) -> isize;
+
+/// The type of a C write function: write up to `len` bytes from `buf`, returning the number of
+/// bytes written or a negative value on error.
+pub type CWriteFn =
+    unsafe extern "C" fn(buf: *const u8, len: usize, user_data: *mut c_void) -> isize;
+
+/// The type of a C flush function, returning zero on success or a negative value on error.
+pub type CFlushFn = unsafe extern "C" fn(user_data: *mut c_void) -> c_int;
+
+/// CReader implements [`Read`] by calling a C read function, allowing Rust code to consume a
+/// stream supplied by C.
+///
+/// # Example
+///
+/// ```
+/// # use ffizz_passby::{CReader, CReadFn};
+/// # use std::ffi::c_void;
+/// # use std::io::Read;
+/// #[no_mangle]
+/// pub unsafe extern "C" fn mylib_count_bytes(read_fn: CReadFn, user_data: *mut c_void) -> i64 {
+///     // SAFETY: read_fn and user_data are valid during this call (documented in API)
+///     let reader = unsafe { CReader::new(read_fn, user_data) };
+///     match reader.bytes().try_fold(0, |n, b| b.map(|_| n + 1)) {
+///         Ok(n) => n,
+///         Err(_) => -1,
+///     }
+/// }
+/// ```
+pub struct CReader {
+    read: Callback<(*mut u8, usize), isize>,
+}
+
+impl CReader {
+    /// Create a new CReader from a read function and `user_data` supplied by C.
+    ///
+    /// # Safety
+    ///
+    /// * `read_fn` and `user_data` must meet the requirements of [`Callback::new`].
+    /// * `read_fn` must not write more than `len` bytes to `buf`.
+    pub unsafe fn new(read_fn: CReadFn, user_data: *mut c_void) -> Self {
+        // SAFETY: see docstring
+        let read = unsafe { Callback::new(read_fn, user_data) };
+        CReader { read }
+    }
+}
+
+impl Read for CReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.read.call((buf.as_mut_ptr(), buf.len()));
+        match usize::try_from(n) {
+            Ok(n) if n <= buf.len() => Ok(n),
+            Ok(_) => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "read function returned too many bytes",
+            )),
+            Err(_) => Err(io::Error::other("read function failed")),
+        }
+    }
+}
+
+/// CWriter implements [`Write`] by calling C write and flush functions, allowing Rust code to
+/// produce a stream consumed by C.
+pub struct CWriter {
+    write: Callback<(*const u8, usize), isize>,
+    flush: Option<Callback<(), c_int>>,
+}
+
+impl CWriter {
+    /// Create a new CWriter from a write function, an optional flush function, and `user_data`
+    /// supplied by C.  If the flush function is NULL, flushing does nothing.
+    ///
+    /// # Safety
+    ///
+    /// * `write_fn`, `flush_fn`, and `user_data` must meet the requirements of
+    ///   [`Callback::new`].
+    pub unsafe fn new(
+        write_fn: CWriteFn,
+        flush_fn: Option<CFlushFn>,
+        user_data: *mut c_void,
+    ) -> Self {
+        // SAFETY: see docstring
+        unsafe {
+            CWriter {
+                write: Callback::new(write_fn, user_data),
+                flush: Callback::from_nullable(flush_fn, user_data),
+            }
+        }
+    }
+}
+
+impl Write for CWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.write.call((buf.as_ptr(), buf.len()));
+        match usize::try_from(n) {
+            Ok(n) if n <= buf.len() => Ok(n),
+            Ok(_) => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "write function wrote too many bytes",
+            )),
+            Err(_) => Err(io::Error::other("write function failed")),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &self.flush {
+            Some(flush) if flush.call(()) < 0 => Err(io::Error::other("flush function failed")),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// BoxedRead exposes a Rust [`Read`] implementation to C behind a pointer, with "read" and
+/// "free" functions.
+///
+/// # Example
+///
+/// ```
+/// # use ffizz_passby::BoxedRead;
+/// #[no_mangle]
+/// pub unsafe extern "C" fn mylib_reader_read(reader: *mut BoxedRead, buf: *mut u8, len: usize) -> isize {
+///     // SAFETY: reader is valid and buf has len bytes (documented in API)
+///     unsafe { BoxedRead::read(reader, buf, len) }
+/// }
+/// ```
+pub struct BoxedRead {
+    inner: Box<dyn Read>,
+}
+
+impl BoxedRead {
+    /// Return a reader to C, boxing it and transferring ownership.
+    ///
+    /// # Safety
+    ///
+    /// * The caller must ensure that the reader is eventually freed.
+    pub unsafe fn return_val<R: Read + 'static>(reader: R) -> *mut Self {
+        // SAFETY: see docstring
+        unsafe {
+            Boxed::return_val(BoxedRead {
+                inner: Box::new(reader),
+            })
+        }
+    }
+
+    /// Read up to `len` bytes into `buf`, returning the number of bytes read, zero at end of
+    /// stream, or -1 on error.
+    ///
+    /// # Safety
+    ///
+    /// * `reader` must not be NULL and must be a value returned from [`BoxedRead::return_val`].
+    /// * No other thread may _access_ the reader until this function returns.
+    /// * `buf` must point to `len` bytes, which need not be initialized, and may be NULL only if
+    ///   `len` is zero.
+    pub unsafe fn read(reader: *mut Self, buf: *mut u8, len: usize) -> isize {
+        if !buf.is_null() {
+            // C may pass an uninitialized buffer, but Rust requires that it be initialized
+            // SAFETY: buf points to len bytes (see docstring)
+            unsafe { buf.write_bytes(0, len) };
+        }
+        // SAFETY: see docstring
+        unsafe {
+            Boxed::with_ref_mut_nonnull(reader, |reader| {
+                SliceMut::with_ref_mut(buf, len, |buf| retry_interrupted(|| reader.inner.read(buf)))
+            })
+        }
+    }
+
+    /// Free a reader.  If the pointer is NULL, nothing happens.
+    ///
+    /// # Safety
+    ///
+    /// * If not NULL, `reader` must be a value returned from [`BoxedRead::return_val`].
+    /// * `reader` becomes invalid and must not be used after this call.
+    pub unsafe fn free(reader: *mut Self) {
+        if !reader.is_null() {
+            // SAFETY: see docstring
+            drop(unsafe { Boxed::take_nonnull(reader) });
+        }
+    }
+}
+
+/// BoxedWrite exposes a Rust [`Write`] implementation to C behind a pointer, with "write",
+/// "flush", and "free" functions.
+pub struct BoxedWrite {
+    inner: Box<dyn Write>,
+}
+
+impl BoxedWrite {
+    /// Return a writer to C, boxing it and transferring ownership.
+    ///
+    /// # Safety
+    ///
+    /// * The caller must ensure that the writer is eventually freed.
+    pub unsafe fn return_val<W: Write + 'static>(writer: W) -> *mut Self {
+        // SAFETY: see docstring
+        unsafe {
+            Boxed::return_val(BoxedWrite {
+                inner: Box::new(writer),
+            })
+        }
+    }
+
+    /// Write up to `len` bytes from `buf`, returning the number of bytes written or -1 on error.
+    ///
+    /// # Safety
+    ///
+    /// * `writer` must not be NULL and must be a value returned from [`BoxedWrite::return_val`].
+    /// * No other thread may _access_ the writer until this function returns.
+    /// * `buf` must point to `len` initialized bytes, and may be NULL only if `len` is zero.
+    pub unsafe fn write(writer: *mut Self, buf: *const u8, len: usize) -> isize {
+        // SAFETY: see docstring
+        unsafe {
+            Boxed::with_ref_mut_nonnull(writer, |writer| {
+                Slice::with_ref(buf, len, |buf| {
+                    retry_interrupted(|| writer.inner.write(buf))
+                })
+            })
+        }
+    }
+
+    /// Flush the writer, returning zero on success or -1 on error.
+    ///
+    /// # Safety
+    ///
+    /// * `writer` must not be NULL and must be a value returned from [`BoxedWrite::return_val`].
+    /// * No other thread may _access_ the writer until this function returns.
+    pub unsafe fn flush(writer: *mut Self) -> c_int {
+        // SAFETY: see docstring
+        unsafe {
+            Boxed::with_ref_mut_nonnull(writer, |writer| match writer.inner.flush() {
+                Ok(()) => 0,
+                Err(_) => -1,
+            })
+        }
+    }
+
+    /// Free a writer.  The writer is not flushed, so any buffered data may be lost unless the
+    /// writer flushes itself when dropped.  If the pointer is NULL, nothing happens.
+    ///
+    /// # Safety
+    ///
+    /// * If not NULL, `writer` must be a value returned from [`BoxedWrite::return_val`].
+    /// * `writer` becomes invalid and must not be used after this call.
+    pub unsafe fn free(writer: *mut Self) {
+        if !writer.is_null() {
+            // SAFETY: see docstring
+            drop(unsafe { Boxed::take_nonnull(writer) });
+        }
+    }
+}
+
+/// Call `f` until it does not fail with `ErrorKind::Interrupted`, converting the result into a
+/// C return value.
+fn retry_interrupted(mut f: impl FnMut() -> io::Result<usize>) -> isize {
+    loop {
+        match f() {
+            Ok(n) => return n as isize,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(_) => return -1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    /// A C read function reading from a `&[u8]` pointed to by user_data.
+    unsafe extern "C" fn read_slice(buf: *mut u8, len: usize, user_data: *mut c_void) -> isize {
+        let src = unsafe { &mut *(user_data as *mut &[u8]) };
+        let n = len.min(src.len()).min(2);
+        unsafe { std::ptr::copy_nonoverlapping(src.as_ptr(), buf, n) };
+        *src = &src[n..];
+        n as isize
+    }
+
+    /// A C write function appending to a `Vec<u8>` pointed to by user_data.
+    unsafe extern "C" fn write_vec(buf: *const u8, len: usize, user_data: *mut c_void) -> isize {
+        let dst = unsafe { &mut *(user_data as *mut Vec<u8>) };
+        dst.extend_from_slice(unsafe { std::slice::from_raw_parts(buf, len) });
+        len as isize
+    }
+
+    unsafe extern "C" fn fail_flush(_user_data: *mut c_void) -> c_int {
+        -1
+    }
+
+    unsafe extern "C" fn fail_read(_buf: *mut u8, _len: usize, _user_data: *mut c_void) -> isize {
+        -1
+    }
+
+    #[test]
+    fn c_reader() {
+        let mut src: &[u8] = b"hello";
+        let mut reader = unsafe { CReader::new(read_slice, &mut src as *mut &[u8] as *mut c_void) };
+        let mut content = String::new();
+        reader.read_to_string(&mut content).unwrap();
+        assert_eq!(content, "hello");
+    }
+
+    #[test]
+    fn c_reader_error() {
+        let mut reader = unsafe { CReader::new(fail_read, std::ptr::null_mut()) };
+        assert!(reader.read(&mut [0u8; 4]).is_err());
+    }
+
+    #[test]
+    fn c_writer() {
+        let mut dst = Vec::new();
+        let user_data = &mut dst as *mut Vec<u8> as *mut c_void;
+        {
+            let mut writer = unsafe { CWriter::new(write_vec, None, user_data) };
+            write!(writer, "{}-{}", 1, 2).unwrap();
+            writer.flush().unwrap();
+        }
+        assert_eq!(dst, b"1-2");
+
+        let mut writer = unsafe { CWriter::new(write_vec, Some(fail_flush), user_data) };
+        assert!(writer.flush().is_err());
+    }
+
+    #[test]
+    fn boxed_read() {
+        let mut buf = [0u8; 4];
+        unsafe {
+            let reader = BoxedRead::return_val(&b"hello"[..]);
+            assert_eq!(BoxedRead::read(reader, buf.as_mut_ptr(), buf.len()), 4);
+            assert_eq!(&buf, b"hell");
+            assert_eq!(BoxedRead::read(reader, buf.as_mut_ptr(), buf.len()), 1);
+            assert_eq!(BoxedRead::read(reader, buf.as_mut_ptr(), buf.len()), 0);
+            assert_eq!(BoxedRead::read(reader, std::ptr::null_mut(), 0), 0);
+            BoxedRead::free(reader);
+        }
+    }
+
+    #[test]
+    fn boxed_write() {
+        /// A writer into a shared buffer, so that the test can see what was written.
+        struct Shared(Arc<Mutex<Vec<u8>>>);
+        impl Write for Shared {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.0.lock().unwrap().write(buf)
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let dst = Arc::new(Mutex::new(Vec::new()));
+        unsafe {
+            let writer = BoxedWrite::return_val(Shared(dst.clone()));
+            assert_eq!(BoxedWrite::write(writer, b"abc".as_ptr(), 3), 3);
+            assert_eq!(BoxedWrite::flush(writer), 0);
+            BoxedWrite::free(writer);
+        }
+        assert_eq!(*dst.lock().unwrap(), b"abc");
+    }
+}
@@ -0,0 +1,172 @@
+use crate::{ArcBoxed, Boxed, PinnedBoxed, SmallUnboxed, Unboxed, Value};
+use std::pin::Pin;
+use std::sync::Arc;
+
+/// TakeParam is implemented by the pass-by types in this crate, such as [`Boxed`] and [`Value`],
+/// allowing [`take_all!`] to take ownership of arguments without naming the specific method.
+///
+/// Types passed by pointer are taken with their `_nonnull` method, such as
+/// [`Boxed::take_nonnull`] or [`Unboxed::take_ptr_nonnull`], and panic if the pointer is NULL.
+///
+/// [`take_all!`]: crate::take_all
+pub trait TakeParam {
+    /// The Rust type of the taken value.
+    type RType;
+    /// The type of the argument passed from C.
+    type Arg;
+
+    /// Take ownership of a value passed from C.
+    ///
+    /// # Safety
+    ///
+    /// * The safety requirements are the same as for the pass-by type's method.
+    unsafe fn take_param(arg: Self::Arg) -> Self::RType;
+}
+
+impl<RType: Sized> TakeParam for Boxed<RType> {
+    type RType = RType;
+    type Arg = *mut RType;
+
+    unsafe fn take_param(arg: Self::Arg) -> RType {
+        // SAFETY: see docstring
+        unsafe { Boxed::take_nonnull(arg) }
+    }
+}
+
+impl<RType: Sized> TakeParam for ArcBoxed<RType> {
+    type RType = Arc<RType>;
+    type Arg = *const RType;
+
+    unsafe fn take_param(arg: Self::Arg) -> Arc<RType> {
+        // SAFETY: see docstring
+        unsafe { ArcBoxed::take_nonnull(arg) }
+    }
+}
+
+impl<RType: Sized> TakeParam for PinnedBoxed<RType> {
+    type RType = Pin<Box<RType>>;
+    type Arg = *mut RType;
+
+    unsafe fn take_param(arg: Self::Arg) -> Pin<Box<RType>> {
+        // SAFETY: see docstring
+        unsafe { PinnedBoxed::take_nonnull(arg) }
+    }
+}
+
+impl<RType: Sized, CType: Sized> TakeParam for Unboxed<RType, CType> {
+    type RType = RType;
+    type Arg = *mut CType;
+
+    unsafe fn take_param(arg: Self::Arg) -> RType {
+        // SAFETY: see docstring
+        unsafe { Unboxed::take_ptr_nonnull(arg) }
+    }
+}
+
+impl<RType: Sized, CType: Sized> TakeParam for SmallUnboxed<RType, CType> {
+    type RType = RType;
+    type Arg = *mut CType;
+
+    unsafe fn take_param(arg: Self::Arg) -> RType {
+        // SAFETY: see docstring
+        unsafe { SmallUnboxed::take_ptr_nonnull(arg) }
+    }
+}
+
+impl<RType: Sized, CType: Sized + From<RType> + Into<RType>> TakeParam for Value<RType, CType> {
+    type RType = RType;
+    type Arg = CType;
+
+    unsafe fn take_param(arg: Self::Arg) -> RType {
+        Value::take(arg)
+    }
+}
+
+/// Take ownership of several arguments at once, returning a tuple of the Rust values.
+///
+/// Each argument is given as `(arg, PassBy)`, where `PassBy` is a pass-by type implementing
+/// [`TakeParam`], such as `Boxed<Foo>`.  Every argument is taken before any code that might
+/// return early, so that a function documented as taking ownership of its arguments frees them
+/// all, even if it fails.
+///
+/// The macro must be used in an `unsafe` block, and each argument must meet the safety
+/// requirements of [`TakeParam::take_param`] for its type.
+///
+/// # Example
+///
+/// ```
+/// # use ffizz_passby::{take_all, Boxed};
+/// # struct Point(u32, u32);
+/// type BoxedPoint = Boxed<Point>;
+///
+/// /// Determine the distance between two points, taking ownership of both.  The distance is
+/// /// returned in `dist_out`, unless the points are equal.
+/// #[no_mangle]
+/// pub unsafe extern "C" fn point_dist(a: *mut Point, b: *mut Point, dist_out: *mut u32) -> bool {
+///     // SAFETY: a and b are valid and not used after this call (documented in API)
+///     let (a, b) = unsafe { take_all!((a, BoxedPoint), (b, BoxedPoint)) };
+///     if a.0 == b.0 && a.1 == b.1 {
+///         return false; // a and b are dropped here
+///     }
+///     // SAFETY: dist_out is valid and not NULL (documented in API)
+///     unsafe { *dist_out = a.0.abs_diff(b.0) + a.1.abs_diff(b.1) };
+///     true
+/// }
+/// # let mut dist = 0;
+/// # unsafe {
+/// #     let a = BoxedPoint::return_val(Point(1, 2));
+/// #     let b = BoxedPoint::return_val(Point(4, 0));
+/// #     assert!(point_dist(a, b, &mut dist));
+/// # }
+/// # assert_eq!(dist, 5);
+/// ```
+#[macro_export]
+macro_rules! take_all {
+    ($(($arg:expr, $passby:ty)),+ $(,)?) => {
+        ($(<$passby as $crate::TakeParam>::take_param($arg),)+)
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::static_assert_layout;
+    use std::mem::MaybeUninit;
+
+    #[derive(Debug, PartialEq)]
+    struct RType(u32);
+
+    #[repr(C)]
+    struct CType([u32; 1]);
+
+    static_assert_layout!(RType, CType);
+
+    #[test]
+    fn take_all_kinds() {
+        let mut cval = MaybeUninit::<CType>::uninit();
+        unsafe {
+            let boxed = Boxed::return_val(RType(1));
+            let arc = ArcBoxed::return_val(RType(2));
+            Unboxed::<RType, CType>::to_out_param(RType(3), cval.as_mut_ptr());
+            let (a, b, c, d) = take_all!(
+                (boxed, Boxed<RType>),
+                (arc, ArcBoxed<RType>),
+                (cval.as_mut_ptr(), Unboxed<RType, CType>),
+                (7u32, Value<u32, u32>),
+            );
+            assert_eq!(a, RType(1));
+            assert_eq!(*b, RType(2));
+            assert_eq!(c, RType(3));
+            assert_eq!(d, 7);
+        }
+    }
+
+    #[test]
+    fn take_all_one() {
+        unsafe {
+            let boxed = Boxed::return_val(RType(1));
+            let (a,) = take_all!((boxed, Boxed<RType>));
+            assert_eq!(a, RType(1));
+        }
+    }
+}
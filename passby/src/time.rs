@@ -0,0 +1,368 @@
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const NANOS_PER_SEC: i128 = 1_000_000_000;
+const NANOS_PER_MILLI: i128 = 1_000_000;
+
+/// InvalidTime is the error returned when a time value from C is invalid, such as a negative
+/// duration or a nanoseconds field of a second or more, or when a time cannot be represented in
+/// the C type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidTime;
+
+impl fmt::Display for InvalidTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid or out-of-range time value")
+    }
+}
+
+impl std::error::Error for InvalidTime {}
+
+/// Get the (possibly negative) number of nanoseconds between the epoch and `t`.
+fn nanos_since_epoch(t: SystemTime) -> i128 {
+    match t.duration_since(UNIX_EPOCH) {
+        Ok(d) => d.as_nanos() as i128,
+        Err(e) => -(e.duration().as_nanos() as i128),
+    }
+}
+
+/// Get the time `nanos` nanoseconds after (or before, if negative) the epoch.
+fn from_nanos_since_epoch(nanos: i128) -> Result<SystemTime, InvalidTime> {
+    let secs =
+        u64::try_from(nanos.unsigned_abs() / NANOS_PER_SEC as u128).map_err(|_| InvalidTime)?;
+    let d = Duration::new(secs, (nanos.unsigned_abs() % NANOS_PER_SEC as u128) as u32);
+    if nanos >= 0 {
+        UNIX_EPOCH.checked_add(d)
+    } else {
+        UNIX_EPOCH.checked_sub(d)
+    }
+    .ok_or(InvalidTime)
+}
+
+/// Split nanoseconds since the epoch into seconds, rounded down, and nanoseconds.
+fn split_nanos<S: TryFrom<i128>>(nanos: i128) -> Result<(S, u32), InvalidTime> {
+    let secs = S::try_from(nanos.div_euclid(NANOS_PER_SEC)).map_err(|_| InvalidTime)?;
+    Ok((secs, nanos.rem_euclid(NANOS_PER_SEC) as u32))
+}
+
+/// Join seconds and nanoseconds, checking that the nanoseconds are less than a second.
+fn join_nanos(secs: i128, nanos: i128) -> Result<i128, InvalidTime> {
+    if !(0..NANOS_PER_SEC).contains(&nanos) {
+        return Err(InvalidTime);
+    }
+    Ok(secs * NANOS_PER_SEC + nanos)
+}
+
+/// FzTimestamp is a `#[repr(C)]` representation of a [`SystemTime`], as seconds and nanoseconds
+/// since the Unix epoch, for use with [`crate::Value`].
+///
+/// Times before the epoch have negative `secs`, while `nanos` is always less than a second, so
+/// that a time 1.5 seconds before the epoch is `{ secs: -2, nanos: 500000000 }`.
+///
+/// In C, declare a struct with the same layout:
+///
+/// ```c
+/// typedef struct timestamp_t {
+///     int64_t secs;
+///     uint32_t nanos;
+/// } timestamp_t;
+/// ```
+///
+/// Converting an invalid value to a `SystemTime` with `From` panics.  To validate values from
+/// C, use [`FzTimestamp::try_into_system_time`] instead.
+///
+/// # Example
+///
+/// ```
+/// # use ffizz_passby::{FzTimestamp, Value};
+/// # use std::time::SystemTime;
+/// type TimestampValue = Value<SystemTime, FzTimestamp>;
+///
+/// #[no_mangle]
+/// pub extern "C" fn mylib_now() -> FzTimestamp {
+///     TimestampValue::return_val(SystemTime::now())
+/// }
+///
+/// #[no_mangle]
+/// pub extern "C" fn mylib_set_deadline(deadline: FzTimestamp) -> bool {
+///     let Ok(deadline) = deadline.try_into_system_time() else {
+///         return false;
+///     };
+///     // ...
+///     true
+/// }
+/// ```
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FzTimestamp {
+    /// Seconds since the epoch, rounded down.
+    pub secs: i64,
+    /// Nanoseconds after `secs`, less than one second.
+    pub nanos: u32,
+}
+
+impl FzTimestamp {
+    /// Convert a `SystemTime`, failing if it is too far from the epoch to represent.
+    pub fn try_from_system_time(t: SystemTime) -> Result<Self, InvalidTime> {
+        let (secs, nanos) = split_nanos(nanos_since_epoch(t))?;
+        Ok(FzTimestamp { secs, nanos })
+    }
+
+    /// Convert to a `SystemTime`, failing if the value is invalid or out of range.
+    pub fn try_into_system_time(self) -> Result<SystemTime, InvalidTime> {
+        from_nanos_since_epoch(join_nanos(self.secs as i128, self.nanos as i128)?)
+    }
+}
+
+impl From<SystemTime> for FzTimestamp {
+    fn from(t: SystemTime) -> Self {
+        FzTimestamp::try_from_system_time(t).expect("SystemTime out of range")
+    }
+}
+
+impl From<FzTimestamp> for SystemTime {
+    fn from(cval: FzTimestamp) -> Self {
+        cval.try_into_system_time().expect("invalid timestamp")
+    }
+}
+
+/// FzDuration is a `#[repr(C)]` representation of a [`Duration`], as seconds and nanoseconds,
+/// for use with [`crate::Value`].
+///
+/// In C, declare a struct with the same layout:
+///
+/// ```c
+/// typedef struct duration_t {
+///     uint64_t secs;
+///     uint32_t nanos;
+/// } duration_t;
+/// ```
+///
+/// Converting a value with `nanos` of a second or more to a `Duration` with `From` panics.  To
+/// validate values from C, use [`FzDuration::try_into_duration`] instead.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FzDuration {
+    /// Whole seconds.
+    pub secs: u64,
+    /// Nanoseconds after `secs`, less than one second.
+    pub nanos: u32,
+}
+
+impl FzDuration {
+    /// Convert to a `Duration`, failing if the value is invalid.
+    pub fn try_into_duration(self) -> Result<Duration, InvalidTime> {
+        if self.nanos as i128 >= NANOS_PER_SEC {
+            return Err(InvalidTime);
+        }
+        Ok(Duration::new(self.secs, self.nanos))
+    }
+}
+
+impl From<Duration> for FzDuration {
+    fn from(d: Duration) -> Self {
+        FzDuration {
+            secs: d.as_secs(),
+            nanos: d.subsec_nanos(),
+        }
+    }
+}
+
+impl From<FzDuration> for Duration {
+    fn from(cval: FzDuration) -> Self {
+        cval.try_into_duration().expect("invalid duration")
+    }
+}
+
+/// FzMillis is a number of milliseconds, represented in C as an `int64_t`, for use with
+/// [`crate::Value`].  It can represent either a [`SystemTime`], as milliseconds since the Unix
+/// epoch, or a [`Duration`].
+///
+/// Conversions to `FzMillis` round down to the nearest millisecond.  Converting a value that is
+/// out of range with `From` panics, as does converting a negative value to a `Duration`.  Use
+/// the `try_` methods to validate values instead.
+///
+/// # Example
+///
+/// ```
+/// # use ffizz_passby::{FzMillis, Value};
+/// # use std::time::Duration;
+/// type TimeoutValue = Value<Duration, FzMillis>;
+///
+/// assert_eq!(TimeoutValue::return_val(Duration::from_secs(2)), FzMillis(2000));
+/// assert_eq!(FzMillis(-5).try_into_duration(), Err(ffizz_passby::InvalidTime));
+/// ```
+#[repr(transparent)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FzMillis(pub i64);
+
+impl FzMillis {
+    /// Convert a `SystemTime`, failing if it is too far from the epoch to represent.
+    pub fn try_from_system_time(t: SystemTime) -> Result<Self, InvalidTime> {
+        let millis = nanos_since_epoch(t).div_euclid(NANOS_PER_MILLI);
+        Ok(FzMillis(i64::try_from(millis).map_err(|_| InvalidTime)?))
+    }
+
+    /// Convert to a `SystemTime`, failing if the value is out of range.
+    pub fn try_into_system_time(self) -> Result<SystemTime, InvalidTime> {
+        from_nanos_since_epoch(self.0 as i128 * NANOS_PER_MILLI)
+    }
+
+    /// Convert a `Duration`, failing if it is too long to represent.
+    pub fn try_from_duration(d: Duration) -> Result<Self, InvalidTime> {
+        Ok(FzMillis(
+            i64::try_from(d.as_millis()).map_err(|_| InvalidTime)?,
+        ))
+    }
+
+    /// Convert to a `Duration`, failing if the value is negative.
+    pub fn try_into_duration(self) -> Result<Duration, InvalidTime> {
+        let millis = u64::try_from(self.0).map_err(|_| InvalidTime)?;
+        Ok(Duration::from_millis(millis))
+    }
+}
+
+impl From<SystemTime> for FzMillis {
+    fn from(t: SystemTime) -> Self {
+        FzMillis::try_from_system_time(t).expect("SystemTime out of range")
+    }
+}
+
+impl From<FzMillis> for SystemTime {
+    fn from(cval: FzMillis) -> Self {
+        cval.try_into_system_time().expect("invalid timestamp")
+    }
+}
+
+impl From<Duration> for FzMillis {
+    fn from(d: Duration) -> Self {
+        FzMillis::try_from_duration(d).expect("Duration out of range")
+    }
+}
+
+impl From<FzMillis> for Duration {
+    fn from(cval: FzMillis) -> Self {
+        cval.try_into_duration().expect("invalid duration")
+    }
+}
+
+/// FzTimespec is a POSIX `struct timespec`, representing a [`SystemTime`] as seconds and
+/// nanoseconds since the Unix epoch, for use with [`crate::Value`].  It is available with the
+/// `libc` feature.
+///
+/// As for [`FzTimestamp`], converting an invalid value with `From` panics.  Use
+/// [`FzTimespec::try_into_system_time`] to validate values from C.
+#[cfg(feature = "libc")]
+#[repr(transparent)]
+#[derive(Clone, Copy)]
+pub struct FzTimespec(pub libc::timespec);
+
+#[cfg(feature = "libc")]
+impl FzTimespec {
+    /// Convert a `SystemTime`, failing if it is too far from the epoch to represent.
+    pub fn try_from_system_time(t: SystemTime) -> Result<Self, InvalidTime> {
+        let (tv_sec, nanos) = split_nanos(nanos_since_epoch(t))?;
+        // SAFETY: timespec is a plain C struct, for which all zeroes is valid
+        let mut ts: libc::timespec = unsafe { std::mem::zeroed() };
+        ts.tv_sec = tv_sec;
+        ts.tv_nsec = nanos as _;
+        Ok(FzTimespec(ts))
+    }
+
+    /// Convert to a `SystemTime`, failing if the value is invalid or out of range.
+    pub fn try_into_system_time(self) -> Result<SystemTime, InvalidTime> {
+        from_nanos_since_epoch(join_nanos(self.0.tv_sec as i128, self.0.tv_nsec as i128)?)
+    }
+}
+
+#[cfg(feature = "libc")]
+impl From<SystemTime> for FzTimespec {
+    fn from(t: SystemTime) -> Self {
+        FzTimespec::try_from_system_time(t).expect("SystemTime out of range")
+    }
+}
+
+#[cfg(feature = "libc")]
+impl From<FzTimespec> for SystemTime {
+    fn from(cval: FzTimespec) -> Self {
+        cval.try_into_system_time().expect("invalid timespec")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Value;
+
+    fn epoch_plus(nanos: i128) -> SystemTime {
+        from_nanos_since_epoch(nanos).unwrap()
+    }
+
+    #[test]
+    fn timestamp() {
+        type TimestampValue = Value<SystemTime, FzTimestamp>;
+        let t = epoch_plus(-1_500_000_000);
+        let cval = TimestampValue::return_val(t);
+        assert_eq!(
+            cval,
+            FzTimestamp {
+                secs: -2,
+                nanos: 500_000_000
+            }
+        );
+        assert_eq!(TimestampValue::take(cval), t);
+    }
+
+    #[test]
+    fn timestamp_invalid() {
+        let cval = FzTimestamp {
+            secs: 1,
+            nanos: 1_000_000_000,
+        };
+        assert_eq!(cval.try_into_system_time(), Err(InvalidTime));
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid timestamp")]
+    fn timestamp_invalid_take() {
+        Value::<SystemTime, FzTimestamp>::take(FzTimestamp {
+            secs: 1,
+            nanos: u32::MAX,
+        });
+    }
+
+    #[test]
+    fn duration() {
+        type DurationValue = Value<Duration, FzDuration>;
+        let d = Duration::new(3, 7);
+        assert_eq!(DurationValue::take(DurationValue::return_val(d)), d);
+        let cval = FzDuration {
+            secs: 0,
+            nanos: 1_000_000_000,
+        };
+        assert_eq!(cval.try_into_duration(), Err(InvalidTime));
+    }
+
+    #[test]
+    fn millis() {
+        assert_eq!(FzMillis::from(epoch_plus(-1)), FzMillis(-1));
+        assert_eq!(SystemTime::from(FzMillis(1500)), epoch_plus(1_500_000_000));
+        assert_eq!(FzMillis::from(Duration::from_micros(2500)), FzMillis(2));
+        assert_eq!(Duration::from(FzMillis(2)), Duration::from_millis(2));
+        assert_eq!(FzMillis::try_from_duration(Duration::MAX), Err(InvalidTime));
+        assert_eq!(FzMillis(-1).try_into_duration(), Err(InvalidTime));
+    }
+
+    #[cfg(feature = "libc")]
+    #[test]
+    fn timespec() {
+        let t = epoch_plus(1_000_000_123);
+        let cval = FzTimespec::from(t);
+        assert_eq!((cval.0.tv_sec, cval.0.tv_nsec), (1, 123));
+        assert_eq!(SystemTime::from(cval), t);
+
+        let mut cval = cval;
+        cval.0.tv_nsec = -1;
+        assert_eq!(cval.try_into_system_time(), Err(InvalidTime));
+    }
+}
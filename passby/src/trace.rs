@@ -0,0 +1,85 @@
+//! `tracing` events for ownership transfers, for following the lifetimes of values across the FFI
+//! boundary.
+//!
+//! With the `tracing` feature enabled, an event is emitted at the `TRACE` level each time a
+//! pointer is returned to C, and each time one is taken back, with the pointer and the name of
+//! its Rust type.  A leak appears as a returned pointer that is never taken, and a double free as
+//! a pointer taken twice.
+
+use std::any::type_name;
+
+/// Record that C has gained ownership of the value of type T at `ptr`.
+pub(crate) fn returned<T>(ptr: *const T) {
+    tracing::trace!(?ptr, type_name = type_name::<T>(), "value returned to C");
+}
+
+/// Record that C has given up ownership of the value of type T at `ptr`.
+pub(crate) fn taken<T>(ptr: *const T) {
+    tracing::trace!(?ptr, type_name = type_name::<T>(), "value taken from C");
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Boxed;
+    use std::fmt;
+    use std::sync::{Arc, Mutex};
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+
+    /// A subscriber recording the message and type name of each event.
+    #[derive(Clone, Default)]
+    struct Recorder(Arc<Mutex<Vec<String>>>);
+
+    /// A visitor collecting the values of fields other than the pointer, which varies.
+    struct Fields(Vec<String>);
+
+    impl Visit for Fields {
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            if field.name() != "ptr" {
+                self.0.push(format!("{value:?}"));
+            }
+        }
+
+        fn record_str(&mut self, field: &Field, value: &str) {
+            self.record_debug(field, &format_args!("{value}"));
+        }
+    }
+
+    impl Subscriber for Recorder {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, _: &Attributes<'_>) -> Id {
+            Id::from_u64(1)
+        }
+
+        fn record(&self, _: &Id, _: &Record<'_>) {}
+
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+        fn event(&self, event: &Event<'_>) {
+            let mut fields = Fields(vec![]);
+            event.record(&mut fields);
+            self.0.lock().unwrap().push(fields.0.join(" "));
+        }
+
+        fn enter(&self, _: &Id) {}
+
+        fn exit(&self, _: &Id) {}
+    }
+
+    #[test]
+    fn return_and_take() {
+        let recorder = Recorder::default();
+        tracing::subscriber::with_default(recorder.clone(), || unsafe {
+            let p = Boxed::return_val(10u32);
+            Boxed::take_nonnull(p);
+        });
+        assert_eq!(
+            *recorder.0.lock().unwrap(),
+            vec!["value returned to C u32", "value taken from C u32"]
+        );
+    }
+}
@@ -0,0 +1,167 @@
+//! Type tags on [`Boxed`](crate::Boxed) allocations, for detecting pointers of the wrong type.
+//!
+//! With the `type-tags` feature enabled, each allocation is prefixed with a tag identifying its
+//! type, which is checked whenever a pointer is passed back to Rust.  A mismatch panics with a
+//! message naming both types, which aborts the process at an `extern "C"` boundary.
+//!
+//! This is a debugging aid, and cannot detect all invalid pointers: a pointer that did not come
+//! from `Boxed` at all may fail the check, or may crash while reading the tag.
+
+use std::any::type_name;
+use std::collections::BTreeMap;
+use std::mem;
+use std::sync::Mutex;
+
+#[repr(C)]
+struct Tagged<T> {
+    tag: u64,
+    value: T,
+}
+
+/// The tag written to an allocation as it is freed, to detect use after free.
+const FREED: u64 = 0xdead_dead_dead_dead;
+
+/// The names of the types with each tag, for error messages.  Names are recorded as values are
+/// allocated, so the tag of any live value can be named.
+static NAMES: Mutex<BTreeMap<u64, &'static str>> = Mutex::new(BTreeMap::new());
+
+/// Get the tag for type T, a hash of its name.
+fn tag_of<T>() -> u64 {
+    // FNV-1a
+    type_name::<T>()
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325u64, |h, b| {
+            (h ^ b as u64).wrapping_mul(0x100_0000_01b3)
+        })
+}
+
+/// Get the tag for type T, recording its name for later error messages.
+fn register<T>() -> u64 {
+    let tag = tag_of::<T>();
+    NAMES
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .entry(tag)
+        .or_insert_with(type_name::<T>);
+    tag
+}
+
+/// Get a pointer to the Tagged containing the value at `ptr`.
+fn tagged_ptr<T>(ptr: *const T) -> *mut Tagged<T> {
+    (ptr as *mut u8).wrapping_sub(mem::offset_of!(Tagged<T>, value)) as *mut Tagged<T>
+}
+
+/// Allocate a tagged value, returning a pointer to the value.  This takes a box to match
+/// `Box::into_raw`, which it replaces.
+#[allow(clippy::boxed_local)]
+pub(crate) fn into_raw<T>(rval: Box<T>) -> *mut T {
+    let tagged = Box::into_raw(Box::new(Tagged {
+        tag: register::<T>(),
+        value: *rval,
+    }));
+    // SAFETY: tagged was just allocated
+    unsafe { std::ptr::addr_of_mut!((*tagged).value) }
+}
+
+/// Check that the value at `ptr` has the tag for type T, panicking if not.
+///
+/// # Safety
+///
+/// * `ptr` must not be NULL, and should have been returned from [`into_raw`].
+pub(crate) unsafe fn check<T>(ptr: *const T) {
+    // SAFETY: ptr came from into_raw, so is preceded by a tag (see docstring)
+    let found = unsafe { std::ptr::addr_of!((*tagged_ptr(ptr)).tag).read() };
+    let expected = tag_of::<T>();
+    if found != expected {
+        let found = match found {
+            FREED => "a freed value",
+            _ => NAMES
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .get(&found)
+                .copied()
+                .unwrap_or("an unknown value"),
+        };
+        panic!(
+            "pointer to {found} passed where {} was expected",
+            type_name::<T>()
+        );
+    }
+}
+
+/// Check the tag of the value at `ptr`, and take ownership of the value, freeing the allocation.
+///
+/// # Safety
+///
+/// * `ptr` must not be NULL, and should have been returned from [`into_raw`].
+/// * `ptr` becomes invalid and must not be used after this call.
+pub(crate) unsafe fn take<T>(ptr: *mut T) -> T {
+    // SAFETY: see docstring
+    unsafe {
+        check(ptr);
+        let tagged = tagged_ptr(ptr);
+        // mark the allocation as freed, in case the allocator does not overwrite it
+        std::ptr::addr_of_mut!((*tagged).tag).write_volatile(FREED);
+        Box::from_raw(tagged).value
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Boxed;
+
+    struct Db(#[allow(dead_code)] u32);
+    struct Query(#[allow(dead_code)] String);
+
+    #[test]
+    fn matching_type() {
+        unsafe {
+            let p = Boxed::return_val(String::from("hello"));
+            Boxed::with_ref_nonnull(p, |s| assert_eq!(s, "hello"));
+            assert_eq!(Boxed::take_nonnull(p), "hello");
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "pointer to ffizz_passby::typetag::test::Db passed where \
+        ffizz_passby::typetag::test::Query was expected")]
+    fn wrong_type() {
+        unsafe {
+            let db = Boxed::return_val(Db(1));
+            Boxed::<Query>::with_ref_nonnull(db as *const Query, |_| {});
+        }
+    }
+
+    #[test]
+    fn names_recorded_on_allocation() {
+        struct Allocated(#[allow(dead_code)] u8);
+        struct Checked(#[allow(dead_code)] u8);
+        let recorded = |tag| NAMES.lock().unwrap().contains_key(&tag);
+
+        unsafe {
+            let p = Boxed::return_val(Allocated(1));
+            assert!(recorded(tag_of::<Allocated>()));
+            Boxed::take_nonnull(p);
+
+            // checking a value does not record its name
+            let tagged = Tagged {
+                tag: tag_of::<Checked>(),
+                value: Checked(2),
+            };
+            check(&tagged.value);
+            assert!(!recorded(tag_of::<Checked>()));
+        }
+    }
+
+    #[test]
+    fn over_aligned() {
+        #[repr(align(32))]
+        struct Aligned(u8);
+        unsafe {
+            let p = Boxed::return_val(Aligned(3));
+            assert_eq!(p as usize % 32, 0);
+            assert_eq!(Boxed::take_nonnull(p).0, 3);
+        }
+    }
+}
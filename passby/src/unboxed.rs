@@ -1,15 +1,19 @@
-use crate::util::check_size_and_alignment;
+use crate::util::{catch_panic, check_size_and_alignment};
+use crate::NullPointerError;
 use std::default::Default;
 use std::marker::PhantomData;
 use std::mem;
 
+pub use ffizz_macros::unboxed_api;
+
 /// Unboxed is used to model values that are passed by reference, but where the memory allocation
 /// is handled by C. This approach allows the C code to allocate space for the value on the stack
 /// or in other structs, often avoiding unnecessary heap allocations.
 ///
 /// The two type parameters, RType and CType, must share the same alignment, and RType must not be
-/// larger than CType. Functions in this type will cause a runtime panic in debug builds if these
-/// requirements are violated.
+/// larger than CType. Functions in this type will cause a runtime panic in debug builds, or in all
+/// builds with the `strict-layout` feature, if these requirements are violated.  Use
+/// [`static_assert_layout!`](crate::static_assert_layout) to check them at compile time instead.
 ///
 /// If the fields of the struct are meant to be accessible to C, RType and CType may be the same
 /// type, trivially ensuring the alignment and size requirements are met.
@@ -113,6 +117,8 @@ impl<RType: Sized, CType: Sized> Unboxed<RType, CType> {
         if cptr.is_null() {
             panic!("NULL value not allowed");
         }
+        #[cfg(feature = "tracing")]
+        crate::trace::taken(cptr);
 
         // convert cptr to a reference to MaybeUninit<RType> (which is, for the moment,
         // actually initialized)
@@ -129,6 +135,19 @@ impl<RType: Sized, CType: Sized> Unboxed<RType, CType> {
         unsafe { owned.assume_init() }
     }
 
+    /// Call [`Unboxed::take_ptr_nonnull`], but return an error instead of panicking if `cptr` is NULL.
+    ///
+    /// # Safety
+    ///
+    /// * If `cptr` is not NULL, the requirements are the same as for [`Unboxed::take_ptr_nonnull`].
+    pub unsafe fn take_ptr_nonnull_checked(cptr: *mut CType) -> Result<RType, NullPointerError> {
+        if cptr.is_null() {
+            return Err(NullPointerError);
+        }
+        // SAFETY: cptr is not NULL (just checked), and see docstring
+        Ok(unsafe { Self::take_ptr_nonnull(cptr) })
+    }
+
     /// Call the contained function with a shared reference to the value.
     ///
     /// # Safety
@@ -143,11 +162,46 @@ impl<RType: Sized, CType: Sized> Unboxed<RType, CType> {
             panic!("NULL value not allowed");
         }
 
+        #[cfg(feature = "access-checks")]
+        let _guard = crate::access::shared(cptr as *const RType);
         // SAFETY:
         // - casting to a pointer type with the same alignment and smaller size
         f(unsafe { &*(cptr as *const RType) })
     }
 
+    /// Call [`Unboxed::with_ref_nonnull`], but return an error instead of panicking if `cptr` is NULL.
+    ///
+    /// # Safety
+    ///
+    /// * If `cptr` is not NULL, the requirements are the same as for [`Unboxed::with_ref_nonnull`].
+    pub unsafe fn with_ref_nonnull_checked<T, F: FnOnce(&RType) -> T>(
+        cptr: *const CType,
+        f: F,
+    ) -> Result<T, NullPointerError> {
+        if cptr.is_null() {
+            return Err(NullPointerError);
+        }
+        // SAFETY: cptr is not NULL (just checked), and see docstring
+        Ok(unsafe { Self::with_ref_nonnull(cptr, f) })
+    }
+
+    /// Call the contained function as for [`Unboxed::with_ref_nonnull`], catching any
+    /// panic, including that for a NULL pointer.
+    ///
+    /// The result is an error if a panic occurred, which the caller can convert into an error
+    /// code rather than allowing the panic to unwind into C.
+    ///
+    /// # Safety
+    ///
+    /// * The requirements are the same as for [`Unboxed::with_ref_nonnull`].
+    pub unsafe fn with_ref_nonnull_catch<T, F: FnOnce(&RType) -> T>(
+        cptr: *const CType,
+        f: F,
+    ) -> std::thread::Result<T> {
+        // SAFETY: see docstring
+        catch_panic(|| unsafe { Self::with_ref_nonnull(cptr, f) })
+    }
+
     /// Call the contained function with an exclusive reference to the data type.
     ///
     /// # Safety
@@ -162,11 +216,188 @@ impl<RType: Sized, CType: Sized> Unboxed<RType, CType> {
             panic!("NULL value not allowed");
         }
 
+        #[cfg(feature = "access-checks")]
+        let _guard = crate::access::exclusive(cptr as *const RType);
         // SAFETY:
         // - casting to a pointer type with the same alignment and smaller size
         f(unsafe { &mut *(cptr as *mut RType) })
     }
 
+    /// Call [`Unboxed::with_ref_mut_nonnull`], but return an error instead of panicking if `cptr` is NULL.
+    ///
+    /// # Safety
+    ///
+    /// * If `cptr` is not NULL, the requirements are the same as for [`Unboxed::with_ref_mut_nonnull`].
+    pub unsafe fn with_ref_mut_nonnull_checked<T, F: FnOnce(&mut RType) -> T>(
+        cptr: *mut CType,
+        f: F,
+    ) -> Result<T, NullPointerError> {
+        if cptr.is_null() {
+            return Err(NullPointerError);
+        }
+        // SAFETY: cptr is not NULL (just checked), and see docstring
+        Ok(unsafe { Self::with_ref_mut_nonnull(cptr, f) })
+    }
+
+    /// Call the contained function as for [`Unboxed::with_ref_mut_nonnull`], catching any
+    /// panic, including that for a NULL pointer.
+    ///
+    /// The result is an error if a panic occurred, which the caller can convert into an error
+    /// code rather than allowing the panic to unwind into C.  The value may be left in an
+    /// inconsistent state by a panic.
+    ///
+    /// # Safety
+    ///
+    /// * The requirements are the same as for [`Unboxed::with_ref_mut_nonnull`].
+    pub unsafe fn with_ref_mut_nonnull_catch<T, F: FnOnce(&mut RType) -> T>(
+        cptr: *mut CType,
+        f: F,
+    ) -> std::thread::Result<T> {
+        // SAFETY: see docstring
+        catch_panic(|| unsafe { Self::with_ref_mut_nonnull(cptr, f) })
+    }
+
+    /// Call the contained function with a shared reference to the value, or None if `cptr` is
+    /// NULL.  This allows each C API function to decide what NULL means.
+    ///
+    /// # Safety
+    ///
+    /// * If `cptr` is not NULL, the requirements are the same as for
+    ///   [`Unboxed::with_ref_nonnull`].
+    pub unsafe fn with_ref_opt<T, F: FnOnce(Option<&RType>) -> T>(cptr: *const CType, f: F) -> T {
+        if cptr.is_null() {
+            return f(None);
+        }
+        // SAFETY: cptr is not NULL (just checked), and see docstring
+        unsafe { Self::with_ref_nonnull(cptr, |rref| f(Some(rref))) }
+    }
+
+    /// Call the contained function with an exclusive reference to the value, or None if `cptr`
+    /// is NULL.
+    ///
+    /// # Safety
+    ///
+    /// * If `cptr` is not NULL, the requirements are the same as for
+    ///   [`Unboxed::with_ref_mut_nonnull`].
+    pub unsafe fn with_ref_mut_opt<T, F: FnOnce(Option<&mut RType>) -> T>(
+        cptr: *mut CType,
+        f: F,
+    ) -> T {
+        if cptr.is_null() {
+            return f(None);
+        }
+        // SAFETY: cptr is not NULL (just checked), and see docstring
+        unsafe { Self::with_ref_mut_nonnull(cptr, |rref| f(Some(rref))) }
+    }
+
+    /// Take a pointer to a CType and return an owned value, or None if `cptr` is NULL.
+    ///
+    /// # Safety
+    ///
+    /// * If `cptr` is not NULL, the requirements are the same as for
+    ///   [`Unboxed::take_ptr_nonnull`].
+    pub unsafe fn take_ptr_opt(cptr: *mut CType) -> Option<RType> {
+        if cptr.is_null() {
+            return None;
+        }
+        // SAFETY: cptr is not NULL (just checked), and see docstring
+        Some(unsafe { Self::take_ptr_nonnull(cptr) })
+    }
+
+    /// Call the contained function with shared references to two values, such as for a function
+    /// comparing two values.  The pointers may be equal.
+    ///
+    /// # Safety
+    ///
+    /// * The requirements for each pointer are the same as for [`Unboxed::with_ref_nonnull`].
+    pub unsafe fn with_ref2_nonnull<T, F: FnOnce(&RType, &RType) -> T>(
+        a: *const CType,
+        b: *const CType,
+        f: F,
+    ) -> T {
+        // SAFETY: see docstring
+        unsafe { Self::with_ref_nonnull(a, |a| Self::with_ref_nonnull(b, |b| f(a, b))) }
+    }
+
+    /// Call the contained function with exclusive references to two values, such as for a
+    /// function swapping two values.
+    ///
+    /// This function panics if either pointer is NULL, or if the pointers are equal, as that
+    /// would create two exclusive references to the same value.
+    ///
+    /// # Safety
+    ///
+    /// * The requirements for each pointer are the same as for [`Unboxed::with_ref_mut_nonnull`].
+    pub unsafe fn with_ref_mut2_nonnull<T, F: FnOnce(&mut RType, &mut RType) -> T>(
+        a: *mut CType,
+        b: *mut CType,
+        f: F,
+    ) -> T {
+        if a.is_null() || b.is_null() {
+            panic!("NULL value not allowed");
+        }
+        if a == b {
+            panic!("pointers to the same value not allowed");
+        }
+        // SAFETY: the pointers are distinct (just checked), and see docstring
+        unsafe { Self::with_ref_mut_nonnull(a, |a| Self::with_ref_mut_nonnull(b, |b| f(a, b))) }
+    }
+
+    /// Call the contained function with an exclusive reference to one value and a shared
+    /// reference to another, such as for a function merging `b` into `a`.
+    ///
+    /// This function panics if either pointer is NULL, or if the pointers are equal, as that
+    /// would create exclusive and shared references to the same value.
+    ///
+    /// # Safety
+    ///
+    /// * The requirements for `a` are the same as for [`Unboxed::with_ref_mut_nonnull`].
+    /// * The requirements for `b` are the same as for [`Unboxed::with_ref_nonnull`].
+    pub unsafe fn with_ref_mut_ref_nonnull<T, F: FnOnce(&mut RType, &RType) -> T>(
+        a: *mut CType,
+        b: *const CType,
+        f: F,
+    ) -> T {
+        if a.is_null() || b.is_null() {
+            panic!("NULL value not allowed");
+        }
+        if std::ptr::eq(a, b) {
+            panic!("pointers to the same value not allowed");
+        }
+        // SAFETY: the pointers are distinct (just checked), and see docstring
+        unsafe { Self::with_ref_mut_nonnull(a, |a| Self::with_ref_nonnull(b, |b| f(a, b))) }
+    }
+
+    /// Replace the value pointed to by `cptr` with `rval`, returning the previous value.  This
+    /// supports C API functions like `foo_reset`, where the caller may then drop the old value.
+    ///
+    /// # Safety
+    ///
+    /// * The requirements are the same as for [`Unboxed::with_ref_mut_nonnull`].
+    pub unsafe fn replace(cptr: *mut CType, rval: RType) -> RType {
+        // SAFETY: see docstring
+        unsafe { Self::with_ref_mut_nonnull(cptr, |rref| mem::replace(rref, rval)) }
+    }
+
+    /// Swap the values pointed to by `a` and `b`.
+    ///
+    /// This function panics if either pointer is NULL.  If the pointers are equal, it does
+    /// nothing.
+    ///
+    /// # Safety
+    ///
+    /// * The requirements for each pointer are the same as for [`Unboxed::with_ref_mut_nonnull`].
+    pub unsafe fn swap(a: *mut CType, b: *mut CType) {
+        if a.is_null() || b.is_null() {
+            panic!("NULL value not allowed");
+        }
+        if a == b {
+            return;
+        }
+        // SAFETY: see docstring
+        unsafe { Self::with_ref_mut2_nonnull(a, b, mem::swap) }
+    }
+
     /// Return a CType containing `rval`, moving `rval` in the process.
     ///
     /// # Safety
@@ -191,6 +422,8 @@ impl<RType: Sized, CType: Sized> Unboxed<RType, CType> {
             //  - arg_out is not NULL (just checked)
             //  - arg_out is properly aligned and points to valid memory (see docstring)
             unsafe { *arg_out = Self::into_ctype(rval) };
+            #[cfg(feature = "tracing")]
+            crate::trace::returned(arg_out);
         }
     }
 
@@ -210,6 +443,57 @@ impl<RType: Sized, CType: Sized> Unboxed<RType, CType> {
         //  - arg_out is not NULL (see docstring)
         //  - arg_out is properly aligned and points to valid memory (see docstring)
         unsafe { *arg_out = Self::into_ctype(rval) };
+        #[cfg(feature = "tracing")]
+        crate::trace::returned(arg_out);
+    }
+
+    /// Call [`Unboxed::to_out_param_nonnull`], but return an error instead of panicking if `arg_out` is NULL.
+    ///
+    /// If `arg_out` is NULL, `rval` is dropped.
+    ///
+    /// # Safety
+    ///
+    /// * If `arg_out` is not NULL, the requirements are the same as for [`Unboxed::to_out_param_nonnull`].
+    pub unsafe fn to_out_param_nonnull_checked(
+        rval: RType,
+        arg_out: *mut CType,
+    ) -> Result<(), NullPointerError> {
+        if arg_out.is_null() {
+            return Err(NullPointerError);
+        }
+        // SAFETY: arg_out is not NULL (just checked), and see docstring
+        unsafe { Self::to_out_param_nonnull(rval, arg_out) };
+        Ok(())
+    }
+
+    /// Write values to a caller-provided array of `cap` CType values, returning the number of
+    /// values written.  This supports the common C pattern of a caller providing a buffer of N
+    /// slots for the callee to fill.
+    ///
+    /// Values beyond the first `cap` are dropped.  This function panics if `dst` is NULL and
+    /// `cap` is nonzero.
+    ///
+    /// # Safety
+    ///
+    /// * The caller must ensure that the values written are eventually freed.
+    /// * If not NULL, `dst` must point to valid, properly aligned memory for `cap` CType values.
+    pub unsafe fn to_out_array(
+        values: impl IntoIterator<Item = RType>,
+        dst: *mut CType,
+        cap: usize,
+    ) -> usize {
+        if dst.is_null() && cap != 0 {
+            panic!("NULL pointer with nonzero length");
+        }
+        let mut count = 0;
+        for rval in values {
+            if count < cap {
+                // SAFETY: dst has space for cap values (see docstring), and count < cap
+                unsafe { dst.add(count).write(Self::into_ctype(rval)) };
+                count += 1;
+            }
+        }
+        count
     }
 
     /// Transmute a Rust value into a C value.
@@ -269,7 +553,8 @@ impl<RType: Sized + Default, CType: Sized> Unboxed<RType, CType> {
     /// Call the contained function with a shared reference to the value.
     ///
     /// If the given pointer is NULL, the contained function is called with a reference to RType's
-    /// default value, which is subsequently dropped.
+    /// default value, which is subsequently dropped.  Use [`Unboxed::with_ref_opt`] to handle NULL
+    /// explicitly.
     ///
     /// # Safety
     ///
@@ -283,15 +568,34 @@ impl<RType: Sized + Default, CType: Sized> Unboxed<RType, CType> {
             return f(&nullval);
         }
 
+        #[cfg(feature = "access-checks")]
+        let _guard = crate::access::shared(cptr as *const RType);
         // SAFETY:
         // - casting to a pointer type with the same alignment and smaller size
         f(unsafe { &*(cptr as *const RType) })
     }
 
+    /// Call the contained function as for [`Unboxed::with_ref`], catching any panic.
+    ///
+    /// The result is an error if a panic occurred, which the caller can convert into an error
+    /// code rather than allowing the panic to unwind into C.
+    ///
+    /// # Safety
+    ///
+    /// * The requirements are the same as for [`Unboxed::with_ref`].
+    pub unsafe fn with_ref_catch<T, F: FnOnce(&RType) -> T>(
+        cptr: *const CType,
+        f: F,
+    ) -> std::thread::Result<T> {
+        // SAFETY: see docstring
+        catch_panic(|| unsafe { Self::with_ref(cptr, f) })
+    }
+
     /// Call the contained function with an exclusive reference to the data type.
     ///
     /// If the given pointer is NULL, the contained function is called with a reference to RType's
-    /// default value, which is subsequently dropped.
+    /// default value, which is subsequently dropped.  Use [`Unboxed::with_ref_mut_opt`] to handle
+    /// NULL explicitly.
     ///
     /// # Safety
     ///
@@ -305,15 +609,34 @@ impl<RType: Sized + Default, CType: Sized> Unboxed<RType, CType> {
             return f(&mut nullval);
         }
 
+        #[cfg(feature = "access-checks")]
+        let _guard = crate::access::exclusive(cptr as *const RType);
         // SAFETY:
         // - casting to a pointer type with the same alignment and smaller size
         f(unsafe { &mut *(cptr as *mut RType) })
     }
 
+    /// Call the contained function as for [`Unboxed::with_ref_mut`], catching any panic.
+    ///
+    /// The result is an error if a panic occurred, which the caller can convert into an error
+    /// code rather than allowing the panic to unwind into C.  The value may be left in an
+    /// inconsistent state by a panic.
+    ///
+    /// # Safety
+    ///
+    /// * The requirements are the same as for [`Unboxed::with_ref_mut`].
+    pub unsafe fn with_ref_mut_catch<T, F: FnOnce(&mut RType) -> T>(
+        cptr: *mut CType,
+        f: F,
+    ) -> std::thread::Result<T> {
+        // SAFETY: see docstring
+        catch_panic(|| unsafe { Self::with_ref_mut(cptr, f) })
+    }
+
     /// Take a pointer to a CType and return an owned value.
     ///
     /// This is similar to [`Unboxed::take_ptr_nonnull`], but if given a NULL pointer will return the
-    /// default value.
+    /// default value.  Use [`Unboxed::take_ptr_opt`] to handle NULL explicitly.
     ///
     /// # Safety
     ///
@@ -324,6 +647,8 @@ impl<RType: Sized + Default, CType: Sized> Unboxed<RType, CType> {
         if cptr.is_null() {
             return RType::default();
         }
+        #[cfg(feature = "tracing")]
+        crate::trace::taken(cptr);
 
         // convert cptr to a reference to MaybeUninit<RType> (which is, for the moment,
         // actually initialized)
@@ -345,13 +670,22 @@ impl<RType: Sized + Default, CType: Sized> Unboxed<RType, CType> {
 mod test {
     mod size_panic {
         use super::super::*;
+        #[allow(dead_code)]
         struct TwoInts(u64, u64);
+        #[allow(dead_code)]
         struct OneInt(u64);
 
         type UnboxedTwoInts = Unboxed<TwoInts, OneInt>;
 
         #[test]
-        #[should_panic]
+        #[cfg_attr(
+            not(any(debug_assertions, feature = "strict-layout")),
+            ignore = "layout checks only run in debug builds or with strict-layout"
+        )]
+        #[should_panic(
+            expected = "size_panic::TwoInts (size 16, alignment 8) is larger than \
+            ffizz_passby::unboxed::test::size_panic::OneInt (size 8, alignment 8)"
+        )]
         fn test() {
             let cval = OneInt(10);
             unsafe {
@@ -362,13 +696,22 @@ mod test {
 
     mod align_panic {
         use super::super::*;
+        #[allow(dead_code)]
         struct OneInt(u64);
+        #[allow(dead_code)]
         struct EightBytes([u8; 8]);
 
         type UnboxedOneInt = Unboxed<OneInt, EightBytes>;
 
         #[test]
-        #[should_panic]
+        #[cfg_attr(
+            not(any(debug_assertions, feature = "strict-layout")),
+            ignore = "layout checks only run in debug builds or with strict-layout"
+        )]
+        #[should_panic(
+            expected = "align_panic::OneInt (size 8, alignment 8) has a different \
+            alignment than ffizz_passby::unboxed::test::align_panic::EightBytes (size 8, alignment 1)"
+        )]
         fn test() {
             let cval = EightBytes([0u8; 8]);
             unsafe {
@@ -380,6 +723,7 @@ mod test {
     use super::*;
     #[derive(Default)]
     struct RType(u32, u64);
+    #[allow(dead_code)]
     struct CType([u64; 3]); // NOTE: larger than RType
 
     type UnboxedTuple = Unboxed<RType, CType>;
@@ -460,6 +804,130 @@ mod test {
         }
     }
 
+    #[test]
+    fn with_ref_opt() {
+        unsafe {
+            let mut cval = UnboxedTuple::return_val(RType(10, 20));
+            assert_eq!(
+                UnboxedTuple::with_ref_opt(&cval, |r| r.map(|r| r.0)),
+                Some(10)
+            );
+            assert_eq!(
+                UnboxedTuple::with_ref_opt(std::ptr::null(), |r| r.map(|r| r.0)),
+                None
+            );
+            UnboxedTuple::with_ref_mut_opt(&mut cval, |r| r.unwrap().1 += 1);
+            assert!(UnboxedTuple::with_ref_mut_opt(std::ptr::null_mut(), |r| r.is_none()));
+            assert!(UnboxedTuple::take_ptr_opt(std::ptr::null_mut()).is_none());
+            assert_eq!(UnboxedTuple::take_ptr_opt(&mut cval).unwrap().1, 21);
+        }
+    }
+
+    #[test]
+    fn with_ref2() {
+        unsafe {
+            let mut a = UnboxedTuple::return_val(RType(1, 2));
+            let mut b = UnboxedTuple::return_val(RType(10, 20));
+            assert_eq!(
+                UnboxedTuple::with_ref2_nonnull(&a, &a, |a1, a2| a1.0 + a2.0),
+                2
+            );
+            UnboxedTuple::with_ref_mut2_nonnull(&mut a, &mut b, mem::swap);
+            UnboxedTuple::with_ref_mut_ref_nonnull(&mut a, &b, |a, b| a.1 += b.1);
+            assert_eq!(UnboxedTuple::take(a).1, 22);
+            assert_eq!(UnboxedTuple::take(b).0, 1);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "pointers to the same value not allowed")]
+    fn with_ref_mut2_same() {
+        unsafe {
+            let mut a = UnboxedTuple::return_val(RType(1, 2));
+            UnboxedTuple::with_ref_mut2_nonnull(&mut a, &mut a, |_, _| {});
+        }
+    }
+
+    #[test]
+    fn replace_and_swap() {
+        unsafe {
+            let mut a = UnboxedTuple::return_val(RType(1, 2));
+            let mut b = UnboxedTuple::return_val(RType(10, 20));
+            let old = UnboxedTuple::replace(&mut a, RType(3, 4));
+            assert_eq!((old.0, old.1), (1, 2));
+            UnboxedTuple::swap(&mut a, &mut b);
+            UnboxedTuple::swap(&mut a, &mut a);
+            assert_eq!(UnboxedTuple::take(a).0, 10);
+            assert_eq!(UnboxedTuple::take(b).1, 4);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "NULL value not allowed")]
+    fn swap_null() {
+        unsafe {
+            let mut a = UnboxedTuple::return_val(RType(1, 2));
+            UnboxedTuple::swap(&mut a, std::ptr::null_mut());
+        }
+    }
+
+    #[test]
+    #[cfg_attr(panic = "abort", ignore = "catching a panic requires unwinding")]
+    fn with_ref_catch() {
+        unsafe {
+            let mut cval = UnboxedTuple::return_val(RType(10, 20));
+            assert_eq!(
+                UnboxedTuple::with_ref_nonnull_catch(&cval, |rref| rref.0).unwrap(),
+                10
+            );
+            assert!(UnboxedTuple::with_ref_catch(&cval, |_| -> () { panic!("oops") }).is_err());
+            assert!(UnboxedTuple::with_ref_mut_catch(&mut cval, |rref| rref.1 += 1).is_ok());
+            assert!(UnboxedTuple::with_ref_nonnull_catch(std::ptr::null(), |_| {}).is_err());
+            assert!(
+                UnboxedTuple::with_ref_mut_nonnull_catch(std::ptr::null_mut(), |_| {}).is_err()
+            );
+            assert_eq!(UnboxedTuple::take(cval).1, 21);
+        }
+    }
+
+    #[test]
+    fn checked() {
+        unsafe {
+            let mut cval = mem::MaybeUninit::<CType>::uninit();
+            assert_eq!(
+                UnboxedTuple::to_out_param_nonnull_checked(RType(1, 2), std::ptr::null_mut()),
+                Err(NullPointerError)
+            );
+            assert_eq!(
+                UnboxedTuple::to_out_param_nonnull_checked(RType(1, 2), cval.as_mut_ptr()),
+                Ok(())
+            );
+            assert_eq!(
+                UnboxedTuple::with_ref_nonnull_checked(std::ptr::null(), |_| ()),
+                Err(NullPointerError)
+            );
+            assert_eq!(
+                UnboxedTuple::with_ref_nonnull_checked(cval.as_ptr(), |r| r.0),
+                Ok(1)
+            );
+            assert_eq!(
+                UnboxedTuple::with_ref_mut_nonnull_checked(std::ptr::null_mut(), |_| ()),
+                Err(NullPointerError)
+            );
+            assert_eq!(
+                UnboxedTuple::with_ref_mut_nonnull_checked(cval.as_mut_ptr(), |r| r.1 += 1),
+                Ok(())
+            );
+            assert!(UnboxedTuple::take_ptr_nonnull_checked(std::ptr::null_mut()).is_err());
+            assert_eq!(
+                UnboxedTuple::take_ptr_nonnull_checked(cval.as_mut_ptr())
+                    .unwrap()
+                    .1,
+                3
+            );
+        }
+    }
+
     #[test]
     fn to_out_param_null() {
         unsafe {
@@ -477,6 +945,32 @@ mod test {
         }
     }
 
+    #[test]
+    fn to_out_array() {
+        unsafe {
+            let mut cvals: [mem::MaybeUninit<CType>; 2] =
+                [mem::MaybeUninit::uninit(), mem::MaybeUninit::uninit()];
+            let values = (1..=3).map(|i| RType(i, i as u64 * 10));
+            let count = UnboxedTuple::to_out_array(values, cvals[0].as_mut_ptr(), cvals.len());
+            assert_eq!(count, 2);
+            UnboxedTuple::take(cvals[0].assume_init_read());
+            let rval = UnboxedTuple::take(cvals[1].assume_init_read());
+            assert_eq!(rval.0, 2);
+            assert_eq!(rval.1, 20);
+
+            let count = UnboxedTuple::to_out_array([RType(1, 2)], std::ptr::null_mut(), 0);
+            assert_eq!(count, 0);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn to_out_array_null() {
+        unsafe {
+            UnboxedTuple::to_out_array([RType(1, 2)], std::ptr::null_mut(), 1);
+        }
+    }
+
     #[test]
     fn return_val() {
         unsafe {
@@ -514,7 +1008,7 @@ mod test {
 
             // deallocate by turning cvalptr back into a Box and dropping the Box, but
             // using MaybeUninit to prevent dropping the (invalid) enclosed CType.
-            unsafe { Box::from_raw(cvalptr as *mut mem::MaybeUninit<CType>) };
+            drop(unsafe { Box::from_raw(cvalptr as *mut mem::MaybeUninit<CType>) });
         }
     }
 
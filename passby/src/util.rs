@@ -1,12 +1,57 @@
 use std::mem;
 
+// With the `strict-layout` feature, layout checks are retained in release builds.
+#[cfg(feature = "strict-layout")]
+use std::assert as layout_assert;
+#[cfg(not(feature = "strict-layout"))]
+use std::debug_assert as layout_assert;
+
 /// Verify that CType and RType have the same alignment requirements, and that RType is not larger
 /// than CType.
 ///
 /// These checks will compile to nothing if the requirements are met, and will compile to
 /// `debug_assert!(false)` if they are not met, causing all trait methods to panic.  That should be
-/// enough to get someone's attention!
+/// enough to get someone's attention!  The panic message gives the size and alignment of both
+/// types.  Use `static_assert_layout!` to catch this at compile time instead.
+///
+/// With the `strict-layout` feature, these are `assert!(false)` instead, so the checks also panic
+/// in release builds.
 pub(crate) fn check_size_and_alignment<CType: Sized, RType: Sized>() {
-    debug_assert!(mem::size_of::<RType>() <= mem::size_of::<CType>());
-    debug_assert!(mem::align_of::<RType>() == mem::align_of::<CType>());
+    layout_assert!(
+        mem::size_of::<RType>() <= mem::size_of::<CType>(),
+        "{}",
+        layout_mismatch::<CType, RType>("is larger than")
+    );
+    layout_assert!(
+        mem::align_of::<RType>() == mem::align_of::<CType>(),
+        "{}",
+        layout_mismatch::<CType, RType>("has a different alignment than")
+    );
+}
+
+/// Describe the layouts of CType and RType, for a failed check.
+#[cold]
+#[inline(never)]
+fn layout_mismatch<CType: Sized, RType: Sized>(problem: &str) -> String {
+    format!(
+        "{} (size {}, alignment {}) {problem} {} (size {}, alignment {})",
+        std::any::type_name::<RType>(),
+        mem::size_of::<RType>(),
+        mem::align_of::<RType>(),
+        std::any::type_name::<CType>(),
+        mem::size_of::<CType>(),
+        mem::align_of::<CType>(),
+    )
+}
+
+/// Call `f`, catching any panic.
+pub(crate) fn catch_panic<T>(f: impl FnOnce() -> T) -> std::thread::Result<T> {
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(f))
+}
+
+/// Convert a Vec into its pointer, length, and capacity, without dropping it.  This is a stable
+/// version of the unstable `Vec::into_raw_parts`.
+pub(crate) fn vec_into_raw_parts<T>(vec: Vec<T>) -> (*mut T, usize, usize) {
+    let mut vec = std::mem::ManuallyDrop::new(vec);
+    (vec.as_mut_ptr(), vec.len(), vec.capacity())
 }
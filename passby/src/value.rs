@@ -1,3 +1,4 @@
+use crate::NullPointerError;
 use std::marker::PhantomData;
 
 /// Value is used to "pass by value' semantics.
@@ -85,6 +86,121 @@ where
         //  - arg_out is properly aligned and points to valid memory (see docstring)
         unsafe { *arg_out = CType::from(rval) };
     }
+
+    /// Call [`Value::to_out_param_nonnull`], but return an error instead of panicking if `arg_out` is NULL.
+    ///
+    /// If `arg_out` is NULL, `rval` is dropped.
+    ///
+    /// # Safety
+    ///
+    /// * If `arg_out` is not NULL, the requirements are the same as for [`Value::to_out_param_nonnull`].
+    pub unsafe fn to_out_param_nonnull_checked(
+        rval: RType,
+        arg_out: *mut CType,
+    ) -> Result<(), NullPointerError> {
+        if arg_out.is_null() {
+            return Err(NullPointerError);
+        }
+        // SAFETY: arg_out is not NULL (just checked), and see docstring
+        unsafe { Self::to_out_param_nonnull(rval, arg_out) };
+        Ok(())
+    }
+
+    /// Take a pointer to a CType and return an owned value.
+    ///
+    /// This is intended for C API functions that take a value by pointer, such as a small struct
+    /// passed as `const foo_t *`.  The value is moved out of the pointer.  For Copy types this
+    /// has no visible effect; for others, the C code must not use the value again.
+    ///
+    /// # Safety
+    ///
+    /// * `cptr` must not be NULL and must point to a valid CType value (see [`Value::take_ptr`] for a
+    ///   version allowing NULL).
+    /// * Unless CType is Copy, the value pointed to by `cptr` must not be used after this
+    ///   function returns.
+    pub unsafe fn take_ptr_nonnull(cptr: *mut CType) -> RType {
+        if cptr.is_null() {
+            panic!("NULL value not allowed");
+        }
+        // SAFETY:
+        //  - cptr is not NULL (just checked)
+        //  - cptr points to a valid CType, which is not used again (see docstring)
+        unsafe { std::ptr::read(cptr) }.into()
+    }
+}
+
+impl<RType, CType> Value<RType, CType>
+where
+    RType: Sized,
+    CType: Sized + Clone + From<RType> + Into<RType>,
+{
+    /// Call the contained function with a shared reference to the Rust value corresponding to
+    /// the CType pointed to by `cptr`.
+    ///
+    /// The Rust value is converted from a clone of the CType, and is dropped when the function
+    /// returns.
+    ///
+    /// # Safety
+    ///
+    /// * `cptr` must not be NULL and must point to a valid CType value (see [`Value::with_ref`] for a
+    ///   version allowing NULL).
+    /// * No other thread may mutate the value pointed to by `cptr` until the function returns.
+    pub unsafe fn with_ref_nonnull<T, F: FnOnce(&RType) -> T>(cptr: *const CType, f: F) -> T {
+        if cptr.is_null() {
+            panic!("NULL value not allowed");
+        }
+        // SAFETY:
+        //  - cptr is not NULL (just checked)
+        //  - cptr points to a valid CType (see docstring)
+        let rval: RType = unsafe { &*cptr }.clone().into();
+        f(&rval)
+    }
+}
+
+impl<RType, CType> Value<RType, CType>
+where
+    RType: Sized + Default,
+    CType: Sized + From<RType> + Into<RType>,
+{
+    /// Take a pointer to a CType and return an owned value.
+    ///
+    /// This is similar to [`Value::take_ptr_nonnull`], but if given a NULL pointer will return the
+    /// default value.
+    ///
+    /// # Safety
+    ///
+    /// * If not NULL, the requirements are the same as for [`Value::take_ptr_nonnull`].
+    pub unsafe fn take_ptr(cptr: *mut CType) -> RType {
+        if cptr.is_null() {
+            return RType::default();
+        }
+        // SAFETY: cptr is not NULL (just checked), and see docstring
+        unsafe { Self::take_ptr_nonnull(cptr) }
+    }
+}
+
+impl<RType, CType> Value<RType, CType>
+where
+    RType: Sized + Default,
+    CType: Sized + Clone + From<RType> + Into<RType>,
+{
+    /// Call the contained function with a shared reference to the Rust value corresponding to
+    /// the CType pointed to by `cptr`.
+    ///
+    /// If the given pointer is NULL, the contained function is called with a reference to RType's
+    /// default value.
+    ///
+    /// # Safety
+    ///
+    /// * If not NULL, the requirements are the same as for [`Value::with_ref_nonnull`].
+    pub unsafe fn with_ref<T, F: FnOnce(&RType) -> T>(cptr: *const CType, f: F) -> T {
+        if cptr.is_null() {
+            let nullval = RType::default();
+            return f(&nullval);
+        }
+        // SAFETY: cptr is not NULL (just checked), and see docstring
+        unsafe { Self::with_ref_nonnull(cptr, f) }
+    }
 }
 
 #[cfg(test)]
@@ -99,6 +215,7 @@ mod test {
         error_code: u32,
     }
 
+    #[allow(clippy::from_over_into)]
     impl Into<Result<(), u32>> for result_t {
         fn into(self) -> Result<(), u32> {
             if self.is_ok {
@@ -167,6 +284,73 @@ mod test {
         assert_eq!(ResultValue::take(unsafe { cval.assume_init() }), Ok(()));
     }
 
+    #[test]
+    fn to_out_param_nonnull_checked() {
+        let mut cval = mem::MaybeUninit::uninit();
+        // SAFETY: arg_out is NULL or valid
+        unsafe {
+            assert_eq!(
+                ResultValue::to_out_param_nonnull_checked(Err(3), std::ptr::null_mut()),
+                Err(NullPointerError)
+            );
+            assert_eq!(
+                ResultValue::to_out_param_nonnull_checked(Err(3), cval.as_mut_ptr()),
+                Ok(())
+            );
+        }
+        // SAFETY: to_out_param_nonnull_checked initialized cval
+        assert_eq!(ResultValue::take(unsafe { cval.assume_init() }), Err(3));
+    }
+
+    type IntValue = Value<u32, u32>;
+
+    #[test]
+    fn with_ref() {
+        let cval = result_t {
+            is_ok: false,
+            error_code: 13,
+        };
+        // SAFETY: cptr is valid or NULL
+        unsafe {
+            assert_eq!(ResultValue::with_ref_nonnull(&cval, |rval| *rval), Err(13));
+            assert_eq!(IntValue::with_ref(&7, |rval| *rval), 7);
+            assert_eq!(IntValue::with_ref(std::ptr::null(), |rval| *rval), 0);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "NULL value not allowed")]
+    fn with_ref_nonnull_null() {
+        // SAFETY: well, it's not safe, that's why it panics!
+        unsafe {
+            ResultValue::with_ref_nonnull(std::ptr::null(), |_| {});
+        }
+    }
+
+    #[test]
+    fn take_ptr() {
+        let mut cval = result_t {
+            is_ok: true,
+            error_code: 0,
+        };
+        let mut ival = 5;
+        // SAFETY: cptr is valid or NULL, and not used again
+        unsafe {
+            assert_eq!(ResultValue::take_ptr_nonnull(&mut cval), Ok(()));
+            assert_eq!(IntValue::take_ptr(&mut ival), 5);
+            assert_eq!(IntValue::take_ptr(std::ptr::null_mut()), 0);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "NULL value not allowed")]
+    fn take_ptr_nonnull_null() {
+        // SAFETY: well, it's not safe, that's why it panics!
+        unsafe {
+            IntValue::take_ptr_nonnull(std::ptr::null_mut());
+        }
+    }
+
     #[test]
     #[should_panic]
     fn to_out_param_nonnull_null() {
@@ -0,0 +1,208 @@
+use crate::NullPointerError;
+use std::marker::PhantomData;
+
+/// ConvertWith defines conversions between a C type and a Rust type that require some context,
+/// such as an interner or a registry.  It is implemented on the C type, for use with
+/// [`ValueWith`].
+pub trait ConvertWith<RType, Ctx: ?Sized>: Sized {
+    /// Convert a Rust value into this C type.
+    fn from_rust(rval: RType, ctx: &Ctx) -> Self;
+
+    /// Convert this C value into the Rust type.
+    fn into_rust(self, ctx: &Ctx) -> RType;
+}
+
+/// ValueWith is like [`crate::Value`], but for conversions that require a context value, given
+/// as a `&Ctx` to each method.  This avoids storing the context in a global.
+///
+/// CType must implement [`ConvertWith<RType, Ctx>`].
+///
+/// # Example
+///
+/// ```
+/// # use ffizz_passby::{ConvertWith, ValueWith};
+/// # use std::collections::HashMap;
+/// #[derive(Default)]
+/// pub struct Interner(Vec<String>, HashMap<String, u32>);
+///
+/// #[repr(C)]
+/// pub struct symbol_t(u32);
+///
+/// impl ConvertWith<String, Interner> for symbol_t {
+///     fn from_rust(rval: String, ctx: &Interner) -> Self {
+///         symbol_t(ctx.1[&rval])
+///     }
+///
+///     fn into_rust(self, ctx: &Interner) -> String {
+///         ctx.0[self.0 as usize].clone()
+///     }
+/// }
+///
+/// type SymbolValue = ValueWith<String, symbol_t, Interner>;
+///
+/// let interner = Interner(vec!["x".into()], [("x".into(), 0)].into_iter().collect());
+/// let sym = SymbolValue::return_val("x".into(), &interner);
+/// assert_eq!(sym.0, 0);
+/// assert_eq!(SymbolValue::take(sym, &interner), "x");
+/// ```
+#[non_exhaustive]
+pub struct ValueWith<RType, CType, Ctx>
+where
+    RType: Sized,
+    CType: Sized + ConvertWith<RType, Ctx>,
+    Ctx: ?Sized,
+{
+    _phantom: PhantomData<(RType, CType, *const Ctx)>,
+}
+
+impl<RType, CType, Ctx> ValueWith<RType, CType, Ctx>
+where
+    RType: Sized,
+    CType: Sized + ConvertWith<RType, Ctx>,
+    Ctx: ?Sized,
+{
+    /// Take a CType and return an owned value.
+    pub fn take(cval: CType, ctx: &Ctx) -> RType {
+        cval.into_rust(ctx)
+    }
+
+    /// Return a CType containing rval, moving rval in the process.
+    pub fn return_val(rval: RType, ctx: &Ctx) -> CType {
+        CType::from_rust(rval, ctx)
+    }
+
+    /// Initialize the value pointed to `arg_out` with rval, "moving" rval into the pointer.
+    ///
+    /// If the pointer is NULL, rval is dropped.  Use [`ValueWith::to_out_param_nonnull`] to
+    /// panic in this situation.
+    ///
+    /// # Safety
+    ///
+    /// * if `arg_out` is not NULL, then it must be aligned for and have enough space for
+    ///   CType.
+    pub unsafe fn to_out_param(rval: RType, ctx: &Ctx, arg_out: *mut CType) {
+        if !arg_out.is_null() {
+            // SAFETY: arg_out is not NULL (just checked), and see docstring
+            unsafe { *arg_out = CType::from_rust(rval, ctx) };
+        }
+    }
+
+    /// Initialize the value pointed to `arg_out` with rval, "moving" rval into the pointer.
+    ///
+    /// If the pointer is NULL, this method will panic.
+    ///
+    /// # Safety
+    ///
+    /// * `arg_out` must not be NULL, must be aligned for CType and have enough space for CType.
+    pub unsafe fn to_out_param_nonnull(rval: RType, ctx: &Ctx, arg_out: *mut CType) {
+        if arg_out.is_null() {
+            panic!("out param pointer is NULL");
+        }
+        // SAFETY: see docstring
+        unsafe { *arg_out = CType::from_rust(rval, ctx) };
+    }
+
+    /// Call [`ValueWith::to_out_param_nonnull`], but return an error instead of panicking if
+    /// `arg_out` is NULL.
+    ///
+    /// If `arg_out` is NULL, `rval` is dropped.
+    ///
+    /// # Safety
+    ///
+    /// * If `arg_out` is not NULL, the requirements are the same as for
+    ///   [`ValueWith::to_out_param_nonnull`].
+    pub unsafe fn to_out_param_nonnull_checked(
+        rval: RType,
+        ctx: &Ctx,
+        arg_out: *mut CType,
+    ) -> Result<(), NullPointerError> {
+        if arg_out.is_null() {
+            return Err(NullPointerError);
+        }
+        // SAFETY: arg_out is not NULL (just checked), and see docstring
+        unsafe { Self::to_out_param_nonnull(rval, ctx, arg_out) };
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::mem;
+
+    /// Lengths, in a unit system given by the context.
+    enum Units {
+        Millimeters,
+        Inches,
+    }
+
+    #[allow(non_camel_case_types)]
+    #[derive(Debug, PartialEq)]
+    struct length_t(f64);
+
+    /// A length in meters.
+    #[derive(Debug, PartialEq)]
+    struct Length(f64);
+
+    impl ConvertWith<Length, Units> for length_t {
+        fn from_rust(rval: Length, ctx: &Units) -> Self {
+            match ctx {
+                Units::Millimeters => length_t(rval.0 * 1000.0),
+                Units::Inches => length_t(rval.0 / 0.0254),
+            }
+        }
+
+        fn into_rust(self, ctx: &Units) -> Length {
+            match ctx {
+                Units::Millimeters => Length(self.0 / 1000.0),
+                Units::Inches => Length(self.0 * 0.0254),
+            }
+        }
+    }
+
+    type LengthValue = ValueWith<Length, length_t, Units>;
+
+    #[test]
+    fn take_and_return() {
+        assert_eq!(
+            LengthValue::take(length_t(2000.0), &Units::Millimeters),
+            Length(2.0)
+        );
+        assert_eq!(
+            LengthValue::return_val(Length(0.254), &Units::Inches),
+            length_t(10.0)
+        );
+    }
+
+    #[test]
+    fn to_out_param() {
+        let mut cval = mem::MaybeUninit::uninit();
+        unsafe {
+            LengthValue::to_out_param(Length(1.0), &Units::Millimeters, cval.as_mut_ptr());
+            assert_eq!(cval.assume_init(), length_t(1000.0));
+            LengthValue::to_out_param(Length(1.0), &Units::Millimeters, std::ptr::null_mut());
+        }
+    }
+
+    #[test]
+    fn to_out_param_nonnull_checked() {
+        assert_eq!(
+            unsafe {
+                LengthValue::to_out_param_nonnull_checked(
+                    Length(1.0),
+                    &Units::Inches,
+                    std::ptr::null_mut(),
+                )
+            },
+            Err(NullPointerError)
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn to_out_param_nonnull_null() {
+        unsafe {
+            LengthValue::to_out_param_nonnull(Length(1.0), &Units::Inches, std::ptr::null_mut())
+        };
+    }
+}
@@ -0,0 +1,24 @@
+use std::fmt;
+
+/// RegistryError is the error returned from [`Registry`](crate::Registry) methods.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegistryError {
+    /// No object has the given id: it is zero, was never issued, or its object was removed.
+    NotFound,
+    /// The object with the given id is not of the requested type.
+    WrongType,
+    /// Every id has been issued, so no more objects can be inserted.
+    Exhausted,
+}
+
+impl fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegistryError::NotFound => write!(f, "no object with that id"),
+            RegistryError::WrongType => write!(f, "object is not of the requested type"),
+            RegistryError::Exhausted => write!(f, "no more ids are available"),
+        }
+    }
+}
+
+impl std::error::Error for RegistryError {}
@@ -0,0 +1,7 @@
+#![doc = include_str!("crate-doc.md")]
+
+mod error;
+mod registry;
+
+pub use error::*;
+pub use registry::*;
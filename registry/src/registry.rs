@@ -0,0 +1,247 @@
+use crate::RegistryError;
+use std::any::Any;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard};
+
+/// The number of shards in a registry.  Ids are issued sequentially, so consecutive objects are
+/// in different shards.
+const SHARDS: usize = 16;
+
+type Shard = BTreeMap<u64, Box<dyn Any + Send>>;
+
+/// Registry stores objects of any `Send` type, identifying each with a `u64` id.
+///
+/// This is an alternative to passing pointers, for hosts where that is impossible or unsafe,
+/// such as WASM hosts, JNI, or scripting engines.  An id is just a number, so a host cannot
+/// corrupt memory with a stale or forged id: it will only fail to find the object.
+///
+/// Ids are never reused, and zero is never issued, so it can be used as a "NULL" id.  Inserting
+/// an object fails with [`RegistryError::Exhausted`] once every id has been issued, rather than
+/// wrapping around and reusing ids.
+///
+/// The objects are divided among several shards, each protected by a mutex, so that threads using
+/// different objects rarely contend.  A shard's mutex is held while the closures passed to
+/// [`Registry::with_ref`] and [`Registry::with_ref_mut`] run, so those closures must not access
+/// the same registry.
+///
+/// Most libraries can use the process-wide registry returned from [`Registry::global`].
+///
+/// # Example
+///
+/// ```
+/// # use ffizz_registry::Registry;
+/// struct Session {
+///     requests: u32,
+/// }
+///
+/// #[no_mangle]
+/// pub extern "C" fn mylib_session_new() -> u64 {
+///     Registry::global()
+///         .insert(Session { requests: 0 })
+///         .unwrap_or(0)
+/// }
+///
+/// #[no_mangle]
+/// pub extern "C" fn mylib_session_request(id: u64) -> bool {
+///     Registry::global()
+///         .with_ref_mut(id, |session: &mut Session| session.requests += 1)
+///         .is_ok()
+/// }
+///
+/// #[no_mangle]
+/// pub extern "C" fn mylib_session_free(id: u64) {
+///     let _ = Registry::global().remove::<Session>(id);
+/// }
+/// # let id = mylib_session_new();
+/// # assert!(mylib_session_request(id));
+/// # mylib_session_free(id);
+/// # assert!(!mylib_session_request(id));
+/// ```
+pub struct Registry {
+    shards: [Mutex<Shard>; SHARDS],
+    next_id: AtomicU64,
+}
+
+static GLOBAL: Registry = Registry::new();
+
+impl Registry {
+    /// Create a new, empty Registry.
+    pub const fn new() -> Self {
+        #[allow(clippy::declare_interior_mutable_const)]
+        const EMPTY: Mutex<Shard> = Mutex::new(BTreeMap::new());
+        Registry {
+            shards: [EMPTY; SHARDS],
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    /// Get the process-wide registry.
+    pub fn global() -> &'static Registry {
+        &GLOBAL
+    }
+
+    /// Store an object in the registry, returning its id.
+    pub fn insert<T: Any + Send>(&self, value: T) -> Result<u64, RegistryError> {
+        let id = self
+            .next_id
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |id| id.checked_add(1))
+            .map_err(|_| RegistryError::Exhausted)?;
+        self.shard(id).insert(id, Box::new(value));
+        Ok(id)
+    }
+
+    /// Call the contained function with a shared reference to the object with the given id.
+    pub fn with_ref<T: Any + Send, R, F: FnOnce(&T) -> R>(
+        &self,
+        id: u64,
+        f: F,
+    ) -> Result<R, RegistryError> {
+        let shard = self.shard(id);
+        let value = shard.get(&id).ok_or(RegistryError::NotFound)?;
+        let value = value.downcast_ref().ok_or(RegistryError::WrongType)?;
+        Ok(f(value))
+    }
+
+    /// Call the contained function with an exclusive reference to the object with the given id.
+    pub fn with_ref_mut<T: Any + Send, R, F: FnOnce(&mut T) -> R>(
+        &self,
+        id: u64,
+        f: F,
+    ) -> Result<R, RegistryError> {
+        let mut shard = self.shard(id);
+        let value = shard.get_mut(&id).ok_or(RegistryError::NotFound)?;
+        let value = value.downcast_mut().ok_or(RegistryError::WrongType)?;
+        Ok(f(value))
+    }
+
+    /// Get a copy of the object with the given id.
+    pub fn get<T: Any + Send + Clone>(&self, id: u64) -> Result<T, RegistryError> {
+        self.with_ref(id, T::clone)
+    }
+
+    /// Remove the object with the given id from the registry and return it.  The id is no longer
+    /// valid after this call.
+    ///
+    /// If the object is not of type T, it remains in the registry.
+    pub fn remove<T: Any + Send>(&self, id: u64) -> Result<T, RegistryError> {
+        let mut shard = self.shard(id);
+        if !shard.get(&id).ok_or(RegistryError::NotFound)?.is::<T>() {
+            return Err(RegistryError::WrongType);
+        }
+        let value = shard.remove(&id).unwrap();
+        Ok(*value.downcast().unwrap())
+    }
+
+    /// Determine whether the registry contains an object with the given id.
+    pub fn contains(&self, id: u64) -> bool {
+        self.shard(id).contains_key(&id)
+    }
+
+    /// Get the number of objects in the registry.
+    pub fn len(&self) -> usize {
+        (0..SHARDS).map(|i| self.lock(i).len()).sum()
+    }
+
+    /// Determine whether the registry is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn shard(&self, id: u64) -> MutexGuard<'_, Shard> {
+        self.lock(id as usize % SHARDS)
+    }
+
+    fn lock(&self, shard: usize) -> MutexGuard<'_, Shard> {
+        // a panic in a closure cannot leave a shard inconsistent, so ignore poisoning
+        self.shards[shard].lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn insert_get_remove() {
+        let reg = Registry::new();
+        let a = reg.insert(String::from("a")).unwrap();
+        let b = reg.insert(10u32).unwrap();
+        assert_ne!(a, 0);
+        assert_ne!(a, b);
+        assert_eq!(reg.len(), 2);
+
+        assert_eq!(reg.get(a), Ok(String::from("a")));
+        reg.with_ref_mut(b, |v: &mut u32| *v += 1).unwrap();
+        assert_eq!(reg.with_ref(b, |v: &u32| *v), Ok(11));
+        assert_eq!(reg.remove(b), Ok(11u32));
+        assert!(!reg.contains(b));
+        assert_eq!(reg.remove(a), Ok(String::from("a")));
+        assert!(reg.is_empty());
+    }
+
+    #[test]
+    fn errors() {
+        let reg = Registry::new();
+        assert_eq!(reg.get::<u32>(0), Err(RegistryError::NotFound));
+        let a = reg.insert(10u32).unwrap();
+        assert_eq!(reg.get::<u64>(a), Err(RegistryError::WrongType));
+        assert_eq!(reg.remove::<String>(a), Err(RegistryError::WrongType));
+        assert!(reg.contains(a));
+        assert_eq!(reg.remove::<u32>(a), Ok(10));
+        assert_eq!(reg.remove::<u32>(a), Err(RegistryError::NotFound));
+    }
+
+    #[test]
+    fn ids_not_reused() {
+        let reg = Registry::new();
+        let a = reg.insert(1u32).unwrap();
+        reg.remove::<u32>(a).unwrap();
+        let b = reg.insert(2u32).unwrap();
+        assert_ne!(a, b);
+        assert_eq!(reg.get::<u32>(a), Err(RegistryError::NotFound));
+    }
+
+    #[test]
+    fn exhausted() {
+        let reg = Registry::new();
+        reg.next_id.store(u64::MAX - 1, Ordering::Relaxed);
+        assert_eq!(reg.insert(1u32), Ok(u64::MAX - 1));
+        assert_eq!(reg.insert(2u32), Err(RegistryError::Exhausted));
+        assert_eq!(reg.insert(3u32), Err(RegistryError::Exhausted));
+        assert_eq!(reg.len(), 1);
+    }
+
+    #[test]
+    fn threads() {
+        let reg = Arc::new(Registry::new());
+        let handles: Vec<_> = (0..8u32)
+            .map(|t| {
+                let reg = reg.clone();
+                std::thread::spawn(move || {
+                    for i in 0..100 {
+                        let id = reg.insert(t * 1000 + i).unwrap();
+                        assert_eq!(reg.remove(id), Ok(t * 1000 + i));
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert!(reg.is_empty());
+    }
+
+    #[test]
+    fn global() {
+        let id = Registry::global().insert(5u8).unwrap();
+        assert_eq!(Registry::global().remove(id), Ok(5u8));
+    }
+}
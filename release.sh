@@ -4,10 +4,18 @@ set -e
 
 # ordered by dependencies, with sleep's in between to allow crates.io's DB to
 # catch up
-cargo publish -p ffizz-passby
-sleep 10
 cargo publish -p ffizz-macros
 sleep 10
+cargo publish -p ffizz-passby
+sleep 10
 cargo publish -p ffizz-header
 sleep 10
+cargo publish -p ffizz-build
+sleep 10
 cargo publish -p ffizz-string
+sleep 10
+cargo publish -p ffizz-future
+sleep 10
+cargo publish -p ffizz-registry
+sleep 10
+cargo publish -p ffizz
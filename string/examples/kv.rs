@@ -303,7 +303,7 @@ pub use ffizz_string::fz_string_content as kvstore_string_content;
 /// It is safe to free Null-variant strings.
 ///
 /// ```c
-/// kvstore_string_free(kvstore_string_t *);
+/// void kvstore_string_free(kvstore_string_t *);
 /// ```
 pub use ffizz_string::fz_string_free as kvstore_string_free;
 
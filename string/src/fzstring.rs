@@ -70,6 +70,9 @@ pub struct fz_string_t {
 
 type UnboxedString<'a> = Unboxed<FzString<'a>, fz_string_t>;
 
+// the reserved field is sized in pointers, so this holds on 32-bit targets such as wasm32, too
+ffizz_passby::static_assert_layout!(FzString<'static>, fz_string_t);
+
 impl<'a> FzString<'a> {
     /// Check if this is a Null FzString.
     pub fn is_null(&self) -> bool {
@@ -183,20 +186,26 @@ impl<'a> FzString<'a> {
     ///
     /// The Null varaiant is represented as None.
     pub fn into_path_buf(self) -> Result<Option<PathBuf>, std::str::Utf8Error> {
-        #[cfg(unix)]
+        #[cfg(any(unix, target_os = "wasi"))]
         let path: Option<OsString> = {
-            // on UNIX, we can use the bytes directly, without requiring that they
+            // on UNIX and WASI, we can use the bytes directly, without requiring that they
             // be valid UTF-8.
             use std::ffi::OsStr;
+            #[cfg(unix)]
             use std::os::unix::ffi::OsStrExt;
+            #[cfg(target_os = "wasi")]
+            use std::os::wasi::ffi::OsStrExt;
             self.as_bytes()
                 .map(|bytes| OsStr::from_bytes(bytes).to_os_string())
         };
-        #[cfg(windows)]
+        #[cfg(not(any(unix, target_os = "wasi")))]
         let path: Option<OsString> = {
-            // on Windows, we assume the filename is valid Unicode, so it can be
-            // represented as UTF-8.
-            self.into_string()?.map(|s| OsString::from(s))
+            // elsewhere, such as on Windows, we assume the filename is valid Unicode, so it can
+            // be represented as UTF-8.
+            match self.as_bytes() {
+                Some(bytes) => Some(std::str::from_utf8(bytes)?.into()),
+                None => None,
+            }
         };
         Ok(path.map(|p| p.into()))
     }
@@ -415,6 +424,18 @@ impl<'a> FzString<'a> {
     }
 }
 
+/// Strings can be taken with `ffizz_passby::take_all!`, using [`FzString::take_ptr`].  A NULL
+/// pointer is taken as [`FzString::Null`].
+impl ffizz_passby::TakeParam for FzString<'static> {
+    type RType = Self;
+    type Arg = *mut fz_string_t;
+
+    unsafe fn take_param(arg: Self::Arg) -> Self {
+        // SAFETY: see docstring
+        unsafe { FzString::take_ptr(arg) }
+    }
+}
+
 impl From<String> for FzString<'static> {
     fn from(string: String) -> FzString<'static> {
         FzString::String(string)
@@ -476,7 +497,7 @@ impl From<Option<&[u8]>> for FzString<'static> {
 }
 
 fn has_nul_bytes(bytes: &[u8]) -> bool {
-    bytes.iter().any(|c| *c == b'\x00')
+    bytes.contains(&b'\x00')
 }
 
 #[cfg(test)]
@@ -490,7 +511,7 @@ mod test {
     }
 
     fn make_cstr() -> FzString<'static> {
-        let cstr = CStr::from_bytes_with_nul(b"a string\x00").unwrap();
+        let cstr = c"a string";
         FzString::CStr(cstr)
     }
 
@@ -757,9 +778,9 @@ mod test {
 
     #[test]
     fn into_path_buf_invalid_bytes() {
-        #[cfg(windows)] // windows filenames are unicode
+        #[cfg(not(any(unix, target_os = "wasi")))] // windows filenames are unicode
         assert!(make_invalid_bytes().into_path_buf().is_err());
-        #[cfg(unix)] // UNIX doesn't care
+        #[cfg(any(unix, target_os = "wasi"))] // UNIX doesn't care
         assert!(make_invalid_bytes().into_path_buf().is_ok());
     }
 
@@ -909,4 +930,14 @@ mod test {
             FzString::Bytes(INVALID_UTF8.into())
         );
     }
+
+    #[test]
+    fn take_all() {
+        let mut a = unsafe { FzString::from("a").return_val() };
+        let (a, b) = unsafe {
+            ffizz_passby::take_all!((&mut a, FzString), (std::ptr::null_mut(), FzString))
+        };
+        assert_eq!(a, FzString::String(String::from("a")));
+        assert_eq!(b, FzString::Null);
+    }
 }
@@ -10,5 +10,6 @@ mod utilfns;
 
 pub use error::*;
 pub use fzstring::{fz_string_t, FzString};
+#[allow(unused_imports)]
 pub use macros::*;
 pub use utilfns::*;
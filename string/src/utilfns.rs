@@ -10,7 +10,7 @@ use std::ffi::{CStr, CString};
 
 // This type is used in the `reexport!` macro.
 #[doc(hidden)]
-pub type c_char = libc::c_char;
+pub type c_char = std::ffi::c_char;
 
 /// Create a new fz_string_t containing a pointer to the given C string.
 ///
@@ -220,7 +220,7 @@ pub unsafe fn fz_string_is_null(fzstr: *const fz_string_t) -> bool {
 /// It is safe to free Null-variant strings.
 ///
 /// ```c
-/// fz_string_free(fz_string_t *);
+/// void fz_string_free(fz_string_t *);
 /// ```
 #[inline(always)]
 pub unsafe fn fz_string_free(fzstr: *mut fz_string_t) {
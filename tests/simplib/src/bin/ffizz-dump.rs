@@ -0,0 +1,2 @@
+// Write the header for this library: `cargo run --features ffizz-dump --bin ffizz-dump`
+ffizz_header::emit_main!();
@@ -1,3 +1,5 @@
+#![allow(clippy::missing_safety_doc)]
+
 ffizz_header::snippet! {
 #[ffizz(name="top", order=0)]
 /// SimpLib -- addition, simplified.
@@ -27,3 +29,39 @@ pub unsafe extern "C" fn add(left: u64, right: u64) -> u64 {
 pub fn generate_header() -> String {
     ffizz_header::generate()
 }
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn header_is_current() {
+        ffizz_header::assert_header_matches!("simplib.h");
+    }
+
+    #[test]
+    fn header_with_filter() {
+        assert_eq!(
+            ffizz_header::generate_with_filter(|item| item.name != "add"),
+            "// SimpLib -- addition, simplified.\n\n#include <stdint.h>\n"
+        );
+    }
+
+    #[test]
+    fn find_items() {
+        assert_eq!(ffizz_header::names(), vec!["top", "includes", "add"]);
+        let add = ffizz_header::find("add").unwrap();
+        assert!(add
+            .content
+            .contains("uint64_t add(uint64_t left, uint64_t right);"));
+        assert!(ffizz_header::find("sub").is_none());
+    }
+
+    #[test]
+    fn header_from_template() {
+        assert_eq!(
+            ffizz_header::HeaderConfig::new()
+                .generate_from_template("#pragma once\n{{ items() }}\n")
+                .unwrap(),
+            ffizz_header::generate().replace("// SimpLib", "#pragma once\n// SimpLib")
+        );
+    }
+}